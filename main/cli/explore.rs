@@ -0,0 +1,685 @@
+//! Exploration commands built on the search primitives: calibration,
+//! opposites, concept axes, fused expressions, drift, model comparison, analogies, odd-one-out puzzles,
+//! spelling correction, interpolation paths, translation between aligned spaces, bias tests, vocabulary listing and
+//! summary statistics.
+
+use std::borrow::Cow;
+use std::fs;
+
+use glove::align::{translate, Mapping};
+use glove::analogy::{analogy_expression, solve, Method};
+use glove::args::{take_flag, take_list, take_option, take_values};
+use glove::axis::{known_antonym, Axis, ConceptAxis};
+use glove::calibrate::{calibrate, calibration_path};
+use glove::correct::corrections;
+use glove::dedup::find_duplicates;
+use glove::drift::{neighbor_overlap, neighbors, second_order_shift, shared_sample};
+use glove::fuse::{fuse, WeightedExpression};
+use glove::metric::BuiltinMetric;
+use glove::model_stats::model_stats;
+use glove::odd_one_out::doesnt_match;
+use glove::path::interpolation_path;
+use glove::pattern::Pattern;
+use glove::rng::Rng;
+use glove::search::{find_nearest_neighbors, top_k};
+use glove::store::unknown_words;
+use glove::weat::{weat, WordSets};
+use glove::{Error, Source};
+
+use super::{default_top, open, skipping_unknown, take_source_or_usage, take_store_args};
+
+/// Handles `calibrate <source>`: suggests a similarity threshold for the file and caches it
+pub fn run_calibrate(mut args: Vec<String>) {
+    let usage = "calibrate <source> [--samples N] [--seed S] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let samples = take_option(&mut args, "--samples").map_or(Ok(10_000), |s| s.parse());
+    let seed = take_option(&mut args, "--seed").map(|s| s.parse::<u64>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(samples), 2) = (samples, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let mut rng = match seed {
+        Some(Ok(seed)) => Rng::new(seed),
+        Some(Err(_)) => {
+            eprintln!("--seed must be a non-negative integer");
+            return;
+        }
+        None => Rng::from_time(),
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let calibration = calibrate(store.as_ref(), &source, samples, &mut rng);
+    print!("{}", calibration);
+    if calibration.random.count < samples {
+        eprintln!("Only sampled {} of {} random pairs", calibration.random.count, samples);
+    }
+
+    let path = calibration_path(&source);
+    match calibration.save(&path) {
+        Ok(()) => println!("Saved calibration to {}", path.display()),
+        Err(e) => eprintln!("Unable to save calibration to {}: {}", path.display(), e),
+    }
+}
+
+/// Handles `opposite <source> --word w [--axis a b]`: reflects a word across an
+/// antonym axis and lists the words nearest to the reflection
+pub fn run_opposite(mut args: Vec<String>) {
+    let usage = "opposite <source> --word <word> [--axis <from> <to>] [--top N] [--allow-inputs] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let word = take_option(&mut args, "--word");
+    let axis = take_values(&mut args, "--axis", 2);
+    let allow_inputs = take_flag(&mut args, "--allow-inputs");
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(10)), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Some(word), Ok(top), 2) = (word, top, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    // Without --axis, pair the word with its bundled antonym
+    let (from, to) = match axis {
+        Some(pair) => (pair[0].clone(), pair[1].clone()),
+        None => match known_antonym(&word) {
+            Some(antonym) => (word.clone(), antonym.to_string()),
+            None => {
+                eprintln!("No known antonym for '{}'; pass --axis <from> <to>", word);
+                return;
+            }
+        },
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let Some(vec) = store.get(&word) else {
+        println!("Unknown word: {}", word);
+        return;
+    };
+    let Some(axis) = Axis::between(store.as_ref(), &from, &to) else {
+        println!("Axis words must both be in the vocabulary: {} {}", from, to);
+        return;
+    };
+
+    println!("Reflecting '{}' across the {} <-> {} axis:", word, from, to);
+    let reflected = axis.reflect(&vec);
+    let exclude = if allow_inputs { Vec::new() } else { vec![word, from, to] };
+    let found = find_nearest_neighbors(&reflected, store.as_ref(), &exclude, top, &BuiltinMetric::Cosine);
+    for (candidate, similarity) in found {
+        println!("  {} (similarity: {:.4})", candidate, similarity);
+    }
+}
+
+/// Handles `axis <source> --pair man woman [--pair ...] [word ...]`: ranks words by
+/// their signed projection on the axis the pairs define, or lists the words at
+/// either end of it when none are given
+pub fn run_axis(mut args: Vec<String>) {
+    let usage = "axis <source> --pair <from> <to> [--pair <from> <to> ...] [word ...] [--top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let mut pairs = Vec::new();
+    while let Some(pair) = take_values(&mut args, "--pair", 2) {
+        pairs.push((pair[0].clone(), pair[1].clone()));
+    }
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(10)), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), false) = (top, pairs.is_empty()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let axis = match ConceptAxis::from_pairs(store.as_ref(), &pairs) {
+        Ok(axis) => axis,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let names: Vec<String> = pairs.iter().map(|(from, to)| format!("{} <-> {}", from, to)).collect();
+    println!("Axis: {}", names.join(", "));
+
+    if args.len() > 2 {
+        let mut scored = Vec::new();
+        for word in &args[2..] {
+            match store.get(word) {
+                Some(vec) => scored.push((word, axis.project(&vec))),
+                None => println!("{}", skipping_unknown(store.as_ref(), word)),
+            }
+        }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (word, projection) in scored {
+            println!("  {:+.4}  {}", projection, word);
+        }
+        return;
+    }
+
+    let to: Vec<&str> = pairs.iter().map(|(_, to)| to.as_str()).collect();
+    let from: Vec<&str> = pairs.iter().map(|(from, _)| from.as_str()).collect();
+    let exclude: Vec<String> = from.iter().chain(&to).map(|w| w.to_string()).collect();
+    let scores = store.cosine_scores(&axis.direction);
+    for (end, positive) in [(to, true), (from, false)] {
+        println!("Toward {}:", end.join(", "));
+        for (word, projection) in top_k(scores.clone(), store.as_ref(), &exclude, top, positive) {
+            println!("  {:+.4}  {}", projection, word);
+        }
+    }
+}
+
+/// Handles `fuse <source> 0.7:"paris - france + italy" 0.3:"rome"`: ranks words
+/// by their weighted similarity to several expressions
+pub fn run_fuse(mut args: Vec<String>) {
+    let usage = "fuse <source> <weight>:<expression> ... [--top N] [--allow-inputs] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let allow_inputs = take_flag(&mut args, "--allow-inputs");
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(10)), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), true) = (top, args.len() > 2) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let expressions: Result<Vec<_>, _> = args[2..].iter().map(|a| WeightedExpression::parse(a)).collect();
+    let expressions = match expressions {
+        Ok(expressions) => expressions,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    match fuse(store.as_ref(), &expressions, top, allow_inputs) {
+        Ok(ranked) => {
+            for (word, score) in ranked {
+                println!("  {} (fused similarity: {:.4})", word, score);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Handles `drift --word w --models a.txt b.txt ...`: lists the word's neighbors
+/// in each snapshot and how much its neighborhood shifts between them
+pub fn run_drift(mut args: Vec<String>) {
+    let usage = "drift --word <word> --models <a.txt> <b.txt> ... [--top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let word = take_option(&mut args, "--word");
+    let models = take_list(&mut args, "--models");
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(10)), |t| t.parse::<usize>());
+    let (Some(word), Some(models), Ok(top), 2) = (word, models, top, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    if models.len() < 2 {
+        eprintln!("--models needs at least two snapshots");
+        return;
+    }
+
+    let mut snapshots = Vec::new();
+    for model in &models {
+        let Some(store) = open(&store_args, &Source::from(model.as_str())) else { return };
+        let found = neighbors(store.as_ref(), &word, top);
+        snapshots.push((model, store, found));
+    }
+
+    for (model, _, found) in &snapshots {
+        match found {
+            Some(found) => {
+                let listed: Vec<String> = found.iter().map(|(w, s)| format!("{} ({:.3})", w, s)).collect();
+                println!("{}: {}", model, listed.join(", "));
+            }
+            None => println!("{}: unknown word '{}'", model, word),
+        }
+    }
+
+    println!("Shift between snapshots (1 - cosine of second-order similarity profiles):");
+    for (i, (a_model, a, a_found)) in snapshots.iter().enumerate() {
+        for (b_model, b, b_found) in &snapshots[i + 1..] {
+            let (Some(a_found), Some(b_found)) = (a_found, b_found) else { continue };
+            match second_order_shift(&word, (a.as_ref(), a_found), (b.as_ref(), b_found)) {
+                Some(s) => println!(
+                    "  {} -> {}: {:.4} ({} of {} neighbors shared)",
+                    a_model, b_model, s.shift, s.shared, top
+                ),
+                None => println!("  {} -> {}: no neighbors in common vocabulary", a_model, b_model),
+            }
+        }
+    }
+}
+
+/// Handles `compare <a.txt> <b.txt>`: how much the nearest neighbors of sampled
+/// or listed words agree between two spaces
+pub fn run_compare(mut args: Vec<String>) {
+    let usage = "compare <a.txt> <b.txt> [--words <list.txt> | --samples N [--pool N] [--seed S]] [--k K] \
+                 [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let words = take_option(&mut args, "--words");
+    let samples = take_option(&mut args, "--samples").map_or(Ok(100), |n| n.parse::<usize>());
+    let pool = take_option(&mut args, "--pool").map_or(Ok(10_000), |n| n.parse::<usize>());
+    let seed = take_option(&mut args, "--seed").map_or(Ok(0), |s| s.parse::<u64>());
+    let k = take_option(&mut args, "--k").map_or(Ok(default_top(10)), |k| k.parse::<usize>());
+    let (Ok(samples), Ok(pool), Ok(seed), Ok(k @ 1..), 4) = (samples, pool, seed, k, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let listed = match &words {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => Some(text.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string).collect()),
+            Err(e) => {
+                eprintln!("Unable to read {}: {}", path, e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let Some(a) = open(&store_args, &Source::from(args[2].as_str())) else { return };
+    let Some(b) = open(&store_args, &Source::from(args[3].as_str())) else { return };
+    let words: Vec<String> = match listed {
+        Some(words) => words,
+        None => shared_sample(a.as_ref(), b.as_ref(), samples, pool, &mut Rng::new(seed)),
+    };
+
+    println!("Overlap (Jaccard) of the {} nearest neighbors in {} and {}:", k, args[2], args[3]);
+    let mut total = 0.0;
+    let mut compared = 0;
+    for (word, overlap) in neighbor_overlap(a.as_ref(), b.as_ref(), &words, k) {
+        match overlap {
+            Some(overlap) => {
+                println!("  {} ({:.4})", word, overlap);
+                total += overlap;
+                compared += 1;
+            }
+            None => println!("  {} (not in both vocabularies)", word),
+        }
+    }
+    match compared {
+        0 => println!("No word is in both vocabularies."),
+        n => println!("Mean overlap over {} words: {:.4}", n, total / n as f32),
+    }
+}
+
+/// Handles `analogy <source> a b c`: "a is to b as c is to ?"
+pub fn run_analogy(mut args: Vec<String>) {
+    let usage = "analogy <source> <a> <b> <c> [--method 3cosadd|3cosmul] [--top N] [--allow-inputs] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let allow_inputs = take_flag(&mut args, "--allow-inputs");
+    let method = take_option(&mut args, "--method").map_or(Some(Method::default()), |m| Method::parse(&m));
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(1)), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Some(method), Ok(top), 5) = (method, top, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let expression = analogy_expression(&args[2], &args[3], &args[4]);
+    match solve(store.as_ref(), &expression, method, top, allow_inputs) {
+        Ok(answers) => {
+            println!("{} is to {} as {} is to:", args[2], args[3], args[4]);
+            for (word, score) in answers {
+                println!("  {} (score: {:.4})", word, score);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Handles `doesnt-match <source> w1 w2 w3 ...`: the word least similar to the mean of the others
+pub fn run_doesnt_match(mut args: Vec<String>) {
+    let usage = "doesnt-match <source> <word1> <word2> <word3> ... [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    if args.len() < 5 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    let Some(store) = open(&store_args, &source) else { return };
+    for word in args[2..].iter().filter(|w| !store.contains(w)) {
+        println!("{}", skipping_unknown(store.as_ref(), word));
+    }
+    match doesnt_match(store.as_ref(), &args[2..]) {
+        Ok(scored) => {
+            println!("Doesn't match: {}", scored[0].0);
+            println!("Similarity of each word to the mean of the others:");
+            for (word, similarity) in &scored {
+                println!("  {} ({:.4})", word, similarity);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Handles `correct <source> <word> [context ...]`: spellings within a few edits
+/// of the word, ranked by how well they fit the context words
+pub fn run_correct(mut args: Vec<String>) {
+    let usage = "correct <source> <word> [<context word> ...] [--top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(5)), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), 3..) = (top, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let (word, context) = (&args[2], &args[3..]);
+    for unknown in context.iter().filter(|w| !store.contains(w)) {
+        println!("{}", skipping_unknown(store.as_ref(), unknown));
+    }
+    let found = corrections(store.as_ref(), word, context, top);
+    if found.is_empty() {
+        println!("No words within a few edits of '{}'", word);
+        return;
+    }
+    if context.is_empty() {
+        println!("Corrections for '{}':", word);
+    } else {
+        println!("Corrections for '{}' among {}:", word, context.join(" "));
+    }
+    for correction in found {
+        let edits = match correction.edits {
+            0 => "as written".to_string(),
+            1 => "1 edit".to_string(),
+            n => format!("{} edits", n),
+        };
+        match correction.context_similarity {
+            Some(similarity) => println!(
+                "  {} (score: {:.4}; {}, context similarity {:.4})",
+                correction.word, correction.score, edits, similarity
+            ),
+            None => println!("  {} ({})", correction.word, edits),
+        }
+    }
+}
+
+/// Handles `path <source> <word1> <word2> [--steps N]`: the nearest word at evenly
+/// spaced points on the line between two vectors
+pub fn run_path(mut args: Vec<String>) {
+    let usage = "path <source> <word1> <word2> [--steps N] [--exclude-endpoints] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let steps = take_option(&mut args, "--steps").map_or(Ok(10), |s| s.parse::<usize>());
+    let exclude_endpoints = take_flag(&mut args, "--exclude-endpoints");
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(steps @ 1..), 4) = (steps, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    match interpolation_path(store.as_ref(), &args[2], &args[3], steps, exclude_endpoints) {
+        Ok(path) => {
+            println!("Path from '{}' to '{}' in {} steps:", args[2], args[3], steps);
+            for step in path {
+                println!("  {:.2}  {} (similarity: {:.4})", step.t, step.word, step.similarity);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Handles `translate <source.txt> <target.txt> <word> ... --mapping <mapping.txt>`:
+/// maps each source word with a matrix saved by `align --save-mapping` and
+/// lists its nearest neighbors in the target space
+pub fn run_translate(mut args: Vec<String>) {
+    let usage = "translate <source.txt> <target.txt> <word> ... --mapping <mapping.txt> [--top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let mapping = take_option(&mut args, "--mapping");
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(5)), |t| t.parse::<usize>());
+    let (Some(mapping), Ok(top), true) = (mapping, top, args.len() > 4) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let mapping = match Mapping::load(&mapping) {
+        Ok(mapping) => mapping,
+        Err(e) => {
+            eprintln!("Unable to read the mapping {}", e);
+            return;
+        }
+    };
+
+    let Some(source) = open(&store_args, &Source::from(args[2].as_str())) else { return };
+    let Some(target) = open(&store_args, &Source::from(args[3].as_str())) else { return };
+    if source.dim() != mapping.dim() || target.dim() != mapping.dim() {
+        eprintln!(
+            "The mapping is {}x{}, but the spaces have {} and {} dimensions",
+            mapping.dim(),
+            mapping.dim(),
+            source.dim(),
+            target.dim()
+        );
+        return;
+    }
+
+    for word in &args[4..] {
+        match translate(source.as_ref(), target.as_ref(), &mapping, word, top) {
+            Some(found) => {
+                println!("{}:", word);
+                for (candidate, similarity) in found {
+                    println!("  {} (similarity: {:.4})", candidate, similarity);
+                }
+            }
+            None => println!("{}: not in {}", word, args[2]),
+        }
+    }
+}
+
+/// Handles `eval <test> ...`; WEAT is the only test so far
+pub fn run_eval(args: Vec<String>) {
+    match args.get(2).map(|a| a.as_str()) {
+        Some("weat") => run_weat(args),
+        _ => eprintln!("Usage: {} eval weat <source> ...", args[0]),
+    }
+}
+
+/// Handles `eval weat <source> --targets-x ... --targets-y ... --attributes-a ... --attributes-b ...`
+fn run_weat(mut args: Vec<String>) {
+    let usage = "eval weat <source> --targets-x <word> ... --targets-y <word> ... --attributes-a <word> ... \
+                 --attributes-b <word> ... [--permutations N] [--seed S] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let sets = (
+        take_list(&mut args, "--targets-x"),
+        take_list(&mut args, "--targets-y"),
+        take_list(&mut args, "--attributes-a"),
+        take_list(&mut args, "--attributes-b"),
+    );
+    let permutations = take_option(&mut args, "--permutations").map_or(Ok(100_000), |n| n.parse::<usize>());
+    let seed = take_option(&mut args, "--seed").map(|s| s.parse::<u64>());
+    let Some(source) = take_source_or_usage(&mut args, 3, usage) else { return };
+    let ((Some(targets_x), Some(targets_y), Some(attributes_a), Some(attributes_b)), Ok(permutations), 3) =
+        (sets, permutations, args.len())
+    else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let mut rng = match seed {
+        Some(Ok(seed)) => Rng::new(seed),
+        Some(Err(_)) => {
+            eprintln!("--seed must be a non-negative integer");
+            return;
+        }
+        None => Rng::from_time(),
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let sets = WordSets { targets_x, targets_y, attributes_a, attributes_b };
+    let result = match weat(store.as_ref(), &sets, permutations, &mut rng) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    if !result.unknown.is_empty() {
+        println!("Skipping unknown words: {}", result.unknown.join(", "));
+    }
+    println!("Effect size: {:.4}", result.effect_size);
+    println!("Test statistic: {:.4}", result.statistic);
+    println!(
+        "p-value: {:.4} ({} {} partitions)",
+        result.p_value,
+        if result.exact { "all" } else { "sampled" },
+        result.partitions
+    );
+}
+
+/// Handles `vocab <source> [word ...] [--regex <pattern>] [--prefix <prefix>]`: the
+/// vocabulary size, the words matching a pattern or prefix, and whether given words are known
+pub fn run_vocab(mut args: Vec<String>) {
+    let usage = "vocab <source> [word ...] [--regex <pattern>] [--prefix <prefix>] [--limit N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let pattern = take_option(&mut args, "--regex").map(|p| Pattern::parse(&p));
+    let prefix = take_option(&mut args, "--prefix");
+    let limit = take_option(&mut args, "--limit").map(|n| n.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(limit), true) = (limit.transpose(), args.len() >= 2) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let pattern = match pattern.transpose() {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("--regex: {}", e);
+            return;
+        }
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    println!("{} words, {} dimensions", store.len(), store.dim());
+
+    if pattern.is_some() || prefix.is_some() {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut matches = 0;
+        for (word, _) in store.iter() {
+            let selected = pattern.as_ref().is_none_or(|p| p.is_match(&word))
+                && prefix.as_ref().is_none_or(|p| word.starts_with(p.as_str()));
+            if selected {
+                if matches < limit {
+                    println!("{}", word);
+                }
+                matches += 1;
+            }
+        }
+        match matches {
+            n if n > limit => println!("{} matching words ({} shown)", n, limit),
+            1 => println!("1 matching word"),
+            n => println!("{} matching words", n),
+        }
+    }
+
+    for word in &args[2..] {
+        match store.id(word) {
+            Some(id) => match store.get_by_id(id) {
+                Some((found, _)) if found != word.as_str() => println!("{}: row {} (as {})", word, id, found),
+                _ => println!("{}: row {}", word, id),
+            },
+            None => {
+                let suggestion = match unknown_words(store.as_ref(), &[word]) {
+                    Error::OutOfVocabulary(mut unknown) => unknown.pop().and_then(|(_, suggestion)| suggestion),
+                    _ => None,
+                };
+                match suggestion {
+                    Some(suggestion) => println!("{}: not in the vocabulary (did you mean {}?)", word, suggestion),
+                    None => println!("{}: not in the vocabulary", word),
+                }
+            }
+        }
+    }
+}
+
+/// Handles `vocab-diff <a> <b>`: counts the words only in A, only in B and in
+/// both, listing a few of each and writing the full lists when asked to
+pub fn run_vocab_diff(mut args: Vec<String>) {
+    let usage = "vocab-diff <a.txt> <b.txt> [--show N] [--only-a <out.txt>] [--only-b <out.txt>] [--both <out.txt>] \
+                 [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let show = take_option(&mut args, "--show").map_or(Ok(10), |n| n.parse::<usize>());
+    let outputs = ["--only-a", "--only-b", "--both"].map(|name| take_option(&mut args, name));
+    let (Ok(show), 4) = (show, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(a) = open(&store_args, &Source::from(args[2].as_str())) else { return };
+    let Some(b) = open(&store_args, &Source::from(args[3].as_str())) else { return };
+    let (mut only_a, mut both) = (Vec::new(), Vec::new());
+    for (word, _) in a.iter() {
+        match b.contains(&word) {
+            true => both.push(word.into_owned()),
+            false => only_a.push(word.into_owned()),
+        }
+    }
+    let only_b: Vec<String> = b.iter().map(|(word, _)| word).filter(|w| !a.contains(w)).map(Cow::into_owned).collect();
+
+    let lists = [
+        (format!("Only in {}", args[2]), only_a),
+        (format!("Only in {}", args[3]), only_b),
+        ("In both".to_string(), both),
+    ];
+    for ((label, words), output) in lists.iter().zip(&outputs) {
+        let shown: Vec<&str> = words.iter().take(show).map(String::as_str).collect();
+        let more = if words.len() > shown.len() { ", ..." } else { "" };
+        match shown.is_empty() {
+            true => println!("{}: {} words", label, words.len()),
+            false => println!("{}: {} words ({}{})", label, words.len(), shown.join(", "), more),
+        }
+        if let Some(path) = output {
+            let text: String = words.iter().map(|w| format!("{}\n", w)).collect();
+            match fs::write(path, text) {
+                Ok(()) => println!("  wrote them to {}", path),
+                Err(e) => eprintln!("Unable to write {}: {}", path, e),
+            }
+        }
+    }
+}
+
+/// Handles `stats <source>`: norms, isotropy and hubness of the vectors
+pub fn run_stats(mut args: Vec<String>) {
+    let usage = "stats <source> [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    if args.len() != 2 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    let Some(store) = open(&store_args, &source) else { return };
+    println!("{}", model_stats(store.as_ref()));
+}
+
+/// Handles `dedup-report <source> [--threshold T]`: lists words with identical
+/// vectors and pairs of words whose vectors are nearly so
+pub fn run_dedup_report(mut args: Vec<String>) {
+    let usage = "dedup-report <source> [--threshold T] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let threshold = take_option(&mut args, "--threshold").map_or(Ok(0.99), |t| t.parse::<f32>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(threshold), 2) = (threshold, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        eprintln!("--threshold must be a cosine similarity above 0 and at most 1");
+        return;
+    }
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let report = find_duplicates(store.as_ref(), threshold);
+    match report.identical.len() {
+        0 => println!("No identical vectors."),
+        n => {
+            let words: usize = report.identical.iter().map(Vec::len).sum();
+            println!("Identical vectors: {} groups, {} words", n, words);
+            for group in &report.identical {
+                println!("  {}", group.join(", "));
+            }
+        }
+    }
+    match report.near.len() {
+        0 => println!("No other pairs with cosine similarity >= {}.", threshold),
+        n => {
+            println!("Near duplicates (cosine similarity >= {}): {} pairs", threshold, n);
+            for (a, b, similarity) in &report.near {
+                println!("  {} ~ {} ({:.4})", a, b, similarity);
+            }
+        }
+    }
+}
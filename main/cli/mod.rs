@@ -0,0 +1,323 @@
+//! Subcommand handlers for the `main` binary, and the flag handling and
+//! loading code they share.
+
+pub mod explore;
+pub mod query;
+pub mod readline;
+pub mod storage;
+pub mod text;
+#[cfg(unix)]
+pub mod tui;
+
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use glove::args::{
+    take_flag, take_index, take_load_options, take_mask, take_option, take_postprocess, take_source,
+};
+use glove::background::BackgroundLoad;
+use glove::config::Config;
+#[cfg(feature = "gpu")]
+use glove::gpu::with_gpu;
+use glove::index::{with_index, IndexSpec};
+use glove::lemma::with_lemmas;
+pub use glove::json::json_string;
+use glove::mask::{with_mask, DimMask};
+use glove::postprocess::{with_postprocess, Postprocess};
+use glove::shards::is_sharded;
+use glove::store::BACKENDS;
+use glove::subword::with_subwords;
+use glove::suggest::did_you_mean;
+use glove::{open_store, EmbeddingStore, LoadOptions, Precision, Source};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Reads the configuration file once, before any command runs; false if it is invalid
+pub fn load_config() -> bool {
+    match Config::load() {
+        Ok(config) => {
+            if let Some(dir) = &config.cache_dir {
+                glove::models::set_cache_dir(dir.clone());
+            }
+            let _ = CONFIG.set(config);
+            true
+        }
+        Err(e) => {
+            eprintln!("Invalid configuration file {}", e);
+            false
+        }
+    }
+}
+
+/// The defaults from the configuration file
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// The configured number of neighbors, else `fallback`
+pub fn default_top(fallback: usize) -> usize {
+    config().top.unwrap_or(fallback)
+}
+
+/// Flags shared by every command that opens a vector source
+pub struct StoreArgs {
+    pub backend: String,
+    pub load: LoadOptions,
+    pub mask: Option<DimMask>,
+    /// Transform applied to every vector once the source is open
+    pub postprocess: Option<Postprocess>,
+    /// Approximate index to search through instead of scanning every row
+    pub index: Option<IndexSpec>,
+    /// Run cosine scans on the GPU when one is available
+    pub gpu: bool,
+    /// Look missing words up by their lemma
+    pub lemmatize: bool,
+    /// Give missing words vectors from their character n-grams
+    pub subwords: bool,
+    /// Where the n-gram buckets are read from, or saved to after training
+    pub subword_file: Option<String>,
+    /// Expected dimensionality, checked once the vectors are open
+    pub dim: Option<usize>,
+}
+
+/// Removes `--backend <name>` (default: dense), `--dim N`, the loading flags,
+/// the dimension mask, the post-processing and index options, `--gpu`, `--lemmatize` and the
+/// subword flags from the arguments
+pub fn take_store_args(args: &mut Vec<String>) -> Option<StoreArgs> {
+    let backend = take_option(args, "--backend").unwrap_or_else(|| "dense".to_string());
+    if !BACKENDS.contains(&backend.as_str()) {
+        eprintln!("--backend must be one of: {}", BACKENDS.join(", "));
+        return None;
+    }
+    let dim = match take_option(args, "--dim") {
+        None => config().dim,
+        Some(n) => match n.parse() {
+            Ok(dim) => Some(dim),
+            Err(_) => {
+                eprintln!("--dim must be a number of dimensions");
+                return None;
+            }
+        },
+    };
+    let gpu = take_flag(args, "--gpu");
+    let lemmatize = take_flag(args, "--lemmatize");
+    let subword_file = take_option(args, "--subword-file");
+    let subwords = take_flag(args, "--subwords") || subword_file.is_some();
+    let postprocess = match take_postprocess(args) {
+        Ok(postprocess) => postprocess,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
+    match (take_load_options(args), take_mask(args), take_index(args)) {
+        (Ok(_), Ok(_), Ok(Some(_))) if gpu => {
+            eprintln!("use either --gpu or --index, not both");
+            None
+        }
+        // Post-processed vectors are f32 anyway, and re-scoring would read the raw ones
+        (Ok(load), Ok(_), Ok(_)) if postprocess.is_some() && load.precision != Precision::F32 => {
+            eprintln!("--postprocess keeps f32 vectors; it cannot be combined with --precision");
+            None
+        }
+        (Ok(load), Ok(mask), Ok(index)) => Some(StoreArgs {
+            backend,
+            load,
+            mask,
+            postprocess,
+            index,
+            gpu,
+            lemmatize,
+            subwords,
+            subword_file,
+            dim,
+        }),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+/// Stands in for `glove::gpu::with_gpu` in builds without the `gpu` feature
+#[cfg(not(feature = "gpu"))]
+fn with_gpu(store: Box<dyn EmbeddingStore>, quiet: bool) -> Box<dyn EmbeddingStore> {
+    if !quiet {
+        eprintln!("This build has no GPU support; searching on the CPU");
+    }
+    store
+}
+
+/// Removes the vector source from the arguments, reporting usage errors.
+/// The configured `embeddings` file is used when no source flag is given and
+/// the argument at `index` is not an existing file.
+pub fn take_source_or_usage(args: &mut Vec<String>, index: usize, usage: &str) -> Option<Source> {
+    if let Some(embeddings) = &config().embeddings {
+        let flagged = args.iter().any(|a| a == "--model" || a == "--archive");
+        if !flagged && args.get(index).is_none_or(|a| !Path::new(a).exists() && !is_sharded(a)) {
+            args.insert(index.min(args.len()), embeddings.clone());
+        }
+    }
+    match take_source(args, index) {
+        Ok(source) => Some(source),
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: {} {}", args[0], usage);
+            None
+        }
+    }
+}
+
+/// Opens the vector source, reporting progress and failures to the user
+pub fn open(store_args: &StoreArgs, source: &Source) -> Option<Box<dyn EmbeddingStore>> {
+    if !store_args.load.quiet {
+        println!("Loading GloVe vectors...");
+    }
+    let start = Instant::now();
+    let opened = open_store(&store_args.backend, source, &store_args.load).map_err(|e| e.to_string());
+    finish_open(store_args, source, opened, start)
+}
+
+/// Applies the mask, post-processing, index and lookup fallbacks to opened
+/// vectors, reporting how it went
+fn finish_open(
+    store_args: &StoreArgs,
+    source: &Source,
+    opened: Result<Box<dyn EmbeddingStore>, String>,
+    start: Instant,
+) -> Option<Box<dyn EmbeddingStore>> {
+    let quiet = store_args.load.quiet;
+    let opened = opened
+        .and_then(|store| with_mask(store, store_args.mask.as_ref()))
+        .map(|store| with_postprocess(store, store_args.postprocess.as_ref()))
+        .and_then(|store| with_index(store, store_args.index.as_ref()).map_err(|e| e.to_string()))
+        .map(|store| if store_args.gpu { with_gpu(store, quiet) } else { store })
+        .map(|store| with_lemmas(store, store_args.lemmatize))
+        .and_then(|store| {
+            with_subwords(store, store_args.subwords, store_args.subword_file.as_deref()).map_err(|e| e.to_string())
+        });
+    match opened {
+        Ok(store) if store_args.dim.is_some_and(|dim| dim != store.dim()) => {
+            eprintln!(
+                "{} has {} dimensions, expected {}",
+                source,
+                store.dim(),
+                store_args.dim.unwrap_or_default()
+            );
+            None
+        }
+        Ok(store) => {
+            if !quiet {
+                println!(
+                    "Loaded {} words ({} dimensions) in {:.2}s",
+                    store.len(),
+                    store.dim(),
+                    start.elapsed().as_secs_f64()
+                );
+            }
+            Some(store)
+        }
+        Err(e) => {
+            eprintln!("Unable to open {}: {}", source, e);
+            None
+        }
+    }
+}
+
+/// Vectors being opened while a session starts; see `open_in_background`
+pub enum Opening {
+    /// Being read into memory on another thread
+    Loading { load: BackgroundLoad, start: Instant },
+    /// Opened already, or failed to open
+    Opened(Option<Box<dyn EmbeddingStore>>),
+}
+
+/// Starts opening the vectors so a session can take input meanwhile: those
+/// the `dense` backend reads into memory load on another thread, and the
+/// other backends, which open without parsing the file, open at once
+pub fn open_in_background(store_args: &StoreArgs, source: &Source) -> Opening {
+    if store_args.backend != "dense" {
+        return Opening::Opened(open(store_args, source));
+    }
+    Opening::Loading { load: BackgroundLoad::start(source, &store_args.load), start: Instant::now() }
+}
+
+impl Opening {
+    /// The opened vectors, waiting for them to load if need be; with
+    /// `show_progress`, how far the load has got is drawn on stderr meanwhile
+    pub fn wait(self, store_args: &StoreArgs, source: &Source, show_progress: bool) -> Option<Box<dyn EmbeddingStore>> {
+        let (load, start) = match self {
+            Opening::Opened(store) => return store,
+            Opening::Loading { load, start } => (load, start),
+        };
+        let show_progress = show_progress && !load.is_finished() && io::stderr().is_terminal();
+        let loaded = load.wait(|bytes, total_bytes| {
+            if show_progress {
+                let mb = bytes as f64 / 1e6;
+                let line = match total_bytes {
+                    Some(total) if total > 0 => {
+                        let percent = (bytes as f64 / total as f64 * 100.0).min(100.0);
+                        format!("Still loading {}: {:.0}% ({:.1}/{:.1} MB)", source, percent, mb, total as f64 / 1e6)
+                    }
+                    _ => format!("Still loading {}: {:.1} MB", source, mb),
+                };
+                eprint!("\r{}\x1b[K", line);
+            }
+        });
+        if show_progress {
+            eprint!("\r\x1b[K");
+        }
+        let opened = loaded.map(|store| store as Box<dyn EmbeddingStore>).map_err(|e| e.to_string());
+        finish_open(store_args, source, opened, start)
+    }
+}
+
+/// How command results are printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    /// Sentences for people
+    #[default]
+    Text,
+    /// Tab-separated rows
+    Tsv,
+    /// JSON, one value per line
+    Json,
+}
+
+/// Removes `--output text|tsv|json` from the arguments
+pub fn take_output(args: &mut Vec<String>) -> Result<Output, String> {
+    match take_option(args, "--output").as_deref() {
+        None | Some("text") => Ok(Output::Text),
+        Some("tsv") => Ok(Output::Tsv),
+        Some("json") => Ok(Output::Json),
+        Some(_) => Err("--output must be text, tsv or json".to_string()),
+    }
+}
+
+/// The warning for a word missing from `store`, with a "did you mean" suggestion when one is close
+pub fn skipping_unknown(store: &dyn EmbeddingStore, word: &str) -> String {
+    match did_you_mean(store, word) {
+        Some(suggestion) => format!("Skipping unknown word: {} (did you mean {}?)", word, suggestion),
+        None => format!("Skipping unknown word: {}", word),
+    }
+}
+
+/// Formats a score for JSON output; JSON has no NaN or infinity
+pub fn json_number(x: f32) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Formats ranked neighbors as a JSON array of `{"word", "score"}` objects
+pub fn json_results(found: &[(String, f32)]) -> String {
+    let items: Vec<String> = found
+        .iter()
+        .map(|(w, s)| format!("{{\"word\": {}, \"score\": {}}}", json_string(w), json_number(*s)))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
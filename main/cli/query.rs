@@ -0,0 +1,1109 @@
+//! The query commands: `sum`, `average`, `nearest`, `similarity` and `matrix`,
+//! and `bench`, which times the ways of answering them.
+
+use std::cell::OnceCell;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Lines, StdinLock, Write};
+
+use glove::args::{take_flag, take_metric, take_option, take_rescore};
+use glove::bench::{bench, exact_answers, sample_queries, Config, CONFIGS};
+use glove::cache::{LruCache, DEFAULT_CAPACITY};
+use glove::calibrate::{calibration_path, Calibration};
+use glove::explain::{explain, operand_similarities};
+use glove::expression::{Combine, Expression, Phrase};
+use glove::mask::with_mask;
+use glove::metric::{BuiltinMetric, Metric};
+use glove::pattern::Pattern;
+use glove::pq::PQ_RESCORE;
+use glove::rng::Rng;
+use glove::search::{find_nearest_neighbors_filtered, rank_all, rescore, CandidateFilter};
+use glove::session::{parse_let, Binding, Bindings, BoundStore};
+use glove::store::open_exact_subset;
+use glove::vector_file::{load_vector, save_vector};
+use glove::vector::normalize;
+use glove::{EmbeddingStore, Precision, Source, WordVec};
+
+use super::readline::LineEditor;
+use super::{
+    config, default_top, json_number, json_results, json_string, open, open_in_background, skipping_unknown,
+    take_output, take_source_or_usage, take_store_args, Output, StoreArgs,
+};
+
+/// Shown before each line read interactively by `--stdin`
+const PROMPT: &str = "> ";
+
+/// What `Tab` completes at the start of a `--stdin` line, besides words
+const SESSION_COMMANDS: &[&str] = &[":cache-stats", ":save", ":source", "let"];
+
+/// How deeply `:source` scripts may source other scripts
+const MAX_SOURCE_DEPTH: usize = 8;
+
+/// Dimensions listed by `--explain`
+const EXPLAIN_DIMENSIONS: usize = 10;
+
+/// Flags shared by the query commands
+struct QueryArgs {
+    metric: BuiltinMetric,
+    /// Number of neighbors reported per query
+    top: usize,
+    rescore_top: usize,
+    /// Only report neighbors scoring at least this (similarities) or at
+    /// most this (distances)
+    threshold: Option<f32>,
+    /// `--min-similarity calibrated`: take the threshold from the source's
+    /// saved calibration once the source is known
+    calibrated: bool,
+    output: Output,
+    /// Keep the query's own words among the results
+    allow_inputs: bool,
+    /// Save the single query's vector to this file
+    save_vector: Option<String>,
+    /// Scale the saved vector to unit length
+    normalize: bool,
+    /// Write every word's score for the single query to this CSV file
+    dump_all: Option<String>,
+    /// Search around the vector in this file instead of words
+    query_vector: Option<String>,
+    /// Read queries from this file, one per line
+    queries: Option<String>,
+    /// Read queries from stdin, one per line
+    from_stdin: bool,
+    /// Results kept for repeated `--stdin` queries
+    cache: usize,
+    /// Break the best neighbor's similarity down by dimension and input word
+    explain: bool,
+    /// Report the query vector's similarity to each input word
+    operands: bool,
+    /// Only words matching this can be neighbors
+    include: Option<Pattern>,
+    /// Words matching this are never neighbors
+    exclude: Option<Pattern>,
+}
+
+impl QueryArgs {
+    fn batch(&self) -> bool {
+        self.queries.is_some() || self.from_stdin
+    }
+
+    /// Whether a neighbor's score passes `--min-similarity` / `--max-distance`
+    fn passes(&self, score: f32) -> bool {
+        match self.threshold {
+            None => true,
+            Some(min) if self.metric.higher_is_better() => score >= min,
+            Some(max) => score <= max,
+        }
+    }
+}
+
+/// Removes `--metric` (or the `--cosine` / `--euclidean` shorthands), `--top`,
+/// `--rescore`, `--min-similarity` or `--max-distance`, `--output`,
+/// `--allow-inputs`, `--save-vector`, `--normalize`, `--dump-all`, `--query-vector`, `--queries`,
+/// `--stdin`, `--cache`, `--explain`, `--operands`, `--include-pattern` and
+/// `--exclude-pattern` from the arguments
+fn take_query_args(args: &mut Vec<String>, fallback_top: usize) -> Result<QueryArgs, String> {
+    let shorthand = match (take_flag(args, "--cosine"), take_flag(args, "--euclidean")) {
+        (true, true) => return Err("use either --cosine or --euclidean, not both".to_string()),
+        (true, false) => Some(BuiltinMetric::Cosine),
+        (false, true) => Some(BuiltinMetric::Euclidean),
+        (false, false) => None,
+    };
+    let metric = match (take_metric(args)?, shorthand) {
+        (Some(_), Some(_)) => return Err("use either --metric or --cosine / --euclidean, not both".to_string()),
+        (metric, shorthand) => metric.or(shorthand).or(config().metric).unwrap_or_default(),
+    };
+    let top = match take_option(args, "--top") {
+        None => default_top(fallback_top),
+        Some(n) => n.parse().ok().filter(|&n| n > 0).ok_or("--top must be a positive number")?,
+    };
+    // Calibration measures cosine, so its suggestion only stands in for a cosine threshold
+    let min_similarity = take_option(args, "--min-similarity");
+    let calibrated = min_similarity.as_deref() == Some("calibrated");
+    if calibrated && metric != BuiltinMetric::Cosine {
+        return Err("--min-similarity calibrated needs the cosine metric".to_string());
+    }
+    let threshold = match (min_similarity.filter(|_| !calibrated), take_option(args, "--max-distance")) {
+        (None, None) => None,
+        (Some(_), Some(_)) => return Err("use either --min-similarity or --max-distance, not both".to_string()),
+        (Some(_), None) if !metric.higher_is_better() => {
+            return Err(format!("{} is a distance, use --max-distance instead of --min-similarity", metric.name()))
+        }
+        (None, Some(_)) if metric.higher_is_better() => {
+            return Err(format!("{} is a similarity, use --min-similarity instead of --max-distance", metric.name()))
+        }
+        (Some(n), None) => Some(n.parse().map_err(|_| "--min-similarity must be a number")?),
+        (None, Some(n)) => Some(n.parse().map_err(|_| "--max-distance must be a number")?),
+    };
+    let queries = take_option(args, "--queries");
+    let from_stdin = take_flag(args, "--stdin");
+    if queries.is_some() && from_stdin {
+        return Err("use either --queries or --stdin, not both".to_string());
+    }
+    let save_vector = take_option(args, "--save-vector");
+    if save_vector.is_some() && (queries.is_some() || from_stdin) {
+        return Err("--save-vector needs a single query, not --queries or --stdin".to_string());
+    }
+    let normalize = take_flag(args, "--normalize");
+    if normalize && save_vector.is_none() {
+        return Err("--normalize needs --save-vector".to_string());
+    }
+    let dump_all = take_option(args, "--dump-all");
+    if dump_all.is_some() && (queries.is_some() || from_stdin) {
+        return Err("--dump-all needs a single query, not --queries or --stdin".to_string());
+    }
+    let cache = match take_option(args, "--cache") {
+        None => DEFAULT_CAPACITY,
+        Some(_) if !from_stdin => return Err("--cache needs --stdin".to_string()),
+        Some(n) => n.parse().map_err(|_| "--cache must be a number of queries")?,
+    };
+    let query_vector = take_option(args, "--query-vector");
+    if query_vector.is_some() && (queries.is_some() || from_stdin || save_vector.is_some()) {
+        return Err("--query-vector cannot be combined with --queries, --stdin or --save-vector".to_string());
+    }
+    let explain = take_flag(args, "--explain");
+    if explain && (queries.is_some() || from_stdin || query_vector.is_some()) {
+        return Err("--explain needs a query of words, not --queries, --stdin or --query-vector".to_string());
+    }
+    let operands = take_flag(args, "--operands");
+    if operands && (queries.is_some() || from_stdin || query_vector.is_some()) {
+        return Err("--operands needs a query of words, not --queries, --stdin or --query-vector".to_string());
+    }
+    let mut pattern = |name: &str| match take_option(args, name) {
+        None => Ok(None),
+        Some(p) => Pattern::parse(&p).map(Some).map_err(|e| format!("{}: {}", name, e)),
+    };
+    let include = pattern("--include-pattern")?;
+    let exclude = pattern("--exclude-pattern")?;
+    Ok(QueryArgs {
+        metric,
+        top,
+        rescore_top: take_rescore(args)?,
+        threshold,
+        calibrated,
+        output: take_output(args)?,
+        allow_inputs: take_flag(args, "--allow-inputs"),
+        save_vector,
+        normalize,
+        dump_all,
+        query_vector,
+        queries,
+        from_stdin,
+        cache,
+        explain,
+        operands,
+        include,
+        exclude,
+    })
+}
+
+/// Handles `sum <source> word1 word2 ...`: the nearest neighbors of the words' sum
+pub fn run_sum(args: Vec<String>) {
+    run_combined(args, "sum", Combine::Sum, 1);
+}
+
+/// Handles `average <source> word1 word2 ...`: the nearest neighbors of the words' mean
+pub fn run_average(args: Vec<String>) {
+    run_combined(args, "average", Combine::Average, 1);
+}
+
+/// Handles `nearest <source> <word>`: the words closest to one word
+pub fn run_nearest(args: Vec<String>) {
+    run_combined(args, "nearest", Combine::Sum, 10);
+}
+
+fn run_combined(mut args: Vec<String>, command: &str, combine: Combine, default_top: usize) {
+    let words = if command == "nearest" { "<word>" } else { "word1 word2 ..." };
+    let pool = if command == "average" { " [--pool mean|min|max|minmax | --combine <name>]" } else { "" };
+    let usage = format!(
+        "{} <source> {}{} [--metric <name>] [--top N] [--min-similarity S|calibrated | --max-distance D] \
+         [--allow-inputs] [--output text|tsv|json] [--save-vector <out.txt|out.npy> [--normalize]] \
+         [--dump-all <out.csv>] [--explain] [--operands] [--include-pattern <regex>] [--exclude-pattern <regex>] \
+         [--query-vector <file> | --queries <file> | --stdin]",
+        command, words, pool
+    );
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let mut query_args = match take_query_args(&mut args, default_top) {
+        Ok(query_args) => query_args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // These only replace the mean, so only `average` takes them
+    let combine = match (take_option(&mut args, "--pool"), take_option(&mut args, "--combine")) {
+        (None, None) => combine,
+        (Some(_), Some(_)) => {
+            eprintln!("use either --pool or --combine, not both");
+            return;
+        }
+        _ if command != "average" => {
+            eprintln!("--pool and --combine need the average command");
+            return;
+        }
+        (Some(name), None) => match Combine::parse_pool(&name) {
+            Some(pool) => pool,
+            None => {
+                eprintln!("--pool must be one of: {}", Combine::POOLS.join(", "));
+                return;
+            }
+        },
+        (None, Some(name)) => match Combine::parse(&name) {
+            Some(combine) => combine,
+            None => {
+                eprintln!("--combine must be one of: {}", Combine::NAMES.join(", "));
+                return;
+            }
+        },
+    };
+    if query_args.explain && !combine.is_linear() {
+        eprintln!("--explain needs a sum or mean, not pooling or a median");
+        return;
+    }
+    let Some(source) = take_source_or_usage(&mut args, 2, &usage) else { return };
+    let word_count_ok = match (query_args.batch() || query_args.query_vector.is_some(), command) {
+        (true, _) => args.len() == 2,
+        (false, "nearest") => args.len() == 3,
+        (false, _) => args.len() > 2,
+    };
+    if !word_count_ok {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+    if query_args.calibrated {
+        let min = match Calibration::load(&calibration_path(&source)) {
+            Ok(calibration) => calibration.suggested_min_sim,
+            Err(_) => {
+                eprintln!("No calibration for {}; run `{} calibrate {}` first", source, args[0], source);
+                return;
+            }
+        };
+        if !store_args.load.quiet {
+            eprintln!("Using --min-similarity {:.4} from the calibration of {}", min, source);
+        }
+        query_args.threshold = Some(min);
+    }
+
+    // Batch modes and machine-readable output keep stdout for results
+    let quiet = store_args.load.quiet;
+    store_args.load.quiet |= query_args.batch() || query_args.output != Output::Text;
+    // A session takes its first line while the vectors load, and that line waits for them
+    let (glove, session) = if query_args.from_stdin {
+        let opening = open_in_background(&store_args, &source);
+        let mut input = SessionInput::open();
+        let first = match input.read_line(&|_| Vec::new()) {
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Unable to read stdin: {}", e);
+                return;
+            }
+        };
+        let Some(glove) = opening.wait(&store_args, &source, !quiet) else { return };
+        (glove, Some((input, first)))
+    } else {
+        let Some(glove) = open(&store_args, &source) else { return };
+        (glove, None)
+    };
+    let query = Query {
+        glove: glove.as_ref(),
+        filter: match (&query_args.include, &query_args.exclude) {
+            (None, None) => None,
+            (include, exclude) => Some(CandidateFilter::new(glove.as_ref(), include.as_ref(), exclude.as_ref())),
+        },
+        store_args: &store_args,
+        source: &source,
+        exact: OnceCell::new(),
+        combine,
+        args: &query_args,
+    };
+
+    if let Some(path) = &query_args.queries {
+        match fs::read_to_string(path) {
+            Ok(text) => query.run_batch(text.lines()),
+            Err(e) => eprintln!("Unable to read {}: {}", path, e),
+        }
+    } else if let Some((input, first)) = session {
+        query.run_stdin(input, first);
+    } else if let Some(path) = &query_args.query_vector {
+        match load_vector(path) {
+            Ok(vec) => query.run_vector(vec),
+            Err(e) => eprintln!("Unable to read {}: {}", path, e),
+        }
+    } else {
+        query.run_single(Expression::sum(&args[2..]));
+    }
+}
+
+/// What a query searches around
+enum Input {
+    Expression(Expression),
+    /// A raw vector from `--query-vector`
+    Vector(WordVec),
+    /// An expression over session variables, already worked out, and the
+    /// vocabulary words behind it
+    Bound { vector: WordVec, words: Vec<String> },
+}
+
+impl Input {
+    fn vector(&self, store: &dyn EmbeddingStore, combine: Combine) -> Option<WordVec> {
+        match self {
+            Input::Expression(expression) => expression.query_vector(store, combine),
+            Input::Vector(vec) | Input::Bound { vector: vec, .. } => Some(vec.clone()),
+        }
+    }
+
+    /// The words the query was built from; none for a raw vector
+    fn words(&self) -> Vec<&str> {
+        match self {
+            Input::Expression(expression) => expression.words().collect(),
+            Input::Vector(_) => Vec::new(),
+            Input::Bound { words, .. } => words.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Everything needed to answer queries against one loaded store
+struct Query<'a> {
+    glove: &'a dyn EmbeddingStore,
+    /// Rows allowed by `--include-pattern` / `--exclude-pattern`
+    filter: Option<CandidateFilter>,
+    store_args: &'a StoreArgs,
+    source: &'a Source,
+    /// Full-precision store a `--stdin` session re-scores against, opened on
+    /// its first query rather than re-read for every line
+    exact: OnceCell<Result<Box<dyn EmbeddingStore>, String>>,
+    combine: Combine,
+    args: &'a QueryArgs,
+}
+
+impl Query<'_> {
+    /// Finds the nearest neighbors of each input that aren't among its own
+    /// words (unless `--allow-inputs` is given), skipping unknown words and
+    /// neighbors that miss the `--min-similarity` / `--max-distance` bound.
+    /// Reduced-precision shortlists are re-scored with exact vectors when
+    /// asked to, reading them once for every query (or once per `--stdin`
+    /// session).
+    fn answer(&self, inputs: &[Input]) -> Vec<Vec<(String, f32)>> {
+        let (metric, top) = (self.args.metric, self.args.top);
+        // Product quantization is coarse enough that its shortlist is always re-scored
+        let rescore_top = match self.store_args.load.precision {
+            Precision::Pq { .. } if self.args.rescore_top == 0 => PQ_RESCORE,
+            _ => self.args.rescore_top,
+        };
+        let exact_wanted = self.store_args.load.precision != Precision::F32 && rescore_top > 0;
+        let shortlist = if exact_wanted { rescore_top.max(top) } else { top };
+        // Inputs with a vector are searched together, so a batch of queries shares each scan
+        let mut positions = Vec::new();
+        let mut queries = Vec::new();
+        for (i, input) in inputs.iter().enumerate() {
+            let Some(query) = input.vector(self.glove, self.combine) else { continue };
+            let exclude: Vec<String> = if self.args.allow_inputs {
+                Vec::new()
+            } else {
+                // A joined phrase such as `new_york` also excludes `new` and `york`
+                let parts = input.words().into_iter().flat_map(|w| w.split('_').chain([w]));
+                parts.map(str::to_string).collect()
+            };
+            positions.push(i);
+            queries.push((query, exclude));
+        }
+        let mut candidates: Vec<Vec<(String, f32)>> = vec![Vec::new(); inputs.len()];
+        let found = find_nearest_neighbors_filtered(&queries, self.glove, shortlist, &metric, self.filter.as_ref());
+        for (i, found) in positions.into_iter().zip(found) {
+            candidates[i] = found;
+        }
+
+        if exact_wanted {
+            let opened;
+            let exact = if self.args.from_stdin {
+                self.exact.get_or_init(|| self.open_exact(None))
+            } else {
+                let mut words: Vec<String> = candidates.iter().flatten().map(|(w, _)| w.clone()).collect();
+                words.extend(inputs.iter().flat_map(|i| i.words().into_iter().map(str::to_string)));
+                opened = self.open_exact(Some(&words));
+                &opened
+            };
+            match exact {
+                Ok(exact) => {
+                    for (input, found) in inputs.iter().zip(&mut candidates) {
+                        // Rebuild the query from exact vectors too, so the scores are fully f32
+                        if let Some(query) = input.vector(exact.as_ref(), self.combine) {
+                            *found = rescore(&query, std::mem::take(found), exact.as_ref(), &metric);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Unable to re-score with exact vectors: {}", e),
+            }
+        }
+
+        for found in &mut candidates {
+            found.truncate(top);
+            found.retain(|&(_, score)| self.args.passes(score));
+        }
+        candidates
+    }
+
+    /// Opens the source again at full precision, with only `words` if given
+    fn open_exact(&self, words: Option<&[String]>) -> Result<Box<dyn EmbeddingStore>, String> {
+        let store_args = self.store_args;
+        open_exact_subset(&store_args.backend, self.source, words, &store_args.load)
+            .map_err(|e| e.to_string())
+            .and_then(|exact| with_mask(exact, store_args.mask.as_ref()))
+    }
+
+    /// Answers the words given on the command line, reporting phrase lookups
+    /// unless `--quiet`
+    fn run_single(&self, expression: Expression) {
+        let text = self.args.output == Output::Text;
+        let (expression, phrases) = expression.join_phrases(self.glove);
+        for phrase in phrases.iter().filter(|_| !self.store_args.load.quiet) {
+            match phrase {
+                Phrase::Joined { words, token } => println!("Using phrase {} for '{}'", token, words.join(" ")),
+                Phrase::Separate { words } => {
+                    println!("No phrase {} in the vocabulary, using the words separately", words.join("_"))
+                }
+            }
+        }
+        let mut known = 0;
+        for word in expression.words() {
+            if self.glove.contains(word) {
+                known += 1;
+                // A word found through `--lemmatize` comes back as its lemma,
+                // and one known through `--subwords` has no row at all
+                let quiet = self.store_args.load.quiet;
+                match self.glove.id(word).and_then(|id| self.glove.get_by_id(id)) {
+                    Some((found, _)) if found != word && !quiet => println!("Using {} for '{}'", found, word),
+                    None if !quiet => println!("Approximating '{}' from its character n-grams", word),
+                    _ => {}
+                }
+            } else if text {
+                println!("{}", skipping_unknown(self.glove, word));
+            } else {
+                eprintln!("{}", skipping_unknown(self.glove, word));
+            }
+        }
+        if known == 0 {
+            if text {
+                println!("No valid input words found in the database.");
+            } else {
+                eprintln!("No valid input words found in the database.");
+            }
+            return;
+        }
+
+        if let Some(path) = &self.args.save_vector {
+            let mut vec = expression.combine(self.glove, self.combine).unwrap_or_default();
+            if self.args.normalize {
+                normalize(&mut vec);
+            }
+            match save_vector(path, &vector_name(&expression), &vec) {
+                Ok(()) if text => println!("Saved the query vector to {}", path),
+                Ok(()) => {}
+                Err(e) => eprintln!("Unable to save the query vector to {}: {}", path, e),
+            }
+        }
+
+        if self.args.operands {
+            self.print_operands(&expression);
+        }
+        self.dump_all(&Input::Expression(expression.clone()));
+        let answers = self.answer(&[Input::Expression(expression.clone())]).pop().unwrap_or_default();
+        self.print_answers(&answers);
+        if let (true, Some((best, _))) = (self.args.explain, answers.first()) {
+            self.print_explanation(&expression, best);
+        }
+    }
+
+    /// Prints lines about a query: with the results for text output, and on
+    /// stderr otherwise so stdout stays machine-readable
+    fn print_notes(&self, lines: &[String]) {
+        for line in lines {
+            match self.args.output {
+                Output::Text => println!("{}", line),
+                _ => eprintln!("{}", line),
+            }
+        }
+    }
+
+    /// Prints the cosine similarity of the query vector to each input word,
+    /// on stderr unless the output is text
+    fn print_operands(&self, expression: &Expression) {
+        let mut lines = vec!["Similarity of the query vector to each input word:".to_string()];
+        for (word, similarity) in operand_similarities(self.glove, expression, self.combine) {
+            lines.push(format!("  {} ({:.4})", word, similarity));
+        }
+        self.print_notes(&lines);
+    }
+
+    /// Prints what each input word and the leading dimensions add to the
+    /// cosine similarity of `neighbor`, on stderr unless the output is text
+    fn print_explanation(&self, expression: &Expression, neighbor: &str) {
+        let Some(explanation) = explain(self.glove, expression, self.combine, neighbor, EXPLAIN_DIMENSIONS) else {
+            return;
+        };
+        let mut lines = vec![format!("Why '{}' (cosine similarity: {:.4}):", neighbor, explanation.cosine)];
+        lines.push("  by input word:".to_string());
+        for (word, share) in &explanation.terms {
+            lines.push(format!("    {:+.4}  {}", share, word));
+        }
+        lines.push("  by dimension:".to_string());
+        for dimension in &explanation.dimensions {
+            let terms: Vec<String> =
+                dimension.terms.iter().map(|(word, part)| format!("{} {:+.4}", word, part)).collect();
+            lines.push(format!("    {:+.4}  dim {} ({})", dimension.share, dimension.dim, terms.join(", ")));
+        }
+        self.print_notes(&lines);
+    }
+
+    /// Answers `--query-vector`, a vector read from a file
+    fn run_vector(&self, vec: WordVec) {
+        if vec.len() != self.glove.dim() {
+            eprintln!("The query vector has {} dimensions, the vectors {}", vec.len(), self.glove.dim());
+            return;
+        }
+        let input = Input::Vector(vec);
+        self.dump_all(&input);
+        self.print_answers(&self.answer(&[input]).pop().unwrap_or_default());
+    }
+
+    /// Writes every word ranked by its score against the query to the
+    /// `--dump-all` file, as CSV rows of rank, word and score
+    fn dump_all(&self, input: &Input) {
+        let Some(path) = &self.args.dump_all else { return };
+        let Some(query) = input.vector(self.glove, self.combine) else { return };
+        let ranked = rank_all(&query, self.glove, &self.args.metric);
+        let write = || -> io::Result<()> {
+            let mut out = BufWriter::new(File::create(path)?);
+            writeln!(out, "rank,word,{}", self.args.metric.score_label())?;
+            for (rank, (id, score)) in ranked.iter().enumerate() {
+                let Some((word, _)) = self.glove.get_by_id(*id) else { continue };
+                writeln!(out, "{},{},{}", rank + 1, csv_field(&word), score)?;
+            }
+            out.flush()
+        };
+        match write() {
+            Ok(()) if self.args.output == Output::Text => println!("Wrote {} scores to {}", ranked.len(), path),
+            Ok(()) => {}
+            Err(e) => eprintln!("Unable to write {}: {}", path, e),
+        }
+    }
+
+    /// Prints the results of a single query
+    fn print_answers(&self, answers: &[(String, f32)]) {
+        let label = self.args.metric.score_label();
+        match self.args.output {
+            Output::Text if answers.is_empty() && self.args.threshold.is_some() => {
+                println!("No sufficiently similar word found.")
+            }
+            Output::Text if answers.is_empty() => println!("No nearest neighbor found."),
+            Output::Text if self.args.top == 1 => {
+                println!("Nearest neighbor: {} ({}: {:.4})", answers[0].0, label, answers[0].1)
+            }
+            Output::Text => {
+                println!("Nearest neighbors:");
+                for (word, score) in answers {
+                    println!("  {} ({}: {:.4})", word, label, score);
+                }
+            }
+            Output::Tsv => {
+                for (word, score) in answers {
+                    println!("{}\t{:.4}", word, score);
+                }
+            }
+            Output::Json => println!("{}", json_results(answers)),
+        }
+    }
+
+    /// Answers every line of a queries file, re-scoring them all in one pass
+    fn run_batch<'l>(&self, lines: impl Iterator<Item = &'l str>) {
+        let lines: Vec<&str> = lines.map(str::trim).filter(|l| !l.is_empty()).collect();
+        let parsed: Vec<Option<Expression>> = lines.iter().map(|l| self.parse_line(l, self.glove)).collect();
+        let inputs: Vec<Input> = parsed.iter().flatten().cloned().map(Input::Expression).collect();
+        let mut answers = self.answer(&inputs).into_iter();
+        for (line, expression) in lines.iter().zip(&parsed) {
+            let found = expression.as_ref().and_then(|_| answers.next()).unwrap_or_default();
+            println!("{}", self.row(line, &found));
+        }
+    }
+
+    /// Answers each line of stdin as it arrives, so the tool can also be driven
+    /// interactively. Results are cached by expression, metric and `--top`;
+    /// `let <name> = <expression>` names a vector for later lines to use, and
+    /// the session commands are `:cache-stats` (reports the cache on stderr),
+    /// `:source <file>` and `:save <file>`. `first` is the line read while
+    /// the vectors loaded.
+    fn run_stdin(&self, mut input: SessionInput, first: String) {
+        let mut session = Session {
+            cache: LruCache::new(self.args.cache),
+            bindings: Bindings::default(),
+            transcript: Vec::new(),
+        };
+        self.run_session_line(&mut session, &first, 0);
+        loop {
+            match input.read_line(&|word| self.completions(word, &session.bindings)) {
+                Ok(Some(line)) => self.run_session_line(&mut session, &line, 0),
+                Ok(None) => return,
+                Err(e) => {
+                    eprintln!("Unable to read stdin: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Runs one line of a `--stdin` session or of a script it sources, `depth`
+    /// scripts deep. Lines starting with `#` are comments.
+    fn run_session_line(&self, session: &mut Session, line: &str, depth: usize) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim();
+        let output = match command {
+            ":source" | ":save" if argument.is_empty() => {
+                eprintln!("Usage: {} <file>", command);
+                return;
+            }
+            ":source" if depth >= MAX_SOURCE_DEPTH => {
+                eprintln!("{}: scripts are sourced too deeply (more than {})", argument, MAX_SOURCE_DEPTH);
+                return;
+            }
+            ":source" => {
+                match fs::read_to_string(argument) {
+                    Ok(text) => text.lines().for_each(|l| self.run_session_line(session, l, depth + 1)),
+                    Err(e) => eprintln!("Unable to read {}: {}", argument, e),
+                }
+                return;
+            }
+            ":save" => {
+                match fs::write(argument, session.script()) {
+                    Ok(()) => eprintln!("Saved {} lines to {}", session.transcript.len(), argument),
+                    Err(e) => eprintln!("Unable to write {}: {}", argument, e),
+                }
+                return;
+            }
+            ":cache-stats" => {
+                let stats = format!("Cache: {}", session.cache.stats());
+                eprintln!("{}", stats);
+                vec![stats]
+            }
+            _ => match parse_let(line) {
+                Some(binding) => {
+                    let notes = self.bind(&mut session.bindings, binding);
+                    notes.iter().for_each(|note| eprintln!("{}", note));
+                    notes
+                }
+                None => {
+                    let row = self.row(line, &self.session_query(session, line));
+                    println!("{}", row);
+                    vec![row]
+                }
+            },
+        };
+        session.transcript.push((line.to_string(), output));
+    }
+
+    /// Answers a query line of a session, from the cache when it uses no variables
+    fn session_query(&self, session: &mut Session, line: &str) -> Vec<(String, f32)> {
+        let bound = BoundStore::new(self.glove, &session.bindings);
+        match self.parse_line(line, &bound) {
+            // Variables can be rebound, so queries using them are not cached
+            Some(expression) if session.bindings.used_by(&expression) => {
+                let words = session.bindings.expand(&expression);
+                match expression.query_vector(&bound, self.combine) {
+                    Some(vector) => self.answer(&[Input::Bound { vector, words }]).pop().unwrap_or_default(),
+                    None => Vec::new(),
+                }
+            }
+            Some(expression) => {
+                let key = (expression.to_string(), self.args.metric.name(), self.args.top);
+                match session.cache.get(&key) {
+                    Some(found) => found,
+                    None => {
+                        let found = self.answer(&[Input::Expression(expression)]).pop().unwrap_or_default();
+                        session.cache.put(key, found.clone());
+                        found
+                    }
+                }
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// The session commands, variables and vocabulary words starting with
+    /// `word`, for `Tab` completion
+    fn completions(&self, word: &str, bindings: &Bindings) -> Vec<String> {
+        if word.is_empty() {
+            return Vec::new();
+        }
+        let commands = SESSION_COMMANDS.iter().copied().chain(bindings.names());
+        let mut found: Vec<String> = commands.filter(|c| c.starts_with(word)).map(str::to_string).collect();
+        if !word.starts_with(':') {
+            let words = self.glove.iter().map(|(w, _)| w).filter(|w| w.starts_with(word));
+            found.extend(words.map(|w| w.into_owned()));
+        }
+        found
+    }
+
+    /// Defines a session variable from a parsed `let` line, returning the
+    /// lines that report it, which go to stderr so stdout keeps only result rows
+    fn bind(&self, bindings: &mut Bindings, parsed: Result<(String, Expression), String>) -> Vec<String> {
+        let (name, expression) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => return vec![e],
+        };
+        let bound = BoundStore::new(self.glove, bindings);
+        let (expression, _) = expression.join_phrases(&bound);
+        let mut notes: Vec<String> = expression
+            .words()
+            .filter(|w| !bound.contains(w))
+            .map(|word| format!("{}: skipping unknown word: {}", name, word))
+            .collect();
+        match expression.query_vector(&bound, self.combine) {
+            Some(vector) => {
+                let words = bindings.expand(&expression);
+                bindings.insert(name.clone(), Binding { vector, words });
+                notes.push(format!("{} = {}", name, expression));
+            }
+            None => notes.push(format!("{}: no known words, not defined", name)),
+        }
+        notes
+    }
+
+    /// Parses one batch query, reporting syntax errors and words `store`
+    /// does not know on stderr
+    fn parse_line(&self, line: &str, store: &dyn EmbeddingStore) -> Option<Expression> {
+        match Expression::parse(line) {
+            Ok(expression) => {
+                let (expression, _) = expression.join_phrases(store);
+                for word in expression.words().filter(|w| !store.contains(w)) {
+                    eprintln!("{}: skipping unknown word: {}", line, word);
+                }
+                Some(expression)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// A batch result row: the query followed by each neighbor and its score,
+    /// tab-separated (empty fields when there is no answer), or one JSON
+    /// object per line
+    fn row(&self, line: &str, found: &[(String, f32)]) -> String {
+        if self.args.output == Output::Json {
+            return format!("{{\"query\": {}, \"results\": {}}}", json_string(line), json_results(found));
+        }
+        if found.is_empty() {
+            return format!("{}\t\t", line);
+        }
+        let fields: Vec<String> = found.iter().map(|(w, s)| format!("{}\t{:.4}", w, s)).collect();
+        format!("{}\t{}", line, fields.join("\t"))
+    }
+}
+
+/// A query's normalized expression, metric and `--top`
+type CacheKey = (String, &'static str, usize);
+
+/// Where `--stdin` session lines come from: at a terminal, a line editor with
+/// history and completion, else stdin as it is piped in
+struct SessionInput {
+    editor: Option<LineEditor>,
+    piped: Option<Lines<StdinLock<'static>>>,
+}
+
+impl SessionInput {
+    fn open() -> SessionInput {
+        SessionInput { editor: LineEditor::open(), piped: None }
+    }
+
+    /// The next line, completing words with `complete` at a terminal
+    fn read_line(&mut self, complete: &dyn Fn(&str) -> Vec<String>) -> io::Result<Option<String>> {
+        match &mut self.editor {
+            Some(editor) => editor.read_line(PROMPT, complete),
+            None => self.piped.get_or_insert_with(|| io::stdin().lock().lines()).next().transpose(),
+        }
+    }
+}
+
+/// The state of a `--stdin` session
+struct Session {
+    cache: LruCache<CacheKey, Vec<(String, f32)>>,
+    bindings: Bindings,
+    /// Each line run so far, with what it printed
+    transcript: Vec<(String, Vec<String>)>,
+}
+
+impl Session {
+    /// The session as a script `:source` can replay: each line as it was
+    /// run, followed by what it printed as `#` comments
+    fn script(&self) -> String {
+        let mut script = String::new();
+        for (line, output) in &self.transcript {
+            script.push_str(line);
+            script.push('\n');
+            for printed in output {
+                script.push_str(&format!("# {}\n", printed));
+            }
+        }
+        script
+    }
+}
+
+/// Names a saved query vector after its expression, e.g. `king-man+woman`
+fn vector_name(expression: &Expression) -> String {
+    let mut name = String::new();
+    for (i, (sign, word)) in expression.terms.iter().enumerate() {
+        if *sign < 0.0 {
+            name.push('-');
+        } else if i > 0 {
+            name.push('+');
+        }
+        name.push_str(word);
+    }
+    name
+}
+
+/// Handles `similarity <source> <word1> <word2>`: the score of two words under
+/// `--metric`, or under every metric with `--all-metrics`
+pub fn run_similarity(mut args: Vec<String>) {
+    let usage = "similarity <source> <word1> <word2> [--metric <name> | --all-metrics] [--output text|tsv|json]";
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let all_metrics = take_flag(&mut args, "--all-metrics");
+    let (metric, output) = match (take_metric(&mut args), take_output(&mut args)) {
+        (Ok(Some(_)), _) if all_metrics => {
+            eprintln!("use either --metric or --all-metrics, not both");
+            return;
+        }
+        (Ok(metric), Ok(output)) => (metric.or(config().metric).unwrap_or_default(), output),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    if args.len() != 4 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    store_args.load.quiet |= output != Output::Text;
+    let Some(glove) = open(&store_args, &source) else { return };
+    let (a, b) = (&args[2], &args[3]);
+    let metrics = if all_metrics { BuiltinMetric::ALL } else { std::slice::from_ref(&metric) };
+    let scores = match metrics.iter().map(|&m| glove.similarity(a, b, &m)).collect::<glove::Result<Vec<f32>>>() {
+        Ok(scores) => scores,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    match output {
+        Output::Text => {
+            for (metric, score) in metrics.iter().zip(&scores) {
+                println!("{} between {} and {} ({}): {:.4}", metric.score_label(), a, b, metric.name(), score);
+            }
+        }
+        // One metric keeps the original three columns; several add the metric's name
+        Output::Tsv if !all_metrics => println!("{}\t{}\t{:.4}", a, b, scores[0]),
+        Output::Tsv => {
+            for (metric, score) in metrics.iter().zip(&scores) {
+                println!("{}\t{}\t{}\t{:.4}", a, b, metric.name(), score);
+            }
+        }
+        Output::Json if !all_metrics => println!(
+            "{{\"word1\": {}, \"word2\": {}, \"metric\": {}, \"score\": {}}}",
+            json_string(a),
+            json_string(b),
+            json_string(metric.name()),
+            json_number(scores[0])
+        ),
+        Output::Json => {
+            let fields: Vec<String> = metrics
+                .iter()
+                .zip(&scores)
+                .map(|(metric, &score)| format!("{}: {}", json_string(metric.name()), json_number(score)))
+                .collect();
+            println!(
+                "{{\"word1\": {}, \"word2\": {}, \"scores\": {{{}}}}}",
+                json_string(a),
+                json_string(b),
+                fields.join(", ")
+            );
+        }
+    }
+}
+
+/// Formats `matrix` prints in: the usual `--output` ones plus `csv`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MatrixOutput {
+    Table,
+    Csv,
+    Tsv,
+    Json,
+}
+
+/// Handles `matrix <source> w1 w2 w3 ...`: the score of every pair of words
+/// under `--metric`, as a table, CSV, TSV or JSON
+pub fn run_matrix(mut args: Vec<String>) {
+    let usage = "matrix <source> word1 word2 ... [--metric <name>] [--output text|csv|tsv|json]";
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let output = match take_option(&mut args, "--output").as_deref() {
+        None | Some("text") => MatrixOutput::Table,
+        Some("csv") => MatrixOutput::Csv,
+        Some("tsv") => MatrixOutput::Tsv,
+        Some("json") => MatrixOutput::Json,
+        Some(_) => {
+            eprintln!("--output must be text, csv, tsv or json");
+            return;
+        }
+    };
+    let metric = match take_metric(&mut args) {
+        Ok(metric) => metric.or(config().metric).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    if args.len() < 4 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    store_args.load.quiet |= output != MatrixOutput::Table;
+    let Some(glove) = open(&store_args, &source) else { return };
+    let mut words = Vec::new();
+    let mut vectors = Vec::new();
+    for word in &args[2..] {
+        match glove.get(word) {
+            Some(vec) => {
+                words.push(word.as_str());
+                vectors.push(vec);
+            }
+            None => eprintln!("{}", skipping_unknown(glove.as_ref(), word)),
+        }
+    }
+    if words.is_empty() {
+        eprintln!("No valid input words found in the database.");
+        return;
+    }
+    let rows: Vec<Vec<f32>> = vectors.iter().map(|a| vectors.iter().map(|b| metric.score(a, b)).collect()).collect();
+
+    match output {
+        MatrixOutput::Table => {
+            println!("{} ({}):", metric.score_label(), metric.name());
+            let width = words.iter().map(|w| w.chars().count()).max().unwrap_or(0);
+            let column = width.max(7);
+            let header: Vec<String> = words.iter().map(|w| format!("{:>column$}", w)).collect();
+            println!("{:width$}  {}", "", header.join(" "));
+            for (word, row) in words.iter().zip(&rows) {
+                let cells: Vec<String> = row.iter().map(|s| format!("{:>column$.4}", s)).collect();
+                println!("{:width$}  {}", word, cells.join(" "));
+            }
+        }
+        MatrixOutput::Csv | MatrixOutput::Tsv => {
+            let (separator, quote): (&str, fn(&str) -> String) = if output == MatrixOutput::Csv {
+                (",", csv_field)
+            } else {
+                ("\t", str::to_string)
+            };
+            let header: Vec<String> = words.iter().map(|w| quote(w)).collect();
+            println!("{}{}", separator, header.join(separator));
+            for (word, row) in words.iter().zip(&rows) {
+                let cells: Vec<String> = row.iter().map(|s| format!("{:.4}", s)).collect();
+                println!("{}{}{}", quote(word), separator, cells.join(separator));
+            }
+        }
+        MatrixOutput::Json => {
+            let names: Vec<String> = words.iter().map(|w| json_string(w)).collect();
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|row| format!("[{}]", row.iter().map(|&s| json_number(s)).collect::<Vec<_>>().join(", ")))
+                .collect();
+            println!(
+                "{{\"words\": [{}], \"metric\": {}, \"matrix\": [{}]}}",
+                names.join(", "),
+                json_string(metric.name()),
+                rows.join(", ")
+            );
+        }
+    }
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Handles `bench <source>`: times random queries under each search
+/// configuration and reports latency percentiles, throughput and recall
+pub fn run_bench(mut args: Vec<String>) {
+    let usage = format!(
+        "bench <source> [--configs {}] [--samples N] [--top K] [--seed S] [--index <name> <parameters>] \
+         [--backend <name>]",
+        CONFIGS.join(",")
+    );
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let names = take_option(&mut args, "--configs").unwrap_or_else(|| CONFIGS.join(","));
+    let samples = take_option(&mut args, "--samples").map_or(Ok(200), |n| n.parse::<usize>());
+    let top = take_option(&mut args, "--top").map_or(Ok(10), |n| n.parse::<usize>());
+    let seed = take_option(&mut args, "--seed").map_or(Ok(0), |s| s.parse::<u64>());
+    let Some(source) = take_source_or_usage(&mut args, 2, &usage) else { return };
+    let (Ok(samples), Ok(top), Ok(seed), 2) = (samples, top, seed, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let mut configs = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match Config::parse(name) {
+            Some(config) => configs.push(config),
+            None => {
+                eprintln!("--configs takes a list of: {}", CONFIGS.join(", "));
+                return;
+            }
+        }
+    }
+    // An index given with `--index` replaces that configuration's defaults
+    if let Some(spec) = store_args.index.take() {
+        let custom = Config::Index(spec);
+        for config in configs.iter_mut().filter(|c| c.name() == custom.name()) {
+            *config = custom.clone();
+        }
+    }
+
+    let Some(glove) = open(&store_args, &source) else { return };
+    if glove.is_empty() {
+        eprintln!("{} has no vectors", source);
+        return;
+    }
+    let queries = sample_queries(glove.as_ref(), samples, &mut Rng::new(seed));
+    let truth = exact_answers(glove.as_ref(), &queries, top);
+    println!("{} queries, top {}", queries.len(), top);
+    println!(
+        "{:<8} {:>10} {:>10} {:>10} {:>10} {:>12} {:>8}",
+        "config", "build", "p50", "p95", "p99", "queries/s", "recall"
+    );
+    let ms = |d: std::time::Duration| format!("{:.3}ms", d.as_secs_f64() * 1000.0);
+    for config in &configs {
+        match bench(glove.as_ref(), config, &queries, &truth, top) {
+            Ok(result) => println!(
+                "{:<8} {:>10} {:>10} {:>10} {:>10} {:>12.1} {:>8.3}",
+                config.name(),
+                result.build.map_or("-".to_string(), |d| format!("{:.2}s", d.as_secs_f64())),
+                ms(result.percentile(0.5)),
+                ms(result.percentile(0.95)),
+                ms(result.percentile(0.99)),
+                result.throughput(),
+                result.recall
+            ),
+            Err(e) => eprintln!("{}: {}", config.name(), e),
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Line editing for interactive `--stdin` sessions: cursor movement, history
+//! on the arrow keys kept between sessions, `Ctrl-R` reverse search, and
+//! `Tab` completion.
+//!
+//! Like `tui`, it drives the terminal with ANSI escape sequences and `stty`,
+//! switching to raw mode only while a line is being read, so results print
+//! normally. The prompt and the line go to stderr, keeping stdout for results.
+//! Where `stty` is missing, lines are read plainly instead.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Lines kept in the history file
+const HISTORY_SIZE: usize = 1000;
+
+/// Completions listed under the line when `Tab` cannot pick one
+const LISTED_COMPLETIONS: usize = 40;
+
+/// `$XDG_STATE_HOME/glove-demo/history`, or `~/.local/state/glove-demo/history`
+fn history_path() -> PathBuf {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".local").join("state"),
+    };
+    base.join("glove-demo").join("history")
+}
+
+/// Runs `stty` on the terminal on stdin, returning what it prints
+pub fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Raw mode for the duration of one line, restored when dropped
+struct RawLine {
+    saved: String,
+}
+
+impl RawLine {
+    fn enter() -> io::Result<RawLine> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Ok(RawLine { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for RawLine {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// A key press decoded from the raw input
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    Tab,
+    /// `Ctrl-R`
+    Search,
+    /// `Ctrl-U`: delete up to the cursor
+    KillStart,
+    /// `Ctrl-K`: delete from the cursor
+    KillEnd,
+    /// `Ctrl-W`: delete the word before the cursor
+    KillWord,
+    /// `Ctrl-C`
+    Interrupt,
+    /// `Ctrl-D`
+    Eof,
+    /// `Esc` or `Ctrl-G`
+    Cancel,
+}
+
+/// Splits a chunk of raw input into keys; unknown escape sequences are dropped
+fn decode_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' if matches!(chars.peek(), Some('[') | Some('O')) => {
+                chars.next();
+                let mut sequence = String::new();
+                while let Some(&c) = chars.peek() {
+                    chars.next();
+                    sequence.push(c);
+                    if c.is_ascii_alphabetic() || c == '~' {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "A" => Key::Up,
+                    "B" => Key::Down,
+                    "C" => Key::Right,
+                    "D" => Key::Left,
+                    "H" | "1~" | "7~" => Key::Home,
+                    "F" | "4~" | "8~" => Key::End,
+                    "3~" => Key::Delete,
+                    _ => continue,
+                }
+            }
+            '\x1b' | '\x07' => Key::Cancel,
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\t' => Key::Tab,
+            '\x01' => Key::Home,
+            '\x05' => Key::End,
+            '\x02' => Key::Left,
+            '\x06' => Key::Right,
+            '\x10' => Key::Up,
+            '\x0e' => Key::Down,
+            '\x12' => Key::Search,
+            '\x15' => Key::KillStart,
+            '\x0b' => Key::KillEnd,
+            '\x17' => Key::KillWord,
+            '\x03' => Key::Interrupt,
+            '\x04' => Key::Eof,
+            c if !c.is_control() => Key::Char(c),
+            _ => continue,
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// The longest prefix shared by every candidate
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else { return String::new() };
+    let mut prefix: &str = first;
+    for candidate in &candidates[1..] {
+        let shared = prefix.chars().zip(candidate.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a.len_utf8());
+        prefix = &prefix[..shared.sum()];
+    }
+    prefix.to_string()
+}
+
+/// A `Ctrl-R` search in progress
+struct Search {
+    query: String,
+    /// The history entry matched, if any
+    found: Option<usize>,
+}
+
+/// Reads lines from the terminal with editing, history and completion
+pub struct LineEditor {
+    history: Vec<String>,
+    path: PathBuf,
+}
+
+impl LineEditor {
+    /// An editor over the history file, or `None` unless stdin and stderr
+    /// are both terminals that `stty` can drive
+    pub fn open() -> Option<LineEditor> {
+        if !io::stdin().is_terminal() || !io::stderr().is_terminal() || stty(&["-g"]).is_err() {
+            return None;
+        }
+        let path = history_path();
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let mut history: Vec<String> = text.lines().filter(|l| !l.trim().is_empty()).map(str::to_string).collect();
+        let excess = history.len().saturating_sub(HISTORY_SIZE);
+        history.drain(..excess);
+        if excess > 0 {
+            let _ = fs::write(&path, history.iter().map(|l| format!("{}\n", l)).collect::<String>());
+        }
+        Some(LineEditor { history, path })
+    }
+
+    /// Adds a line to the history and appends it to the history file;
+    /// failing to save it is not worth interrupting the session for
+    fn remember(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&self.path) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    /// Reads one line after showing `prompt`; `None` at `Ctrl-D` on an empty
+    /// line. `complete` lists the completions of the word before the cursor.
+    pub fn read_line(&mut self, prompt: &str, complete: &dyn Fn(&str) -> Vec<String>) -> io::Result<Option<String>> {
+        let raw = RawLine::enter()?;
+        let mut stdin = io::stdin().lock();
+        let mut buffer = [0; 64];
+        // The line as characters, so the cursor moves by whole characters
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // Position in the history; `history.len()` is the line being typed
+        let mut position = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+        let mut search: Option<Search> = None;
+        loop {
+            match &search {
+                Some(search) => {
+                    let found = search.found.map_or("", |i| self.history[i].as_str());
+                    draw(&format!("(reverse-i-search)`{}': ", search.query), &found.chars().collect::<Vec<_>>(), 0)?;
+                }
+                None => draw(prompt, &line, cursor)?,
+            }
+            let read = stdin.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            for key in decode_keys(&buffer[..read]) {
+                if let Some(active) = &mut search {
+                    let restart = match key {
+                        Key::Char(c) => {
+                            active.query.push(c);
+                            self.history.len()
+                        }
+                        Key::Backspace => {
+                            active.query.pop();
+                            self.history.len()
+                        }
+                        Key::Search => active.found.unwrap_or(self.history.len()),
+                        Key::Cancel | Key::Interrupt => {
+                            search = None;
+                            continue;
+                        }
+                        key => {
+                            if let Some(i) = active.found {
+                                line = self.history[i].chars().collect();
+                                cursor = line.len();
+                            }
+                            search = None;
+                            if key != Key::Enter {
+                                continue;
+                            }
+                            finish_line(prompt, &line)?;
+                            drop(raw);
+                            let line: String = line.into_iter().collect();
+                            self.remember(&line);
+                            return Ok(Some(line));
+                        }
+                    };
+                    let query = &active.query;
+                    let found = self.history[..restart].iter().rposition(|l| l.contains(query.as_str()));
+                    active.found = found.or(active.found.filter(|_| !query.is_empty()));
+                    continue;
+                }
+                match key {
+                    Key::Char(c) => {
+                        line.insert(cursor, c);
+                        cursor += 1;
+                    }
+                    Key::Enter => {
+                        finish_line(prompt, &line)?;
+                        drop(raw);
+                        let line: String = line.into_iter().collect();
+                        self.remember(&line);
+                        return Ok(Some(line));
+                    }
+                    Key::Backspace if cursor > 0 => {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                    Key::Delete if cursor < line.len() => {
+                        line.remove(cursor);
+                    }
+                    Key::Left => cursor = cursor.saturating_sub(1),
+                    Key::Right => cursor = (cursor + 1).min(line.len()),
+                    Key::Home => cursor = 0,
+                    Key::End => cursor = line.len(),
+                    Key::Up if position > 0 => {
+                        if position == self.history.len() {
+                            draft = line.clone();
+                        }
+                        position -= 1;
+                        line = self.history[position].chars().collect();
+                        cursor = line.len();
+                    }
+                    Key::Down if position < self.history.len() => {
+                        position += 1;
+                        line = match self.history.get(position) {
+                            Some(entry) => entry.chars().collect(),
+                            None => draft.clone(),
+                        };
+                        cursor = line.len();
+                    }
+                    Key::Tab => cursor = self.complete(prompt, &mut line, cursor, complete)?,
+                    Key::Search => search = Some(Search { query: String::new(), found: None }),
+                    Key::KillStart => {
+                        line.drain(..cursor);
+                        cursor = 0;
+                    }
+                    Key::KillEnd => line.truncate(cursor),
+                    Key::KillWord => {
+                        let end = cursor;
+                        while cursor > 0 && line[cursor - 1].is_whitespace() {
+                            cursor -= 1;
+                        }
+                        while cursor > 0 && !line[cursor - 1].is_whitespace() {
+                            cursor -= 1;
+                        }
+                        line.drain(cursor..end);
+                    }
+                    Key::Interrupt => {
+                        eprint!("^C\r\n");
+                        line.clear();
+                        cursor = 0;
+                        position = self.history.len();
+                    }
+                    Key::Eof if line.is_empty() => {
+                        eprint!("\r\n");
+                        return Ok(None);
+                    }
+                    Key::Eof if cursor < line.len() => {
+                        line.remove(cursor);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Completes the word before the cursor: fully when there is one
+    /// candidate, as far as the candidates agree otherwise, and lists them
+    /// when they do not agree any further. Returns the new cursor.
+    fn complete(
+        &self,
+        prompt: &str,
+        line: &mut Vec<char>,
+        cursor: usize,
+        complete: &dyn Fn(&str) -> Vec<String>,
+    ) -> io::Result<usize> {
+        let mut start = cursor;
+        while start > 0 && !line[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        // An operator attached to the word (`-man`) is not part of it
+        if start < cursor && matches!(line[start], '+' | '-') {
+            start += 1;
+        }
+        let word: String = line[start..cursor].iter().collect();
+        let mut candidates = complete(&word);
+        candidates.sort();
+        candidates.dedup();
+        let insert = match candidates.len() {
+            0 => return Ok(cursor),
+            1 => format!("{} ", candidates[0]),
+            _ => common_prefix(&candidates),
+        };
+        if candidates.len() > 1 && insert.chars().count() <= word.chars().count() {
+            let mut listing = candidates.iter().take(LISTED_COMPLETIONS).cloned().collect::<Vec<_>>().join("  ");
+            if candidates.len() > LISTED_COMPLETIONS {
+                listing.push_str(&format!("  ... ({} more)", candidates.len() - LISTED_COMPLETIONS));
+            }
+            finish_line(prompt, line)?;
+            eprint!("{}\r\n", listing);
+            return Ok(cursor);
+        }
+        let inserted: Vec<char> = insert.chars().collect();
+        let count = inserted.len();
+        line.splice(start..cursor, inserted);
+        Ok(start + count)
+    }
+}
+
+/// Redraws the line with the cursor at `cursor`
+fn draw(prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+    let text: String = line.iter().collect();
+    let mut out = io::stderr().lock();
+    write!(out, "\r\x1b[K{}{}", prompt, text)?;
+    if cursor < line.len() {
+        write!(out, "\x1b[{}D", line.len() - cursor)?;
+    }
+    out.flush()
+}
+
+/// Leaves the line as it is and moves to the next one
+fn finish_line(prompt: &str, line: &[char]) -> io::Result<()> {
+    draw(prompt, line, line.len())?;
+    eprint!("\r\n");
+    Ok(())
+}
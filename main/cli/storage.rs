@@ -0,0 +1,745 @@
+//! Commands that convert, export, serve or fetch vector files.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::net::TcpListener;
+
+use glove::all_pairs::all_pairs;
+use glove::align::{mean_similarity, paired_vectors, procrustes, read_dictionary, shared_words, transform};
+use glove::annoy::AnnoyIndex;
+use glove::binary::write_binary;
+use glove::cache::DEFAULT_CAPACITY;
+use glove::cooccur::{
+    build_vocabulary, count_cooccurrences, read_cooccurrences, write_cooccurrences, CooccurOptions, Cooccurrence,
+    Vocabulary, Weighting,
+};
+use glove::counterfit::{counterfit, DEFAULT_EPOCHS};
+use glove::debias::{hard_debias, read_pairs, Neutralize};
+use glove::export::{export_projector, write_glove_text, write_npy, write_npz};
+use glove::graph::{seed_graph, vocabulary_graph, write_dot, write_gexf};
+use glove::hdf5::write_hdf5;
+use glove::index::IndexSpec;
+use glove::models::{find_model, MODELS};
+use glove::merge::Merger;
+use glove::parquet::write_parquet;
+use glove::pattern::Pattern;
+use glove::pca::{reduce, SAMPLE_ROWS};
+use glove::remote::RemoteStore;
+use glove::retrofit::{read_lexicon, retrofit, DEFAULT_ITERATIONS};
+use glove::safetensors::write_safetensors;
+use glove::train::{into_store, train, TrainOptions};
+use glove::{DenseStore, EmbeddingStore, Source};
+use glove::args::{take_flag, take_list, take_option};
+
+use super::{default_top, open, open_in_background, skipping_unknown, take_source_or_usage, take_store_args};
+
+/// Handles `export <format> ...` commands
+pub fn run_export(mut args: Vec<String>) {
+    // Usage: main export projector glove.txt tensor.tsv metadata.tsv
+    //        main export parquet|npy|npz|safetensors|hdf5 glove.txt <out>
+    //        main export annoy glove.txt vectors.ann --trees 10
+    let usage = "export projector <source> <tensor.tsv> <metadata.tsv> \
+                 | export parquet|npy|npz|safetensors|hdf5 <source> <out> \
+                 | export annoy <source> <out.ann> [--trees N] \
+                 [--backend <name>]";
+    // Taken first, as `--trees` is otherwise an `--index annoy` parameter
+    let trees = take_option(&mut args, "--trees").map(|n| n.parse::<usize>().ok().filter(|&n| n > 0));
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let Some(source) = take_source_or_usage(&mut args, 3, usage) else { return };
+    match (args.get(2).map(String::as_str), args.len(), trees) {
+        (Some("projector"), 5, None) | (Some("parquet" | "npy" | "npz" | "safetensors" | "hdf5"), 4, None) => {}
+        (Some("annoy"), 4, None | Some(Some(_))) => {}
+        _ => {
+            eprintln!("Usage: {} {}", args[0], usage);
+            return;
+        }
+    }
+
+    let Some(store) = open(&store_args, &source) else { return };
+
+    match args[2].as_str() {
+        "projector" => match export_projector(store.as_ref(), &args[3], &args[4]) {
+            Ok(()) => println!("Wrote {} vectors to {} and {}", store.len(), args[3], args[4]),
+            Err(e) => eprintln!("Export failed: {}", e),
+        },
+        "annoy" => {
+            let index = AnnoyIndex::build(store.as_ref(), trees.flatten().unwrap_or(IndexSpec::ANNOY_TREES), None);
+            match index.save(&args[3]) {
+                Ok(()) => println!("Wrote {} trees over {} vectors to {}", index.trees(), store.len(), args[3]),
+                Err(e) => eprintln!("Export failed: {}", e),
+            }
+        }
+        format => write_store(store.as_ref(), format, &args[3]),
+    }
+}
+
+/// Writes a store as `--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb`, reporting the outcome
+fn write_store(store: &dyn EmbeddingStore, target: &str, path: &str) {
+    let result = match target {
+        "text" => write_glove_text(store, path),
+        "binary" => write_binary(store, path),
+        "parquet" => write_parquet(store, path),
+        "npy" => write_npy(store, path),
+        "npz" => write_npz(store, path),
+        "safetensors" => write_safetensors(store, path),
+        "hdf5" => write_hdf5(store, path),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => glove::sqlite::SqliteStore::create(path, store),
+        #[cfg(feature = "rocksdb")]
+        "rocksdb" => glove::rocksdb::RocksStore::create(path, store),
+        other => {
+            eprintln!("Cannot write '{}' files with this build", other);
+            return;
+        }
+    };
+    match result {
+        Ok(()) => println!("Wrote {} vectors to {}", store.len(), path),
+        Err(e) => eprintln!("Unable to write {}: {}", path, e),
+    }
+}
+
+/// Handles `convert <glove.txt> <out> --to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb`
+pub fn run_convert(mut args: Vec<String>) {
+    let usage = "convert <source> <out> --to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb \
+                 [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let target = take_option(&mut args, "--to");
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Some(target), 3) = (target, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(glove) = open(&store_args, &source) else { return };
+    write_store(glove.as_ref(), &target, &args[2]);
+}
+
+/// Handles `export-subset <source> <out> --words <list.txt> | --regex <pattern> | --top N`:
+/// writes only the selected rows; the selections can be combined
+pub fn run_export_subset(mut args: Vec<String>) {
+    let usage = "export-subset <source> <out> [--words <list.txt>] [--regex <pattern>] [--top N] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let words = take_option(&mut args, "--words");
+    let pattern = take_option(&mut args, "--regex").map(|p| Pattern::parse(&p));
+    let top = take_option(&mut args, "--top").map(|n| n.parse::<usize>());
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), 3) = (top.transpose(), args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let pattern = match pattern.transpose() {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("--regex: {}", e);
+            return;
+        }
+    };
+    if words.is_none() && pattern.is_none() && top.is_none() {
+        eprintln!("Select rows with --words, --regex and/or --top");
+        return;
+    }
+
+    // A word list and a frequency cut are applied while loading, so unselected rows are never kept
+    if let Some(path) = &words {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let list = text.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string);
+                store_args.load.words = Some(list.collect());
+            }
+            Err(e) => {
+                eprintln!("Unable to read {}: {}", path, e);
+                return;
+            }
+        }
+    }
+    if top.is_some() {
+        store_args.load.max_vocab = top;
+    }
+
+    let Some(glove) = open(&store_args, &source) else { return };
+    let mut subset = DenseStore::new();
+    for (word, vec) in glove.iter() {
+        if pattern.as_ref().is_none_or(|p| p.is_match(&word)) {
+            subset.insert(word.into_owned(), vec.into_owned());
+        }
+    }
+    write_store(&subset, &target, &args[2]);
+}
+
+/// Handles `reduce <source> <out> --dims N`: projects every vector on the
+/// first N principal components
+pub fn run_reduce(mut args: Vec<String>) {
+    let usage = "reduce <source> <out> --dims N [--sample N] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let dims = take_option(&mut args, "--dims").map(|n| n.parse::<usize>());
+    let sample = take_option(&mut args, "--sample").map_or(Ok(SAMPLE_ROWS), |n| n.parse::<usize>());
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Some(Ok(dims @ 1..)), Ok(sample @ 1..), 3) = (dims, sample, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let Some(glove) = open(&store_args, &source) else { return };
+    if dims >= glove.dim() {
+        eprintln!("--dims must be below the {} dimensions of the vectors", glove.dim());
+        return;
+    }
+    let (reduced, pca) = reduce(glove.as_ref(), dims, sample);
+    println!(
+        "Kept {} of {} dimensions, explaining {:.1}% of the variance",
+        dims,
+        glove.dim(),
+        100.0 * pca.explained(dims)
+    );
+    write_store(&reduced, &target, &args[2]);
+}
+
+/// Parses the value of option `name`, or gives `default` if it is absent
+fn parse_option<T: std::str::FromStr>(args: &mut Vec<String>, name: &str, default: T) -> Result<T, String> {
+    match take_option(args, name) {
+        None => Ok(default),
+        Some(value) => value.parse().map_err(|_| format!("invalid {} '{}'", name, value)),
+    }
+}
+
+/// Removes `--window`, `--weighting`, `--min-count` and `--max-vocab` from the arguments
+fn take_cooccur_options(args: &mut Vec<String>) -> Result<CooccurOptions, String> {
+    let defaults = CooccurOptions::default();
+    let weighting = match take_option(args, "--weighting") {
+        None => defaults.weighting,
+        Some(name) => Weighting::parse(&name).ok_or_else(|| {
+            format!("unknown weighting '{}' (expected one of: {})", name, Weighting::NAMES.join(", "))
+        })?,
+    };
+    let options = CooccurOptions {
+        window: parse_option(args, "--window", defaults.window)?,
+        weighting,
+        min_count: parse_option(args, "--min-count", defaults.min_count)?,
+        max_vocab: take_option(args, "--max-vocab")
+            .map(|n| n.parse().map_err(|_| format!("invalid --max-vocab '{}'", n)))
+            .transpose()?,
+    };
+    if options.window == 0 {
+        return Err("--window must be positive".to_string());
+    }
+    Ok(options)
+}
+
+/// Removes `--dim`, `--iterations`, `--x-max`, `--alpha`, `--learning-rate`
+/// and `--seed` from the arguments
+fn take_train_options(args: &mut Vec<String>) -> Result<TrainOptions, String> {
+    let defaults = TrainOptions::default();
+    let options = TrainOptions {
+        dim: parse_option(args, "--dim", defaults.dim)?,
+        iterations: parse_option(args, "--iterations", defaults.iterations)?,
+        x_max: parse_option(args, "--x-max", defaults.x_max)?,
+        alpha: parse_option(args, "--alpha", defaults.alpha)?,
+        learning_rate: parse_option(args, "--learning-rate", defaults.learning_rate)?,
+        seed: parse_option(args, "--seed", defaults.seed)?,
+    };
+    if options.dim == 0 {
+        return Err("--dim must be positive".to_string());
+    }
+    if options.x_max <= 0.0 || options.learning_rate <= 0.0 {
+        return Err("--x-max and --learning-rate must be positive".to_string());
+    }
+    Ok(options)
+}
+
+/// Builds the vocabulary of the corpus at `path`, then counts its
+/// co-occurrences, reading it once for each and reporting both on stderr
+fn count_corpus(path: &str, options: &CooccurOptions) -> Result<(Vocabulary, Vec<Cooccurrence>), String> {
+    let read = || File::open(path).map(BufReader::new).map_err(|e| format!("Unable to read {}: {}", path, e));
+    let vocab = build_vocabulary(read()?, options).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    if vocab.is_empty() {
+        return Err(format!("No token of {} occurs at least {} times", path, options.min_count));
+    }
+    eprintln!("Vocabulary: {} words seen at least {} times", vocab.len(), options.min_count);
+    let entries =
+        count_cooccurrences(read()?, &vocab, options).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    eprintln!("Co-occurrences: {} nonzero pairs within {} words", entries.len(), options.window);
+    Ok((vocab, entries))
+}
+
+/// Handles `cooccur <corpus.txt> <vocab.txt> <cooccurrence.bin>`: writes a
+/// corpus's vocabulary and co-occurrence counts in the reference GloVe files
+pub fn run_cooccur(mut args: Vec<String>) {
+    let usage = "cooccur <corpus.txt> <vocab.txt> <cooccurrence.bin> [--window N] \
+                 [--weighting harmonic|uniform|linear] [--min-count N] [--max-vocab N]";
+    let options = match take_cooccur_options(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    if args.len() != 5 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    let (vocab, entries) = match count_corpus(&args[2], &options) {
+        Ok(counted) => counted,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    match vocab.write(&args[3]) {
+        Ok(()) => println!("Wrote {} words to {}", vocab.len(), args[3]),
+        Err(e) => eprintln!("Unable to write {}: {}", args[3], e),
+    }
+    match write_cooccurrences(&args[4], &entries) {
+        Ok(()) => println!("Wrote {} co-occurrence records to {}", entries.len(), args[4]),
+        Err(e) => eprintln!("Unable to write {}: {}", args[4], e),
+    }
+}
+
+/// Handles `train <corpus.txt> <out>` and `train --cooccurrences <file>
+/// --vocab <file> <out>`: trains GloVe vectors on a corpus, or on counts
+/// written by `cooccur`
+pub fn run_train(mut args: Vec<String>) {
+    let usage = "train (<corpus.txt> [--window N] [--weighting harmonic|uniform|linear] [--min-count N] \
+                 [--max-vocab N] | --cooccurrences <cooccurrence.bin> --vocab <vocab.txt>) <out> [--dim N] \
+                 [--iterations N] [--x-max X] [--alpha A] [--learning-rate R] [--seed S] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb]";
+    let cooccurrences = take_option(&mut args, "--cooccurrences");
+    let vocab_path = take_option(&mut args, "--vocab");
+    let options = match (take_cooccur_options(&mut args), take_train_options(&mut args)) {
+        (Ok(cooccur), Ok(train)) => (cooccur, train),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let (cooccur_options, options) = options;
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    let counted = match (cooccurrences, vocab_path, args.len()) {
+        (None, None, 4) => count_corpus(&args[2], &cooccur_options),
+        (Some(cooccurrences), Some(vocab_path), 3) => Vocabulary::read(&vocab_path)
+            .map_err(|e| format!("Unable to read {}: {}", vocab_path, e))
+            .and_then(|vocab| {
+                let entries = read_cooccurrences(&cooccurrences, vocab.len())
+                    .map_err(|e| format!("Unable to read {}: {}", cooccurrences, e))?;
+                Ok((vocab, entries))
+            }),
+        _ => {
+            eprintln!("Usage: {} {}", args[0], usage);
+            return;
+        }
+    };
+    let (vocab, entries) = match counted {
+        Ok(counted) => counted,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let matrix = train(&entries, vocab.len(), &options, |iteration, cost| {
+        eprintln!("Iteration {}/{}, cost {:.6}", iteration, options.iterations, cost);
+    });
+    let out = args.last().expect("checked above");
+    write_store(&into_store(&vocab, &matrix, options.dim), &target, out);
+}
+
+/// Handles `serve <source> [addr]`, exposing the vectors to `--backend remote` clients
+pub fn run_serve(mut args: Vec<String>) {
+    let usage = "serve <source> [host:port] [--cache N] [--normalize] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let cache = take_option(&mut args, "--cache").map_or(Ok(DEFAULT_CAPACITY), |n| n.parse::<usize>());
+    let normalize = take_flag(&mut args, "--normalize");
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(cache), true) = (cache, args.len() <= 3) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let addr = args.get(2).map_or("127.0.0.1:7878", |a| a.as_str());
+    // Listening before the vectors load lets clients connect at once; their
+    // requests are answered when the vectors are ready
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Unable to listen on {}: {}", addr, e);
+            return;
+        }
+    };
+    let opening = open_in_background(&store_args, &source);
+    if let Ok(local) = listener.local_addr() {
+        eprintln!("Listening on {} while {} loads", local, source);
+    }
+    let Some(store) = opening.wait(&store_args, &source, true) else { return };
+
+    if let Err(e) = glove::server::serve_on(listener, store.as_ref(), cache, normalize) {
+        eprintln!("Server failed: {}", e);
+    }
+}
+
+/// Handles `working-set [addr]`, printing a running server's access report
+pub fn run_working_set(args: Vec<String>) {
+    if args.len() > 3 {
+        eprintln!("Usage: {} working-set [host:port]", args[0]);
+        return;
+    }
+    let addr = args.get(2).map_or("127.0.0.1:7878", |a| a.as_str());
+
+    match RemoteStore::connect(addr).and_then(|remote| remote.working_set()) {
+        Ok(report) => print!("{}", report),
+        Err(e) => eprintln!("Unable to fetch report from {}: {}", addr, e),
+    }
+}
+
+/// Handles `download [model]`: fetches a pretrained model into the cache, or lists them
+pub fn run_download(args: Vec<String>) {
+    let Some(name) = args.get(2) else {
+        println!("Available models (cached in {}):", glove::models::cache_dir().display());
+        for model in MODELS {
+            let status = if model.is_downloaded() { "downloaded" } else { "" };
+            println!("  {:<24} {}", model.name, status);
+        }
+        return;
+    };
+
+    let Some(model) = find_model(name) else {
+        eprintln!("Unknown model '{}'; run `{} download` to list them", name, args[0]);
+        return;
+    };
+
+    println!("Downloading {} from {}...", model.name, model.url);
+    match model.download() {
+        Ok(path) => println!("Saved to {}; use it with --model {}", path.display(), model.name),
+        Err(e) => eprintln!("Download failed: {}", e),
+    }
+}
+
+/// Handles `graph <source> <out.dot|out.gexf> --seeds w1 w2 ...` (or
+/// `--vocab-top N`): writes the k-nearest-neighbor graph of the seeds, or of the
+/// N most frequent words among themselves
+pub fn run_graph(mut args: Vec<String>) {
+    let usage = "graph <source> <out.dot|out.gexf> (--seeds <word> ... | --vocab-top N) [--k K] \
+                 [--format dot|gexf] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let seeds = take_list(&mut args, "--seeds");
+    let vocab_top = take_option(&mut args, "--vocab-top").map(|n| n.parse::<usize>());
+    let k = take_option(&mut args, "--k").map_or(Ok(default_top(5)), |k| k.parse::<usize>());
+    let format = take_option(&mut args, "--format");
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(k), Ok(vocab_top), 3) = (k, vocab_top.transpose(), args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    let out = &args[2];
+    let format = format.unwrap_or_else(|| if out.ends_with(".gexf") { "gexf" } else { "dot" }.to_string());
+    if format != "dot" && format != "gexf" {
+        eprintln!("--format must be dot or gexf");
+        return;
+    }
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let graph = match (seeds, vocab_top) {
+        (Some(seeds), None) if !seeds.is_empty() => {
+            for seed in seeds.iter().filter(|s| !store.contains(s)) {
+                println!("{}", skipping_unknown(store.as_ref(), seed));
+            }
+            seed_graph(store.as_ref(), &seeds, k)
+        }
+        (None, Some(n)) => vocabulary_graph(store.as_ref(), n, k),
+        _ => {
+            eprintln!("Pass either --seeds <word> ... or --vocab-top N");
+            return;
+        }
+    };
+
+    let written = if format == "gexf" { write_gexf(&graph, out) } else { write_dot(&graph, out) };
+    match written {
+        Ok(()) => println!("Wrote {} nodes and {} edges to {}", graph.nodes.len(), graph.edges.len(), out),
+        Err(e) => eprintln!("Unable to write {}: {}", out, e),
+    }
+}
+
+/// Handles `all-pairs <source> <out.tsv>`: writes the `--k` nearest cosine
+/// neighbors of every word (or of the `--words` listed, or the `--vocab-top N`
+/// most frequent) as TSV rows of the word followed by each neighbor and score
+pub fn run_all_pairs(mut args: Vec<String>) {
+    let usage = "all-pairs <source> <out.tsv> [--k K] [--words <list.txt> | --vocab-top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let k = take_option(&mut args, "--k").map_or(Ok(default_top(10)), |k| k.parse::<usize>());
+    let words = take_option(&mut args, "--words");
+    let vocab_top = take_option(&mut args, "--vocab-top").map(|n| n.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(k @ 1..), Ok(vocab_top), 3) = (k, vocab_top.transpose(), args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    if words.is_some() && vocab_top.is_some() {
+        eprintln!("Pass either --words <list.txt> or --vocab-top N, not both");
+        return;
+    }
+    let listed: Option<Vec<String>> = match &words {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => Some(text.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string).collect()),
+            Err(e) => {
+                eprintln!("Unable to read {}: {}", path, e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let store = store.as_ref();
+    let queries: Vec<usize> = match listed {
+        Some(words) => words
+            .iter()
+            .filter_map(|word| {
+                let id = store.id(word);
+                if id.is_none() {
+                    eprintln!("{}", skipping_unknown(store, word));
+                }
+                id
+            })
+            .collect(),
+        None => (0..vocab_top.unwrap_or(store.len()).min(store.len())).collect(),
+    };
+
+    let out = &args[2];
+    let show_progress = !store_args.load.quiet && io::stderr().is_terminal();
+    let write = || -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(out)?);
+        let emit = |query: usize, neighbors: &[(usize, f32)]| -> io::Result<()> {
+            let Some((word, _)) = store.get_by_id(query) else { return Ok(()) };
+            write!(writer, "{}", word)?;
+            for &(id, score) in neighbors {
+                if let Some((neighbor, _)) = store.get_by_id(id) {
+                    write!(writer, "\t{}\t{:.4}", neighbor, score)?;
+                }
+            }
+            writeln!(writer)
+        };
+        let progress = |done: usize| {
+            if show_progress {
+                eprint!("\rScored {} of {} words", done, queries.len());
+            }
+        };
+        all_pairs(store, &queries, k, emit, progress)?;
+        if show_progress {
+            eprintln!();
+        }
+        writer.flush()
+    };
+    match write() {
+        Ok(()) => println!("Wrote the {} nearest neighbors of {} words to {}", k, queries.len(), out),
+        Err(e) => eprintln!("Unable to write {}: {}", out, e),
+    }
+}
+
+/// Handles `merge <out> <a.txt> <b.txt> ... [--on-conflict keep-first|keep-second|average]`
+pub fn run_merge(mut args: Vec<String>) {
+    let usage = "merge <out> <a.txt> <b.txt> ... [--on-conflict keep-first|keep-second|average] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    // `--on-conflict` is a loading flag, as it also merges the shards of one source
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    if args.len() < 5 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    let mut merger = Merger::new(store_args.load.conflict);
+    for input in &args[3..] {
+        let Some(store) = open(&store_args, &Source::from(input.as_str())) else { return };
+        match merger.add(store.as_ref()) {
+            Ok(added) => println!("{}: {} new words, {} already present", input, added.new, added.conflicts),
+            Err(e) => {
+                eprintln!("Unable to merge {}: {}", input, e);
+                return;
+            }
+        }
+    }
+    write_store(&merger.finish(), &target, &args[2]);
+}
+
+/// Handles `align <a.txt> <b.txt> <out>`: learns the orthogonal map from
+/// space A to space B on the seed dictionary (by default every shared word)
+/// and writes A's vectors mapped into B
+pub fn run_align(mut args: Vec<String>) {
+    let usage = "align <a.txt> <b.txt> <out> [--dictionary <pairs.txt>] [--save-mapping <mapping.txt>] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let dictionary = take_option(&mut args, "--dictionary");
+    let save_mapping = take_option(&mut args, "--save-mapping");
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    if args.len() != 5 {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    }
+
+    let dictionary = match dictionary.map(|path| (read_dictionary(&path), path)) {
+        Some((Ok(pairs), _)) => Some(pairs),
+        Some((Err(e), path)) => {
+            eprintln!("Unable to read {}: {}", path, e);
+            return;
+        }
+        None => None,
+    };
+    let Some(a) = open(&store_args, &Source::from(args[2].as_str())) else { return };
+    let Some(b) = open(&store_args, &Source::from(args[3].as_str())) else { return };
+    let dictionary = dictionary.unwrap_or_else(|| shared_words(a.as_ref(), b.as_ref()));
+    let pairs = paired_vectors(a.as_ref(), b.as_ref(), &dictionary);
+    println!("Aligning on {} of {} seed pairs", pairs.len(), dictionary.len());
+    let mapping = match procrustes(&pairs) {
+        Ok(mapping) => mapping,
+        Err(e) => {
+            eprintln!("Unable to align {} to {}: {}", args[2], args[3], e);
+            return;
+        }
+    };
+    println!(
+        "Mean seed similarity: {:.4} before, {:.4} after",
+        mean_similarity(&pairs, None),
+        mean_similarity(&pairs, Some(&mapping))
+    );
+    if let Some(path) = save_mapping {
+        match mapping.save(&path) {
+            Ok(()) => println!("Saved the {}x{} mapping to {}", mapping.dim(), mapping.dim(), path),
+            Err(e) => eprintln!("Unable to write {}: {}", path, e),
+        }
+    }
+    write_store(&transform(a.as_ref(), &mapping), &target, &args[4]);
+}
+
+/// Handles `debias <source> <out> --definitional <pairs.txt>`: hard-debiases
+/// every vector against the bias subspace of the definitional pairs
+pub fn run_debias(mut args: Vec<String>) {
+    let usage = "debias <source> <out> --definitional <pairs.txt> [--equalize <pairs.txt>] \
+                 [--neutralize <words.txt> | --keep <words.txt>] [--subspace-dim K] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let definitional = take_option(&mut args, "--definitional");
+    let equalize = take_option(&mut args, "--equalize");
+    let neutralize = take_option(&mut args, "--neutralize");
+    let keep = take_option(&mut args, "--keep");
+    let k = take_option(&mut args, "--subspace-dim").map_or(Ok(1), |k| k.parse::<usize>());
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Some(definitional), Ok(k @ 1..), 3) = (definitional, k, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let read = |path: &str| read_pairs(path).map_err(|e| format!("Unable to read {}: {}", path, e));
+    let read_words = |path: &str| -> Result<HashSet<String>, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        Ok(text.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string).collect())
+    };
+    let lists = (
+        read(&definitional),
+        equalize.as_deref().map(read).transpose(),
+        match (neutralize, keep) {
+            (Some(_), Some(_)) => Err("use either --neutralize or --keep, not both".to_string()),
+            (Some(path), None) => read_words(&path).map(Neutralize::Only),
+            (None, Some(path)) => read_words(&path).map(Neutralize::AllExcept),
+            (None, None) => Ok(Neutralize::AllExcept(HashSet::new())),
+        },
+    );
+    let (definitional, equalize, neutralize) = match lists {
+        (Ok(definitional), Ok(equalize), Ok(neutralize)) => (definitional, equalize, neutralize),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // Without a separate list, the definitional pairs are also the ones equalized
+    let equalize = equalize.unwrap_or_else(|| definitional.clone());
+
+    let Some(glove) = open(&store_args, &source) else { return };
+    let (debiased, report) = match hard_debias(glove.as_ref(), &definitional, &equalize, &neutralize, k) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Unable to debias: {}", e);
+            return;
+        }
+    };
+    for (a, b) in &report.unknown_pairs {
+        println!("Skipping pair {} {}: not in the vocabulary", a, b);
+    }
+    println!(
+        "Bias subspace of {} dimension(s) from {} definitional pairs; neutralized {} words, equalized {} pairs",
+        k, report.definitional, report.neutralized, report.equalized
+    );
+    write_store(&debiased, &target, &args[2]);
+}
+
+/// Handles `retrofit <source> <out> --lexicon <lexicon.txt>`: moves the
+/// vectors of related lexicon words towards each other
+pub fn run_retrofit(mut args: Vec<String>) {
+    let usage = "retrofit <source> <out> --lexicon <lexicon.txt> [--iterations N] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let lexicon = take_option(&mut args, "--lexicon");
+    let iterations = take_option(&mut args, "--iterations").map_or(Ok(DEFAULT_ITERATIONS), |n| n.parse::<usize>());
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Some(lexicon_path), Ok(iterations), 3) = (lexicon, iterations, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let lexicon = match read_lexicon(&lexicon_path) {
+        Ok(lexicon) => lexicon,
+        Err(e) => {
+            eprintln!("Unable to read {}: {}", lexicon_path, e);
+            return;
+        }
+    };
+    let Some(glove) = open(&store_args, &source) else { return };
+    let (retrofitted, report) = retrofit(glove.as_ref(), &lexicon, iterations);
+    println!(
+        "Retrofitted {} words over {} lexicon relations in {} iterations ({} lexicon words not in the vocabulary)",
+        report.retrofitted, report.edges, iterations, report.unknown
+    );
+    write_store(&retrofitted, &target, &args[2]);
+}
+
+/// Handles `counterfit <source> <out> --synonyms <pairs.txt> --antonyms <pairs.txt>`:
+/// pulls synonyms together and pushes antonyms apart
+pub fn run_counterfit(mut args: Vec<String>) {
+    let usage = "counterfit <source> <out> [--synonyms <pairs.txt>] [--antonyms <pairs.txt>] [--epochs N] \
+                 [--to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let synonyms = take_option(&mut args, "--synonyms");
+    let antonyms = take_option(&mut args, "--antonyms");
+    let epochs = take_option(&mut args, "--epochs").map_or(Ok(DEFAULT_EPOCHS), |n| n.parse::<usize>());
+    let target = take_option(&mut args, "--to").unwrap_or_else(|| "text".to_string());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (true, Ok(epochs), 3) = (synonyms.is_some() || antonyms.is_some(), epochs, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    let read = |path: &str| read_pairs(path).map_err(|e| format!("Unable to read {}: {}", path, e));
+    let lists = (synonyms.as_deref().map(read).transpose(), antonyms.as_deref().map(read).transpose());
+    let (synonyms, antonyms) = match lists {
+        (Ok(synonyms), Ok(antonyms)) => (synonyms.unwrap_or_default(), antonyms.unwrap_or_default()),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let Some(glove) = open(&store_args, &source) else { return };
+    let (counterfitted, report) = counterfit(glove.as_ref(), &synonyms, &antonyms, epochs);
+    for (a, b) in &report.unknown_pairs {
+        println!("Skipping pair {} {}: not in the vocabulary", a, b);
+    }
+    println!(
+        "Counter-fitted {} words to {} synonym and {} antonym pairs, preserving {} neighbor relations, in {} epochs",
+        report.moved, report.synonyms, report.antonyms, report.preserved, epochs
+    );
+    write_store(&counterfitted, &target, &args[2]);
+}
@@ -0,0 +1,393 @@
+//! Commands that work on running text rather than single words.
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use glove::args::{take_flag, take_option};
+use glove::coverage::coverage;
+use glove::expression::Combine;
+use glove::metric::BuiltinMetric;
+use glove::search::find_nearest_neighbors;
+use glove::sentence::{embed, Sif, SentenceVector, SIF_A};
+use glove::stopwords::Stopwords;
+use glove::vector::{cosine_similarity, normalize};
+use glove::EmbeddingStore;
+
+use super::{
+    default_top, json_number, json_results, json_string, open, skipping_unknown, take_output, take_source_or_usage,
+    take_store_args, Output,
+};
+
+/// Handles `embed-sentence <source> <sentence ...>`: averages (or with `--sum`,
+/// sums) the sentence's word vectors and prints the nearest vocabulary words,
+/// or with `--vector` the vector itself
+pub fn run_embed_sentence(mut args: Vec<String>) {
+    let usage = "embed-sentence <source> <sentence ...> [--sum | --sif <freq.txt> [--sif-a A] \
+                 [--sif-reference <sentences.txt>]] [--stopwords <list.txt> | --no-stopwords] [--vector [--normalize]] \
+                 [--top N] [--allow-inputs] [--output text|tsv|json]";
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let combine = if take_flag(&mut args, "--sum") { Combine::Sum } else { Combine::Average };
+    let sif = match take_sif_args(&mut args) {
+        Ok(sif) if sif.is_some() && combine == Combine::Sum => {
+            eprintln!("use either --sum or --sif, not both");
+            return;
+        }
+        Ok(sif) => sif,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let stopwords = match take_stopwords(&mut args) {
+        Ok(stopwords) => stopwords,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let print_vector = take_flag(&mut args, "--vector");
+    let normalize_vector = take_flag(&mut args, "--normalize");
+    if normalize_vector && !print_vector {
+        eprintln!("--normalize needs --vector");
+        return;
+    }
+    let allow_inputs = take_flag(&mut args, "--allow-inputs");
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(10)), |t| t.parse::<usize>());
+    let output = match take_output(&mut args) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), true) = (top, args.len() > 2) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    store_args.load.quiet |= output != Output::Text;
+    let Some(glove) = open(&store_args, &source) else { return };
+    let sentence = args[2..].join(" ");
+    let embedded = match sif {
+        Some(sif_args) => {
+            let Some(sif) = sif_args.fit(glove.as_ref(), output == Output::Text, stopwords.as_ref()) else { return };
+            sif.embed(glove.as_ref(), &sentence, stopwords.as_ref())
+        }
+        None => embed(glove.as_ref(), &sentence, combine, stopwords.as_ref()),
+    };
+    let Some(mut embedded) = embedded else {
+        eprintln!("No words of the sentence are in the vocabulary.");
+        return;
+    };
+    if normalize_vector {
+        normalize(&mut embedded.vector);
+    }
+    if output == Output::Text {
+        if !embedded.stopped.is_empty() {
+            println!("Ignoring stopwords: {}", embedded.stopped.join(", "));
+        }
+        for word in &embedded.unknown {
+            println!("{}", skipping_unknown(glove.as_ref(), word));
+        }
+    }
+
+    if print_vector {
+        let values: Vec<String> = embedded.vector.iter().map(|x| x.to_string()).collect();
+        match output {
+            Output::Text => println!("{}", values.join(" ")),
+            Output::Tsv => println!("{}", values.join("\t")),
+            Output::Json => {
+                let values: Vec<String> = embedded.vector.iter().map(|&x| json_number(x)).collect();
+                println!(
+                    "{{\"sentence\": {}, \"words\": {}, \"vector\": [{}]}}",
+                    json_string(&sentence),
+                    embedded.known.len(),
+                    values.join(", ")
+                );
+            }
+        }
+        return;
+    }
+
+    let exclude = if allow_inputs { Vec::new() } else { embedded.known.clone() };
+    let found = find_nearest_neighbors(&embedded.vector, glove.as_ref(), &exclude, top, &BuiltinMetric::Cosine);
+    match output {
+        Output::Text => {
+            println!("Nearest words to the sentence ({} of its words used):", embedded.known.len());
+            for (word, similarity) in &found {
+                println!("  {} (similarity: {:.4})", word, similarity);
+            }
+        }
+        Output::Tsv => {
+            for (word, similarity) in &found {
+                println!("{}\t{:.4}", word, similarity);
+            }
+        }
+        Output::Json => println!("{}", json_results(&found)),
+    }
+}
+
+/// `--sif <freq.txt>`, `--sif-a A` and `--sif-reference <sentences.txt>`
+pub struct SifArgs {
+    pub frequencies: String,
+    pub a: f32,
+    pub reference: Option<String>,
+}
+
+/// Removes the SIF flags from the arguments; `None` without `--sif`
+pub fn take_sif_args(args: &mut Vec<String>) -> Result<Option<SifArgs>, String> {
+    let a = match take_option(args, "--sif-a") {
+        None => SIF_A,
+        Some(a) => a.parse().ok().filter(|&a: &f32| a > 0.0).ok_or("--sif-a must be a positive number")?,
+    };
+    let reference = take_option(args, "--sif-reference");
+    match take_option(args, "--sif") {
+        Some(frequencies) => Ok(Some(SifArgs { frequencies, a, reference })),
+        None if reference.is_some() => Err("--sif-reference needs --sif".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Removes `--stopwords <list.txt>` and `--no-stopwords` from the arguments:
+/// the stopwords to leave out of sentence vectors, by default the built-in list
+pub fn take_stopwords(args: &mut Vec<String>) -> Result<Option<Stopwords>, String> {
+    match (take_option(args, "--stopwords"), take_flag(args, "--no-stopwords")) {
+        (Some(_), true) => Err("use either --stopwords or --no-stopwords, not both".to_string()),
+        (Some(path), false) => {
+            Stopwords::from_file(&path).map(Some).map_err(|e| format!("Unable to read stopwords {}", e))
+        }
+        (None, true) => Ok(None),
+        (None, false) => Ok(Some(Stopwords::english())),
+    }
+}
+
+impl SifArgs {
+    /// Loads the frequencies and fits the common component to the reference
+    /// sentences (one per line), reporting failures
+    pub fn fit(&self, store: &dyn EmbeddingStore, verbose: bool, stopwords: Option<&Stopwords>) -> Option<Sif> {
+        self.fit_or(store, verbose, stopwords, &[])
+    }
+
+    /// Like `fit`, but without `--sif-reference` the common component is
+    /// fitted to `fallback` (if it has at least two usable sentences)
+    pub fn fit_or(
+        &self,
+        store: &dyn EmbeddingStore,
+        verbose: bool,
+        stopwords: Option<&Stopwords>,
+        fallback: &[&str],
+    ) -> Option<Sif> {
+        let mut sif = match Sif::from_file(&self.frequencies, self.a) {
+            Ok(sif) => sif,
+            Err(e) => {
+                eprintln!("Unable to read word frequencies {}", e);
+                return None;
+            }
+        };
+        let Some(path) = &self.reference else {
+            if sif.fit_component(store, fallback, stopwords) && verbose {
+                println!("Removing the common component of {} lines", fallback.len());
+            }
+            return Some(sif);
+        };
+        let sentences = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Unable to read {}: {}", path, e);
+                return None;
+            }
+        };
+        let sentences: Vec<&str> = sentences.lines().collect();
+        if !sif.fit_component(store, &sentences, stopwords) {
+            eprintln!("{}: need at least two sentences with known words to find the common component", path);
+            return None;
+        }
+        if verbose {
+            println!("Removing the common component of {} reference sentences", sentences.len());
+        }
+        Some(sif)
+    }
+}
+
+/// Handles `doc-sim <source> <a.txt> <b.txt>`, the cosine similarity of two
+/// documents' averaged (or SIF-weighted) word vectors, and
+/// `doc-sim <source> <a.txt> --dir <docs>`, every file in `docs` ranked by
+/// similarity to `a.txt`
+pub fn run_doc_sim(mut args: Vec<String>) {
+    let usage = "doc-sim <source> <a.txt> (<b.txt> | --dir <docs>) [--sif <freq.txt> [--sif-a A] \
+                 [--sif-reference <sentences.txt>]] [--stopwords <list.txt> | --no-stopwords] [--top N] \
+                 [--output text|tsv|json]";
+    let Some(mut store_args) = take_store_args(&mut args) else { return };
+    let dir = take_option(&mut args, "--dir");
+    let top = take_option(&mut args, "--top").map(|t| t.parse::<usize>());
+    let (sif, stopwords, output) = match (take_sif_args(&mut args), take_stopwords(&mut args), take_output(&mut args)) {
+        (Ok(sif), Ok(stopwords), Ok(output)) => (sif, stopwords, output),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let expected_args = if dir.is_some() { 3 } else { 4 };
+    let (Ok(top), true) = (top.transpose(), args.len() == expected_args) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+
+    // Every document is read up front so unreadable files fail before loading
+    let first = PathBuf::from(&args[2]);
+    let mut paths = vec![first.clone()];
+    match &dir {
+        Some(dir) => match list_documents(dir) {
+            Ok(found) => paths.extend(found.into_iter().filter(|p| *p != first)),
+            Err(e) => {
+                eprintln!("Unable to list {}: {}", dir, e);
+                return;
+            }
+        },
+        None => paths.push(PathBuf::from(&args[3])),
+    }
+    let mut texts = Vec::with_capacity(paths.len());
+    for path in &paths {
+        match fs::read_to_string(path) {
+            Ok(text) => texts.push(text),
+            Err(e) => {
+                eprintln!("Unable to read {}: {}", path.display(), e);
+                return;
+            }
+        }
+    }
+
+    store_args.load.quiet |= output != Output::Text;
+    let Some(glove) = open(&store_args, &source) else { return };
+    let sif = match sif {
+        Some(sif_args) => {
+            let lines: Vec<&str> = texts.iter().flat_map(|t| t.lines()).collect();
+            let verbose = output == Output::Text;
+            let Some(sif) = sif_args.fit_or(glove.as_ref(), verbose, stopwords.as_ref(), &lines) else { return };
+            Some(sif)
+        }
+        None => None,
+    };
+    let embed_document = |text: &str| -> Option<SentenceVector> {
+        match &sif {
+            Some(sif) => sif.embed(glove.as_ref(), text, stopwords.as_ref()),
+            None => embed(glove.as_ref(), text, Combine::Average, stopwords.as_ref()),
+        }
+    };
+
+    let Some(query) = embed_document(&texts[0]) else {
+        eprintln!("{} has no words in the vocabulary", paths[0].display());
+        return;
+    };
+    let mut ranked: Vec<(String, f32)> = paths[1..]
+        .iter()
+        .zip(&texts[1..])
+        .filter_map(|(path, text)| {
+            let Some(doc) = embed_document(text) else {
+                if dir.is_some() && output == Output::Text {
+                    println!("Skipping {}: no words in the vocabulary", path.display());
+                }
+                return None;
+            };
+            Some((path.display().to_string(), cosine_similarity(&query.vector, &doc.vector)))
+        })
+        .collect();
+
+    if dir.is_none() {
+        let Some((b, similarity)) = ranked.pop() else {
+            eprintln!("{} has no words in the vocabulary", paths[1].display());
+            return;
+        };
+        let a = paths[0].display().to_string();
+        match output {
+            Output::Text => println!("Cosine similarity between {} and {}: {:.4}", a, b, similarity),
+            Output::Tsv => println!("{}\t{}\t{:.4}", a, b, similarity),
+            Output::Json => println!(
+                "{{\"a\": {}, \"b\": {}, \"similarity\": {}}}",
+                json_string(&a),
+                json_string(&b),
+                json_number(similarity)
+            ),
+        }
+        return;
+    }
+
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    if let Some(top) = top {
+        ranked.truncate(top);
+    }
+    match output {
+        Output::Text => {
+            println!("Documents most similar to {}:", paths[0].display());
+            for (path, similarity) in &ranked {
+                println!("  {} (similarity: {:.4})", path, similarity);
+            }
+        }
+        Output::Tsv => {
+            for (path, similarity) in &ranked {
+                println!("{}\t{:.4}", path, similarity);
+            }
+        }
+        Output::Json => {
+            let items: Vec<String> = ranked
+                .iter()
+                .map(|(p, s)| format!("{{\"path\": {}, \"similarity\": {}}}", json_string(p), json_number(*s)))
+                .collect();
+            println!("[{}]", items.join(", "));
+        }
+    }
+}
+
+/// The regular files directly inside `dir`, sorted by name
+fn list_documents(dir: &str) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Handles `coverage <source> <corpus.txt>`: the share of the corpus's tokens
+/// and distinct words the vocabulary knows, and its most frequent unknown words
+pub fn run_coverage(mut args: Vec<String>) {
+    let usage = "coverage <source> <corpus.txt> [--top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let top = take_option(&mut args, "--top").map_or(Ok(default_top(20)), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), 3) = (top, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    // Opened before loading, so a wrong path fails at once
+    let corpus = match File::open(&args[2]) {
+        Ok(file) => BufReader::new(file),
+        Err(e) => {
+            eprintln!("Unable to read {}: {}", args[2], e);
+            return;
+        }
+    };
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let report = match coverage(store.as_ref(), corpus) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Unable to read {}: {}", args[2], e);
+            return;
+        }
+    };
+    println!("{}", report);
+    if top > 0 && !report.missing.is_empty() {
+        let shown = top.min(report.missing.len());
+        println!("Most frequent unknown words ({} of {}):", shown, report.missing.len());
+        for (word, count) in &report.missing[..shown] {
+            println!("  {} ({})", word, count);
+        }
+    }
+}
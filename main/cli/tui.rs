@@ -0,0 +1,290 @@
+//! `tui`: a full-screen explorer with a query box, a scrollable neighbor list
+//! with similarity bars, and pivoting to any result as the next query.
+//!
+//! The terminal is driven with ANSI escape sequences and put into raw mode
+//! with `stty`, so it needs a Unix terminal but no extra dependencies.
+
+use std::io::{self, IsTerminal, Read, Write};
+
+use glove::args::take_option;
+use glove::expression::Expression;
+use glove::metric::BuiltinMetric;
+use glove::search::find_nearest_neighbors;
+use glove::store::unknown_words;
+use glove::EmbeddingStore;
+
+use super::readline::stty;
+use super::{open, take_source_or_usage, take_store_args};
+
+/// Rows above and below the neighbor list: title, query, rule, and the key help line
+const CHROME_ROWS: usize = 4;
+
+/// Columns given to the similarity bars
+const BAR_WIDTH: usize = 30;
+
+/// Puts the terminal into raw mode and restores its settings when dropped,
+/// so an early return or a panic does not leave it unusable
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(RawMode { saved: saved.trim().to_string() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// The terminal's rows and columns, or 24 x 80 if `stty` cannot tell
+fn terminal_size() -> (usize, usize) {
+    let size = stty(&["size"]).unwrap_or_default();
+    let mut numbers = size.split_whitespace().filter_map(|n| n.parse().ok());
+    match (numbers.next(), numbers.next()) {
+        (Some(rows), Some(cols)) if rows > CHROME_ROWS => (rows, cols),
+        _ => (24, 80),
+    }
+}
+
+/// A key press decoded from the raw input
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Quit,
+}
+
+/// Splits a chunk of raw input into keys; unknown escape sequences are dropped
+fn decode_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                let mut sequence = String::new();
+                while let Some(&c) = chars.peek() {
+                    chars.next();
+                    sequence.push(c);
+                    if c.is_ascii_alphabetic() || c == '~' {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "A" => Key::Up,
+                    "B" => Key::Down,
+                    "C" => Key::Right,
+                    "D" => Key::Left,
+                    "5~" => Key::PageUp,
+                    "6~" => Key::PageDown,
+                    _ => continue,
+                }
+            }
+            '\x1b' | '\x03' | '\x04' => Key::Quit,
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\t' => Key::Right,
+            c if !c.is_control() => Key::Char(c),
+            _ => continue,
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// What the explorer shows
+struct Explorer<'a> {
+    store: &'a dyn EmbeddingStore,
+    top: usize,
+    /// The text in the query box
+    input: String,
+    /// The query the results are for
+    shown: String,
+    results: Vec<(String, f32)>,
+    selected: usize,
+    /// First result row on screen
+    scroll: usize,
+    /// Earlier queries, for going back after a pivot
+    history: Vec<String>,
+    status: String,
+}
+
+impl Explorer<'_> {
+    /// Searches for the text in the query box
+    fn search(&mut self) {
+        let query = self.input.trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+        let expression = match Expression::parse(&query) {
+            Ok(expression) => expression.join_phrases(self.store).0,
+            Err(e) => {
+                self.status = e;
+                return;
+            }
+        };
+        let words: Vec<&str> = expression.words().collect();
+        let Some(vector) = expression.known_vector(self.store) else {
+            self.status = unknown_words(self.store, &words).to_string();
+            return;
+        };
+        self.status = match words.iter().any(|w| !self.store.contains(w)) {
+            true => format!("{}, skipped", unknown_words(self.store, &words)),
+            false => String::new(),
+        };
+        let exclude: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        self.results = find_nearest_neighbors(&vector, self.store, &exclude, self.top, &BuiltinMetric::Cosine);
+        if !self.shown.is_empty() && self.shown != query {
+            self.history.push(std::mem::take(&mut self.shown));
+        }
+        self.shown = query;
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// Makes the selected result the next query
+    fn pivot(&mut self) {
+        if let Some((word, _)) = self.results.get(self.selected) {
+            self.input = word.clone();
+            self.search();
+        }
+    }
+
+    /// Returns to the query before the last search
+    fn back(&mut self) {
+        if let Some(previous) = self.history.pop() {
+            self.input = previous;
+            self.shown.clear();
+            self.search();
+        }
+    }
+
+    fn select(&mut self, delta: isize, visible: usize) {
+        let last = self.results.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible {
+            self.scroll = self.selected + 1 - visible;
+        }
+    }
+
+    /// Redraws the whole screen
+    fn draw(&self, rows: usize, cols: usize) -> io::Result<()> {
+        let fit = |line: String| line.chars().take(cols).collect::<String>();
+        let mut screen = String::from("\x1b[H\x1b[2J");
+        let title = format!("GloVe explorer: {} words, {} dimensions", self.store.len(), self.store.dim());
+        screen.push_str(&format!("\x1b[1m{}\x1b[0m\r\n", fit(title)));
+        screen.push_str(&fit(format!("Query: {}", self.input)));
+        screen.push_str("\x1b[7m \x1b[0m\r\n");
+        let heading = if self.shown.is_empty() { String::new() } else { format!(" nearest to {} ", self.shown) };
+        screen.push_str(&fit(format!("──{}{}", heading, "─".repeat(cols))));
+        screen.push_str("\r\n");
+
+        let visible = rows - CHROME_ROWS;
+        let width = self.results.iter().map(|(w, _)| w.chars().count()).max().unwrap_or(0).min(cols / 2);
+        for (i, (word, score)) in self.results.iter().enumerate().skip(self.scroll).take(visible) {
+            let bar = "█".repeat((score.max(0.0) * BAR_WIDTH as f32).round() as usize);
+            let marker = if i == self.selected { '>' } else { ' ' };
+            let line = fit(format!("{} {:<width$} {:>7.4} {}", marker, word, score, bar));
+            if i == self.selected {
+                screen.push_str(&format!("\x1b[7m{}\x1b[0m\r\n", line));
+            } else {
+                screen.push_str(&format!("{}\r\n", line));
+            }
+        }
+        for _ in self.results.len().saturating_sub(self.scroll).min(visible)..visible {
+            screen.push_str("\r\n");
+        }
+        let help = "Enter search  ↑↓ select  → pivot  ← back  Esc quit";
+        let status = if self.status.is_empty() { help.to_string() } else { self.status.clone() };
+        screen.push_str(&format!("\x1b[2m{}\x1b[0m", fit(status)));
+
+        let mut out = io::stdout().lock();
+        out.write_all(screen.as_bytes())?;
+        out.flush()
+    }
+}
+
+/// Handles `tui <source> [--top N]`: the interactive explorer
+pub fn run_tui(mut args: Vec<String>) {
+    let usage = "tui <source> [--top N] [--backend <name>]";
+    let Some(store_args) = take_store_args(&mut args) else { return };
+    let top = take_option(&mut args, "--top").map_or(Ok(100), |t| t.parse::<usize>());
+    let Some(source) = take_source_or_usage(&mut args, 2, usage) else { return };
+    let (Ok(top), 2) = (top, args.len()) else {
+        eprintln!("Usage: {} {}", args[0], usage);
+        return;
+    };
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        eprintln!("tui needs an interactive terminal");
+        return;
+    }
+
+    let Some(store) = open(&store_args, &source) else { return };
+    let mut explorer = Explorer {
+        store: store.as_ref(),
+        top,
+        input: String::new(),
+        shown: String::new(),
+        results: Vec::new(),
+        selected: 0,
+        scroll: 0,
+        history: Vec::new(),
+        status: String::new(),
+    };
+    if let Err(e) = explore(&mut explorer) {
+        eprintln!("Terminal error: {}", e);
+    }
+}
+
+/// Reads keys and redraws until the user quits
+fn explore(explorer: &mut Explorer) -> io::Result<()> {
+    let _raw = RawMode::enter()?;
+    let mut stdin = io::stdin().lock();
+    let mut buffer = [0; 64];
+    loop {
+        // Checked every time, so resizing the window just works
+        let (rows, cols) = terminal_size();
+        let visible = rows - CHROME_ROWS;
+        explorer.draw(rows, cols)?;
+        let read = stdin.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        for key in decode_keys(&buffer[..read]) {
+            match key {
+                Key::Quit => return Ok(()),
+                Key::Char(c) => explorer.input.push(c),
+                Key::Backspace => {
+                    explorer.input.pop();
+                }
+                Key::Enter => explorer.search(),
+                Key::Up => explorer.select(-1, visible),
+                Key::Down => explorer.select(1, visible),
+                Key::PageUp => explorer.select(-(visible as isize), visible),
+                Key::PageDown => explorer.select(visible as isize, visible),
+                Key::Right => explorer.pivot(),
+                Key::Left => explorer.back(),
+            }
+        }
+    }
+}
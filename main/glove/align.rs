@@ -0,0 +1,241 @@
+//! Orthogonal Procrustes alignment between two embedding spaces.
+//!
+//! Given pairs of vectors `(x_i, y_i)` for words known to correspond (the
+//! same word in two snapshots, or a translation pair), the orthogonal matrix
+//! `W` minimizing `sum |x_i W - y_i|^2` is `U V^T`, where `U S V^T` is the SVD
+//! of `X^T Y` (Schönemann, 1966). Being orthogonal, `W` rotates space A onto
+//! space B without distorting distances within A.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use crate::dense::DenseStore;
+use crate::metric::BuiltinMetric;
+use crate::search::find_nearest_neighbors;
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+use crate::WordVec;
+
+/// A square matrix applied to row vectors, stored row-major
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mapping {
+    dim: usize,
+    rows: Vec<f64>,
+}
+
+impl Mapping {
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// `vec W`
+    pub fn apply(&self, vec: &[f32]) -> WordVec {
+        let mut out = vec![0.0f64; self.dim];
+        for (&x, row) in vec.iter().zip(self.rows.chunks(self.dim)) {
+            for (o, w) in out.iter_mut().zip(row) {
+                *o += x as f64 * w;
+            }
+        }
+        out.into_iter().map(|x| x as f32).collect()
+    }
+
+    /// Writes the matrix as text: a `<rows> <cols>` line, then one row per line
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{} {}", self.dim, self.dim)?;
+        for row in self.rows.chunks(self.dim) {
+            let values: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+            writeln!(out, "{}", values.join(" "))?;
+        }
+        out.flush()
+    }
+
+    /// Reads a matrix written by `save`
+    pub fn load(path: &str) -> Result<Mapping, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header: Vec<usize> = lines
+            .next()
+            .map(|l| l.split_whitespace().filter_map(|n| n.parse().ok()).collect())
+            .unwrap_or_default();
+        let [dim, cols] = header[..] else {
+            return Err(format!("{}: expected a '<rows> <cols>' header", path));
+        };
+        if dim != cols {
+            return Err(format!("{}: the mapping must be square, found {}x{}", path, dim, cols));
+        }
+        let rows: Result<Vec<f64>, _> = lines.flat_map(str::split_whitespace).map(str::parse).collect();
+        match rows {
+            Ok(rows) if rows.len() == dim * dim => Ok(Mapping { dim, rows }),
+            _ => Err(format!("{}: expected {} rows of {} numbers", path, dim, dim)),
+        }
+    }
+}
+
+/// Reads a seed dictionary: one `source target` pair per line, or a single
+/// word that is the same in both spaces; `#` starts a comment
+pub fn read_dictionary(path: &str) -> io::Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").split_whitespace().collect::<Vec<_>>())
+        .filter_map(|tokens| match tokens[..] {
+            [word] => Some((word.to_string(), word.to_string())),
+            [a, b, ..] => Some((a.to_string(), b.to_string())),
+            [] => None,
+        })
+        .collect())
+}
+
+/// Every word of `a` that `b` also has, paired with itself
+pub fn shared_words(a: &dyn EmbeddingStore, b: &dyn EmbeddingStore) -> Vec<(String, String)> {
+    a.iter()
+        .filter(|(word, _)| b.contains(word))
+        .map(|(word, _)| (word.to_string(), word.to_string()))
+        .collect()
+}
+
+/// The word pairs of `dictionary` found in both stores, as `(a, b)` vectors
+pub fn paired_vectors(
+    a: &dyn EmbeddingStore,
+    b: &dyn EmbeddingStore,
+    dictionary: &[(String, String)],
+) -> Vec<(WordVec, WordVec)> {
+    dictionary
+        .iter()
+        .filter_map(|(wa, wb)| Some((a.get(wa)?.into_owned(), b.get(wb)?.into_owned())))
+        .collect()
+}
+
+/// Learns the orthogonal mapping from the `a` side of `pairs` to the `b` side
+pub fn procrustes(pairs: &[(WordVec, WordVec)]) -> Result<Mapping, String> {
+    let Some((first, _)) = pairs.first() else {
+        return Err("no word pairs to align on".to_string());
+    };
+    let dim = first.len();
+    if pairs.iter().any(|(x, y)| x.len() != dim || y.len() != dim) {
+        return Err("both spaces must have the same dimensionality".to_string());
+    }
+
+    // M = X^T Y, kept as columns for the Jacobi rotations below
+    let mut columns = vec![vec![0.0f64; dim]; dim];
+    for (x, y) in pairs {
+        for (column, &yj) in columns.iter_mut().zip(y) {
+            for (m, &xi) in column.iter_mut().zip(x) {
+                *m += xi as f64 * yj as f64;
+            }
+        }
+    }
+
+    let (u, v) = svd(columns);
+    // W = U V^T, with U and V held as columns
+    let mut rows = vec![0.0f64; dim * dim];
+    for (i, row) in rows.chunks_mut(dim).enumerate() {
+        for (j, w) in row.iter_mut().enumerate() {
+            *w = u.iter().zip(&v).map(|(uk, vk)| uk[i] * vk[j]).sum();
+        }
+    }
+    Ok(Mapping { dim, rows })
+}
+
+/// The mean cosine similarity of the pairs, with the `a` side mapped first if given
+pub fn mean_similarity(pairs: &[(WordVec, WordVec)], mapping: Option<&Mapping>) -> f32 {
+    let total: f32 = pairs
+        .iter()
+        .map(|(x, y)| match mapping {
+            Some(mapping) => cosine_similarity(&mapping.apply(x), y),
+            None => cosine_similarity(x, y),
+        })
+        .sum();
+    total / pairs.len().max(1) as f32
+}
+
+/// Every vector of `store` mapped into the target space
+pub fn transform(store: &dyn EmbeddingStore, mapping: &Mapping) -> DenseStore {
+    let mut out = DenseStore::new();
+    for (word, vec) in store.iter() {
+        out.insert(word.into_owned(), mapping.apply(&vec));
+    }
+    out
+}
+
+/// The `k` words of `target` nearest to `word` of `source` mapped into the
+/// target space, or `None` if `source` does not have the word
+pub fn translate(
+    source: &dyn EmbeddingStore,
+    target: &dyn EmbeddingStore,
+    mapping: &Mapping,
+    word: &str,
+    k: usize,
+) -> Option<Vec<(String, f32)>> {
+    let mapped = mapping.apply(&source.get(word)?);
+    Some(find_nearest_neighbors(&mapped, target, &[], k, &BuiltinMetric::Cosine))
+}
+
+/// One-sided Jacobi SVD of the matrix with these columns, returning the left
+/// and right singular vectors as columns. Left vectors of zero singular
+/// values are completed to an orthonormal basis, so `U` is always orthogonal.
+fn svd(mut a: Vec<Vec<f64>>) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+
+    for _ in 0..60 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let alpha: f64 = a[p].iter().map(|x| x * x).sum();
+                let beta: f64 = a[q].iter().map(|x| x * x).sum();
+                let gamma: f64 = a[p].iter().zip(&a[q]).map(|(x, y)| x * y).sum();
+                if gamma.abs() <= 1e-15 * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for m in [&mut a, &mut v] {
+                    let (left, right) = m.split_at_mut(q);
+                    for (x, y) in left[p].iter_mut().zip(right[0].iter_mut()) {
+                        let (xp, xq) = (*x, *y);
+                        *x = c * xp - s * xq;
+                        *y = s * xp + c * xq;
+                    }
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    // The rotated columns are U S; normalize them, then fill in any null directions
+    let largest = a.iter().map(|c| norm(c)).fold(0.0, f64::max);
+    let mut u: Vec<Option<Vec<f64>>> = a
+        .into_iter()
+        .map(|c| {
+            let sigma = norm(&c);
+            (sigma > largest * 1e-10 && sigma > 0.0).then(|| c.iter().map(|x| x / sigma).collect())
+        })
+        .collect();
+    let mut basis = 0;
+    for i in 0..n {
+        while u[i].is_none() {
+            let mut candidate: Vec<f64> = (0..n).map(|j| if j == basis { 1.0 } else { 0.0 }).collect();
+            basis += 1;
+            for other in u.iter().flatten() {
+                let dot: f64 = candidate.iter().zip(other).map(|(x, y)| x * y).sum();
+                candidate.iter_mut().zip(other).for_each(|(x, y)| *x -= dot * y);
+            }
+            let length = norm(&candidate);
+            if length > 1e-6 {
+                u[i] = Some(candidate.iter().map(|x| x / length).collect());
+            }
+        }
+    }
+    (u.into_iter().flatten().collect(), v)
+}
+
+fn norm(vec: &[f64]) -> f64 {
+    vec.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
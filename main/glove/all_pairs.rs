@@ -0,0 +1,90 @@
+//! The k nearest cosine neighbors of many words at once, for building
+//! semantic networks over a whole vocabulary.
+//!
+//! Every vector is normalized once into a row-major matrix, and the query
+//! words are scored a block at a time: each row is compared with the whole
+//! block while it is in cache, so the matrix is read from memory once per
+//! block of queries rather than once per query. Each block's results are
+//! handed over as soon as it is done, so they can be streamed to disk without
+//! holding the whole neighbor table.
+
+use std::io;
+
+use crate::store::EmbeddingStore;
+use crate::vector::norm;
+
+/// Query words scored together; 128 rows of 300 dimensions take 150 KB,
+/// which stays in cache while the matrix streams past
+const QUERY_BLOCK: usize = 128;
+
+/// The rows of `store` scaled to unit length, as one row-major matrix
+/// (zero vectors stay zero)
+fn unit_matrix(store: &dyn EmbeddingStore) -> Vec<f32> {
+    let mut matrix = Vec::with_capacity(store.len() * store.dim());
+    for (_, vec) in store.iter() {
+        let n = norm(&vec);
+        let scale = if n > 0.0 { 1.0 / n } else { 0.0 };
+        matrix.extend(vec.iter().map(|x| x * scale));
+    }
+    matrix
+}
+
+/// Dot product in eight independent lanes, which the compiler can vectorize
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0.0f32; 8];
+    let (a_chunks, b_chunks) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(x, y)| x * y).sum();
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for i in 0..8 {
+            lanes[i] += x[i] * y[i];
+        }
+    }
+    lanes.iter().sum::<f32>() + tail
+}
+
+/// Adds a candidate to a best-first list capped at `k` entries
+fn offer(best: &mut Vec<(usize, f32)>, k: usize, id: usize, score: f32) {
+    if best.len() == k && best.last().is_some_and(|&(_, worst)| score <= worst) {
+        return;
+    }
+    let pos = best.partition_point(|&(_, s)| s >= score);
+    best.insert(pos, (id, score));
+    best.truncate(k);
+}
+
+/// Finds the `k` rows with the highest cosine similarity to each row id in
+/// `queries` (never the row itself), calling `emit` with each query's id and
+/// its neighbors, best first, in the order of `queries`. `progress` is called
+/// with the number of queries done after each block. Stops at the first error
+/// `emit` returns.
+pub fn all_pairs(
+    store: &dyn EmbeddingStore,
+    queries: &[usize],
+    k: usize,
+    mut emit: impl FnMut(usize, &[(usize, f32)]) -> io::Result<()>,
+    mut progress: impl FnMut(usize),
+) -> io::Result<()> {
+    let dim = store.dim();
+    let matrix = unit_matrix(store);
+    let rows = matrix.len().checked_div(dim).unwrap_or(0);
+    let row = |id: usize| &matrix[id * dim..(id + 1) * dim];
+    let mut done = 0;
+    for block in queries.chunks(QUERY_BLOCK) {
+        let block: Vec<usize> = block.iter().copied().filter(|&id| id < rows).collect();
+        let mut best: Vec<Vec<(usize, f32)>> = vec![Vec::with_capacity(k.min(rows) + 1); block.len()];
+        for id in 0..rows {
+            let candidate = row(id);
+            for (query, best) in block.iter().zip(&mut best) {
+                if *query != id {
+                    offer(best, k, id, dot(row(*query), candidate));
+                }
+            }
+        }
+        for (query, best) in block.iter().zip(&best) {
+            emit(*query, best)?;
+        }
+        done += block.len();
+        progress(done);
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Analogy solving ("a is to b as c is to ?") with 3CosAdd or 3CosMul.
+
+use crate::expression::Expression;
+use crate::metric::BuiltinMetric;
+use crate::search::{find_nearest_neighbors, top_k};
+use crate::store::EmbeddingStore;
+
+/// Smoothing term of 3CosMul, keeping a near-zero denominator from dominating
+pub const COSMUL_EPSILON: f32 = 0.001;
+
+/// How candidates for an analogy are scored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Method {
+    /// Cosine to the offset vector `b - a + c`
+    #[default]
+    CosAdd,
+    /// `cos(x, b) * cos(x, c) / (cos(x, a) + epsilon)`, with cosines shifted to [0, 1]
+    CosMul,
+}
+
+impl Method {
+    pub fn parse(name: &str) -> Option<Method> {
+        match name {
+            "3cosadd" => Some(Method::CosAdd),
+            "3cosmul" => Some(Method::CosMul),
+            _ => None,
+        }
+    }
+}
+
+/// The expression `b - a + c` for "a is to b as c is to ?"
+pub fn analogy_expression(a: &str, b: &str, c: &str) -> Expression {
+    Expression {
+        terms: vec![(1.0, b.to_string()), (-1.0, a.to_string()), (1.0, c.to_string())],
+    }
+}
+
+/// Finds the `k` best answers to an expression, excluding its own words
+/// unless `allow_inputs` is set.
+///
+/// With 3CosMul, positive words multiply into the numerator and negative words
+/// into the denominator (Levy & Goldberg, 2014), so one strongly matching term
+/// cannot make up for a poor match on another.
+pub fn solve(
+    store: &dyn EmbeddingStore,
+    expression: &Expression,
+    method: Method,
+    k: usize,
+    allow_inputs: bool,
+) -> Result<Vec<(String, f32)>, String> {
+    let exclude: Vec<String> = if allow_inputs {
+        Vec::new()
+    } else {
+        expression.words().map(str::to_string).collect()
+    };
+    if method == Method::CosAdd {
+        let query = expression.vector(store)?;
+        return Ok(find_nearest_neighbors(&query, store, &exclude, k, &BuiltinMetric::Cosine));
+    }
+
+    let mut numerator = vec![1.0f32; store.len()];
+    let mut denominator = vec![1.0f32; store.len()];
+    let mut has_negative = false;
+    for (sign, word) in &expression.terms {
+        let vec = store.get(word).ok_or_else(|| format!("unknown word: {}", word))?;
+        let product = if *sign > 0.0 {
+            &mut numerator
+        } else {
+            has_negative = true;
+            &mut denominator
+        };
+        for (p, cos) in product.iter_mut().zip(store.cosine_scores(&vec)) {
+            *p *= (cos + 1.0) / 2.0;
+        }
+    }
+
+    let scores = if has_negative {
+        numerator.iter().zip(&denominator).map(|(n, d)| n / (d + COSMUL_EPSILON)).collect()
+    } else {
+        numerator
+    };
+    Ok(top_k(scores, store, &exclude, k, true))
+}
@@ -0,0 +1,381 @@
+//! Annoy (Spotify's "approximate nearest neighbors oh yeah") forests of
+//! random-projection trees, in Annoy's own `.ann` file layout for the
+//! `angular` metric, so an index built by the Python `annoy` package can be
+//! searched here and one built here can be loaded there.
+//!
+//! A file is an array of fixed-size nodes: `n_descendants` (i32), two
+//! children (i32; an item's squared norm is kept in their place), then the
+//! vector. Nodes `0..n_items` are the items themselves; a node with at most
+//! `dim + 2` descendants lists them in place of its children and vector, and
+//! any larger node splits by the sign of the dot product with its vector.
+//! The roots are copied to the end of the file, where loading finds them.
+//!
+//! Item `i` of an index is row `i` of the vector file, so an index only
+//! matches the file (and row order) it was built from.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use crate::index::CandidateIndex;
+use crate::rng::Rng;
+use crate::store::EmbeddingStore;
+use crate::vector::dot_product;
+
+/// Fixed so the same file always gets the same trees
+const SEED: u64 = 0x0a22_0e55_7ee5_5eed;
+
+/// Steps of the two-means split that picks each hyperplane, as in Annoy
+const TWO_MEANS_STEPS: usize = 200;
+
+/// A split leaving more than this share of the items on one side is redone at random
+const MAX_IMBALANCE: f32 = 0.95;
+
+/// Results per query assumed by the default `search_k`, as Annoy's default
+/// for `get_nns_by_vector(v, 10)` is 10 × the number of trees
+const DEFAULT_RESULTS: usize = 10;
+
+pub struct AnnoyIndex {
+    dim: usize,
+    /// Every node, `dim + 3` 32-bit words each
+    nodes: Vec<u32>,
+    roots: Vec<usize>,
+    items: usize,
+    /// Nodes to visit per query; more raise recall
+    search_k: usize,
+}
+
+/// Heap entry ordered by priority alone
+struct Pending(f32, usize);
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl AnnoyIndex {
+    /// Loads a `.ann` file built for `dim`-dimensional vectors with the angular metric
+    pub fn load(path: &str, dim: usize, search_k: Option<usize>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let node_bytes = (dim + 3) * 4;
+        let not_annoy = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not an angular Annoy index of {}-d vectors", path, dim),
+            )
+        };
+        if bytes.is_empty() || bytes.len() % node_bytes != 0 {
+            return Err(not_annoy());
+        }
+        let nodes = bytes.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect();
+        let mut index = AnnoyIndex { dim, nodes, roots: Vec::new(), items: 0, search_k: 0 };
+
+        // The roots are the trailing run of nodes whose descendants are every item
+        let count = index.nodes.len() / (dim + 3);
+        let mut items = None;
+        for node in (0..count).rev() {
+            let descendants = index.descendants(node);
+            if items.is_some_and(|n| n != descendants) {
+                break;
+            }
+            items = Some(descendants);
+            index.roots.push(node);
+        }
+        // The run starts with the last tree's root itself, before the copies
+        if index.roots.len() > 1 && index.child(index.roots[0], 0) == index.child(index.roots[index.roots.len() - 1], 0)
+        {
+            index.roots.pop();
+        }
+        index.roots.reverse();
+        index.items = items.unwrap_or(0);
+        if !index.is_well_formed(count) {
+            return Err(not_annoy());
+        }
+        index.search_k = search_k.unwrap_or(DEFAULT_RESULTS * index.roots.len());
+        Ok(index)
+    }
+
+    /// Whether every node `candidates` can reach points where it should:
+    /// leaves at items, and split nodes at nodes before them, as Annoy
+    /// writes children before their parent. Nothing then indexes past the
+    /// file, and no descent can loop.
+    fn is_well_formed(&self, count: usize) -> bool {
+        self.items <= count
+            && (0..count).all(|node| match self.descendants(node) {
+                1 if node < self.items => true,
+                n if n <= self.max_leaf() => (0..n).all(|i| self.child(node, i) < self.items),
+                _ => (0..2).all(|i| self.child(node, i) < node),
+            })
+    }
+
+    /// Builds `trees` trees over every row of `store`
+    pub fn build(store: &dyn EmbeddingStore, trees: usize, search_k: Option<usize>) -> Self {
+        let dim = store.dim();
+        let mut nodes = Vec::with_capacity(store.len() * (dim + 3) * 2);
+        for (_, vec) in store.iter() {
+            nodes.extend([1, dot_product(&vec, &vec).to_bits(), 0]);
+            nodes.extend(vec.iter().map(|x| x.to_bits()));
+        }
+        let items = store.len();
+        let mut index = AnnoyIndex { dim, nodes, roots: Vec::new(), items, search_k: 0 };
+
+        let mut rng = Rng::new(SEED);
+        for _ in 0..trees {
+            let root = index.make_tree((0..items).collect(), true, &mut rng);
+            index.roots.push(root);
+        }
+        // Copy the roots to the end, where loading looks for them
+        for i in 0..index.roots.len() {
+            let root = index.roots[i] * (dim + 3);
+            index.nodes.extend_from_within(root..root + dim + 3);
+        }
+        index.search_k = search_k.unwrap_or(DEFAULT_RESULTS * trees);
+        index
+    }
+
+    /// Writes the index as a `.ann` file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        for word in &self.nodes {
+            out.write_all(&word.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    pub fn trees(&self) -> usize {
+        self.roots.len()
+    }
+
+    fn node(&self, node: usize) -> &[u32] {
+        &self.nodes[node * (self.dim + 3)..(node + 1) * (self.dim + 3)]
+    }
+
+    fn descendants(&self, node: usize) -> usize {
+        self.node(node)[0] as i32 as usize
+    }
+
+    fn child(&self, node: usize, i: usize) -> usize {
+        self.node(node)[1 + i] as usize
+    }
+
+    fn vector(&self, node: usize) -> Vec<f32> {
+        self.node(node)[3..].iter().map(|&w| f32::from_bits(w)).collect()
+    }
+
+    /// Dot product of a node's vector with `v`, without copying the vector out
+    fn dot(&self, node: usize, v: &[f32]) -> f32 {
+        self.node(node)[3..].iter().zip(v).map(|(&w, x)| f32::from_bits(w) * x).sum()
+    }
+
+    /// Item ids listed directly by nodes this small
+    fn max_leaf(&self) -> usize {
+        self.dim + 2
+    }
+
+    /// Splits `indices` recursively, appending the nodes; returns the root's id
+    fn make_tree(&mut self, indices: Vec<usize>, is_root: bool, rng: &mut Rng) -> usize {
+        if indices.len() == 1 && !is_root {
+            return indices[0];
+        }
+        let descendants = if is_root { self.items } else { indices.len() };
+        if indices.len() <= self.max_leaf() && (!is_root || self.items <= self.max_leaf() || indices.len() == 1) {
+            let mut node = vec![0; self.dim + 3];
+            node[0] = descendants as u32;
+            node[1..1 + indices.len()].iter_mut().zip(&indices).for_each(|(w, &i)| *w = i as u32);
+            self.nodes.extend(node);
+            return self.nodes.len() / (self.dim + 3) - 1;
+        }
+
+        let mut normal = self.split(&indices, rng);
+        let mut sides: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+        for &i in &indices {
+            let margin = self.dot(i, &normal);
+            let side = if margin != 0.0 { margin > 0.0 } else { rng.below(2) == 1 };
+            sides[side as usize].push(i);
+        }
+        // Without a usable hyperplane, fall back to random sides
+        while imbalance(&sides) > MAX_IMBALANCE {
+            normal = vec![0.0; self.dim];
+            sides = [Vec::new(), Vec::new()];
+            for &i in &indices {
+                sides[rng.below(2)].push(i);
+            }
+        }
+
+        // The smaller side is built first, so nodes are numbered as Annoy numbers them
+        let [left, right] = sides;
+        let children = if left.len() > right.len() {
+            let right = self.make_tree(right, false, rng);
+            [self.make_tree(left, false, rng), right]
+        } else {
+            let left = self.make_tree(left, false, rng);
+            [left, self.make_tree(right, false, rng)]
+        };
+
+        self.nodes.extend([descendants as u32, children[0] as u32, children[1] as u32]);
+        self.nodes.extend(normal.iter().map(|x| x.to_bits()));
+        self.nodes.len() / (self.dim + 3) - 1
+    }
+
+    /// Unit normal of a hyperplane between two cluster centers found by
+    /// spherical two-means on a sample of `indices`
+    fn split(&self, indices: &[usize], rng: &mut Rng) -> Vec<f32> {
+        let i = rng.below(indices.len());
+        let j = rng.below(indices.len() - 1);
+        let j = j + (j >= i) as usize;
+        let mut p = unit(self.vector(indices[i]));
+        let mut q = unit(self.vector(indices[j]));
+        let (mut ic, mut jc) = (1.0, 1.0);
+        for _ in 0..TWO_MEANS_STEPS {
+            let x = self.vector(indices[rng.below(indices.len())]);
+            let norm = dot_product(&x, &x).sqrt();
+            if norm <= 0.0 {
+                continue;
+            }
+            let di = ic * angular(&p, &x);
+            let dj = jc * angular(&q, &x);
+            let (center, count) = match di.partial_cmp(&dj) {
+                Some(Ordering::Less) => (&mut p, &mut ic),
+                Some(Ordering::Greater) => (&mut q, &mut jc),
+                _ => continue,
+            };
+            for (c, v) in center.iter_mut().zip(&x) {
+                *c = (*c * *count + v / norm) / (*count + 1.0);
+            }
+            *count += 1.0;
+        }
+        unit(p.iter().zip(&q).map(|(a, b)| a - b).collect())
+    }
+}
+
+impl CandidateIndex for AnnoyIndex {
+    /// Annoy's search: descend every tree best-first, by the smallest margin
+    /// along the path, until `search_k` items have been collected
+    fn candidates(&self, query: &[f32]) -> Vec<usize> {
+        let mut pending: BinaryHeap<Pending> = self.roots.iter().map(|&root| Pending(f32::INFINITY, root)).collect();
+        let mut found = Vec::new();
+        while found.len() < self.search_k {
+            let Some(Pending(priority, node)) = pending.pop() else { break };
+            let descendants = self.descendants(node);
+            if descendants == 1 && node < self.items {
+                found.push(node);
+            } else if descendants <= self.max_leaf() {
+                found.extend((0..descendants).map(|i| self.child(node, i)));
+            } else {
+                let margin = self.dot(node, query);
+                pending.push(Pending(priority.min(margin), self.child(node, 1)));
+                pending.push(Pending(priority.min(-margin), self.child(node, 0)));
+            }
+        }
+        found
+    }
+}
+
+/// Annoy's angular distance, `2 - 2 cos`
+fn angular(a: &[f32], b: &[f32]) -> f32 {
+    let norms = dot_product(a, a) * dot_product(b, b);
+    if norms > 0.0 {
+        2.0 - 2.0 * dot_product(a, b) / norms.sqrt()
+    } else {
+        2.0
+    }
+}
+
+fn unit(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = dot_product(&v, &v).sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+/// Share of the items on the larger side
+fn imbalance(sides: &[Vec<usize>; 2]) -> f32 {
+    let left = sides[0].len() as f32 / (sides[0].len() + sides[1].len()) as f32;
+    left.max(1.0 - left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use crate::DenseStore;
+
+    fn random_store(rows: usize, dim: usize) -> DenseStore {
+        let mut rng = Rng::new(7);
+        let mut store = DenseStore::new();
+        for i in 0..rows {
+            store.insert(format!("w{}", i), (0..dim).map(|_| rng.below(2001) as f32 / 1000.0 - 1.0).collect());
+        }
+        store
+    }
+
+    /// Offset in the file of word `word` of `node`
+    fn at(index: &AnnoyIndex, node: usize, word: usize) -> usize {
+        (node * (index.dim + 3) + word) * 4
+    }
+
+    #[test]
+    fn saved_indexes_load_back_the_same() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.ann");
+        let store = random_store(300, 4);
+        let built = AnnoyIndex::build(&store, 3, None);
+        built.save(&path).unwrap();
+        let loaded = AnnoyIndex::load(&path, 4, None).unwrap();
+        assert_eq!((loaded.trees(), loaded.items, &loaded.nodes), (3, 300, &built.nodes));
+        for (_, vec) in store.iter().take(10) {
+            assert_eq!(loaded.candidates(&vec), built.candidates(&vec));
+        }
+    }
+
+    #[test]
+    fn files_of_partial_nodes_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.ann");
+        AnnoyIndex::build(&random_store(300, 4), 3, None).save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(AnnoyIndex::load(&path, 4, None).is_err());
+        fs::write(&path, &bytes).unwrap();
+        assert!(AnnoyIndex::load(&path, 5, None).is_err());
+    }
+
+    #[test]
+    fn links_out_of_range_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.ann");
+        let index = AnnoyIndex::build(&random_store(300, 4), 3, None);
+        index.save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let count = bytes.len() / at(&index, 1, 0);
+        let split = (index.items..count).find(|&n| index.descendants(n) > index.max_leaf()).unwrap();
+        let leaf = (index.items..count).find(|&n| (2..=index.max_leaf()).contains(&index.descendants(n))).unwrap();
+        for (offset, value) in [
+            (at(&index, split, 1), count as u32 + 5),
+            (at(&index, split, 2), split as u32),
+            (at(&index, leaf, 1), index.items as u32),
+        ] {
+            let mut corrupt = bytes.clone();
+            corrupt[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            fs::write(&path, &corrupt).unwrap();
+            assert!(AnnoyIndex::load(&path, 4, None).is_err(), "{} at byte {} went unnoticed", value, offset);
+        }
+    }
+}
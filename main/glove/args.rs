@@ -0,0 +1,219 @@
+//! Tiny helpers for pulling flags out of the raw argument list,
+//! so the remaining arguments can be treated positionally.
+
+use crate::index::{IndexSpec, INDEXES};
+use crate::input::{archive_members, Source};
+use crate::load::{LoadOptions, Precision};
+use crate::mask::DimMask;
+use crate::merge::Conflict;
+use crate::metric::BuiltinMetric;
+use crate::models::{find_model, MODELS};
+use crate::postprocess::{Postprocess, POSTPROCESSES};
+
+/// Removes `name` from `args`, returning whether it was present
+pub fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes `name <value>` from `args`, returning the value
+pub fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == name)?;
+    if i + 1 >= args.len() {
+        args.remove(i);
+        return None;
+    }
+    args.remove(i);
+    Some(args.remove(i))
+}
+
+/// Removes `name <v1> ... <vn>` from `args`, returning the `n` values
+pub fn take_values(args: &mut Vec<String>, name: &str, n: usize) -> Option<Vec<String>> {
+    let i = args.iter().position(|a| a == name)?;
+    if i + n >= args.len() {
+        args.remove(i);
+        return None;
+    }
+    args.remove(i);
+    Some(args.drain(i..i + n).collect())
+}
+
+/// Removes `name` and the values after it, up to the next `--flag`
+pub fn take_list(args: &mut Vec<String>, name: &str) -> Option<Vec<String>> {
+    let i = args.iter().position(|a| a == name)?;
+    args.remove(i);
+    let end = args[i..].iter().position(|a| a.starts_with("--")).map_or(args.len(), |n| i + n);
+    Some(args.drain(i..end).collect())
+}
+
+/// Removes the loading flags (`--quiet`, `--strict`, `--precision f32|f16|int8|pq`
+/// with `--pq-subquantizers M --pq-bits B`, `--max-vocab N`, `--language`,
+/// `--tensor`, `--on-conflict`, `--threads N`) from `args`;
+/// `--quantize int8` is accepted as a shorthand for `--precision int8`
+pub fn take_load_options(args: &mut Vec<String>) -> Result<LoadOptions, String> {
+    let precision = match (take_option(args, "--precision"), take_option(args, "--quantize")) {
+        (None, None) => Precision::F32,
+        (Some(name), None) => Precision::parse(&name).ok_or("--precision must be f32, f16, int8 or pq")?,
+        (None, Some(name)) if name == "int8" => Precision::Int8,
+        (None, Some(_)) => return Err("--quantize must be int8".to_string()),
+        (Some(_), Some(_)) => return Err("use either --precision or --quantize, not both".to_string()),
+    };
+    let subquantizers = take_option(args, "--pq-subquantizers");
+    let pq_bits = take_option(args, "--pq-bits");
+    let precision = match precision {
+        Precision::Pq { subquantizers: default_m, bits: default_bits } => Precision::Pq {
+            subquantizers: match subquantizers {
+                None => default_m,
+                Some(m) => {
+                    let m = m.parse().ok().filter(|&m| m > 0);
+                    Some(m.ok_or("--pq-subquantizers must be a positive number")?)
+                }
+            },
+            bits: match pq_bits {
+                None => default_bits,
+                Some(b) => b.parse().ok().filter(|b| (1..=8).contains(b)).ok_or("--pq-bits must be between 1 and 8")?,
+            },
+        },
+        _ if subquantizers.is_some() || pq_bits.is_some() => {
+            return Err("--pq-subquantizers and --pq-bits need --precision pq".to_string())
+        }
+        precision => precision,
+    };
+    let conflict = match take_option(args, "--on-conflict") {
+        None => Conflict::default(),
+        Some(name) => Conflict::parse(&name)
+            .ok_or_else(|| format!("--on-conflict must be one of: {}", Conflict::NAMES.join(", ")))?,
+    };
+    let max_vocab = match take_option(args, "--max-vocab") {
+        None => None,
+        Some(n) => Some(n.parse().map_err(|_| "--max-vocab must be a number of words")?),
+    };
+    let threads = match take_option(args, "--threads") {
+        None => None,
+        Some(n) => Some(n.parse().ok().filter(|&n| n > 0).ok_or("--threads must be a positive number")?),
+    };
+    Ok(LoadOptions {
+        quiet: take_flag(args, "--quiet"),
+        precision,
+        max_vocab,
+        strict: take_flag(args, "--strict"),
+        language: take_option(args, "--language"),
+        tensor: take_option(args, "--tensor"),
+        conflict,
+        threads,
+        ..LoadOptions::default()
+    })
+}
+
+/// Removes `--rescore N` from `args` (0 when absent)
+pub fn take_rescore(args: &mut Vec<String>) -> Result<usize, String> {
+    match take_option(args, "--rescore") {
+        None => Ok(0),
+        Some(n) => n.parse().map_err(|_| "--rescore must be a number of candidates".to_string()),
+    }
+}
+
+/// Removes `--metric <name>` from `args`
+pub fn take_metric(args: &mut Vec<String>) -> Result<Option<BuiltinMetric>, String> {
+    match take_option(args, "--metric") {
+        None => Ok(None),
+        Some(name) => BuiltinMetric::parse(&name)
+            .map(Some)
+            .ok_or_else(|| format!("--metric must be one of: {}", BuiltinMetric::NAMES.join(", "))),
+    }
+}
+
+/// Removes `--mask-dims <list>` or `--mask-file <path>` from `args`
+pub fn take_mask(args: &mut Vec<String>) -> Result<Option<DimMask>, String> {
+    match (take_option(args, "--mask-dims"), take_option(args, "--mask-file")) {
+        (Some(_), Some(_)) => Err("use either --mask-dims or --mask-file, not both".to_string()),
+        (Some(spec), None) => DimMask::parse(&spec).map(Some),
+        (None, Some(path)) => DimMask::from_file(&path).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Removes `--index <name>` and its parameters (`--bits N --tables N
+/// --candidates N` for `lsh`, `--nlist N --nprobe N` for `ivf`, `--trees N
+/// --search-k N --index-file <path>` for `annoy`) from `args`
+pub fn take_index(args: &mut Vec<String>) -> Result<Option<IndexSpec>, String> {
+    let nlist = match take_option(args, "--nlist") {
+        None => None,
+        Some(n) => Some(n.parse().ok().filter(|&n| n > 0).ok_or("--nlist must be a positive number")?),
+    };
+    let mut number = |name: &str, default: usize| match take_option(args, name) {
+        None => Ok(default),
+        Some(n) => n.parse().ok().filter(|&n| n > 0).ok_or(format!("{} must be a positive number", name)),
+    };
+    let bits = number("--bits", IndexSpec::LSH_BITS)?;
+    let tables = number("--tables", IndexSpec::LSH_TABLES)?;
+    let candidates = number("--candidates", IndexSpec::LSH_CANDIDATES)?;
+    let nprobe = number("--nprobe", IndexSpec::IVF_NPROBE)?;
+    let trees = number("--trees", IndexSpec::ANNOY_TREES)?;
+    let search_k = match take_option(args, "--search-k") {
+        None => None,
+        Some(n) => Some(n.parse().ok().filter(|&n| n > 0).ok_or("--search-k must be a positive number")?),
+    };
+    let file = take_option(args, "--index-file");
+    match take_option(args, "--index").as_deref() {
+        Some("annoy") => Ok(Some(IndexSpec::Annoy { trees, search_k, file })),
+        _ if file.is_some() => Err("--index-file needs --index annoy".to_string()),
+        None => Ok(None),
+        Some("lsh") => Ok(Some(IndexSpec::Lsh { bits, tables, candidates })),
+        Some("ivf") => Ok(Some(IndexSpec::Ivf { nlist, nprobe })),
+        Some(_) => Err(format!("--index must be one of: {}", INDEXES.join(", "))),
+    }
+}
+
+/// Removes `--postprocess <name>` and its parameters (`--abtt-components D`
+/// for `abtt`) from `args`
+pub fn take_postprocess(args: &mut Vec<String>) -> Result<Option<Postprocess>, String> {
+    let components = match take_option(args, "--abtt-components") {
+        None => None,
+        Some(n) => Some(n.parse().ok().filter(|&n| n > 0).ok_or("--abtt-components must be a positive number")?),
+    };
+    match take_option(args, "--postprocess").as_deref() {
+        Some("abtt") => Ok(Some(Postprocess::Abtt { components })),
+        _ if components.is_some() => Err("--abtt-components needs --postprocess abtt".to_string()),
+        None => Ok(None),
+        Some("whiten") => Ok(Some(Postprocess::Whiten)),
+        Some(_) => Err(format!("--postprocess must be one of: {}", POSTPROCESSES.join(", "))),
+    }
+}
+
+/// Removes the vector source from `args`: `--model <name>` (downloaded on first use),
+/// `--archive <zip> --member <file>`, or the positional argument at `index`
+pub fn take_source(args: &mut Vec<String>, index: usize) -> Result<Source, String> {
+    if let Some(name) = take_option(args, "--model") {
+        let Some(model) = find_model(&name) else {
+            let names: Vec<&str> = MODELS.iter().map(|m| m.name).collect();
+            return Err(format!("unknown model '{}', expected one of: {}", name, names.join(", ")));
+        };
+        if !model.is_downloaded() {
+            eprintln!("Downloading {} from {}...", model.name, model.url);
+            model.download().map_err(|e| e.to_string())?;
+        }
+        return Ok(model.source());
+    }
+
+    let member = take_option(args, "--member");
+    if let Some(archive) = take_option(args, "--archive") {
+        let Some(member) = member else {
+            let members = archive_members(&archive).map_err(|e| format!("{}: {}", archive, e))?;
+            return Err(format!("--archive needs --member, one of: {}", members.join(", ")));
+        };
+        return Ok(Source::ArchiveMember { archive, member });
+    }
+    if member.is_some() {
+        return Err("--member needs --archive".to_string());
+    }
+    if index >= args.len() {
+        return Err("missing vector file".to_string());
+    }
+    Ok(Source::Path(args.remove(index)))
+}
@@ -0,0 +1,59 @@
+//! The vectors as `ndarray` arrays, for passing them to the Rust scientific
+//! crates built on it (linfa, ndarray-stats, ...). In-memory vectors are
+//! viewed where they lie, without copying; other backends are copied out.
+
+use ::ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+
+use crate::dense::DenseStore;
+use crate::expression::{Combine, Expression};
+use crate::metric::Metric;
+use crate::search::find_nearest_neighbors;
+use crate::store::EmbeddingStore;
+
+/// The `len x dim` matrix of in-memory vectors, one row per word in file order
+pub fn matrix_view(store: &DenseStore) -> ArrayView2<'_, f32> {
+    ArrayView2::from_shape((store.len(), store.dim()), store.matrix()).expect("rows are all `dim` long")
+}
+
+/// A copy of any store's vectors as a `len x dim` matrix, row `i` being the word of id `i`
+pub fn to_array2(store: &dyn EmbeddingStore) -> Array2<f32> {
+    let mut data = Vec::with_capacity(store.len() * store.dim());
+    for (_, vec) in store.iter() {
+        data.extend_from_slice(&vec);
+    }
+    Array2::from_shape_vec((store.len(), store.dim()), data).expect("rows are all `dim` long")
+}
+
+/// The vector of `word`, viewed in the matrix
+pub fn word_view<'a>(store: &'a DenseStore, word: &str) -> Option<ArrayView1<'a, f32>> {
+    store.vector(word).map(ArrayView1::from)
+}
+
+/// The vector an expression such as `king - man + woman` searches around
+pub fn query_array(store: &dyn EmbeddingStore, expression: &Expression, combine: Combine) -> Option<Array1<f32>> {
+    expression.query_vector(store, combine).map(Array1::from)
+}
+
+/// The `k` words nearest to a query vector computed with ndarray; a view
+/// that is not contiguous is copied first
+pub fn nearest_to(
+    store: &dyn EmbeddingStore,
+    query: ArrayView1<'_, f32>,
+    k: usize,
+    metric: &dyn Metric,
+) -> Vec<(String, f32)> {
+    match query.as_slice() {
+        Some(query) => find_nearest_neighbors(query, store, &[], k, metric),
+        None => find_nearest_neighbors(&query.to_vec(), store, &[], k, metric),
+    }
+}
+
+/// In-memory vectors from the rows of `matrix`, row `i` being `words[i]`; a
+/// repeated word keeps its last row
+pub fn from_array2(words: &[String], matrix: ArrayView2<'_, f32>) -> DenseStore {
+    let mut store = DenseStore::new();
+    for (word, row) in words.iter().zip(matrix.rows()) {
+        store.insert(word.clone(), row.to_vec());
+    }
+    store
+}
@@ -0,0 +1,118 @@
+//! Semantic axes defined by a pair of opposite words, e.g. hot ↔ cold, and
+//! concept axes averaged over several pairs, e.g. man ↔ woman and king ↔ queen.
+
+use crate::store::{unknown_words, EmbeddingStore};
+use crate::vector::{cosine_similarity, norm};
+use crate::{Error, WordVec};
+
+/// Bundled antonym pairs used when no axis is given explicitly
+pub const ANTONYM_PAIRS: &[(&str, &str)] = &[
+    ("hot", "cold"),
+    ("good", "bad"),
+    ("big", "small"),
+    ("large", "small"),
+    ("high", "low"),
+    ("up", "down"),
+    ("old", "new"),
+    ("young", "old"),
+    ("rich", "poor"),
+    ("happy", "sad"),
+    ("love", "hate"),
+    ("war", "peace"),
+    ("day", "night"),
+    ("light", "dark"),
+    ("fast", "slow"),
+    ("strong", "weak"),
+    ("early", "late"),
+    ("open", "closed"),
+    ("win", "lose"),
+    ("buy", "sell"),
+    ("first", "last"),
+    ("black", "white"),
+    ("man", "woman"),
+    ("king", "queen"),
+    ("north", "south"),
+    ("east", "west"),
+    ("left", "right"),
+    ("true", "false"),
+    ("begin", "end"),
+    ("friend", "enemy"),
+];
+
+/// Finds a bundled antonym of `word`, if it has one
+pub fn known_antonym(word: &str) -> Option<&'static str> {
+    ANTONYM_PAIRS.iter().find_map(|&(a, b)| match word {
+        w if w == a => Some(b),
+        w if w == b => Some(a),
+        _ => None,
+    })
+}
+
+/// The line from one word's vector to another's
+pub struct Axis {
+    pub from: WordVec,
+    pub to: WordVec,
+}
+
+impl Axis {
+    /// Builds the axis between two in-vocabulary words
+    pub fn between(store: &dyn EmbeddingStore, from: &str, to: &str) -> Option<Axis> {
+        Some(Axis {
+            from: store.get(from)?.into_owned(),
+            to: store.get(to)?.into_owned(),
+        })
+    }
+
+    /// Mirrors `vec` across the hyperplane through the axis midpoint that is
+    /// perpendicular to the axis: the component along the axis flips sign
+    /// while everything orthogonal to it is kept, so `from` maps onto `to`.
+    pub fn reflect(&self, vec: &[f32]) -> WordVec {
+        let dir: WordVec = self.from.iter().zip(&self.to).map(|(a, b)| a - b).collect();
+        let norm_sq = dir.iter().map(|x| x * x).sum::<f32>() + 1e-10;
+
+        // Offset from the midpoint, projected onto the axis direction
+        let along = vec
+            .iter()
+            .zip(self.from.iter().zip(&self.to))
+            .zip(&dir)
+            .map(|((v, (a, b)), d)| (v - (a + b) / 2.0) * d)
+            .sum::<f32>()
+            / norm_sq;
+
+        vec.iter().zip(&dir).map(|(v, d)| v - 2.0 * along * d).collect()
+    }
+}
+
+/// A direction in the space defined by one or more word pairs, such as
+/// man ↔ woman and king ↔ queen: the mean of the pairs' unit-vector
+/// offsets, scaled to unit length
+pub struct ConceptAxis {
+    pub direction: WordVec,
+}
+
+impl ConceptAxis {
+    /// Builds the axis pointing from the first word of each pair to the second
+    pub fn from_pairs(store: &dyn EmbeddingStore, pairs: &[(String, String)]) -> Result<ConceptAxis, Error> {
+        let words: Vec<&str> = pairs.iter().flat_map(|(a, b)| [a.as_str(), b.as_str()]).collect();
+        if words.iter().any(|w| !store.contains(w)) {
+            return Err(unknown_words(store, &words));
+        }
+        let mut direction = vec![0.0f32; store.dim()];
+        for (from, to) in pairs {
+            let (Some(from), Some(to)) = (store.get(from), store.get(to)) else { continue };
+            let (from_norm, to_norm) = (norm(&from).max(f32::MIN_POSITIVE), norm(&to).max(f32::MIN_POSITIVE));
+            for ((d, a), b) in direction.iter_mut().zip(from.iter()).zip(to.iter()) {
+                *d += b / to_norm - a / from_norm;
+            }
+        }
+        let length = norm(&direction).max(f32::MIN_POSITIVE);
+        direction.iter_mut().for_each(|d| *d /= length);
+        Ok(ConceptAxis { direction })
+    }
+
+    /// The signed projection of `vec`'s unit vector on the axis: positive
+    /// towards the pairs' second words, negative towards their first
+    pub fn project(&self, vec: &[f32]) -> f32 {
+        cosine_similarity(vec, &self.direction)
+    }
+}
@@ -0,0 +1,62 @@
+//! Loading vectors on another thread, so an interactive session or server can
+//! start taking input while a large file is parsed. Whoever needs the vectors
+//! first waits on the `BackgroundLoad`, which reports how far the load has got.
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::input::Source;
+use crate::load::LoadOptions;
+use crate::shards::is_sharded;
+use crate::store::{load_dense, EmbeddingStore};
+
+/// How often `wait` reports progress
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Vectors being read into memory on another thread
+pub struct BackgroundLoad {
+    handle: JoinHandle<Result<Box<dyn EmbeddingStore + Send>>>,
+    bytes: Arc<AtomicU64>,
+    total_bytes: Option<u64>,
+}
+
+impl BackgroundLoad {
+    /// Starts reading `source` into memory as the `dense` backend does; the
+    /// load's own progress line is left off, as the terminal is in use
+    pub fn start(source: &Source, options: &LoadOptions) -> BackgroundLoad {
+        let bytes = Arc::new(AtomicU64::new(0));
+        let total_bytes = match source {
+            Source::Path(path) if is_sharded(path) => None,
+            _ => source.text_len(),
+        };
+        let options = LoadOptions { quiet: true, progress: Some(Arc::clone(&bytes)), ..options.clone() };
+        let source = source.clone();
+        let handle = thread::spawn(move || load_dense(&source, &options));
+        BackgroundLoad { handle, bytes, total_bytes }
+    }
+
+    /// Whether the load has finished, successfully or not
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Bytes read so far, and the size of the source when known
+    pub fn progress(&self) -> (u64, Option<u64>) {
+        (self.bytes.load(Ordering::Relaxed), self.total_bytes)
+    }
+
+    /// Waits for the vectors, calling `waiting` with the progress every so
+    /// often until they are loaded
+    pub fn wait(self, mut waiting: impl FnMut(u64, Option<u64>)) -> Result<Box<dyn EmbeddingStore + Send>> {
+        while !self.is_finished() {
+            let (bytes, total_bytes) = self.progress();
+            waiting(bytes, total_bytes);
+            thread::sleep(POLL_INTERVAL);
+        }
+        self.handle.join().map_err(|_| io::Error::other("the loading thread panicked"))?
+    }
+}
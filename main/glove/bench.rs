@@ -0,0 +1,164 @@
+//! Timing of the ways a store can be searched, so the fastest configuration
+//! that still finds the true neighbors can be picked for a file.
+//!
+//! Queries are the vectors of random rows (each excluding its own word), and
+//! recall is measured against the exact cosine top-k.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::index::{build_index, candidate_scores, IndexSpec};
+use crate::metric::BuiltinMetric;
+use crate::rng::Rng;
+use crate::search::{find_nearest_neighbors_batch, top_k};
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+use crate::WordVec;
+
+/// Names of the configurations `bench` can time
+pub const CONFIGS: &[&str] = &["exact", "scan", "batch", "lsh", "ivf", "annoy"];
+
+/// One way of answering a cosine query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Config {
+    /// The store's own scan of every row (matrix-vector product for loaded
+    /// vectors, BLAS in builds with the `blas` feature)
+    Exact,
+    /// Row-by-row scoring through the store's iterator
+    Scan,
+    /// The store's own scan, 64 queries at a time
+    Batch,
+    /// An approximate index
+    Index(IndexSpec),
+}
+
+impl Config {
+    /// The configuration named `name`, with default index parameters
+    pub fn parse(name: &str) -> Option<Config> {
+        Some(match name {
+            "exact" => Config::Exact,
+            "scan" => Config::Scan,
+            "batch" => Config::Batch,
+            "lsh" => Config::Index(IndexSpec::Lsh {
+                bits: IndexSpec::LSH_BITS,
+                tables: IndexSpec::LSH_TABLES,
+                candidates: IndexSpec::LSH_CANDIDATES,
+            }),
+            "ivf" => Config::Index(IndexSpec::Ivf { nlist: None, nprobe: IndexSpec::IVF_NPROBE }),
+            "annoy" => Config::Index(IndexSpec::Annoy { trees: IndexSpec::ANNOY_TREES, search_k: None, file: None }),
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Config::Exact => "exact",
+            Config::Scan => "scan",
+            Config::Batch => "batch",
+            Config::Index(IndexSpec::Lsh { .. }) => "lsh",
+            Config::Index(IndexSpec::Ivf { .. }) => "ivf",
+            Config::Index(IndexSpec::Annoy { .. }) => "annoy",
+        }
+    }
+}
+
+/// Timings and recall of one configuration
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub config: Config,
+    /// Time to build the index, if there is one
+    pub build: Option<Duration>,
+    /// Each query's latency, sorted; for `batch`, the batch's time split evenly
+    latencies: Vec<Duration>,
+    pub total: Duration,
+    /// Mean share of the exact top-k found
+    pub recall: f64,
+}
+
+impl BenchResult {
+    /// The latency `p` (0 to 1) of the way through the sorted latencies
+    pub fn percentile(&self, p: f64) -> Duration {
+        let i = ((self.latencies.len() as f64 * p).ceil() as usize).clamp(1, self.latencies.len().max(1)) - 1;
+        self.latencies.get(i).copied().unwrap_or_default()
+    }
+
+    /// Queries answered per second
+    pub fn throughput(&self) -> f64 {
+        self.latencies.len() as f64 / self.total.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// The vectors of `count` random rows, each excluding its own word
+pub fn sample_queries(store: &dyn EmbeddingStore, count: usize, rng: &mut Rng) -> Vec<(WordVec, Vec<String>)> {
+    (0..count)
+        .filter_map(|_| store.get_by_id(rng.below(store.len())))
+        .map(|(word, vec)| (vec.into_owned(), vec![word.into_owned()]))
+        .collect()
+}
+
+/// The exact cosine top-`k` words of each query
+pub fn exact_answers(store: &dyn EmbeddingStore, queries: &[(WordVec, Vec<String>)], k: usize) -> Vec<Vec<String>> {
+    queries.iter().map(|(query, exclude)| words(top_k(store.cosine_scores(query), store, exclude, k, true))).collect()
+}
+
+fn words(found: Vec<(String, f32)>) -> Vec<String> {
+    found.into_iter().map(|(word, _)| word).collect()
+}
+
+/// Answers every query with `config`, timing each, and compares the answers with `truth`
+pub fn bench(
+    store: &dyn EmbeddingStore,
+    config: &Config,
+    queries: &[(WordVec, Vec<String>)],
+    truth: &[Vec<String>],
+    k: usize,
+) -> io::Result<BenchResult> {
+    let mut build = None;
+    let mut latencies = Vec::with_capacity(queries.len());
+    let mut answers = Vec::with_capacity(queries.len());
+    let start = Instant::now();
+    match config {
+        Config::Batch => {
+            answers.extend(find_nearest_neighbors_batch(queries, store, k, &BuiltinMetric::Cosine));
+            let each = start.elapsed() / queries.len().max(1) as u32;
+            latencies.resize(queries.len(), each);
+        }
+        _ => {
+            let index = match config {
+                Config::Index(spec) => {
+                    let index = build_index(store, spec)?;
+                    build = Some(start.elapsed());
+                    Some(index)
+                }
+                _ => None,
+            };
+            for (query, exclude) in queries {
+                let asked = Instant::now();
+                let scores = match (config, &index) {
+                    (_, Some(index)) => candidate_scores(store, index.as_ref(), query),
+                    (Config::Scan, None) => store.iter().map(|(_, vec)| cosine_similarity(query, &vec)).collect(),
+                    _ => store.cosine_scores(query),
+                };
+                answers.push(top_k(scores, store, exclude, k, true));
+                latencies.push(asked.elapsed());
+            }
+        }
+    }
+    let total = start.elapsed() - build.unwrap_or_default();
+    latencies.sort();
+
+    let recall = answers
+        .into_iter()
+        .zip(truth)
+        .map(|(found, truth)| {
+            let hits = words(found).iter().filter(|w| truth.contains(w)).count();
+            if truth.is_empty() {
+                1.0
+            } else {
+                hits as f64 / truth.len() as f64
+            }
+        })
+        .sum::<f64>()
+        / queries.len().max(1) as f64;
+    Ok(BenchResult { config: config.clone(), build, latencies, total, recall })
+}
@@ -0,0 +1,88 @@
+//! Compact binary vector file, laid out so it can be memory-mapped:
+//!
+//! ```text
+//! "GLVB" | u32 version | u32 dim | u64 count      (20-byte header, little-endian)
+//! count * dim f32                                 (row-major matrix)
+//! count * (u32 byte length, UTF-8 word)           (word table, same row order)
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::store::EmbeddingStore;
+
+pub const MAGIC: &[u8; 4] = b"GLVB";
+pub const VERSION: u32 = 1;
+pub const HEADER_LEN: usize = 20;
+
+/// Dimensions read from the start of a binary vector file
+pub struct Header {
+    pub dim: usize,
+    pub count: usize,
+    words_offset: usize,
+}
+
+impl Header {
+    /// Byte offset of the word table
+    pub fn words_offset(&self) -> usize {
+        self.words_offset
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Checks the magic and version of a whole binary vector file and reads the
+/// matrix shape, which the file must be long enough to hold
+pub fn parse_header(bytes: &[u8]) -> io::Result<Header> {
+    let header = parse_stream_header(bytes)?;
+    if header.words_offset > bytes.len() {
+        return Err(invalid("binary vector file is shorter than its header says"));
+    }
+    Ok(header)
+}
+
+/// `parse_header` for the first `HEADER_LEN` bytes of a file being streamed,
+/// whose length is not known yet
+pub fn parse_stream_header(bytes: &[u8]) -> io::Result<Header> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(invalid("not a binary vector file"));
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(invalid("unsupported binary vector file version"));
+    }
+    let dim = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let count = usize::try_from(u64::from_le_bytes(bytes[12..20].try_into().unwrap()))
+        .map_err(|_| invalid("binary vector file has too many rows"))?;
+    let words_offset = count
+        .checked_mul(dim)
+        .and_then(|n| n.checked_mul(4))
+        .and_then(|n| n.checked_add(HEADER_LEN))
+        .ok_or_else(|| invalid("binary vector file has too many rows"))?;
+    Ok(Header { dim, count, words_offset })
+}
+
+/// Writes every entry of a store as a binary vector file
+pub fn write_binary(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+    out.write_all(&(store.dim() as u32).to_le_bytes())?;
+    out.write_all(&(store.len() as u64).to_le_bytes())?;
+
+    for (_, vec) in store.iter() {
+        for val in vec.iter() {
+            out.write_all(&val.to_le_bytes())?;
+        }
+    }
+
+    for (word, _) in store.iter() {
+        out.write_all(&(word.len() as u32).to_le_bytes())?;
+        out.write_all(word.as_bytes())?;
+    }
+
+    out.flush()
+}
@@ -0,0 +1,54 @@
+//! Matrix-vector products through the system BLAS (`libblas`, e.g. the
+//! reference implementation or OpenBLAS), via its CBLAS interface.
+
+use std::ffi::c_int;
+
+const CBLAS_ROW_MAJOR: c_int = 101;
+const CBLAS_NO_TRANS: c_int = 111;
+
+#[link(name = "blas")]
+extern "C" {
+    #[allow(clippy::too_many_arguments)]
+    fn cblas_sgemv(
+        order: c_int,
+        trans: c_int,
+        m: c_int,
+        n: c_int,
+        alpha: f32,
+        a: *const f32,
+        lda: c_int,
+        x: *const f32,
+        incx: c_int,
+        beta: f32,
+        y: *mut f32,
+        incy: c_int,
+    );
+}
+
+/// `matrix * query` for a row-major `rows x query.len()` matrix
+pub fn sgemv(matrix: &[f32], rows: usize, query: &[f32]) -> Vec<f32> {
+    let dim = query.len();
+    assert_eq!(matrix.len(), rows * dim, "matrix is not rows x dim");
+    let mut out = vec![0.0f32; rows];
+    // CBLAS sizes are C ints, so very tall matrices go in slices
+    let max_rows = (c_int::MAX as usize / dim.max(1)).max(1);
+    for (block, out) in matrix.chunks(max_rows * dim).zip(out.chunks_mut(max_rows)) {
+        unsafe {
+            cblas_sgemv(
+                CBLAS_ROW_MAJOR,
+                CBLAS_NO_TRANS,
+                out.len() as c_int,
+                dim as c_int,
+                1.0,
+                block.as_ptr(),
+                dim as c_int,
+                query.as_ptr(),
+                1,
+                0.0,
+                out.as_mut_ptr(),
+                1,
+            );
+        }
+    }
+    out
+}
@@ -0,0 +1,85 @@
+//! A least-recently-used cache for query results, so queries that recur in
+//! `--stdin` sessions and on the server are answered without another scan.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+
+/// Entries kept by default
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Holds up to `capacity` values, evicting the one used longest ago
+pub struct LruCache<K, V> {
+    capacity: usize,
+    /// Each key's value and the tick it was last used at
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by the tick they were last used at, oldest first
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    /// A cache of `capacity` entries; with 0 it stores nothing
+    pub fn new(capacity: usize) -> Self {
+        LruCache { capacity, entries: HashMap::new(), order: BTreeMap::new(), tick: 0, hits: 0, misses: 0 }
+    }
+
+    /// The cached value of `key`, marking it as just used
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let Some((value, used)) = self.entries.get_mut(key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    /// Caches `value` for `key`, evicting the least recently used entry if full
+    pub fn put(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        } else if self.entries.len() == self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats { entries: self.entries.len(), capacity: self.capacity, hits: self.hits, misses: self.misses }
+    }
+}
+
+/// Occupancy and hit counts of a cache
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lookups = self.hits + self.misses;
+        let rate = if lookups > 0 { 100.0 * self.hits as f64 / lookups as f64 } else { 0.0 };
+        write!(
+            f,
+            "{} of {} entries, {} hits, {} misses ({:.1}% hit rate)",
+            self.entries, self.capacity, self.hits, self.misses, rate
+        )
+    }
+}
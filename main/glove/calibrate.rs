@@ -0,0 +1,222 @@
+//! Suggests similarity thresholds for a particular embedding file by comparing
+//! the cosine of random word pairs against pairs from a small synonym lexicon.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::input::Source;
+use crate::models::cache_dir;
+use crate::rng::Rng;
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+
+/// Bundled pairs of near-synonyms; pairs missing from a vocabulary are skipped
+pub const SYNONYM_PAIRS: &[(&str, &str)] = &[
+    ("big", "large"),
+    ("small", "little"),
+    ("fast", "quick"),
+    ("happy", "glad"),
+    ("sad", "unhappy"),
+    ("begin", "start"),
+    ("end", "finish"),
+    ("buy", "purchase"),
+    ("help", "assist"),
+    ("answer", "reply"),
+    ("car", "automobile"),
+    ("house", "home"),
+    ("children", "kids"),
+    ("rich", "wealthy"),
+    ("smart", "intelligent"),
+    ("angry", "furious"),
+    ("difficult", "hard"),
+    ("easy", "simple"),
+    ("shout", "yell"),
+    ("error", "mistake"),
+    ("gift", "present"),
+    ("doctor", "physician"),
+    ("said", "told"),
+    ("city", "town"),
+    ("road", "street"),
+    ("job", "work"),
+    ("stone", "rock"),
+    ("quiet", "silent"),
+    ("cry", "weep"),
+    ("mother", "mom"),
+    ("father", "dad"),
+    ("woman", "lady"),
+    ("man", "gentleman"),
+    ("world", "globe"),
+    ("show", "display"),
+    ("choose", "select"),
+    ("idea", "notion"),
+];
+
+/// Distribution of cosine similarities for one kind of pair
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f32,
+    pub p05: f32,
+    pub p50: f32,
+    pub p95: f32,
+}
+
+impl Summary {
+    fn from_scores(scores: &mut [f32]) -> Summary {
+        if scores.is_empty() {
+            return Summary::default();
+        }
+        scores.sort_by(|a, b| a.total_cmp(b));
+        let at = |q: f32| scores[((scores.len() - 1) as f32 * q).round() as usize];
+        Summary {
+            count: scores.len(),
+            mean: scores.iter().sum::<f32>() / scores.len() as f32,
+            p05: at(0.05),
+            p50: at(0.5),
+            p95: at(0.95),
+        }
+    }
+}
+
+/// Calibration results for one embedding file
+#[derive(Clone, Debug, Default)]
+pub struct Calibration {
+    pub source: String,
+    pub random: Summary,
+    pub synonyms: Summary,
+    /// Threshold that best separates synonym pairs from random pairs
+    pub suggested_min_sim: f32,
+}
+
+/// Cosine of up to `samples` random pairs and every in-vocabulary synonym pair.
+/// Gives up after `samples * 10` draws, so `random.count` may fall short when
+/// the store has few readable rows.
+pub fn calibrate(store: &dyn EmbeddingStore, source: &Source, samples: usize, rng: &mut Rng) -> Calibration {
+    let mut random = Vec::with_capacity(samples);
+    if store.len() >= 2 {
+        for _ in 0..samples.saturating_mul(10) {
+            if random.len() >= samples {
+                break;
+            }
+            let (a, b) = (rng.below(store.len()), rng.below(store.len()));
+            if a == b {
+                continue;
+            }
+            if let (Some((_, va)), Some((_, vb))) = (store.get_by_id(a), store.get_by_id(b)) {
+                random.push(cosine_similarity(&va, &vb));
+            }
+        }
+    }
+
+    let mut synonyms: Vec<f32> = SYNONYM_PAIRS
+        .iter()
+        .filter_map(|(a, b)| Some(cosine_similarity(&store.get(a)?, &store.get(b)?)))
+        .collect();
+
+    let suggested_min_sim = best_threshold(&random, &synonyms);
+    Calibration {
+        source: source.to_string(),
+        random: Summary::from_scores(&mut random),
+        synonyms: Summary::from_scores(&mut synonyms),
+        suggested_min_sim,
+    }
+}
+
+/// Threshold maximizing (synonyms accepted - random pairs accepted), i.e. Youden's J.
+/// Without synonym pairs, falls back to rejecting 95% of random pairs.
+fn best_threshold(random: &[f32], synonyms: &[f32]) -> f32 {
+    let accepted = |scores: &[f32], t: f32| {
+        scores.iter().filter(|&&s| s >= t).count() as f32 / scores.len().max(1) as f32
+    };
+    if synonyms.is_empty() {
+        let mut sorted = random.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        return sorted.get(sorted.len() * 95 / 100).copied().unwrap_or(0.0);
+    }
+
+    let mut best = (f32::NEG_INFINITY, 0.0);
+    for &t in random.iter().chain(synonyms.iter()) {
+        let j = accepted(synonyms, t) - accepted(random, t);
+        if j > best.0 {
+            best = (j, t);
+        }
+    }
+    best.1
+}
+
+/// Where the calibration of a source is cached: `<cache>/calibration/<hash>.txt`
+pub fn calibration_path(source: &Source) -> PathBuf {
+    // Canonicalize so `glove.txt` and `./glove.txt` share a calibration
+    let key = match source {
+        Source::Path(path) => fs::canonicalize(path).map_or(path.clone(), |p| p.display().to_string()),
+        other => other.to_string(),
+    };
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    cache_dir().join("calibration").join(format!("{:016x}.txt", hasher.finish()))
+}
+
+impl Calibration {
+    /// Saves as `key=value` lines
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = format!("source={}\nsuggested_min_sim={}\n", self.source, self.suggested_min_sim);
+        for (prefix, s) in [("random", &self.random), ("synonyms", &self.synonyms)] {
+            text.push_str(&format!(
+                "{p}_count={}\n{p}_mean={}\n{p}_p05={}\n{p}_p50={}\n{p}_p95={}\n",
+                s.count,
+                s.mean,
+                s.p05,
+                s.p50,
+                s.p95,
+                p = prefix
+            ));
+        }
+        fs::write(path, text)
+    }
+
+    /// Loads a calibration written by `save`
+    pub fn load(path: &Path) -> io::Result<Calibration> {
+        let mut cal = Calibration::default();
+        for line in fs::read_to_string(path)?.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let num = value.parse::<f32>().unwrap_or(0.0);
+            match key {
+                "source" => cal.source = value.to_string(),
+                "suggested_min_sim" => cal.suggested_min_sim = num,
+                "random_count" => cal.random.count = num as usize,
+                "random_mean" => cal.random.mean = num,
+                "random_p05" => cal.random.p05 = num,
+                "random_p50" => cal.random.p50 = num,
+                "random_p95" => cal.random.p95 = num,
+                "synonyms_count" => cal.synonyms.count = num as usize,
+                "synonyms_mean" => cal.synonyms.mean = num,
+                "synonyms_p05" => cal.synonyms.p05 = num,
+                "synonyms_p50" => cal.synonyms.p50 = num,
+                "synonyms_p95" => cal.synonyms.p95 = num,
+                _ => {}
+            }
+        }
+        Ok(cal)
+    }
+}
+
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Calibration for {}", self.source)?;
+        for (name, s) in [("Random pairs", &self.random), ("Synonym pairs", &self.synonyms)] {
+            writeln!(
+                f,
+                "{:<14} n={:<6} mean={:.4} p5={:.4} median={:.4} p95={:.4}",
+                name, s.count, s.mean, s.p05, s.p50, s.p95
+            )?;
+        }
+        writeln!(f, "Suggested --min-similarity: {:.4}", self.suggested_min_sim)
+    }
+}
@@ -0,0 +1,157 @@
+//! User defaults from `~/.config/glove-demo/config.toml`, so long flags need
+//! not be repeated on every run. Flags given on the command line win.
+//!
+//! Only the part of TOML the file needs is understood: top-level
+//! `key = value` lines with strings or integers, and `#` comments.
+//!
+//! ```toml
+//! embeddings = "~/glove/glove.6B.300d.txt"
+//! dim = 300
+//! metric = "cosine"
+//! top = 5
+//! cache_dir = "/data/glove-cache"
+//! ```
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::metric::BuiltinMetric;
+
+/// Defaults read from the configuration file; every field is optional
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Vector file used when none is given on the command line
+    pub embeddings: Option<String>,
+    /// Expected dimensionality; files with another width are rejected
+    pub dim: Option<usize>,
+    pub metric: Option<BuiltinMetric>,
+    /// Number of neighbors listed by commands that take `--top`
+    pub top: Option<usize>,
+    /// Replaces the default cache directory for downloads and calibrations
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/glove-demo/config.toml`, or `~/.config/glove-demo/config.toml`
+    pub fn path() -> PathBuf {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home().join(".config"),
+        };
+        base.join("glove-demo").join("config.toml")
+    }
+
+    /// Reads the configuration file; a missing file gives the empty configuration
+    pub fn load() -> Result<Config, String> {
+        let path = Config::path();
+        match fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let at = |e: String| format!("line {}: {}", n + 1, e);
+            if line.starts_with('[') {
+                return Err(at("tables are not supported, put every key at the top level".to_string()));
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(at(format!("expected 'key = value', found '{}'", line)));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "embeddings" => config.embeddings = Some(expand_home(&parse_string(value).map_err(at)?)),
+                "dim" => config.dim = Some(parse_integer(value).map_err(at)?),
+                "metric" => {
+                    let name = parse_string(value).map_err(at)?;
+                    let metric = BuiltinMetric::parse(&name)
+                        .ok_or_else(|| at(format!("metric must be one of: {}", BuiltinMetric::NAMES.join(", "))))?;
+                    config.metric = Some(metric);
+                }
+                "top" => match parse_integer(value).map_err(at)? {
+                    0 => return Err(at("top must be a positive number".to_string())),
+                    top => config.top = Some(top),
+                },
+                "cache_dir" => config.cache_dir = Some(PathBuf::from(expand_home(&parse_string(value).map_err(at)?))),
+                _ => return Err(at(format!("unknown key '{}'", key))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn home() -> PathBuf {
+    PathBuf::from(env::var_os("HOME").unwrap_or_default())
+}
+
+/// Expands a leading `~/`, which TOML leaves alone but users expect
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => home().join(rest).to_string_lossy().into_owned(),
+        None => path.to_string(),
+    }
+}
+
+/// Cuts a `#` comment that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Parses a basic (`"..."`, with escapes) or literal (`'...'`) string
+fn parse_string(value: &str) -> Result<String, String> {
+    if let Some(literal) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return Ok(literal.to_string());
+    }
+    let Some(basic) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return Err(format!("expected a quoted string, found '{}'", value));
+    };
+    let mut parsed = String::with_capacity(basic.len());
+    let mut chars = basic.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            parsed.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => parsed.push('"'),
+            Some('\\') => parsed.push('\\'),
+            Some('n') => parsed.push('\n'),
+            Some('t') => parsed.push('\t'),
+            Some(other) => return Err(format!("unsupported escape '\\{}' in {}", other, value)),
+            None => return Err(format!("unterminated string {}", value)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Parses a non-negative integer, allowing TOML's `_` digit separators
+fn parse_integer(value: &str) -> Result<usize, String> {
+    value
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("expected a number, found '{}'", value))
+}
@@ -0,0 +1,215 @@
+//! Word co-occurrence counts of a text corpus, the input GloVe is trained on,
+//! in the files of the reference implementation so its `glove` tool (or any
+//! other) can read them:
+//!
+//! - the vocabulary file has one `word count` line per word, most frequent
+//!   first; a word's id is its line number, counting from 1;
+//! - the co-occurrence file is a sequence of 16-byte little-endian records,
+//!   `i32 word id, i32 context id, f64 weighted count`, one per nonzero pair,
+//!   sorted by word and then context id, with no header.
+//!
+//! Tokens are split as `embed-sentence` splits text (lowercased, surrounding
+//! punctuation trimmed), so the vocabulary matches what the other commands
+//! look up. Windows never cross a line break, and tokens outside the
+//! vocabulary are dropped before distances are measured, as in the reference
+//! code. The counts are held in memory, which limits the corpus to what a
+//! hash map of the vocabulary's pairs can hold.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+use crate::sentence::tokenize;
+
+/// Bytes per record of the co-occurrence file
+const RECORD_LEN: usize = 16;
+
+/// A word id, a context word id (both counting from 0) and their weighted count
+pub type Cooccurrence = (u32, u32, f64);
+
+/// Counting settings; the defaults are those of the reference `demo.sh`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CooccurOptions {
+    /// Context words counted on each side of a word
+    pub window: usize,
+    pub weighting: Weighting,
+    /// Tokens seen fewer times are left out of the vocabulary
+    pub min_count: usize,
+    /// Keep only this many of the most frequent tokens
+    pub max_vocab: Option<usize>,
+}
+
+impl Default for CooccurOptions {
+    fn default() -> Self {
+        CooccurOptions { window: 15, weighting: Weighting::Harmonic, min_count: 5, max_vocab: None }
+    }
+}
+
+/// How much a pair of words counts, by how far apart they are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Weighting {
+    /// One over the distance, as GloVe counts
+    #[default]
+    Harmonic,
+    /// One for every pair in the window
+    Uniform,
+    /// Falling linearly from one next to the word to `1 / window` at the
+    /// window's edge, which is what word2vec's randomly shrunk windows give
+    /// on average
+    Linear,
+}
+
+impl Weighting {
+    pub const NAMES: &'static [&'static str] = &["harmonic", "uniform", "linear"];
+
+    pub fn parse(name: &str) -> Option<Weighting> {
+        match name {
+            "harmonic" => Some(Weighting::Harmonic),
+            "uniform" => Some(Weighting::Uniform),
+            "linear" => Some(Weighting::Linear),
+            _ => None,
+        }
+    }
+
+    /// The weight of a pair `distance` (from 1) tokens apart
+    fn weight(self, distance: usize, window: usize) -> f64 {
+        match self {
+            Weighting::Harmonic => 1.0 / distance as f64,
+            Weighting::Uniform => 1.0,
+            Weighting::Linear => (window + 1 - distance) as f64 / window as f64,
+        }
+    }
+}
+
+/// The words counted, most frequent first, with their counts
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Vocabulary {
+    pub words: Vec<(String, u64)>,
+    ids: HashMap<String, u32>,
+}
+
+impl Vocabulary {
+    fn new(words: Vec<(String, u64)>) -> Vocabulary {
+        let ids = words.iter().enumerate().map(|(id, (word, _))| (word.clone(), id as u32)).collect();
+        Vocabulary { words, ids }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The word's id, counting from 0
+    pub fn id(&self, word: &str) -> Option<u32> {
+        self.ids.get(word).copied()
+    }
+
+    /// Writes one `word count` line per word
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        for (word, count) in &self.words {
+            writeln!(out, "{} {}", word, count)?;
+        }
+        out.flush()
+    }
+
+    /// Reads a file of `word count` lines, as `write` or the reference
+    /// `vocab_count` tool writes them
+    pub fn read(path: &str) -> io::Result<Vocabulary> {
+        let mut words = Vec::new();
+        for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let (Some(word), Some(Ok(count)), None) = (tokens.next(), tokens.next().map(str::parse), tokens.next())
+            else {
+                let msg = format!("line {}: expected a word and its count", number + 1);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            };
+            words.push((word.to_string(), count));
+        }
+        Ok(Vocabulary::new(words))
+    }
+}
+
+/// Counts the tokens of `corpus` and keeps those seen at least `min_count`
+/// times, at most `max_vocab` of them; ties are broken alphabetically so the
+/// vocabulary does not depend on hashing order
+pub fn build_vocabulary(corpus: impl BufRead, options: &CooccurOptions) -> io::Result<Vocabulary> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for line in corpus.lines() {
+        for token in tokenize(&line?) {
+            *counts.entry(token).or_default() += 1;
+        }
+    }
+    let mut words: Vec<(String, u64)> = counts.into_iter().filter(|&(_, n)| n >= options.min_count as u64).collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if let Some(max) = options.max_vocab {
+        words.truncate(max);
+    }
+    Ok(Vocabulary::new(words))
+}
+
+/// The weighted co-occurrence counts of `corpus` between vocabulary words at
+/// most `window` tokens apart on one line, counted both ways, as
+/// `(word, context, count)` entries sorted by word and context id
+pub fn count_cooccurrences(
+    corpus: impl BufRead,
+    vocab: &Vocabulary,
+    options: &CooccurOptions,
+) -> io::Result<Vec<Cooccurrence>> {
+    let (window, weighting) = (options.window, options.weighting);
+    let mut counts: HashMap<(u32, u32), f64> = HashMap::new();
+    for line in corpus.lines() {
+        let ids: Vec<u32> = tokenize(&line?).iter().filter_map(|t| vocab.id(t)).collect();
+        for (i, &word) in ids.iter().enumerate() {
+            for (distance, &context) in ids[i.saturating_sub(window)..i].iter().rev().enumerate() {
+                let weight = weighting.weight(distance + 1, window);
+                *counts.entry((word, context)).or_default() += weight;
+                *counts.entry((context, word)).or_default() += weight;
+            }
+        }
+    }
+    let mut entries: Vec<Cooccurrence> = counts.into_iter().map(|((w, c), x)| (w, c, x)).collect();
+    entries.sort_unstable_by_key(|&(w, c, _)| (w, c));
+    Ok(entries)
+}
+
+/// Writes `entries` as co-occurrence file records, with ids counted from 1
+pub fn write_cooccurrences(path: &str, entries: &[Cooccurrence]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for &(word, context, count) in entries {
+        out.write_all(&(word as i32 + 1).to_le_bytes())?;
+        out.write_all(&(context as i32 + 1).to_le_bytes())?;
+        out.write_all(&count.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+/// Reads a co-occurrence file, checking every id against the `vocab_size`
+/// words of its vocabulary; ids are returned counted from 0
+pub fn read_cooccurrences(path: &str, vocab_size: usize) -> io::Result<Vec<Cooccurrence>> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    if !len.is_multiple_of(RECORD_LEN) {
+        let msg = format!("{} bytes is not a whole number of {}-byte records", len, RECORD_LEN);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    let mut reader = BufReader::new(file);
+    let mut entries = Vec::with_capacity(len / RECORD_LEN);
+    let mut record = [0u8; RECORD_LEN];
+    for _ in 0..len / RECORD_LEN {
+        reader.read_exact(&mut record)?;
+        let word = i32::from_le_bytes(record[0..4].try_into().unwrap());
+        let context = i32::from_le_bytes(record[4..8].try_into().unwrap());
+        let count = f64::from_le_bytes(record[8..16].try_into().unwrap());
+        let valid = |id: i32| id >= 1 && id as usize <= vocab_size;
+        if !valid(word) || !valid(context) {
+            let msg = format!("record {}: word ids {} {} are outside the vocabulary", entries.len() + 1, word, context);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        entries.push((word as u32 - 1, context as u32 - 1, count));
+    }
+    Ok(entries)
+}
@@ -0,0 +1,68 @@
+//! Spelling correction guided by the vectors: the vocabulary words within a
+//! few edits of a word, as "did you mean" suggestions finds them, re-ranked by
+//! how well each fits the words around it, so `pice` next to `slice` and
+//! `cake` becomes `piece` rather than `price` or `pine`.
+//!
+//! A candidate's score is its cosine similarity to the mean of the context
+//! words' unit vectors, less `EDIT_PENALTY` per edit, so the closer spelling
+//! wins unless the context clearly prefers another. A word found in the
+//! vocabulary is its own candidate with no edits, which lets the context flag
+//! real-word errors such as `desert` for `dessert`. Without context words the
+//! candidates keep their edit-distance order.
+
+use crate::store::EmbeddingStore;
+use crate::suggest::candidates;
+use crate::vector::{cosine_similarity, normalize};
+
+/// Score lost per edit
+pub const EDIT_PENALTY: f32 = 0.1;
+
+/// A spelling a word may have been meant as
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Correction {
+    pub word: String,
+    /// Edits from the word as written
+    pub edits: usize,
+    /// Cosine similarity to the context, if any context word is known
+    pub context_similarity: Option<f32>,
+    pub score: f32,
+}
+
+/// Up to `count` corrections of `word` in the company of the `context` words,
+/// best first; context words missing from the vocabulary are ignored
+pub fn corrections<S: AsRef<str>>(
+    store: &dyn EmbeddingStore,
+    word: &str,
+    context: &[S],
+    count: usize,
+) -> Vec<Correction> {
+    let mut centroid: Option<Vec<f32>> = None;
+    for vec in context.iter().filter_map(|w| store.get(w.as_ref())) {
+        let mut vec = vec.into_owned();
+        normalize(&mut vec);
+        match &mut centroid {
+            Some(sum) => sum.iter_mut().zip(&vec).for_each(|(s, x)| *s += x),
+            None => centroid = Some(vec),
+        }
+    }
+
+    let mut pool = candidates(store, word);
+    if store.contains(word) {
+        pool.insert(0, (word.to_string(), 0));
+    }
+    let mut found: Vec<Correction> = pool
+        .into_iter()
+        .map(|(candidate, edits)| {
+            let context_similarity = centroid
+                .as_ref()
+                .and_then(|centroid| Some(cosine_similarity(&store.get(&candidate)?, centroid)));
+            let score = context_similarity.unwrap_or(0.0) - EDIT_PENALTY * edits as f32;
+            Correction { word: candidate, edits, context_similarity, score }
+        })
+        .collect();
+    // Stable, so equal scores keep the edit-distance order
+    found.sort_by(|a, b| b.score.total_cmp(&a.score));
+    found.truncate(count);
+    found
+}
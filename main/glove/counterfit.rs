@@ -0,0 +1,165 @@
+//! Counter-fitting (Mrkšić et al., 2016): injects antonymy and synonymy
+//! constraints into a vector space. Distributional vectors place words that
+//! appear in the same contexts together, so antonyms such as "cheap" and
+//! "expensive" often end up among each other's nearest neighbors.
+//!
+//! Three terms are optimized by gradient steps on unit vectors, with the
+//! paper's hyperparameters (all weights 0.1, δ = 1, γ = 0, ρ = 0.2), where
+//! the distance between two words is one minus their cosine similarity:
+//!
+//! - antonym repel: antonyms closer than δ are pushed apart;
+//! - synonym attract: synonyms further apart than γ are pulled together;
+//! - vector space preservation: a word's original neighbors (within ρ) that
+//!   drifted away are pulled back, so the rest of the space keeps its shape;
+//!   its antonyms are left out even when they were neighbors.
+//!
+//! Only words of the constraint pairs move. Every step's updates are computed
+//! from the vectors as they were at its start, averaged per word, and the
+//! moved vectors are normalized again.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dense::DenseStore;
+use crate::store::EmbeddingStore;
+use crate::vector::normalize;
+
+/// Passes made by default, as in the paper
+pub const DEFAULT_EPOCHS: usize = 20;
+
+/// Weight of each term's gradient
+const ANTONYM_WEIGHT: f32 = 0.1;
+const SYNONYM_WEIGHT: f32 = 0.1;
+const PRESERVE_WEIGHT: f32 = 0.1;
+
+/// Antonyms are repelled until at least this far apart
+const ANTONYM_MARGIN: f32 = 1.0;
+
+/// Synonyms are attracted while further apart than this
+const SYNONYM_MARGIN: f32 = 0.0;
+
+/// Original neighbors within this distance are preserved
+const NEIGHBORHOOD: f32 = 0.2;
+
+/// What a counter-fitting run changed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub synonyms: usize,
+    pub antonyms: usize,
+    /// Words of the pairs, which are the words moved
+    pub moved: usize,
+    /// Original neighbor relations kept by the preservation term
+    pub preserved: usize,
+    /// Pairs left out because a word is not in the vocabulary
+    pub unknown_pairs: Vec<(String, String)>,
+}
+
+/// Counter-fits every vector of `store` to the `synonyms` and `antonyms`
+/// pairs over `epochs` passes, returning unit vectors in the store's row order
+pub fn counterfit(
+    store: &dyn EmbeddingStore,
+    synonyms: &[(String, String)],
+    antonyms: &[(String, String)],
+    epochs: usize,
+) -> (DenseStore, Report) {
+    let dim = store.dim();
+    let mut report = Report::default();
+    let mut words = Vec::with_capacity(store.len());
+    let mut vecs = Vec::with_capacity(store.len() * dim);
+    for (word, vec) in store.iter() {
+        let mut vec = vec.into_owned();
+        normalize(&mut vec);
+        words.push(word.into_owned());
+        vecs.extend_from_slice(&vec);
+    }
+
+    let mut resolve = |pairs: &[(String, String)]| -> Vec<(usize, usize)> {
+        let mut ids = Vec::new();
+        for (a, b) in pairs {
+            match (store.id(a), store.id(b)) {
+                (Some(i), Some(j)) if i != j && i < words.len() && j < words.len() => ids.push((i, j)),
+                (Some(_), Some(_)) => {}
+                _ => report.unknown_pairs.push((a.clone(), b.clone())),
+            }
+        }
+        ids
+    };
+    let synonym_ids = resolve(synonyms);
+    let antonym_ids = resolve(antonyms);
+    report.synonyms = synonym_ids.len();
+    report.antonyms = antonym_ids.len();
+
+    let moved: HashSet<usize> = synonym_ids.iter().chain(&antonym_ids).flat_map(|&(i, j)| [i, j]).collect();
+    let mut moved: Vec<usize> = moved.into_iter().collect();
+    moved.sort_unstable();
+    report.moved = moved.len();
+
+    // Each moved word's original neighbors, with their original distances;
+    // antonyms are often neighbors, and are meant to drift apart
+    let repelled: HashSet<(usize, usize)> = antonym_ids.iter().flat_map(|&(i, j)| [(i, j), (j, i)]).collect();
+    let mut preserve: Vec<(usize, usize, f32)> = Vec::new();
+    for &i in &moved {
+        for (j, score) in store.cosine_scores(&vecs[i * dim..(i + 1) * dim]).into_iter().enumerate() {
+            if j != i && j < words.len() && 1.0 - score <= NEIGHBORHOOD && !repelled.contains(&(i, j)) {
+                preserve.push((i, j, 1.0 - score));
+            }
+        }
+    }
+    report.preserved = preserve.len();
+
+    let row = |vecs: &[f32], id: usize| vecs[id * dim..(id + 1) * dim].to_vec();
+    for _ in 0..epochs {
+        let mut updates: HashMap<usize, (Vec<f32>, usize)> = HashMap::new();
+        let mut add = |id: usize, step: Vec<f32>| {
+            let (sum, count) = updates.entry(id).or_insert_with(|| (vec![0.0; dim], 0));
+            sum.iter_mut().zip(&step).for_each(|(s, x)| *s += x);
+            *count += 1;
+        };
+        for &(i, j) in &antonym_ids {
+            let (u, v) = (row(&vecs, i), row(&vecs, j));
+            if distance(&u, &v) < ANTONYM_MARGIN {
+                add(i, scaled(away_from(&u, &v), ANTONYM_WEIGHT));
+                add(j, scaled(away_from(&v, &u), ANTONYM_WEIGHT));
+            }
+        }
+        for &(i, j) in &synonym_ids {
+            let (u, v) = (row(&vecs, i), row(&vecs, j));
+            if distance(&u, &v) > SYNONYM_MARGIN {
+                add(i, scaled(away_from(&u, &v), -SYNONYM_WEIGHT));
+                add(j, scaled(away_from(&v, &u), -SYNONYM_WEIGHT));
+            }
+        }
+        for &(i, j, original) in &preserve {
+            let (u, v) = (row(&vecs, i), row(&vecs, j));
+            if distance(&u, &v) > original {
+                add(i, scaled(away_from(&u, &v), -PRESERVE_WEIGHT));
+            }
+        }
+        for (id, (sum, count)) in updates {
+            let vec = &mut vecs[id * dim..(id + 1) * dim];
+            vec.iter_mut().zip(&sum).for_each(|(x, s)| *x += s / count as f32);
+            normalize(vec);
+        }
+    }
+
+    let mut out = DenseStore::new();
+    for (id, word) in words.into_iter().enumerate() {
+        out.insert(word, row(&vecs, id));
+    }
+    (out, report)
+}
+
+/// One minus the cosine similarity of two unit vectors
+fn distance(u: &[f32], v: &[f32]) -> f32 {
+    1.0 - u.iter().zip(v).map(|(x, y)| x * y).sum::<f32>()
+}
+
+/// The direction in which moving the unit vector `u` lowers its similarity
+/// to `v` fastest: `u (u·v) - v`, the negated gradient of `u·v` on the sphere
+fn away_from(u: &[f32], v: &[f32]) -> Vec<f32> {
+    let along: f32 = u.iter().zip(v).map(|(x, y)| x * y).sum();
+    u.iter().zip(v).map(|(x, y)| x * along - y).collect()
+}
+
+fn scaled(vec: Vec<f32>, factor: f32) -> Vec<f32> {
+    vec.into_iter().map(|x| x * factor).collect()
+}
@@ -0,0 +1,72 @@
+//! How much of a corpus a vocabulary covers, to judge whether an embedding
+//! file fits some text before relying on it.
+//!
+//! The text is split into tokens as `embed-sentence` and `doc-sim` split it
+//! (lowercased, with surrounding punctuation trimmed), so the figures match
+//! what those commands will find. Coverage is reported over tokens (every
+//! occurrence) and over types (distinct tokens); frequent words dominate the
+//! first, and the second shows how much of the long tail is missing.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::sentence::tokenize;
+use crate::store::EmbeddingStore;
+
+/// The coverage of a corpus by a vocabulary
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+    pub tokens: usize,
+    pub known_tokens: usize,
+    pub types: usize,
+    pub known_types: usize,
+    /// The out-of-vocabulary types with their counts, most frequent first
+    pub missing: Vec<(String, usize)>,
+}
+
+impl Coverage {
+    pub fn token_fraction(&self) -> f64 {
+        self.known_tokens as f64 / self.tokens.max(1) as f64
+    }
+
+    pub fn type_fraction(&self) -> f64 {
+        self.known_types as f64 / self.types.max(1) as f64
+    }
+}
+
+/// Counts the tokens of `corpus`, read line by line, and checks each type
+/// against `store`
+pub fn coverage(store: &dyn EmbeddingStore, corpus: impl BufRead) -> io::Result<Coverage> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for line in corpus.lines() {
+        for token in tokenize(&line?) {
+            *counts.entry(token).or_default() += 1;
+        }
+    }
+    let mut report = Coverage { types: counts.len(), ..Coverage::default() };
+    for (token, count) in counts {
+        report.tokens += count;
+        if store.contains(&token) {
+            report.known_tokens += count;
+            report.known_types += 1;
+        } else {
+            report.missing.push((token, count));
+        }
+    }
+    report.missing.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(report)
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Tokens: {} of {} covered ({:.2}%)",
+            self.known_tokens,
+            self.tokens,
+            100.0 * self.token_fraction()
+        )?;
+        write!(f, "Types: {} of {} covered ({:.2}%)", self.known_types, self.types, 100.0 * self.type_fraction())
+    }
+}
@@ -0,0 +1,161 @@
+//! Hard debiasing (Bolukbasi et al., 2016): removes a bias direction, such
+//! as gender, from words that should be neutral to it, and makes word pairs
+//! that differ only in it (he/she, king/queen) equidistant from every
+//! neutral word.
+//!
+//! The method works on unit vectors, so every vector of the output is
+//! normalized.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+
+use crate::dense::DenseStore;
+use crate::sentence::{first_principal_component, remove_component};
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// Which words lose their bias component
+#[derive(Clone, Debug, PartialEq)]
+pub enum Neutralize {
+    /// Only these words
+    Only(HashSet<String>),
+    /// Every word except these (and the words of the definitional and equalize pairs)
+    AllExcept(HashSet<String>),
+}
+
+/// What a debiasing run changed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Definitional pairs used to find the bias subspace
+    pub definitional: usize,
+    pub neutralized: usize,
+    pub equalized: usize,
+    /// Pairs left out because a word is not in the vocabulary
+    pub unknown_pairs: Vec<(String, String)>,
+}
+
+/// Reads word pairs, one `first second` pair per line; `#` starts a comment
+pub fn read_pairs(path: &str) -> io::Result<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let tokens: Vec<&str> = line.split('#').next().unwrap_or("").split_whitespace().collect();
+        match tokens[..] {
+            [] => {}
+            [a, b] => pairs.push((a.to_string(), b.to_string())),
+            _ => {
+                let msg = format!("line {}: expected two words, found {}", number + 1, tokens.len());
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+/// The `k` principal directions of the definitional pairs' differences from
+/// their midpoints, as orthonormal vectors
+pub fn bias_subspace(store: &dyn EmbeddingStore, pairs: &[(String, String)], k: usize) -> Option<Vec<WordVec>> {
+    let mut offsets = Vec::new();
+    for (a, b) in pairs {
+        let (Some(a), Some(b)) = (store.get(a), store.get(b)) else { continue };
+        let (a, b) = (unit(&a), unit(&b));
+        let center: WordVec = a.iter().zip(&b).map(|(x, y)| (x + y) / 2.0).collect();
+        offsets.push(a.iter().zip(&center).map(|(x, c)| x - c).collect::<WordVec>());
+        offsets.push(b.iter().zip(&center).map(|(x, c)| x - c).collect::<WordVec>());
+    }
+
+    // Deflation: each further direction is found after removing the previous ones
+    let mut subspace = Vec::new();
+    for _ in 0..k {
+        let direction = first_principal_component(&offsets)?;
+        for offset in &mut offsets {
+            remove_component(offset, &direction);
+        }
+        subspace.push(direction);
+    }
+    Some(subspace)
+}
+
+/// Neutralizes and equalizes every word of `store` against the bias subspace
+/// of the `definitional` pairs, returning the debiased vectors
+pub fn hard_debias(
+    store: &dyn EmbeddingStore,
+    definitional: &[(String, String)],
+    equalize: &[(String, String)],
+    neutralize: &Neutralize,
+    k: usize,
+) -> Result<(DenseStore, Report), String> {
+    let mut report = Report::default();
+    for (a, b) in definitional.iter().chain(equalize) {
+        let pair = (a.clone(), b.clone());
+        if (!store.contains(a) || !store.contains(b)) && !report.unknown_pairs.contains(&pair) {
+            report.unknown_pairs.push(pair);
+        }
+    }
+    report.definitional = definitional.iter().filter(|(a, b)| store.contains(a) && store.contains(b)).count();
+    if report.definitional == 0 {
+        return Err("none of the definitional pairs are in the vocabulary".to_string());
+    }
+    let subspace = bias_subspace(store, definitional, k).ok_or("the definitional pairs have no bias direction")?;
+
+    let paired: HashSet<&str> =
+        definitional.iter().chain(equalize).flat_map(|(a, b)| [a.as_str(), b.as_str()]).collect();
+    let mut out = DenseStore::new();
+    for (word, vec) in store.iter() {
+        let mut vec = unit(&vec);
+        let neutral = match neutralize {
+            Neutralize::Only(words) => words.contains(word.as_ref()),
+            Neutralize::AllExcept(kept) => !kept.contains(word.as_ref()) && !paired.contains(word.as_ref()),
+        };
+        if neutral {
+            for direction in &subspace {
+                remove_component(&mut vec, direction);
+            }
+            vec = unit(&vec);
+            report.neutralized += 1;
+        }
+        out.insert(word.into_owned(), vec);
+    }
+
+    // Each pair keeps its shared, bias-free part and gets mirror-image bias parts of unit total length
+    for (a, b) in equalize {
+        let (Some(va), Some(vb)) = (out.get(a), out.get(b)) else { continue };
+        let (va, vb) = (va.into_owned(), vb.into_owned());
+        let mean: WordVec = va.iter().zip(&vb).map(|(x, y)| (x + y) / 2.0).collect();
+        let mean_bias = project(&mean, &subspace);
+        let shared: WordVec = mean.iter().zip(&mean_bias).map(|(m, p)| m - p).collect();
+        let scale = (1.0 - dot(&shared, &shared)).max(0.0).sqrt();
+        for (word, vec) in [(a, va), (b, vb)] {
+            let bias: WordVec = project(&vec, &subspace).iter().zip(&mean_bias).map(|(p, m)| p - m).collect();
+            let bias = unit(&bias);
+            out.insert(word.clone(), shared.iter().zip(&bias).map(|(s, d)| s + scale * d).collect());
+        }
+        report.equalized += 1;
+    }
+    Ok((out, report))
+}
+
+/// The projection of `vec` onto the span of the orthonormal `subspace`
+fn project(vec: &[f32], subspace: &[WordVec]) -> WordVec {
+    let mut projected = vec![0.0; vec.len()];
+    for direction in subspace {
+        let amount = dot(vec, direction);
+        for (p, u) in projected.iter_mut().zip(direction) {
+            *p += amount * u;
+        }
+    }
+    projected
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// `vec` scaled to unit length (zero vectors are left as they are)
+fn unit(vec: &[f32]) -> WordVec {
+    let norm = dot(vec, vec).sqrt();
+    if norm == 0.0 {
+        return vec.to_vec();
+    }
+    vec.iter().map(|x| x / norm).collect()
+}
@@ -0,0 +1,142 @@
+//! Vocabulary entries with identical or nearly identical vectors. In a
+//! trained model two words almost never share a vector, so such entries in a
+//! custom file usually point to a preprocessing bug: rows written twice under
+//! different spellings, placeholder or all-zero vectors, or words mapped to
+//! one fallback vector.
+//!
+//! Identical vectors are grouped exactly, by their bit patterns. Near
+//! duplicates are found with banded random-hyperplane hashing: rows are
+//! bucketed by a short signature in each of several bands, and only rows
+//! sharing a bucket are compared, so the work stays close to linear in the
+//! vocabulary. The band count is chosen so a pair at the threshold is caught
+//! with 99% probability; closer pairs are caught more reliably still.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::lsh::gaussian;
+use crate::rng::Rng;
+use crate::store::EmbeddingStore;
+use crate::vector::norm;
+
+/// Fixed so the same file always gives the same report
+const SEED: u64 = 0xd0_0b1e_5eed;
+
+/// Signature bits per band; a random pair shares a bucket with probability 2^-16
+const BAND_BITS: usize = 16;
+
+/// Bands hashed at most, bounding the time taken for low thresholds
+const MAX_BANDS: usize = 64;
+
+/// Chance of catching a pair exactly at the threshold
+const RECALL: f64 = 0.99;
+
+/// What `find_duplicates` found
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DuplicateReport {
+    /// Groups of words whose vectors are bit-for-bit identical, in row order
+    pub identical: Vec<Vec<String>>,
+    /// Pairs of distinct vectors with a cosine similarity of at least the
+    /// threshold, most similar first. Identical groups appear through their
+    /// first word only.
+    pub near: Vec<(String, String, f32)>,
+}
+
+/// Bands needed to catch a pair with cosine similarity `threshold` with
+/// probability `RECALL`
+fn bands_for(threshold: f32) -> usize {
+    let bit_agreement = 1.0 - (threshold.clamp(-1.0, 1.0) as f64).acos() / std::f64::consts::PI;
+    let band_match = bit_agreement.powi(BAND_BITS as i32);
+    if band_match >= 1.0 {
+        return 1;
+    }
+    let bands = ((1.0 - RECALL).ln() / (1.0 - band_match).ln()).ceil();
+    (bands as usize).clamp(1, MAX_BANDS)
+}
+
+/// Finds the identical vectors of `store`, and the pairs of other vectors
+/// whose cosine similarity is at least `threshold`
+pub fn find_duplicates(store: &dyn EmbeddingStore, threshold: f32) -> DuplicateReport {
+    let dim = store.dim();
+    // Rows are copied out once and compared locally, so the store is never
+    // asked for another row while it is iterating. Indices below are positions
+    // in `words` and `unit`, which stay in step even when `iter` skips a row.
+    let mut words = Vec::with_capacity(store.len());
+    let mut unit = Vec::with_capacity(store.len() * dim);
+    // Rows keyed by a hash of their bits, then split by actual equality
+    let mut by_hash: HashMap<u64, Vec<Vec<usize>>> = HashMap::new();
+    let mut representatives = Vec::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (word, vec) in store.iter() {
+        if vec.len() != dim {
+            continue;
+        }
+        let index = words.len();
+        let mut hasher = DefaultHasher::new();
+        vec.iter().for_each(|x| x.to_bits().hash(&mut hasher));
+        let buckets = by_hash.entry(hasher.finish()).or_default();
+        // Still unscaled here; `unit` is normalized once every row is in
+        let same = |group: &&mut Vec<usize>| {
+            let first = &unit[group[0] * dim..(group[0] + 1) * dim];
+            first.iter().zip(vec.iter()).all(|(a, b): (&f32, &f32)| a.to_bits() == b.to_bits())
+        };
+        match buckets.iter_mut().find(same) {
+            Some(group) => group.push(index),
+            None => {
+                buckets.push(vec![index]);
+                representatives.push(index);
+            }
+        }
+        unit.extend_from_slice(&vec);
+        words.push(word.into_owned());
+    }
+    for row in unit.chunks_mut(dim.max(1)) {
+        let n = norm(row);
+        let scale = if n > 0.0 { 1.0 / n } else { 0.0 };
+        row.iter_mut().for_each(|x| *x *= scale);
+    }
+    for buckets in by_hash.into_values() {
+        groups.extend(buckets.into_iter().filter(|group| group.len() > 1));
+    }
+    groups.sort();
+
+    let row = |id: usize| &unit[id * dim..(id + 1) * dim];
+    let cosine = |a: usize, b: usize| row(a).iter().zip(row(b)).map(|(x, y)| x * y).sum::<f32>();
+    let mut rng = Rng::new(SEED);
+    let mut seen: HashSet<(usize, usize)> = HashSet::new();
+    let mut near = Vec::new();
+    for _ in 0..bands_for(threshold) {
+        let planes: Vec<f32> = (0..BAND_BITS * dim).map(|_| gaussian(&mut rng)).collect();
+        let mut buckets: HashMap<u32, Vec<usize>> = HashMap::new();
+        for &id in &representatives {
+            let mut signature = 0u32;
+            for (bit, plane) in planes.chunks(dim).enumerate() {
+                if plane.iter().zip(row(id)).map(|(p, x)| p * x).sum::<f32>() >= 0.0 {
+                    signature |= 1 << bit;
+                }
+            }
+            buckets.entry(signature).or_default().push(id);
+        }
+        for bucket in buckets.values() {
+            for (i, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[i + 1..] {
+                    if seen.contains(&(a, b)) {
+                        continue;
+                    }
+                    let similarity = cosine(a, b);
+                    if similarity >= threshold {
+                        seen.insert((a, b));
+                        near.push((a, b, similarity));
+                    }
+                }
+            }
+        }
+    }
+    near.sort_by(|x, y| y.2.total_cmp(&x.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+
+    DuplicateReport {
+        identical: groups.iter().map(|group| group.iter().map(|&id| words[id].clone()).collect()).collect(),
+        near: near.into_iter().map(|(a, b, s)| (words[a].clone(), words[b].clone(), s)).collect(),
+    }
+}
@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+use std::hash::{BuildHasher, RandomState};
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::vector::{cosine_from_parts, matrix_vector, norm};
+use crate::WordVec;
+
+/// All vectors held in memory, in file order
+#[derive(Default)]
+pub struct DenseStore {
+    words: Vec<String>,
+    dim: usize,
+    /// Row-major `len * dim` components, so a scan is one matrix-vector product
+    data: Vec<f32>,
+    /// Length of each vector, computed once so a cosine scan is just dot products
+    norms: Vec<f32>,
+    index: WordIndex,
+}
+
+impl DenseStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a word, replacing the vector of an existing entry. Vectors whose
+    /// length differs from the first row's are skipped.
+    pub fn insert(&mut self, word: String, vec: WordVec) {
+        if self.words.is_empty() {
+            self.dim = vec.len();
+        }
+        if vec.len() != self.dim {
+            return;
+        }
+        if let Some(id) = self.index.get(&self.words, &word) {
+            self.norms[id] = norm(&vec);
+            self.data[id * self.dim..(id + 1) * self.dim].copy_from_slice(&vec);
+            return;
+        }
+        self.words.push(word);
+        self.index.add_last(&self.words);
+        self.norms.push(norm(&vec));
+        self.data.extend_from_slice(&vec);
+    }
+
+    /// Copies every entry of another store into memory
+    pub fn from_store(store: &dyn EmbeddingStore) -> Self {
+        let mut dense = Self::new();
+        for (word, vec) in store.iter() {
+            dense.insert(word.into_owned(), vec.into_owned());
+        }
+        dense
+    }
+
+    /// Every vector as one row-major `len * dim` matrix
+    pub fn matrix(&self) -> &[f32] {
+        &self.data
+    }
+
+    /// The vector of `word`, borrowed from the matrix
+    pub fn vector(&self, word: &str) -> Option<&[f32]> {
+        self.index.get(&self.words, word).map(|id| self.row(id))
+    }
+
+    fn row(&self, id: usize) -> &[f32] {
+        &self.data[id * self.dim..(id + 1) * self.dim]
+    }
+}
+
+impl EmbeddingStore for DenseStore {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.index.get(&self.words, word)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        Some((
+            Cow::Borrowed(self.words.get(id)?.as_str()),
+            Cow::Borrowed(self.row(id)),
+        ))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        Box::new(
+            self.words
+                .iter()
+                .enumerate()
+                .map(|(id, w)| (Cow::Borrowed(w.as_str()), Cow::Borrowed(self.row(id)))),
+        )
+    }
+
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        let q_norm = norm(query);
+        matrix_vector(&self.data, self.words.len(), query)
+            .into_iter()
+            .zip(&self.norms)
+            .map(|(dot, &v_norm)| cosine_from_parts(dot, q_norm, v_norm))
+            .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DenseStore {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&Rows::of(self), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DenseStore {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <Rows as serde::Deserialize>::deserialize(deserializer)?.into_store().map_err(serde::de::Error::custom)
+    }
+}
+
+/// How in-memory vectors are serialized: the words in row order and the
+/// row-major matrix, which deserializing checks against them
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct Rows<'a> {
+    dim: usize,
+    words: Cow<'a, [String]>,
+    vectors: Cow<'a, [f32]>,
+}
+
+#[cfg(feature = "serde")]
+impl Rows<'_> {
+    pub(crate) fn of(store: &DenseStore) -> Rows<'_> {
+        Rows { dim: store.dim, words: Cow::Borrowed(&store.words), vectors: Cow::Borrowed(&store.data) }
+    }
+
+    pub(crate) fn into_store(self) -> Result<DenseStore, String> {
+        let (dim, vectors) = (self.dim, self.vectors);
+        let (count, expected) = (self.words.len(), self.words.len() * dim);
+        if vectors.len() != expected {
+            let found = vectors.len();
+            return Err(format!("{} words of {} dimensions need {} values, not {}", count, dim, expected, found));
+        }
+        let mut store = DenseStore::new();
+        for (id, word) in self.words.into_owned().into_iter().enumerate() {
+            store.insert(word, vectors[id * dim..(id + 1) * dim].to_vec());
+        }
+        Ok(store)
+    }
+}
+
+/// Word to row lookup that keeps no second copy of the words: a slot holds a
+/// row number, and a lookup compares the word against the one in that row
+#[derive(Default)]
+struct WordIndex {
+    hasher: RandomState,
+    /// Row + 1 of each used slot, 0 for a free one. Collisions probe the
+    /// following slots; the table is kept at most half full.
+    slots: Vec<usize>,
+}
+
+impl WordIndex {
+    fn get(&self, words: &[String], word: &str) -> Option<usize> {
+        self.find(words, word).ok()
+    }
+
+    /// The row of `word`, or the free slot it would go in
+    fn find(&self, words: &[String], word: &str) -> Result<usize, usize> {
+        if self.slots.is_empty() {
+            return Err(0);
+        }
+        let mask = self.slots.len() - 1;
+        let mut slot = self.hasher.hash_one(word) as usize & mask;
+        loop {
+            match self.slots[slot] {
+                0 => return Err(slot),
+                row if words[row - 1] == word => return Ok(row - 1),
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    /// Indexes the last of `words`, which must not be indexed already
+    fn add_last(&mut self, words: &[String]) {
+        if words.len() * 2 <= self.slots.len() {
+            self.place(words, words.len() - 1);
+            return;
+        }
+        self.slots = vec![0; (words.len() * 2).next_power_of_two().max(16)];
+        for row in 0..words.len() {
+            self.place(words, row);
+        }
+    }
+
+    fn place(&mut self, words: &[String], row: usize) {
+        if let Err(slot) = self.find(words, &words[row]) {
+            self.slots[slot] = row + 1;
+        }
+    }
+}
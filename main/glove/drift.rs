@@ -0,0 +1,122 @@
+//! Tracking how a word's neighborhood moves between embedding snapshots,
+//! e.g. models trained on different decades of text.
+//!
+//! Separately trained models live in unrelated coordinate systems, so vectors
+//! from two snapshots cannot be compared directly. Instead each word is
+//! described by its similarities to a shared set of neighbor words (a
+//! "second-order" profile), and those profiles are compared. Across many
+//! words, the overlap of the two nearest-neighbor lists measures how much two
+//! models differ overall.
+
+use crate::metric::BuiltinMetric;
+use crate::rng::Rng;
+use crate::search::{find_nearest_neighbors, find_nearest_neighbors_batch};
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+
+/// How far a word moved between two snapshots
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shift {
+    /// `1 - cosine` of the two second-order profiles: 0 means the word relates
+    /// to its neighbors exactly as before
+    pub shift: f32,
+    /// Words among the top neighbors of both snapshots
+    pub shared: usize,
+    /// Size of the neighbor union the profiles were built over
+    pub union: usize,
+}
+
+/// The `k` nearest neighbors of `word` in one snapshot, or `None` if it is unknown
+pub fn neighbors(store: &dyn EmbeddingStore, word: &str, k: usize) -> Option<Vec<(String, f32)>> {
+    let vec = store.get(word)?;
+    Some(find_nearest_neighbors(&vec, store, &[word.to_string()], k, &BuiltinMetric::Cosine))
+}
+
+/// Compares `word`'s similarities to the union of both neighbor lists, keeping
+/// only neighbors present in both vocabularies
+pub fn second_order_shift(
+    word: &str,
+    (a, a_neighbors): (&dyn EmbeddingStore, &[(String, f32)]),
+    (b, b_neighbors): (&dyn EmbeddingStore, &[(String, f32)]),
+) -> Option<Shift> {
+    let a_vec = a.get(word)?;
+    let b_vec = b.get(word)?;
+
+    let mut union: Vec<&str> = a_neighbors.iter().map(|(w, _)| w.as_str()).collect();
+    for (w, _) in b_neighbors {
+        if !union.contains(&w.as_str()) {
+            union.push(w);
+        }
+    }
+    let shared = a_neighbors.iter().filter(|(w, _)| b_neighbors.iter().any(|(v, _)| v == w)).count();
+
+    let (mut a_profile, mut b_profile) = (Vec::new(), Vec::new());
+    for n in &union {
+        if let (Some(an), Some(bn)) = (a.get(n), b.get(n)) {
+            a_profile.push(cosine_similarity(&a_vec, &an));
+            b_profile.push(cosine_similarity(&b_vec, &bn));
+        }
+    }
+    if a_profile.is_empty() {
+        return None;
+    }
+
+    Some(Shift {
+        shift: 1.0 - cosine_similarity(&a_profile, &b_profile),
+        shared,
+        union: union.len(),
+    })
+}
+
+/// Jaccard overlap (shared over combined) of each word's `k` nearest
+/// neighbors in two spaces; `None` for words either space lacks
+pub fn neighbor_overlap(
+    a: &dyn EmbeddingStore,
+    b: &dyn EmbeddingStore,
+    words: &[String],
+    k: usize,
+) -> Vec<(String, Option<f32>)> {
+    let mut rows = Vec::new();
+    let (mut a_queries, mut b_queries) = (Vec::new(), Vec::new());
+    for (i, word) in words.iter().enumerate() {
+        let (Some(a_vec), Some(b_vec)) = (a.get(word), b.get(word)) else { continue };
+        rows.push(i);
+        a_queries.push((a_vec.into_owned(), vec![word.clone()]));
+        b_queries.push((b_vec.into_owned(), vec![word.clone()]));
+    }
+    // Each space is scanned once per batch of words rather than once per word
+    let a_found = find_nearest_neighbors_batch(&a_queries, a, k, &BuiltinMetric::Cosine);
+    let b_found = find_nearest_neighbors_batch(&b_queries, b, k, &BuiltinMetric::Cosine);
+
+    let mut overlaps: Vec<(String, Option<f32>)> = words.iter().map(|w| (w.clone(), None)).collect();
+    for (i, (a_found, b_found)) in rows.into_iter().zip(a_found.iter().zip(&b_found)) {
+        let shared = a_found.iter().filter(|(w, _)| b_found.iter().any(|(v, _)| v == w)).count();
+        let union = a_found.len() + b_found.len() - shared;
+        overlaps[i].1 = Some(if union == 0 { 1.0 } else { shared as f32 / union as f32 });
+    }
+    overlaps
+}
+
+/// Up to `count` distinct words drawn at random from the first `pool` rows of
+/// `a` (all of them if `pool` is 0) that `b` also knows
+pub fn shared_sample(
+    a: &dyn EmbeddingStore,
+    b: &dyn EmbeddingStore,
+    count: usize,
+    pool: usize,
+    rng: &mut Rng,
+) -> Vec<String> {
+    let pool = if pool == 0 { a.len() } else { pool.min(a.len()) };
+    let mut words = Vec::new();
+    // Give up after a generous number of draws, should the spaces share few words
+    for _ in 0..count.saturating_mul(20) {
+        if words.len() == count || pool == 0 {
+            break;
+        }
+        let Some((word, _)) = a.get_by_id(rng.below(pool)) else { continue };
+        if b.contains(&word) && !words.iter().any(|w| *w == word) {
+            words.push(word.into_owned());
+        }
+    }
+    words
+}
@@ -0,0 +1,76 @@
+//! The error type returned by the library's loading and lookup functions.
+
+use std::fmt;
+use std::io;
+
+/// Something that went wrong while loading or querying vectors
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing failed
+    Io(io::Error),
+    /// A component of a text vector file is not a number
+    Parse { line: usize, token: String },
+    /// A row of a text vector file has a word but no components
+    EmptyRow { line: usize },
+    /// A vector has a different number of components than the ones before it
+    /// (`line` is set when it comes from a text file)
+    DimensionMismatch { line: Option<usize>, expected: usize, found: usize },
+    /// Words missing from the vocabulary, each with a known spelling to
+    /// suggest instead, if any
+    OutOfVocabulary(Vec<(String, Option<String>)>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse { line, token } => write!(f, "line {}: '{}' is not a number", line, token),
+            Error::EmptyRow { line } => write!(f, "line {}: word has no vector components", line),
+            Error::DimensionMismatch { line: Some(line), expected, found } => {
+                write!(f, "line {}: {} components, expected {}", line, found, expected)
+            }
+            Error::DimensionMismatch { line: None, expected, found } => {
+                write!(f, "{} dimensions, expected {}", found, expected)
+            }
+            Error::OutOfVocabulary(words) => {
+                let words: Vec<String> = words
+                    .iter()
+                    .map(|(word, suggestion)| match suggestion {
+                        Some(suggestion) => format!("{} (did you mean {}?)", word, suggestion),
+                        None => word.clone(),
+                    })
+                    .collect();
+                let noun = if words.len() == 1 { "word" } else { "words" };
+                write!(f, "Unknown {}: {}", noun, words.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// For callers that still speak `io::Result`; anything but an I/O error
+/// becomes `InvalidData`
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other.to_string()),
+        }
+    }
+}
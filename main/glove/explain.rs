@@ -0,0 +1,112 @@
+//! Why a word answered a query: its cosine similarity to the query vector
+//! split into the share of each dimension and of each input word.
+//!
+//! With the query `q = Σ s_j v_j` (divided by the word count for averages),
+//! the cosine with a neighbor `w` is `Σ_i q_i w_i / (|q| |w|)`, so dimension
+//! `i` contributes `q_i w_i / (|q| |w|)` and word `j` contributes
+//! `s_j (v_j · w) / (|q| |w|)`; both sets of shares add up to the cosine.
+//!
+//! The query vector's own cosine with each input word shows whether one word
+//! dominates the combination before any neighbor is involved.
+
+use crate::expression::{Combine, Expression};
+use crate::store::EmbeddingStore;
+use crate::vector::{cosine_similarity, norm};
+
+/// One dimension's share of the cosine, and how the input words split it
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DimensionShare {
+    pub dim: usize,
+    pub share: f32,
+    /// Each known input word's signed part of `share`
+    pub terms: Vec<(String, f32)>,
+}
+
+/// The cosine similarity of a query and one neighbor, taken apart
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Explanation {
+    pub neighbor: String,
+    pub cosine: f32,
+    /// Each known input word's signed share, in expression order, with
+    /// subtracted words prefixed by `-`
+    pub terms: Vec<(String, f32)>,
+    /// The dimensions adding most to the cosine, largest share first
+    pub dimensions: Vec<DimensionShare>,
+}
+
+/// Explains `neighbor`'s cosine similarity to `expression` combined with
+/// `combine`, keeping the `top_dims` dimensions with the largest shares.
+/// `None` if the store knows neither the neighbor nor any input word, or if
+/// `combine` pools the vectors or takes their median, which has no such
+/// breakdown.
+pub fn explain(
+    store: &dyn EmbeddingStore,
+    expression: &Expression,
+    combine: Combine,
+    neighbor: &str,
+    top_dims: usize,
+) -> Option<Explanation> {
+    if !combine.is_linear() {
+        return None;
+    }
+    let target = store.get(neighbor)?;
+    let known: Vec<(f32, String, Vec<f32>)> = expression
+        .terms
+        .iter()
+        .filter_map(|(sign, word)| {
+            let vec = store.get(word)?.into_owned();
+            // Subtracted words are named as they were written, e.g. `-man`
+            let name = if *sign < 0.0 { format!("-{}", word) } else { word.clone() };
+            Some((*sign, name, vec))
+        })
+        .collect();
+    if known.is_empty() {
+        return None;
+    }
+    let scale = match combine {
+        Combine::Average => 1.0 / known.len() as f32,
+        _ => 1.0,
+    };
+    let query = expression.combine(store, combine)?;
+    let denominator = norm(&query) * norm(&target) + 1e-10;
+
+    // Each word's signed, scaled part of each dimension's product with the neighbor
+    let parts: Vec<Vec<f32>> = known
+        .iter()
+        .map(|(sign, _, vec)| vec.iter().zip(target.iter()).map(|(v, w)| sign * scale * v * w / denominator).collect())
+        .collect();
+    let terms = known.iter().zip(&parts).map(|((_, word, _), part)| (word.clone(), part.iter().sum())).collect();
+    let mut dimensions: Vec<DimensionShare> = (0..store.dim())
+        .map(|dim| DimensionShare {
+            dim,
+            share: query[dim] * target[dim] / denominator,
+            terms: known.iter().zip(&parts).map(|((_, word, _), part)| (word.clone(), part[dim])).collect(),
+        })
+        .collect();
+    dimensions.sort_by(|a, b| b.share.total_cmp(&a.share));
+    dimensions.truncate(top_dims);
+
+    Some(Explanation {
+        neighbor: neighbor.to_string(),
+        cosine: query.iter().zip(target.iter()).map(|(q, w)| q * w).sum::<f32>() / denominator,
+        terms,
+        dimensions,
+    })
+}
+
+/// The cosine similarity of the vector `expression` is searched around to
+/// each known input word's vector, in expression order
+pub fn operand_similarities(
+    store: &dyn EmbeddingStore,
+    expression: &Expression,
+    combine: Combine,
+) -> Vec<(String, f32)> {
+    let Some(query) = expression.query_vector(store, combine) else { return Vec::new() };
+    expression
+        .terms
+        .iter()
+        .filter_map(|(_, word)| Some((word.clone(), cosine_similarity(&query, &store.get(word)?))))
+        .collect()
+}
@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::formats::NPY_MAGIC;
+use crate::store::EmbeddingStore;
+
+/// Writes the vectors in TensorBoard Embedding Projector format:
+/// a tensor TSV (one vector per row) and a metadata TSV (one word per row, same order)
+pub fn export_projector(
+    store: &dyn EmbeddingStore,
+    tensor_path: &str,
+    metadata_path: &str,
+) -> io::Result<()> {
+    let mut tensor = BufWriter::new(File::create(tensor_path)?);
+    let mut metadata = BufWriter::new(File::create(metadata_path)?);
+
+    for (word, vec) in store.iter() {
+        let row: Vec<String> = vec.iter().map(|x| x.to_string()).collect();
+        writeln!(tensor, "{}", row.join("\t"))?;
+        // Single-column metadata files must not have a header row
+        writeln!(metadata, "{}", word)?;
+    }
+
+    tensor.flush()?;
+    metadata.flush()
+}
+
+/// Writes every entry of a store as a GloVe text file, one `word v1 v2 ...` row per line
+pub fn write_glove_text(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for (word, vec) in store.iter() {
+        write!(out, "{}", word)?;
+        for val in vec.iter() {
+            write!(out, " {}", val)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+/// Writes the vectors as a float32 `.npy` matrix, and the words one per line
+/// in `<name>.vocab` beside it, where loading a `.npy` file looks for them
+pub fn write_npy(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    let mut matrix = BufWriter::new(File::create(path)?);
+    let mut vocab = BufWriter::new(File::create(Path::new(path).with_extension("vocab"))?);
+    matrix.write_all(&npy_header("<f4", &[store.len(), store.dim()]))?;
+    for (word, vec) in store.iter() {
+        for val in vec.iter() {
+            matrix.write_all(&val.to_le_bytes())?;
+        }
+        writeln!(vocab, "{}", word)?;
+    }
+    matrix.flush()?;
+    vocab.flush()
+}
+
+/// Writes the vectors as an uncompressed `.npz` archive holding `vectors`, a
+/// float32 matrix, and `words`, a unicode array, so that
+/// `np.load(path)["words"]` lines up with `np.load(path)["vectors"]`
+pub fn write_npz(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    let chars = store.iter().map(|(word, _)| word.chars().count()).max().unwrap_or(0).max(1);
+    let vectors_len = store.len() * store.dim() * 4;
+    let words_len = store.len() * chars * 4;
+    // Sizes and offsets are written as 32 bits; larger archives would need ZIP64
+    if (vectors_len + words_len) as u64 + 1024 > u32::MAX as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "too large for a .npz archive; export npy instead"));
+    }
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    zip.start("vectors.npy")?;
+    zip.write_all(&npy_header("<f4", &[store.len(), store.dim()]))?;
+    for (_, vec) in store.iter() {
+        for val in vec.iter() {
+            zip.write_all(&val.to_le_bytes())?;
+        }
+    }
+    zip.start("words.npy")?;
+    zip.write_all(&npy_header(&format!("<U{}", chars), &[store.len()]))?;
+    let mut padded = Vec::with_capacity(chars * 4);
+    for (word, _) in store.iter() {
+        padded.clear();
+        padded.extend(word.chars().flat_map(|c| (c as u32).to_le_bytes()));
+        padded.resize(chars * 4, 0);
+        zip.write_all(&padded)?;
+    }
+    zip.finish()
+}
+
+/// A version 1.0 `.npy` header for a C-ordered array, padded so the data
+/// starts on a 64-byte boundary
+fn npy_header(descr: &str, shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [len] => format!("({},)", len),
+        _ => format!("({})", shape.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut dict = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    let unpadded = NPY_MAGIC.len() + 4 + dict.len() + 1;
+    dict.extend(std::iter::repeat_n(' ', unpadded.next_multiple_of(64) - unpadded));
+    dict.push('\n');
+
+    let mut header = NPY_MAGIC.to_vec();
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+/// CRC-32 (as used by zip) lookup table
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Writes a zip archive of stored (uncompressed) members one after another,
+/// each followed by a data descriptor since its size and CRC are known only
+/// once it has been written
+struct ZipWriter<W: Write> {
+    out: W,
+    offset: u32,
+    /// Name, local header offset, CRC and size of each finished member
+    members: Vec<(String, u32, u32, u32)>,
+    current: Option<(String, u32)>,
+    crc: u32,
+    len: u32,
+}
+
+impl<W: Write> ZipWriter<W> {
+    fn new(out: W) -> Self {
+        ZipWriter { out, offset: 0, members: Vec::new(), current: None, crc: !0, len: 0 }
+    }
+
+    fn raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.offset += bytes.len() as u32;
+        Ok(())
+    }
+
+    /// Ends the current member, if any, and starts one called `name`
+    fn start(&mut self, name: &str) -> io::Result<()> {
+        self.end_member()?;
+        let header_offset = self.offset;
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        // Version 2.0, a data descriptor follows, stored, 1980-01-01 00:00
+        for field in [20u16, 0x0008, 0, 0, 0x0021] {
+            header.extend_from_slice(&field.to_le_bytes());
+        }
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        self.raw(&header)?;
+        self.current = Some((name.to_string(), header_offset));
+        Ok(())
+    }
+
+    fn end_member(&mut self) -> io::Result<()> {
+        let Some((name, header_offset)) = self.current.take() else { return Ok(()) };
+        let crc = !self.crc;
+        let mut descriptor = Vec::with_capacity(16);
+        for field in [0x0807_4b50, crc, self.len, self.len] {
+            descriptor.extend_from_slice(&field.to_le_bytes());
+        }
+        self.raw(&descriptor)?;
+        self.members.push((name, header_offset, crc, self.len));
+        self.crc = !0;
+        self.len = 0;
+        Ok(())
+    }
+
+    /// Writes the central directory
+    fn finish(mut self) -> io::Result<()> {
+        self.end_member()?;
+        let directory_offset = self.offset;
+        let mut directory = Vec::new();
+        for (name, header_offset, crc, len) in &self.members {
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            for field in [20u16, 20, 0x0008, 0, 0, 0x0021] {
+                directory.extend_from_slice(&field.to_le_bytes());
+            }
+            for field in [*crc, *len, *len] {
+                directory.extend_from_slice(&field.to_le_bytes());
+            }
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            // Extra field, comment, disk number, internal and external attributes
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&header_offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let count = (self.members.len() as u16).to_le_bytes();
+        let mut end = 0x0605_4b50u32.to_le_bytes().to_vec();
+        end.extend_from_slice(&[0; 4]);
+        end.extend_from_slice(&count);
+        end.extend_from_slice(&count);
+        end.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        end.extend_from_slice(&directory_offset.to_le_bytes());
+        end.extend_from_slice(&[0; 2]);
+        self.raw(&directory)?;
+        self.raw(&end)?;
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Write for ZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.out.write(buf)?;
+        for &b in &buf[..n] {
+            self.crc = CRC_TABLE[((self.crc ^ b as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+        self.len += n as u32;
+        self.offset += n as u32;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,314 @@
+//! Word-arithmetic expressions such as `paris - france + italy`.
+
+use std::fmt;
+
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// Weiszfeld steps before the geometric median is taken as converged
+const WEISZFELD_ITERATIONS: usize = 100;
+
+/// How the vectors of an expression's words are combined into one query
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Combine {
+    /// The signed sum, e.g. `king - man + woman`
+    #[default]
+    Sum,
+    /// The signed sum divided by the number of words used
+    Average,
+    /// The element-wise minimum of the signed vectors
+    Min,
+    /// The element-wise maximum of the signed vectors
+    Max,
+    /// The minimum and maximum concatenated, twice the dimensions
+    MinMax,
+    /// The point with the least total distance to the signed vectors, which
+    /// unlike the mean is not dragged far off by one outlying word
+    GeometricMedian,
+}
+
+impl Combine {
+    /// Names accepted by `--pool`
+    pub const POOLS: &'static [&'static str] = &["mean", "min", "max", "minmax"];
+
+    /// Names accepted by `--combine`
+    pub const NAMES: &'static [&'static str] = &["mean", "geometric-median", "min", "max", "minmax"];
+
+    /// The combination named `name`, one of `NAMES`
+    pub fn parse(name: &str) -> Option<Combine> {
+        match name {
+            "geometric-median" => Some(Combine::GeometricMedian),
+            _ => Combine::parse_pool(name),
+        }
+    }
+
+    /// The pooling named `name`; `mean` is `Average`
+    pub fn parse_pool(name: &str) -> Option<Combine> {
+        match name {
+            "mean" => Some(Combine::Average),
+            "min" => Some(Combine::Min),
+            "max" => Some(Combine::Max),
+            "minmax" => Some(Combine::MinMax),
+            _ => None,
+        }
+    }
+
+    /// Whether the result is a linear combination of the word vectors
+    pub fn is_linear(self) -> bool {
+        matches!(self, Combine::Sum | Combine::Average)
+    }
+}
+
+/// How a run of adjacent words with the same sign was looked up
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Phrase {
+    /// The words were replaced by their underscore-joined token, e.g. `new_york`
+    Joined { words: Vec<String>, token: String },
+    /// No joined token exists, so the words are used one by one
+    Separate { words: Vec<String> },
+}
+
+/// A signed sum of words such as `paris - france + italy`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expression {
+    /// Each word with its sign (`1.0` or `-1.0`)
+    pub terms: Vec<(f32, String)>,
+}
+
+impl Expression {
+    /// The plain sum of `words`, as given on the command line
+    pub fn sum<S: AsRef<str>>(words: &[S]) -> Expression {
+        Expression {
+            terms: words.iter().map(|w| (1.0, w.as_ref().to_string())).collect(),
+        }
+    }
+
+    /// Parses words joined by `+` and `-`; operators may stand alone or be
+    /// attached to the following word (`paris -france`), and words with no
+    /// operator between them are added, as on the command line
+    pub fn parse(text: &str) -> Result<Expression, String> {
+        let mut terms = Vec::new();
+        let mut sign = 1.0;
+        let mut after_operator = false;
+        for token in text.split_whitespace() {
+            let (op, word) = match token.chars().next() {
+                Some('+') => (Some(1.0), &token[1..]),
+                Some('-') => (Some(-1.0), &token[1..]),
+                _ => (None, token),
+            };
+            if let Some(op) = op {
+                if after_operator {
+                    return Err(format!("misplaced operator in '{}'", text));
+                }
+                sign = op;
+                after_operator = true;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            terms.push((sign, word.to_string()));
+            sign = 1.0;
+            after_operator = false;
+        }
+        if after_operator || terms.is_empty() {
+            return Err(format!("incomplete expression '{}'", text));
+        }
+        Ok(Expression { terms })
+    }
+
+    /// Replaces runs of adjacent words with the same sign by their
+    /// underscore-joined token when the store has one (`new york city` may
+    /// become `new_york_city`, or `new_york` + `city`), preferring the longest.
+    /// Also returns how every run of two or more words was looked up.
+    pub fn join_phrases(&self, store: &dyn EmbeddingStore) -> (Expression, Vec<Phrase>) {
+        let mut terms = Vec::with_capacity(self.terms.len());
+        let mut phrases = Vec::new();
+        let mut start = 0;
+        while start < self.terms.len() {
+            let sign = self.terms[start].0;
+            let run_len = self.terms[start..].iter().take_while(|(s, _)| *s == sign).count();
+            let run: Vec<&str> = self.terms[start..start + run_len].iter().map(|(_, w)| w.as_str()).collect();
+            start += run_len;
+
+            let mut joined_any = false;
+            let mut i = 0;
+            while i < run.len() {
+                let longest = (2..=run.len() - i).rev().find(|&n| store.contains(&run[i..i + n].join("_")));
+                match longest {
+                    Some(n) => {
+                        let token = run[i..i + n].join("_");
+                        let words = run[i..i + n].iter().map(|w| w.to_string()).collect();
+                        phrases.push(Phrase::Joined { words, token: token.clone() });
+                        terms.push((sign, token));
+                        joined_any = true;
+                        i += n;
+                    }
+                    None => {
+                        terms.push((sign, run[i].to_string()));
+                        i += 1;
+                    }
+                }
+            }
+            if run.len() > 1 && !joined_any {
+                phrases.push(Phrase::Separate { words: run.iter().map(|w| w.to_string()).collect() });
+            }
+        }
+        (Expression { terms }, phrases)
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.terms.iter().map(|(_, w)| w.as_str())
+    }
+
+    /// Evaluates the expression, failing on the first unknown word
+    pub fn vector(&self, store: &dyn EmbeddingStore) -> Result<WordVec, String> {
+        if let Some(unknown) = self.words().find(|w| !store.contains(w)) {
+            return Err(format!("unknown word: {}", unknown));
+        }
+        Ok(self.known_vector(store).unwrap_or_else(|| vec![0.0; store.dim()]))
+    }
+
+    /// Combines the vectors of the words the store knows, skipping the rest;
+    /// `None` if it knows none of them
+    pub fn combine(&self, store: &dyn EmbeddingStore, combine: Combine) -> Option<WordVec> {
+        if combine == Combine::GeometricMedian {
+            return self.geometric_median(store);
+        }
+        if !combine.is_linear() {
+            return self.pool(store, combine);
+        }
+        let (sum, known) = self.known_sum(store)?;
+        let scale = if combine == Combine::Average { known as f64 } else { 1.0 };
+        Some(sum.iter().map(|s| (s / scale) as f32).collect())
+    }
+
+    /// The vector to search neighbors around: `combine` itself, except that a
+    /// `MinMax` concatenation becomes the midpoint of its halves, which ranks
+    /// words (by cosine, dot product or distance) as comparing it with each
+    /// word's vector repeated twice would
+    pub fn query_vector(&self, store: &dyn EmbeddingStore, combine: Combine) -> Option<WordVec> {
+        let vec = self.combine(store, combine)?;
+        if combine != Combine::MinMax {
+            return Some(vec);
+        }
+        let (min, max) = vec.split_at(vec.len() / 2);
+        Some(min.iter().zip(max).map(|(a, b)| (a + b) / 2.0).collect())
+    }
+
+    /// The geometric median of the known words' signed vectors, by
+    /// Weiszfeld's algorithm: starting from the mean, each step moves to the
+    /// average of the points weighted by their inverse distance to the current
+    /// estimate
+    fn geometric_median(&self, store: &dyn EmbeddingStore) -> Option<WordVec> {
+        let points: Vec<WordVec> = self
+            .terms
+            .iter()
+            .filter_map(|(sign, word)| Some(store.get(word)?.iter().map(|x| sign * x).collect()))
+            .collect();
+        let dim = points.first()?.len();
+        let mut median = vec![0.0f32; dim];
+        for point in &points {
+            for (m, x) in median.iter_mut().zip(point) {
+                *m += x / points.len() as f32;
+            }
+        }
+        for _ in 0..WEISZFELD_ITERATIONS {
+            let mut next = vec![0.0f32; dim];
+            let mut total_weight = 0.0;
+            for point in &points {
+                let distance = point.iter().zip(&median).map(|(x, m)| (x - m) * (x - m)).sum::<f32>().sqrt();
+                // A point the estimate sits on would get an infinite weight
+                let weight = 1.0 / distance.max(1e-6);
+                for (n, x) in next.iter_mut().zip(point) {
+                    *n += weight * x;
+                }
+                total_weight += weight;
+            }
+            next.iter_mut().for_each(|n| *n /= total_weight);
+            let step = next.iter().zip(&median).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt();
+            let scale = next.iter().map(|x| x * x).sum::<f32>().sqrt();
+            median = next;
+            if step <= 1e-6 * scale.max(1.0) {
+                break;
+            }
+        }
+        Some(median)
+    }
+
+    /// Element-wise min and/or max pooling of the known words' signed vectors
+    fn pool(&self, store: &dyn EmbeddingStore, combine: Combine) -> Option<WordVec> {
+        let mut min: Option<WordVec> = None;
+        let mut max: Option<WordVec> = None;
+        for (sign, word) in &self.terms {
+            let Some(vec) = store.get(word) else { continue };
+            let signed = vec.iter().map(|x| sign * x);
+            match (&mut min, &mut max) {
+                (Some(min), Some(max)) => {
+                    for ((lo, hi), x) in min.iter_mut().zip(max.iter_mut()).zip(signed) {
+                        *lo = lo.min(x);
+                        *hi = hi.max(x);
+                    }
+                }
+                _ => {
+                    min = Some(signed.collect());
+                    max = min.clone();
+                }
+            }
+        }
+        match combine {
+            Combine::Min => min,
+            Combine::Max => max,
+            _ => {
+                let mut both = min?;
+                both.extend(max?);
+                Some(both)
+            }
+        }
+    }
+
+    /// Evaluates the expression over the words the store knows, skipping the
+    /// rest; `None` if it knows none of them
+    pub fn known_vector(&self, store: &dyn EmbeddingStore) -> Option<WordVec> {
+        let (sum, _) = self.known_sum(store)?;
+        Some(sum.iter().map(|&s| s as f32).collect())
+    }
+
+    /// The signed sum of the known words' vectors and how many there were.
+    /// It is accumulated in f64, so long expressions such as whole sentences
+    /// do not pile up f32 rounding error before the result is narrowed back.
+    fn known_sum(&self, store: &dyn EmbeddingStore) -> Option<(Vec<f64>, usize)> {
+        let mut sum: Option<Vec<f64>> = None;
+        let mut known = 0;
+        for (sign, word) in &self.terms {
+            let Some(vec) = store.get(word) else { continue };
+            let sum = sum.get_or_insert_with(|| vec![0.0; vec.len()]);
+            for (s, x) in sum.iter_mut().zip(vec.iter()) {
+                *s += *sign as f64 * *x as f64;
+            }
+            known += 1;
+        }
+        Some((sum?, known))
+    }
+}
+
+/// Writes the expression in the form `parse` reads, e.g. `king - man + woman`,
+/// so equal expressions print alike however they were typed
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (sign, word)) in self.terms.iter().enumerate() {
+            match (i, *sign < 0.0) {
+                (0, false) => {}
+                (0, true) => write!(f, "-")?,
+                (_, false) => write!(f, " + ")?,
+                (_, true) => write!(f, " - ")?,
+            }
+            if sign.abs() != 1.0 {
+                write!(f, "{}*", sign.abs())?;
+            }
+            write!(f, "{}", word)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,776 @@
+//! Embedding file formats and the single `Embeddings::open` entry point.
+//!
+//! The format is sniffed from the first bytes of the (decompressed) input, so
+//! no format flag is needed: GloVe text, word2vec / fastText `.vec` text,
+//! word2vec binary, fastText `.bin`, NumPy `.npy` (with a vocabulary file next
+//! to it) and `.npz`, Apache Parquet tables, safetensors (with a JSON
+//! vocabulary next to it), HDF5 files, Magnitude databases, GGUF model files
+//! (a token embedding tensor and its vocabulary) and the binary cache written
+//! by `convert --to binary`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::ops::Deref;
+use std::path::Path;
+
+use crate::binary::{parse_stream_header, HEADER_LEN, MAGIC};
+#[cfg(feature = "serde")]
+use crate::dense::{DenseStore, Rows};
+use crate::gguf::{read_gguf, GGUF_MAGIC};
+use crate::hdf5::{read_hdf5, HDF5_MAGIC};
+use crate::input::{archive_members, Source};
+use crate::load::{LoadOptions, RowSink};
+use crate::magnitude::{read_magnitude, SQLITE_MAGIC};
+use crate::parquet::read_parquet;
+use crate::safetensors::{is_safetensors, read_safetensors};
+use crate::shards::{is_sharded, shard_paths};
+use crate::store::{open_store, EmbeddingStore};
+use crate::WordVec;
+
+/// Most bytes reserved up front for a matrix whose size a header gives,
+/// so a corrupt header cannot ask for more memory than the data fills
+const MAX_PREALLOC_BYTES: usize = 64 << 20;
+
+/// Capacity to reserve for `count` items read from a file, capped at
+/// `MAX_PREALLOC_BYTES`; the collection still grows past it as items arrive
+fn prealloc<T>(count: usize) -> usize {
+    count.min(MAX_PREALLOC_BYTES / std::mem::size_of::<T>().max(1))
+}
+
+/// First four bytes of a fastText `.bin` model
+const FASTTEXT_MAGIC: i32 = 793712314;
+
+pub(crate) const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Longest `.npy` header read; NumPy itself refuses ones over 10000 bytes
+const MAX_NPY_HEADER: usize = 10_000;
+
+/// First four bytes of a zip archive, which is what a `.npz` file is
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// An embedding file format recognized by `Format::sniff`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    /// `word f1 f2 ...` per line
+    GloveText,
+    /// GloVe-style lines after a `count dim` header (also fastText `.vec`)
+    Word2VecText,
+    /// `count dim` header line, then `word ` followed by raw little-endian f32s
+    Word2VecBinary,
+    /// A full fastText model; word vectors include their subword n-grams
+    FastTextBinary,
+    /// A 2-d float array, with words read from a vocabulary file beside it
+    Npy,
+    /// A zip of `.npy` arrays: the vectors, and the words or a vocabulary file beside it
+    Npz,
+    /// Apache Parquet table with a string column of words and float columns
+    Parquet,
+    /// A safetensors file, with words read from a JSON vocabulary beside it
+    Safetensors,
+    /// An HDF5 file with a matrix dataset and a dataset of words (needs the `hdf5` feature)
+    Hdf5,
+    /// A pymagnitude SQLite database (needs the `sqlite` feature)
+    Magnitude,
+    /// A GGUF model file, read from one tensor and the tokenizer vocabulary
+    Gguf,
+    /// The file written by `convert --to binary`
+    BinaryCache,
+}
+
+impl Format {
+    /// Guesses the format from the first bytes of the input
+    pub fn sniff(head: &[u8]) -> Format {
+        if head.starts_with(MAGIC) {
+            return Format::BinaryCache;
+        }
+        if head.starts_with(NPY_MAGIC) {
+            return Format::Npy;
+        }
+        if head.starts_with(ZIP_MAGIC) {
+            return Format::Npz;
+        }
+        if head.starts_with(b"PAR1") {
+            return Format::Parquet;
+        }
+        if head.starts_with(SQLITE_MAGIC) {
+            return Format::Magnitude;
+        }
+        if head.starts_with(GGUF_MAGIC) {
+            return Format::Gguf;
+        }
+        if head.starts_with(HDF5_MAGIC) {
+            return Format::Hdf5;
+        }
+        if is_safetensors(head) {
+            return Format::Safetensors;
+        }
+        if head.len() >= 4 && i32::from_le_bytes(head[0..4].try_into().unwrap()) == FASTTEXT_MAGIC {
+            return Format::FastTextBinary;
+        }
+
+        let line_end = head.iter().position(|&b| b == b'\n').unwrap_or(head.len());
+        if !is_count_dim_header(&String::from_utf8_lossy(&head[..line_end])) {
+            return Format::GloveText;
+        }
+        // After a word2vec header, raw floats soon produce control bytes or invalid UTF-8
+        let rest = &head[(line_end + 1).min(head.len())..];
+        let control = rest.iter().any(|&b| b < 0x09 || (0x0e..0x20).contains(&b));
+        let invalid = std::str::from_utf8(rest).is_err_and(|e| e.error_len().is_some());
+        if control || invalid {
+            Format::Word2VecBinary
+        } else {
+            Format::Word2VecText
+        }
+    }
+
+    /// Opens `source` and sniffs its first bytes; for shards, those of the first
+    pub fn detect(source: &Source) -> io::Result<Format> {
+        if let Source::Path(path) = source {
+            if is_sharded(path) {
+                return Format::detect(&Source::from(shard_paths(path)?[0].as_str()));
+            }
+        }
+        let mut reader = source.open()?;
+        Ok(Format::sniff(reader.fill_buf()?))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::GloveText => "GloVe text",
+            Format::Word2VecText => "word2vec text",
+            Format::Word2VecBinary => "word2vec binary",
+            Format::FastTextBinary => "fastText binary",
+            Format::Npy => "NumPy array",
+            Format::Npz => "NumPy archive",
+            Format::Parquet => "Parquet",
+            Format::Safetensors => "safetensors",
+            Format::Hdf5 => "HDF5",
+            Format::Magnitude => "Magnitude",
+            Format::Gguf => "GGUF",
+            Format::BinaryCache => "binary cache",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Whether a line is a word2vec `count dim` header rather than a vector row
+pub fn is_count_dim_header(line: &str) -> bool {
+    let mut parts = line.split_whitespace();
+    matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(count), Some(dim), None) if count.parse::<u64>().is_ok() && dim.parse::<u64>().is_ok()
+    )
+}
+
+/// Loaded vectors together with the format they were read from
+pub struct Embeddings {
+    format: Format,
+    store: Box<dyn EmbeddingStore>,
+}
+
+impl Embeddings {
+    /// Opens an embedding file of any supported format, loading it into memory
+    pub fn open(path: &str) -> crate::Result<Embeddings> {
+        Embeddings::open_source(&Source::from(path), &LoadOptions::default())
+    }
+
+    /// Opens a file or archive member of any supported format with the given options
+    pub fn open_source(source: &Source, options: &LoadOptions) -> crate::Result<Embeddings> {
+        let format = Format::detect(source)?;
+        let store = open_store("dense", source, options)?;
+        Ok(Embeddings { format, store })
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn into_store(self) -> Box<dyn EmbeddingStore> {
+        self.store
+    }
+}
+
+impl Deref for Embeddings {
+    type Target = dyn EmbeddingStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.store.as_ref()
+    }
+}
+
+/// Serialized as the format and the vectors in the form `DenseStore` uses;
+/// reduced-precision vectors are written at full precision
+#[cfg(feature = "serde")]
+impl serde::Serialize for Embeddings {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let dense = DenseStore::from_store(self.store.as_ref());
+        serde::Serialize::serialize(&Snapshot { format: self.format, rows: Rows::of(&dense) }, serializer)
+    }
+}
+
+/// Deserialized into in-memory vectors
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Embeddings {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let snapshot = <Snapshot as serde::Deserialize>::deserialize(deserializer)?;
+        let store = snapshot.rows.into_store().map_err(serde::de::Error::custom)?;
+        Ok(Embeddings { format: snapshot.format, store: Box::new(store) })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Snapshot<'a> {
+    format: Format,
+    #[serde(flatten)]
+    rows: Rows<'a>,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn read_array<const N: usize>(reader: &mut dyn BufRead) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_i32(reader: &mut dyn BufRead) -> io::Result<i32> {
+    read_array(reader).map(i32::from_le_bytes)
+}
+
+fn read_i64(reader: &mut dyn BufRead) -> io::Result<i64> {
+    read_array(reader).map(i64::from_le_bytes)
+}
+
+/// Reads exactly `len` bytes; a corrupt `len` fails at the end of the input
+/// rather than allocating all of it first
+pub(crate) fn read_bytes(reader: &mut dyn BufRead, len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOC_BYTES));
+    if (&mut *reader).take(len as u64).read_to_end(&mut bytes)? < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// Reads `len` little-endian f32s
+fn read_f32s(reader: &mut dyn BufRead, len: usize) -> io::Result<WordVec> {
+    let want = len.checked_mul(4).ok_or_else(|| invalid("vector length overflows"))?;
+    Ok(read_bytes(reader, want)?.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect())
+}
+
+/// Reads bytes up to (and dropping) `delimiter`
+fn read_until(reader: &mut dyn BufRead, delimiter: u8) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_until(delimiter, &mut bytes)?;
+    if bytes.pop() != Some(delimiter) {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// Parses the rows of any format but the text ones, which `load` handles itself
+pub(crate) fn read_binary_rows(
+    format: Format,
+    source: &Source,
+    reader: &mut dyn BufRead,
+    sink: &mut RowSink,
+) -> io::Result<()> {
+    match format {
+        Format::Word2VecBinary => read_word2vec_binary(reader, sink),
+        Format::FastTextBinary => read_fasttext(reader, sink),
+        Format::Npy => read_npy(reader, &npy_vocabulary(source)?, sink),
+        Format::Npz => read_npz(source, sink),
+        Format::BinaryCache => read_binary_cache(reader, sink),
+        Format::Parquet => read_parquet(reader, sink),
+        Format::Safetensors => read_safetensors(source, reader, sink),
+        Format::Hdf5 => read_hdf5(source, sink),
+        Format::Magnitude => read_magnitude(source, sink),
+        Format::Gguf => read_gguf(reader, sink),
+        Format::GloveText | Format::Word2VecText => unreachable!("text formats are parsed line by line"),
+    }
+}
+
+fn read_word2vec_binary(reader: &mut dyn BufRead, sink: &mut RowSink) -> io::Result<()> {
+    let header = String::from_utf8_lossy(&read_until(reader, b'\n')?).into_owned();
+    let mut parts = header.split_whitespace().map(|p| p.parse::<usize>());
+    let (Some(Ok(count)), Some(Ok(dim))) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed word2vec header"));
+    };
+
+    for _ in 0..count {
+        if sink.full() {
+            break;
+        }
+        // Rows may or may not end in a newline, so it is skipped before the next word
+        let mut word = read_until(reader, b' ')?;
+        if word.first() == Some(&b'\n') {
+            word.remove(0);
+        }
+        let vec = read_f32s(reader, dim)?;
+        sink.read(word.len() + 1 + dim * 4);
+        let word = String::from_utf8_lossy(&word);
+        if sink.wants(&word) {
+            sink.add(&word, vec);
+        }
+    }
+    Ok(())
+}
+
+/// Hash fastText uses for character n-grams (FNV-1a over sign-extended bytes)
+fn fasttext_hash(ngram: &[u8]) -> u32 {
+    let mut h: u32 = 2166136261;
+    for &b in ngram {
+        h ^= b as i8 as u32;
+        h = h.wrapping_mul(16777619);
+    }
+    h
+}
+
+/// Row ids fastText sums for a dictionary word: the word itself, then its
+/// character n-grams of `<word>` between `minn` and `maxn` characters long
+fn fasttext_subwords(word: &str, id: usize, model: &FastTextModel) -> Vec<usize> {
+    let mut ids = vec![id];
+    if word == "</s>" || model.maxn == 0 || model.bucket == 0 {
+        return ids;
+    }
+    let bytes = format!("<{}>", word).into_bytes();
+    for i in 0..bytes.len() {
+        if bytes[i] & 0xC0 == 0x80 {
+            continue;
+        }
+        let mut j = i;
+        for n in 1..=model.maxn {
+            if j >= bytes.len() {
+                break;
+            }
+            j += 1;
+            while j < bytes.len() && bytes[j] & 0xC0 == 0x80 {
+                j += 1;
+            }
+            if n >= model.minn && !(n == 1 && (i == 0 || j == bytes.len())) {
+                let bucket = (fasttext_hash(&bytes[i..j]) % model.bucket as u32) as i32;
+                match &model.prune {
+                    None => ids.push(model.nwords + bucket as usize),
+                    Some(prune) => {
+                        // Out-of-range remappings are dropped with the other rows past the matrix
+                        if let Some(row) = prune.get(&bucket).and_then(|&to| usize::try_from(to).ok()) {
+                            ids.push(model.nwords.saturating_add(row));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    ids
+}
+
+/// The parts of a fastText model needed to rebuild word vectors
+struct FastTextModel {
+    minn: usize,
+    maxn: usize,
+    bucket: i32,
+    nwords: usize,
+    /// Bucket remapping of a pruned model
+    prune: Option<HashMap<i32, i32>>,
+}
+
+fn read_fasttext(reader: &mut dyn BufRead, sink: &mut RowSink) -> io::Result<()> {
+    let _magic = read_i32(reader)?;
+    let version = read_i32(reader)?;
+    if version > 12 {
+        return Err(invalid(format!("unsupported fastText model version {}", version)));
+    }
+
+    // Training arguments: dim, ws, epoch, minCount, neg, wordNgrams, loss,
+    // model, bucket, minn, maxn, lrUpdateRate, then the sampling threshold
+    let mut args = [0i32; 12];
+    for arg in &mut args {
+        *arg = read_i32(reader)?;
+    }
+    let _t: [u8; 8] = read_array(reader)?;
+
+    let size = usize::try_from(read_i32(reader)?).map_err(|_| invalid("negative fastText vocabulary size"))?;
+    let nwords = usize::try_from(read_i32(reader)?).map_err(|_| invalid("negative fastText word count"))?;
+    let _nlabels = read_i32(reader)?;
+    let _ntokens = read_i64(reader)?;
+    let prune_size = read_i64(reader)?;
+
+    let mut words = Vec::with_capacity(prealloc::<String>(nwords));
+    for _ in 0..size {
+        let word = String::from_utf8_lossy(&read_until(reader, 0)?).into_owned();
+        let _count = read_i64(reader)?;
+        let [entry_type] = read_array(reader)?;
+        if entry_type == 0 {
+            words.push(word);
+        }
+    }
+    let prune = match prune_size {
+        n if n < 0 => None,
+        n => {
+            let n = usize::try_from(n).map_err(|_| invalid("fastText prune size overflows"))?;
+            let mut pairs = HashMap::with_capacity(prealloc::<(i32, i32)>(n));
+            for _ in 0..n {
+                pairs.insert(read_i32(reader)?, read_i32(reader)?);
+            }
+            Some(pairs)
+        }
+    };
+
+    let [quantized] = read_array(reader)?;
+    if quantized != 0 {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "quantized fastText models (.ftz) are not supported"));
+    }
+    let rows = usize::try_from(read_i64(reader)?).map_err(|_| invalid("negative fastText matrix rows"))?;
+    let dim = usize::try_from(read_i64(reader)?).map_err(|_| invalid("negative fastText dimension"))?;
+    let len = rows.checked_mul(dim).ok_or_else(|| invalid("fastText matrix size overflows"))?;
+    let mut matrix = Vec::with_capacity(prealloc::<f32>(len));
+    for _ in 0..rows {
+        matrix.extend(read_f32s(reader, dim)?);
+        sink.read(dim * 4);
+    }
+
+    let model = FastTextModel {
+        minn: args[9].max(0) as usize,
+        maxn: args[10].max(0) as usize,
+        bucket: args[8],
+        nwords,
+        prune,
+    };
+    for (id, word) in words.iter().enumerate() {
+        if sink.full() {
+            break;
+        }
+        if !sink.wants(word) {
+            continue;
+        }
+        let ids: Vec<usize> = fasttext_subwords(word, id, &model).into_iter().filter(|&r| r < rows).collect();
+        let mut vec = vec![0.0; dim];
+        for &row in &ids {
+            for (v, x) in vec.iter_mut().zip(&matrix[row * dim..(row + 1) * dim]) {
+                *v += x;
+            }
+        }
+        for v in &mut vec {
+            *v /= ids.len() as f32;
+        }
+        sink.add(word, vec);
+    }
+    Ok(())
+}
+
+/// Words for a `.npy` matrix: the first token of each line of `<name>.vocab`,
+/// `<name>.words`, `<name>.txt` or `vocab.txt` next to it
+pub(crate) fn npy_vocabulary(source: &Source) -> io::Result<Vec<String>> {
+    let Source::Path(path) = source else {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, ".npy files cannot be read from archives"));
+    };
+    let path = Path::new(path);
+    let candidates = [
+        path.with_extension("vocab"),
+        path.with_extension("words"),
+        path.with_extension("txt"),
+        path.with_file_name("vocab.txt"),
+    ];
+    let Some(vocab) = candidates.iter().find(|p| p.is_file()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no vocabulary for {}; expected {}", path.display(), candidates[0].display()),
+        ));
+    };
+    let text = fs::read_to_string(vocab)?;
+    Ok(text.lines().filter_map(|l| l.split_whitespace().next()).map(str::to_string).collect())
+}
+
+/// The fields of a `.npy` header that matter here
+struct NpyHeader {
+    /// The dtype, e.g. `<f4` or `<U12`
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+fn parse_npy_header(reader: &mut dyn BufRead) -> io::Result<NpyHeader> {
+    let [_, _, _, _, _, _, major, _minor] = read_array(reader)?;
+    let header_len = match major {
+        1 => u16::from_le_bytes(read_array(reader)?) as usize,
+        _ => u32::from_le_bytes(read_array(reader)?) as usize,
+    };
+    if header_len > MAX_NPY_HEADER {
+        return Err(invalid(format!(".npy header of {} bytes is too long", header_len)));
+    }
+    let header = read_bytes(reader, header_len)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let descr = header
+        .split_once("'descr':")
+        .and_then(|(_, rest)| rest.split('\'').nth(1))
+        .unwrap_or_default()
+        .to_string();
+    let shape = header
+        .split_once("'shape':")
+        .and_then(|(_, rest)| rest.split_once('(')?.1.split_once(')'))
+        .map(|(dims, _)| dims.split(',').filter_map(|d| d.trim().parse().ok()).collect())
+        .unwrap_or_default();
+    Ok(NpyHeader { descr, fortran_order: header.contains("'fortran_order': True"), shape })
+}
+
+/// Reads a `.npy` header, returning the element width (4 or 8 bytes) and the shape
+pub(crate) fn read_npy_header(reader: &mut dyn BufRead) -> io::Result<(usize, Vec<usize>)> {
+    let header = parse_npy_header(reader)?;
+    let width = match header.descr.as_str() {
+        "<f4" => 4,
+        "<f8" => 8,
+        _ => return Err(invalid("only little-endian float32 or float64 .npy arrays are supported")),
+    };
+    if header.fortran_order {
+        return Err(invalid("Fortran-ordered .npy arrays are not supported"));
+    }
+    Ok((width, header.shape))
+}
+
+/// Decodes little-endian float32 or float64 values
+pub(crate) fn decode_floats(bytes: &[u8], width: usize) -> WordVec {
+    if width == 4 {
+        bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect()
+    } else {
+        bytes.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32).collect()
+    }
+}
+
+fn read_npy(reader: &mut dyn BufRead, vocab: &[String], sink: &mut RowSink) -> io::Result<()> {
+    let (width, shape) = read_npy_header(reader)?;
+    let [rows, dim] = shape[..] else {
+        return Err(invalid("expected a 2-d .npy array"));
+    };
+    if vocab.len() < rows {
+        return Err(invalid(format!("{} rows but only {} vocabulary words", rows, vocab.len())));
+    }
+
+    let row_len = dim.checked_mul(width).ok_or_else(|| invalid(".npy row size overflows"))?;
+    for word in &vocab[..rows] {
+        if sink.full() {
+            break;
+        }
+        let bytes = read_bytes(reader, row_len)?;
+        sink.read(bytes.len());
+        if !sink.wants(word) {
+            continue;
+        }
+        sink.add(word, decode_floats(&bytes, width));
+    }
+    Ok(())
+}
+
+/// Reads a `.npz` archive (as written by `export npz`, or by
+/// `np.savez(path, vectors=..., words=...)`): the vectors are its 2-d float
+/// array, and the words its 1-d string array, else a vocabulary file beside it
+fn read_npz(source: &Source, sink: &mut RowSink) -> io::Result<()> {
+    let Source::Path(path) = source else {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, ".npz files cannot be read from archives"));
+    };
+    let mut matrix = None;
+    let mut words = None;
+    for member in archive_members(path)? {
+        if !member.ends_with(".npy") {
+            continue;
+        }
+        let member = Source::ArchiveMember { archive: path.clone(), member };
+        let mut reader = member.open()?;
+        let header = parse_npy_header(reader.as_mut())?;
+        match (header.descr.as_bytes().get(1), header.shape.len()) {
+            (Some(b'f'), 2) if matrix.is_none() => matrix = Some(member),
+            (Some(b'U' | b'S'), 1) if words.is_none() => words = Some(read_npy_strings(reader.as_mut(), &header)?),
+            (Some(b'O'), 1) if words.is_none() => {
+                return Err(invalid(format!(
+                    "{} holds Python objects, which cannot be read; save the words with .astype(str)",
+                    member
+                )))
+            }
+            _ => {}
+        }
+    }
+    let Some(matrix) = matrix else {
+        return Err(invalid(format!(
+            "no 2-d float array in {}; to read vectors from a zip archive of text files use --archive",
+            path
+        )));
+    };
+    let words = match words {
+        Some(words) => words,
+        None => npy_vocabulary(source)?,
+    };
+    read_npy(matrix.open()?.as_mut(), &words, sink)
+}
+
+/// Reads a 1-d array of fixed-width strings: `<U` (UTF-32) or `|S` (bytes),
+/// padded with NULs
+fn read_npy_strings(reader: &mut dyn BufRead, header: &NpyHeader) -> io::Result<Vec<String>> {
+    let chars: usize = header.descr[2..].parse().map_err(|_| invalid(format!("bad dtype {}", header.descr)))?;
+    let unicode = header.descr.as_bytes()[1] == b'U';
+    let width = if unicode { chars.checked_mul(4) } else { Some(chars) };
+    let width = width.ok_or_else(|| invalid(format!("bad dtype {}", header.descr)))?;
+    let mut words = Vec::with_capacity(prealloc::<String>(header.shape[0]));
+    for _ in 0..header.shape[0] {
+        let bytes = read_bytes(reader, width)?;
+        let word = if unicode {
+            bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                .take_while(|&c| c != 0)
+                .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        } else {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Streams the binary cache: the matrix comes before the words, so it is held
+/// until the word table has been read
+fn read_binary_cache(reader: &mut dyn BufRead, sink: &mut RowSink) -> io::Result<()> {
+    let header = parse_stream_header(&read_array::<HEADER_LEN>(reader)?)?;
+    // The header's shape is only trusted as far as the data really goes
+    let mut matrix = Vec::with_capacity((header.words_offset() - HEADER_LEN).min(MAX_PREALLOC_BYTES) / 4);
+    for _ in 0..header.count {
+        matrix.extend(read_f32s(reader, header.dim)?);
+        sink.read(header.dim * 4);
+    }
+
+    for row in 0..header.count {
+        if sink.full() {
+            break;
+        }
+        let len = u32::from_le_bytes(read_array(reader)?) as usize;
+        let mut word = Vec::new();
+        if (&mut *reader).take(len as u64).read_to_end(&mut word)? < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        sink.read(4 + len);
+        let word = String::from_utf8_lossy(&word);
+        if sink.wants(&word) {
+            sink.add(&word, matrix[row * header.dim..(row + 1) * header.dim].to_vec());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::write_npz;
+    use crate::testing::{assert_truncations_fail, load_rows, rows, sample_store, TempDir};
+
+    /// Where the dictionary's size field starts in `fasttext_model`
+    const FASTTEXT_SIZE_AT: usize = 64;
+
+    /// A fastText `.bin` model of `store` without subword buckets, so each
+    /// word's vector is its own input row
+    fn fasttext_model(store: &dyn EmbeddingStore) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(FASTTEXT_MAGIC.to_le_bytes());
+        bytes.extend(12i32.to_le_bytes());
+        let args = [store.dim() as i32, 5, 5, 1, 5, 1, 1, 1, 0, 0, 0, 100];
+        args.iter().for_each(|arg| bytes.extend(arg.to_le_bytes()));
+        bytes.extend(1e-4f64.to_le_bytes());
+        assert_eq!(bytes.len(), FASTTEXT_SIZE_AT);
+        bytes.extend((store.len() as i32).to_le_bytes());
+        bytes.extend((store.len() as i32).to_le_bytes());
+        bytes.extend(0i32.to_le_bytes());
+        bytes.extend(100i64.to_le_bytes());
+        bytes.extend((-1i64).to_le_bytes());
+        for (word, _) in store.iter() {
+            bytes.extend(word.as_bytes());
+            bytes.push(0);
+            bytes.extend(1i64.to_le_bytes());
+            bytes.push(0);
+        }
+        bytes.push(0);
+        bytes.extend((store.len() as i64).to_le_bytes());
+        bytes.extend((store.dim() as i64).to_le_bytes());
+        for (_, vec) in store.iter() {
+            vec.iter().for_each(|x| bytes.extend(x.to_le_bytes()));
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_fasttext_models() {
+        let dir = TempDir::new();
+        let path = dir.file("model.bin");
+        let store = sample_store();
+        fs::write(&path, fasttext_model(&store)).unwrap();
+        assert_eq!(load_rows(&path).unwrap(), rows(&store));
+    }
+
+    #[test]
+    fn truncated_fasttext_models_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("model.bin");
+        assert_truncations_fail(&path, &fasttext_model(&sample_store()), 4);
+    }
+
+    #[test]
+    fn corrupt_fasttext_counts_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("model.bin");
+        let model = fasttext_model(&sample_store());
+        let matrix_at = model.len() - 20 * 4 - 16;
+        for (at, value) in [
+            (FASTTEXT_SIZE_AT, (-1i32).to_le_bytes().to_vec()),
+            (FASTTEXT_SIZE_AT + 4, (-1i32).to_le_bytes().to_vec()),
+            (matrix_at, (-1i64).to_le_bytes().to_vec()),
+            (matrix_at, (1i64 << 40).to_le_bytes().to_vec()),
+            (matrix_at + 8, i64::MAX.to_le_bytes().to_vec()),
+        ] {
+            let mut corrupt = model.clone();
+            corrupt[at..at + value.len()].copy_from_slice(&value);
+            fs::write(&path, &corrupt).unwrap();
+            assert!(load_rows(&path).is_err(), "corrupting byte {} went unnoticed", at);
+        }
+    }
+
+    #[test]
+    fn round_trips_npz_archives() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.npz");
+        let store = sample_store();
+        write_npz(&store, &path).unwrap();
+        assert_eq!(load_rows(&path).unwrap(), rows(&store));
+        let bytes = fs::read(&path).unwrap();
+        assert_truncations_fail(&path, &bytes, ZIP_MAGIC.len());
+    }
+
+    #[test]
+    fn npy_headers_are_bounded() {
+        let header = |major: u8, len: u32| {
+            let mut bytes = NPY_MAGIC.to_vec();
+            bytes.extend([major, 0]);
+            bytes.extend(len.to_le_bytes());
+            bytes
+        };
+        let too_long = header(2, u32::MAX);
+        assert!(read_npy_header(&mut &too_long[..]).is_err());
+        let cut_short = header(2, 100);
+        assert!(read_npy_header(&mut &cut_short[..]).is_err());
+
+        let mut valid = header(1, 0);
+        valid.truncate(8);
+        let text = b"{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2), }";
+        valid.extend((text.len() as u16).to_le_bytes());
+        valid.extend(text);
+        assert_eq!(read_npy_header(&mut &valid[..]).unwrap(), (4, vec![3, 2]));
+    }
+}
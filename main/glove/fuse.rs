@@ -0,0 +1,53 @@
+//! Weighted fusion of several word-arithmetic expressions: candidates are
+//! ranked by the weighted sum of their similarities to each expression,
+//! rather than to a single merged vector.
+
+use crate::expression::Expression;
+use crate::search::top_k;
+use crate::store::EmbeddingStore;
+
+/// An expression with its weight in the fused score, written `0.7:paris - france`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedExpression {
+    pub weight: f32,
+    pub expression: Expression,
+}
+
+impl WeightedExpression {
+    /// Parses `weight:expression`; without a weight prefix the weight is 1
+    pub fn parse(text: &str) -> Result<WeightedExpression, String> {
+        let (weight, expression) = match text.split_once(':') {
+            Some((weight, rest)) => {
+                let weight = weight.trim().parse().map_err(|_| format!("invalid weight '{}'", weight))?;
+                (weight, rest)
+            }
+            None => (1.0, text),
+        };
+        Ok(WeightedExpression { weight, expression: Expression::parse(expression)? })
+    }
+}
+
+/// Ranks words by `sum(weight * cosine(word, expression))` and returns the
+/// `k` best, excluding every word that appears in an expression unless
+/// `allow_inputs` is set
+pub fn fuse(
+    store: &dyn EmbeddingStore,
+    expressions: &[WeightedExpression],
+    k: usize,
+    allow_inputs: bool,
+) -> Result<Vec<(String, f32)>, String> {
+    let mut fused = vec![0.0f32; store.len()];
+    for weighted in expressions {
+        let query = weighted.expression.vector(store)?;
+        for (total, score) in fused.iter_mut().zip(store.cosine_scores(&query)) {
+            *total += weighted.weight * score;
+        }
+    }
+
+    let exclude: Vec<String> = if allow_inputs {
+        Vec::new()
+    } else {
+        expressions.iter().flat_map(|w| w.expression.words()).map(str::to_string).collect()
+    };
+    Ok(top_k(fused, store, &exclude, k, true))
+}
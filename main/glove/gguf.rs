@@ -0,0 +1,337 @@
+//! GGUF files, the single-file model format of llama.cpp and the local-LLM
+//! tools built on it: a header of typed metadata key-value pairs and tensor
+//! descriptions, then the tensor data, each tensor aligned.
+//!
+//! The vectors are the rows of one 2-d tensor, `token_embd.weight` (the
+//! token embedding matrix) unless `--tensor <name>` names another, and the
+//! words are the tokenizer's tokens from the `tokenizer.ggml.tokens`
+//! metadata array, row `i` being token `i`. F32, F16, BF16, F64 and Q8_0
+//! tensors are read; other quantizations are refused.
+//!
+//! Tokens are renamed so that words can be looked up as they are typed: a
+//! token starting a word (`▁king` in SentencePiece vocabularies, `Ġking` in
+//! GPT-2 style ones) becomes `king`, and in such vocabularies a token
+//! continuing a word becomes `##ing`, as WordPiece writes it. Control, byte
+//! and other special tokens keep their names.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read};
+
+use crate::formats::{decode_floats, read_bytes};
+use crate::half::f16_to_f32;
+use crate::load::RowSink;
+use crate::WordVec;
+
+/// First four bytes of a GGUF file
+pub(crate) const GGUF_MAGIC: &[u8] = b"GGUF";
+
+/// Tensor read when `--tensor` is not given
+pub const DEFAULT_TENSOR: &str = "token_embd.weight";
+
+/// Data alignment when the file does not give `general.alignment`
+const DEFAULT_ALIGNMENT: u64 = 32;
+
+/// Word-start markers of SentencePiece and GPT-2 byte-level vocabularies
+const WORD_MARKERS: [&str; 2] = ["▁", "Ġ"];
+
+/// `tokenizer.ggml.token_type` of an ordinary token
+const NORMAL_TOKEN: i64 = 1;
+
+/// Values per Q8_0 block, each block being an f16 scale and that many i8s
+const Q8_0_BLOCK: usize = 32;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// A metadata value; only those the reader needs are kept
+enum Value {
+    Int(i64),
+    Strings(Vec<String>),
+    Ints(Vec<i64>),
+    Other,
+}
+
+struct TensorInfo {
+    name: String,
+    dims: Vec<u64>,
+    ggml_type: u32,
+    offset: u64,
+}
+
+/// Reads little-endian values, counting the bytes consumed so the tensor
+/// data can be found by its offset
+struct Reader<'a> {
+    inner: &'a mut dyn BufRead,
+    pos: u64,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let bytes = read_bytes(self.inner, len)?;
+        self.pos += len as u64;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.inner.read_exact(&mut bytes)?;
+        self.pos += N as u64;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u64()?;
+        if len > 1 << 30 {
+            return Err(invalid(format!("GGUF string of {} bytes", len)));
+        }
+        Ok(String::from_utf8_lossy(&self.bytes(len as usize)?).into_owned())
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut *self.inner).take(len), &mut io::sink())?;
+        self.pos += skipped;
+        if skipped < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    /// Reads a scalar of metadata type `kind`, as an integer if it is one
+    fn scalar(&mut self, kind: u32) -> io::Result<Option<i64>> {
+        Ok(match kind {
+            0 => Some(self.array::<1>()?[0] as i64),
+            1 => Some(self.array::<1>()?[0] as i8 as i64),
+            2 => Some(u16::from_le_bytes(self.array()?) as i64),
+            3 => Some(i16::from_le_bytes(self.array()?) as i64),
+            4 => Some(self.u32()? as i64),
+            5 => Some(i32::from_le_bytes(self.array()?) as i64),
+            6 => self.skip(4).map(|_| None)?,
+            7 => Some(self.array::<1>()?[0] as i64),
+            8 => self.string().map(|_| None)?,
+            10 => Some(self.u64()? as i64),
+            11 => Some(i64::from_le_bytes(self.array()?)),
+            12 => self.skip(8).map(|_| None)?,
+            _ => return Err(invalid(format!("unknown GGUF metadata type {}", kind))),
+        })
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        let kind = self.u32()?;
+        if kind != 9 {
+            return Ok(self.scalar(kind)?.map_or(Value::Other, Value::Int));
+        }
+        let (kind, len) = (self.u32()?, self.u64()?);
+        match kind {
+            8 => (0..len).map(|_| self.string()).collect::<io::Result<_>>().map(Value::Strings),
+            9 => Err(invalid("nested GGUF arrays are not supported")),
+            _ => {
+                let mut ints = Vec::new();
+                for _ in 0..len {
+                    if let Some(int) = self.scalar(kind)? {
+                        ints.push(int);
+                    }
+                }
+                Ok(Value::Ints(ints))
+            }
+        }
+    }
+}
+
+/// Reads the rows of the tensor asked for (`token_embd.weight` by default),
+/// one per token of the tokenizer vocabulary
+pub(crate) fn read_gguf(reader: &mut dyn BufRead, sink: &mut RowSink) -> io::Result<()> {
+    let mut reader = Reader { inner: reader, pos: 0 };
+    if reader.bytes(4)? != GGUF_MAGIC {
+        return Err(invalid("not a GGUF file"));
+    }
+    let version = reader.u32()?;
+    if version < 2 {
+        return Err(invalid(format!("GGUF version {} is not supported; convert the file again", version)));
+    }
+    let (tensor_count, kv_count) = (reader.u64()?, reader.u64()?);
+
+    let mut metadata = HashMap::new();
+    for _ in 0..kv_count {
+        let key = reader.string()?;
+        let value = reader.value()?;
+        metadata.insert(key, value);
+    }
+    let Some(Value::Strings(tokens)) = metadata.remove("tokenizer.ggml.tokens") else {
+        return Err(invalid("the GGUF file has no tokenizer.ggml.tokens vocabulary"));
+    };
+    let token_types = match metadata.remove("tokenizer.ggml.token_type") {
+        Some(Value::Ints(types)) => types,
+        _ => Vec::new(),
+    };
+    let alignment = match metadata.get("general.alignment") {
+        Some(&Value::Int(n)) if n > 0 => n as u64,
+        _ => DEFAULT_ALIGNMENT,
+    };
+
+    let mut infos = Vec::new();
+    for _ in 0..tensor_count {
+        let name = reader.string()?;
+        let n_dims = reader.u32()?;
+        let dims = (0..n_dims).map(|_| reader.u64()).collect::<io::Result<_>>()?;
+        let (ggml_type, offset) = (reader.u32()?, reader.u64()?);
+        infos.push(TensorInfo { name, dims, ggml_type, offset });
+    }
+    let wanted = sink.tensor().unwrap_or(DEFAULT_TENSOR).to_string();
+    let Some(info) = infos.iter().find(|t| t.name == wanted) else {
+        let names: Vec<&str> = infos.iter().map(|t| t.name.as_str()).collect();
+        return Err(invalid(format!("no tensor {} in the GGUF file; it has {}", wanted, names.join(", "))));
+    };
+    // The first dimension is the one stored contiguously, so it is the vector length
+    let &[dim, rows] = info.dims.as_slice() else {
+        return Err(invalid(format!("tensor {} has {} dimensions, not 2", info.name, info.dims.len())));
+    };
+    let too_big = || invalid(format!("tensor {} is too large", wanted));
+    let (dim, rows) = (usize::try_from(dim).map_err(|_| too_big())?, usize::try_from(rows).map_err(|_| too_big())?);
+    let row_len = match info.ggml_type {
+        0 => dim.checked_mul(4).ok_or_else(too_big)?,
+        1 | 30 => dim.checked_mul(2).ok_or_else(too_big)?,
+        28 => dim.checked_mul(8).ok_or_else(too_big)?,
+        8 if dim.is_multiple_of(Q8_0_BLOCK) => dim / Q8_0_BLOCK * (2 + Q8_0_BLOCK),
+        8 => return Err(invalid(format!("Q8_0 rows of {} values are not whole blocks", dim))),
+        other => {
+            let msg = format!("tensor {} has GGML type {}; only F32, F16, BF16, F64 and Q8_0 are read", wanted, other);
+            return Err(io::Error::new(io::ErrorKind::Unsupported, msg));
+        }
+    };
+    if tokens.len() < rows {
+        return Err(invalid(format!("tensor {} has {} rows but only {} tokens", wanted, rows, tokens.len())));
+    }
+
+    let data_start = reader.pos.checked_next_multiple_of(alignment).ok_or_else(|| invalid("bad GGUF alignment"))?;
+    let start = data_start.checked_add(info.offset).ok_or_else(|| invalid(format!("bad offset for {}", wanted)))?;
+    reader.skip(start - reader.pos)?;
+    let marked = tokens.iter().any(|t| WORD_MARKERS.iter().any(|m| t.starts_with(m)));
+    for (id, token) in tokens[..rows].iter().enumerate() {
+        if sink.full() {
+            break;
+        }
+        let bytes = reader.bytes(row_len)?;
+        sink.read(row_len);
+        let special = token_types.get(id).is_some_and(|&t| t != NORMAL_TOKEN);
+        let word = if special { token.clone() } else { token_word(token, marked) };
+        if sink.wants(&word) {
+            sink.add(&word, decode(&bytes, info.ggml_type));
+        }
+    }
+    Ok(())
+}
+
+/// The word a token is looked up by: without its word-start marker, or
+/// with `##` before it if `marked` vocabularies use markers and it has none
+fn token_word(token: &str, marked: bool) -> String {
+    match WORD_MARKERS.iter().find_map(|m| token.strip_prefix(m)) {
+        Some("") => token.to_string(),
+        Some(word) => word.to_string(),
+        None if marked => format!("##{}", token),
+        None => token.to_string(),
+    }
+}
+
+fn decode(bytes: &[u8], ggml_type: u32) -> WordVec {
+    let halves = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    match ggml_type {
+        1 => halves.map(f16_to_f32).collect(),
+        30 => halves.map(|h| f32::from_bits((h as u32) << 16)).collect(),
+        28 => decode_floats(bytes, 8),
+        8 => bytes
+            .chunks_exact(2 + Q8_0_BLOCK)
+            .flat_map(|block| {
+                let scale = f16_to_f32(u16::from_le_bytes([block[0], block[1]]));
+                block[2..].iter().map(move |&q| q as i8 as f32 * scale)
+            })
+            .collect(),
+        _ => decode_floats(bytes, 4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::EmbeddingStore;
+    use crate::testing::{assert_truncations_fail, load_rows, rows, sample_store, TempDir};
+
+    fn string(bytes: &mut Vec<u8>, text: &str) {
+        bytes.extend((text.len() as u64).to_le_bytes());
+        bytes.extend(text.as_bytes());
+    }
+
+    /// A GGUF file holding `store` as an F32 `token_embd.weight` tensor, with
+    /// `dims` in place of its shape if given; also returns where the dims are
+    fn gguf_file(store: &dyn EmbeddingStore, dims: Option<[u64; 2]>) -> (Vec<u8>, usize) {
+        let mut bytes = GGUF_MAGIC.to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(1u64.to_le_bytes());
+        bytes.extend(1u64.to_le_bytes());
+        string(&mut bytes, "tokenizer.ggml.tokens");
+        bytes.extend(9u32.to_le_bytes());
+        bytes.extend(8u32.to_le_bytes());
+        bytes.extend((store.len() as u64).to_le_bytes());
+        for (word, _) in store.iter() {
+            string(&mut bytes, &word);
+        }
+        string(&mut bytes, DEFAULT_TENSOR);
+        bytes.extend(2u32.to_le_bytes());
+        let dims_at = bytes.len();
+        for dim in dims.unwrap_or([store.dim() as u64, store.len() as u64]) {
+            bytes.extend(dim.to_le_bytes());
+        }
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        bytes.resize(bytes.len().next_multiple_of(DEFAULT_ALIGNMENT as usize), 0);
+        for (_, vec) in store.iter() {
+            vec.iter().for_each(|x| bytes.extend(x.to_le_bytes()));
+        }
+        (bytes, dims_at)
+    }
+
+    #[test]
+    fn reads_the_token_embeddings() {
+        let dir = TempDir::new();
+        let path = dir.file("model.gguf");
+        let store = sample_store();
+        std::fs::write(&path, gguf_file(&store, None).0).unwrap();
+        assert_eq!(load_rows(&path).unwrap(), rows(&store));
+    }
+
+    #[test]
+    fn truncated_files_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("model.gguf");
+        assert_truncations_fail(&path, &gguf_file(&sample_store(), None).0, GGUF_MAGIC.len());
+    }
+
+    #[test]
+    fn corrupt_headers_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("model.gguf");
+        let store = sample_store();
+        for dims in [[u64::MAX, 5], [1 << 62, 5], [4, u64::MAX], [4, 6]] {
+            std::fs::write(&path, gguf_file(&store, Some(dims)).0).unwrap();
+            assert!(load_rows(&path).is_err(), "dims {:?} went unnoticed", dims);
+        }
+        let (mut bytes, dims_at) = gguf_file(&store, None);
+        // The tensor's offset, after the dims and its type
+        bytes[dims_at + 20..dims_at + 28].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(load_rows(&path).is_err());
+        // The length of the metadata key
+        let mut bytes = gguf_file(&store, None).0;
+        bytes[24..32].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(load_rows(&path).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+//! GPU brute-force cosine search, linked against the system `libwgpu_native`
+//! (the C API of wgpu, v0.19).
+//!
+//! The matrix is normalized and uploaded once, in chunks that fit WebGPU's
+//! default 128 MiB storage-binding limit; each batch of normalized queries is
+//! then scored against every chunk by a compute shader computing dot products.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::vector::norm;
+use crate::WordVec;
+
+type Handle = *mut c_void;
+type WgpuBool = u32;
+
+#[repr(C)]
+struct ChainedStruct {
+    next: *const ChainedStruct,
+    s_type: u32,
+}
+
+#[repr(C)]
+struct RequestAdapterOptions {
+    next_in_chain: *const ChainedStruct,
+    compatible_surface: Handle,
+    power_preference: u32,
+    backend_type: u32,
+    force_fallback_adapter: WgpuBool,
+}
+
+#[repr(C)]
+struct BufferDescriptor {
+    next_in_chain: *const ChainedStruct,
+    label: *const c_char,
+    usage: u32,
+    size: u64,
+    mapped_at_creation: WgpuBool,
+}
+
+#[repr(C)]
+struct ShaderModuleWgslDescriptor {
+    chain: ChainedStruct,
+    code: *const c_char,
+}
+
+#[repr(C)]
+struct ShaderModuleDescriptor {
+    next_in_chain: *const ChainedStruct,
+    label: *const c_char,
+    hint_count: usize,
+    hints: *const c_void,
+}
+
+#[repr(C)]
+struct ProgrammableStageDescriptor {
+    next_in_chain: *const ChainedStruct,
+    module: Handle,
+    entry_point: *const c_char,
+    constant_count: usize,
+    constants: *const c_void,
+}
+
+#[repr(C)]
+struct ComputePipelineDescriptor {
+    next_in_chain: *const ChainedStruct,
+    label: *const c_char,
+    layout: Handle,
+    compute: ProgrammableStageDescriptor,
+}
+
+#[repr(C)]
+struct BindGroupEntry {
+    next_in_chain: *const ChainedStruct,
+    binding: u32,
+    buffer: Handle,
+    offset: u64,
+    size: u64,
+    sampler: Handle,
+    texture_view: Handle,
+}
+
+#[repr(C)]
+struct BindGroupDescriptor {
+    next_in_chain: *const ChainedStruct,
+    label: *const c_char,
+    layout: Handle,
+    entry_count: usize,
+    entries: *const BindGroupEntry,
+}
+
+const STYPE_SHADER_MODULE_WGSL: u32 = 0x6;
+const POWER_HIGH_PERFORMANCE: u32 = 2;
+const STATUS_SUCCESS: u32 = 0;
+const USAGE_MAP_READ: u32 = 0x1;
+const USAGE_COPY_SRC: u32 = 0x4;
+const USAGE_COPY_DST: u32 = 0x8;
+const USAGE_UNIFORM: u32 = 0x40;
+const USAGE_STORAGE: u32 = 0x80;
+const MAP_MODE_READ: u32 = 0x1;
+
+/// WebGPU's default `maxStorageBufferBindingSize`, which every adapter supports
+const MAX_BINDING_BYTES: usize = 128 << 20;
+const WORKGROUP_SIZE: usize = 64;
+/// WebGPU's default `maxComputeWorkgroupsPerDimension`
+const MAX_WORKGROUPS: usize = 65_535;
+
+#[link(name = "wgpu_native")]
+extern "C" {
+    fn wgpuCreateInstance(descriptor: *const c_void) -> Handle;
+    fn wgpuInstanceRequestAdapter(
+        instance: Handle,
+        options: *const RequestAdapterOptions,
+        callback: extern "C" fn(u32, Handle, *const c_char, *mut c_void),
+        userdata: *mut c_void,
+    );
+    fn wgpuAdapterRequestDevice(
+        adapter: Handle,
+        descriptor: *const c_void,
+        callback: extern "C" fn(u32, Handle, *const c_char, *mut c_void),
+        userdata: *mut c_void,
+    );
+    fn wgpuDeviceGetQueue(device: Handle) -> Handle;
+    fn wgpuDeviceCreateBuffer(device: Handle, descriptor: *const BufferDescriptor) -> Handle;
+    fn wgpuDeviceCreateShaderModule(device: Handle, descriptor: *const ShaderModuleDescriptor) -> Handle;
+    fn wgpuDeviceCreateComputePipeline(device: Handle, descriptor: *const ComputePipelineDescriptor) -> Handle;
+    fn wgpuComputePipelineGetBindGroupLayout(pipeline: Handle, group_index: u32) -> Handle;
+    fn wgpuDeviceCreateBindGroup(device: Handle, descriptor: *const BindGroupDescriptor) -> Handle;
+    fn wgpuDeviceCreateCommandEncoder(device: Handle, descriptor: *const c_void) -> Handle;
+    fn wgpuCommandEncoderBeginComputePass(encoder: Handle, descriptor: *const c_void) -> Handle;
+    fn wgpuComputePassEncoderSetPipeline(pass: Handle, pipeline: Handle);
+    fn wgpuComputePassEncoderSetBindGroup(
+        pass: Handle,
+        group_index: u32,
+        group: Handle,
+        dynamic_offset_count: usize,
+        dynamic_offsets: *const u32,
+    );
+    fn wgpuComputePassEncoderDispatchWorkgroups(pass: Handle, x: u32, y: u32, z: u32);
+    fn wgpuComputePassEncoderEnd(pass: Handle);
+    fn wgpuCommandEncoderCopyBufferToBuffer(
+        encoder: Handle,
+        source: Handle,
+        source_offset: u64,
+        destination: Handle,
+        destination_offset: u64,
+        size: u64,
+    );
+    fn wgpuCommandEncoderFinish(encoder: Handle, descriptor: *const c_void) -> Handle;
+    fn wgpuQueueWriteBuffer(queue: Handle, buffer: Handle, offset: u64, data: *const c_void, size: usize);
+    fn wgpuQueueSubmit(queue: Handle, count: usize, commands: *const Handle);
+    fn wgpuBufferMapAsync(
+        buffer: Handle,
+        mode: u32,
+        offset: usize,
+        size: usize,
+        callback: extern "C" fn(u32, *mut c_void),
+        userdata: *mut c_void,
+    );
+    fn wgpuBufferGetConstMappedRange(buffer: Handle, offset: usize, size: usize) -> *const c_void;
+    fn wgpuBufferUnmap(buffer: Handle);
+    fn wgpuDevicePoll(device: Handle, wait: WgpuBool, submission_index: *const c_void) -> WgpuBool;
+
+    fn wgpuInstanceRelease(instance: Handle);
+    fn wgpuAdapterRelease(adapter: Handle);
+    fn wgpuDeviceRelease(device: Handle);
+    fn wgpuQueueRelease(queue: Handle);
+    fn wgpuBufferRelease(buffer: Handle);
+    fn wgpuShaderModuleRelease(module: Handle);
+    fn wgpuComputePipelineRelease(pipeline: Handle);
+    fn wgpuBindGroupLayoutRelease(layout: Handle);
+    fn wgpuBindGroupRelease(group: Handle);
+    fn wgpuCommandEncoderRelease(encoder: Handle);
+    fn wgpuComputePassEncoderRelease(pass: Handle);
+    fn wgpuCommandBufferRelease(commands: Handle);
+}
+
+/// Scores `queries` query rows against `rows` matrix rows of `dim` components
+const SHADER: &CStr = c"
+struct Params { rows: u32, dim: u32, queries: u32, pad: u32 }
+
+@group(0) @binding(0) var<storage, read> matrix: array<f32>;
+@group(0) @binding(1) var<storage, read> queries: array<f32>;
+@group(0) @binding(2) var<storage, read_write> scores: array<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = id.x;
+    let query = id.y;
+    if (row >= params.rows || query >= params.queries) {
+        return;
+    }
+    var dot = 0.0;
+    for (var i = 0u; i < params.dim; i++) {
+        dot += matrix[row * params.dim + i] * queries[query * params.dim + i];
+    }
+    scores[query * params.rows + row] = dot;
+}
+";
+
+/// Where a request callback leaves its result
+struct Reply {
+    status: u32,
+    handle: Handle,
+    message: String,
+}
+
+extern "C" fn on_request(status: u32, handle: Handle, message: *const c_char, userdata: *mut c_void) {
+    let reply = unsafe { &mut *(userdata as *mut Reply) };
+    reply.status = status;
+    reply.handle = handle;
+    if !message.is_null() {
+        reply.message = unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned();
+    }
+}
+
+extern "C" fn on_map(status: u32, userdata: *mut c_void) {
+    unsafe { *(userdata as *mut u32) = status };
+}
+
+/// One slice of the normalized matrix on the GPU
+struct Chunk {
+    first_row: usize,
+    rows: usize,
+    buffer: Handle,
+}
+
+/// View of a store whose cosine scans run on the GPU; everything else is
+/// answered by the wrapped store
+pub struct GpuStore {
+    inner: Box<dyn EmbeddingStore>,
+    instance: Handle,
+    adapter: Handle,
+    device: Handle,
+    queue: Handle,
+    module: Handle,
+    pipeline: Handle,
+    layout: Handle,
+    chunks: Vec<Chunk>,
+}
+
+impl GpuStore {
+    /// Uploads `inner`'s normalized vectors; without a usable adapter the
+    /// store is handed back with the reason, so the caller can stay on the CPU
+    pub fn new(inner: Box<dyn EmbeddingStore>) -> Result<GpuStore, (Box<dyn EmbeddingStore>, String)> {
+        unsafe {
+            let instance = wgpuCreateInstance(ptr::null());
+            if instance.is_null() {
+                return Err((inner, "unable to create a WebGPU instance".to_string()));
+            }
+            let options = RequestAdapterOptions {
+                next_in_chain: ptr::null(),
+                compatible_surface: ptr::null_mut(),
+                power_preference: POWER_HIGH_PERFORMANCE,
+                backend_type: 0,
+                force_fallback_adapter: 0,
+            };
+            let mut adapter = Reply { status: u32::MAX, handle: ptr::null_mut(), message: String::new() };
+            wgpuInstanceRequestAdapter(instance, &options, on_request, &mut adapter as *mut Reply as *mut c_void);
+            if adapter.status != STATUS_SUCCESS || adapter.handle.is_null() {
+                wgpuInstanceRelease(instance);
+                return Err((inner, format!("no GPU adapter available {}", adapter.message).trim_end().to_string()));
+            }
+            let mut device = Reply { status: u32::MAX, handle: ptr::null_mut(), message: String::new() };
+            wgpuAdapterRequestDevice(adapter.handle, ptr::null(), on_request, &mut device as *mut Reply as *mut c_void);
+            if device.status != STATUS_SUCCESS || device.handle.is_null() {
+                wgpuAdapterRelease(adapter.handle);
+                wgpuInstanceRelease(instance);
+                return Err((inner, format!("unable to open the GPU device {}", device.message).trim_end().to_string()));
+            }
+            let device = device.handle;
+
+            let wgsl = ShaderModuleWgslDescriptor {
+                chain: ChainedStruct { next: ptr::null(), s_type: STYPE_SHADER_MODULE_WGSL },
+                code: SHADER.as_ptr(),
+            };
+            let module = wgpuDeviceCreateShaderModule(
+                device,
+                &ShaderModuleDescriptor {
+                    next_in_chain: &wgsl.chain,
+                    label: ptr::null(),
+                    hint_count: 0,
+                    hints: ptr::null(),
+                },
+            );
+            let pipeline = wgpuDeviceCreateComputePipeline(
+                device,
+                &ComputePipelineDescriptor {
+                    next_in_chain: ptr::null(),
+                    label: ptr::null(),
+                    layout: ptr::null_mut(),
+                    compute: ProgrammableStageDescriptor {
+                        next_in_chain: ptr::null(),
+                        module,
+                        entry_point: c"main".as_ptr(),
+                        constant_count: 0,
+                        constants: ptr::null(),
+                    },
+                },
+            );
+            let mut gpu = GpuStore {
+                layout: wgpuComputePipelineGetBindGroupLayout(pipeline, 0),
+                queue: wgpuDeviceGetQueue(device),
+                inner,
+                instance,
+                adapter: adapter.handle,
+                device,
+                module,
+                pipeline,
+                chunks: Vec::new(),
+            };
+            gpu.upload();
+            Ok(gpu)
+        }
+    }
+
+    /// Rows per chunk: within the binding limit and one dispatch's width
+    fn chunk_rows(&self) -> usize {
+        (MAX_BINDING_BYTES / (4 * self.inner.dim().max(1))).clamp(1, MAX_WORKGROUPS * WORKGROUP_SIZE)
+    }
+
+    fn upload(&mut self) {
+        let (dim, chunk_rows) = (self.inner.dim(), self.chunk_rows());
+        let mut data: Vec<f32> = Vec::with_capacity(chunk_rows * dim);
+        let mut first_row = 0;
+        for (id, (_, vec)) in self.inner.iter().enumerate() {
+            let n = norm(&vec);
+            data.extend(vec.iter().map(|x| if n > 0.0 { x / n } else { 0.0 }));
+            if data.len() == chunk_rows * dim || id + 1 == self.inner.len() {
+                let buffer = self.buffer(USAGE_STORAGE | USAGE_COPY_DST, data.len() * 4);
+                unsafe { wgpuQueueWriteBuffer(self.queue, buffer, 0, data.as_ptr() as *const c_void, data.len() * 4) };
+                self.chunks.push(Chunk { first_row, rows: data.len() / dim.max(1), buffer });
+                first_row = id + 1;
+                data.clear();
+            }
+        }
+    }
+
+    /// A buffer of at least `size` bytes (rounded up to WebGPU's 4-byte alignment)
+    fn buffer(&self, usage: u32, size: usize) -> Handle {
+        let descriptor = BufferDescriptor {
+            next_in_chain: ptr::null(),
+            label: ptr::null(),
+            usage,
+            size: size.max(4).next_multiple_of(4) as u64,
+            mapped_at_creation: 0,
+        };
+        unsafe { wgpuDeviceCreateBuffer(self.device, &descriptor) }
+    }
+
+    /// Scores normalized `queries` (row-major) against one chunk, returning
+    /// `queries x chunk.rows` dot products
+    fn score_chunk(&self, chunk: &Chunk, queries: &[f32], count: usize) -> Vec<f32> {
+        let dim = self.inner.dim();
+        let bytes = count * chunk.rows * 4;
+        unsafe {
+            let query_buffer = self.buffer(USAGE_STORAGE | USAGE_COPY_DST, queries.len() * 4);
+            wgpuQueueWriteBuffer(self.queue, query_buffer, 0, queries.as_ptr() as *const c_void, queries.len() * 4);
+            let params = [chunk.rows as u32, dim as u32, count as u32, 0];
+            let params_buffer = self.buffer(USAGE_UNIFORM | USAGE_COPY_DST, 16);
+            wgpuQueueWriteBuffer(self.queue, params_buffer, 0, params.as_ptr() as *const c_void, 16);
+            let scores = self.buffer(USAGE_STORAGE | USAGE_COPY_SRC, bytes);
+            let readback = self.buffer(USAGE_MAP_READ | USAGE_COPY_DST, bytes);
+
+            let entry = |binding: u32, buffer: Handle, size: usize| BindGroupEntry {
+                next_in_chain: ptr::null(),
+                binding,
+                buffer,
+                offset: 0,
+                size: size.max(4).next_multiple_of(4) as u64,
+                sampler: ptr::null_mut(),
+                texture_view: ptr::null_mut(),
+            };
+            let entries = [
+                entry(0, chunk.buffer, chunk.rows * dim * 4),
+                entry(1, query_buffer, queries.len() * 4),
+                entry(2, scores, bytes),
+                entry(3, params_buffer, 16),
+            ];
+            let group = wgpuDeviceCreateBindGroup(
+                self.device,
+                &BindGroupDescriptor {
+                    next_in_chain: ptr::null(),
+                    label: ptr::null(),
+                    layout: self.layout,
+                    entry_count: entries.len(),
+                    entries: entries.as_ptr(),
+                },
+            );
+
+            let encoder = wgpuDeviceCreateCommandEncoder(self.device, ptr::null());
+            let pass = wgpuCommandEncoderBeginComputePass(encoder, ptr::null());
+            wgpuComputePassEncoderSetPipeline(pass, self.pipeline);
+            wgpuComputePassEncoderSetBindGroup(pass, 0, group, 0, ptr::null());
+            wgpuComputePassEncoderDispatchWorkgroups(pass, chunk.rows.div_ceil(WORKGROUP_SIZE) as u32, count as u32, 1);
+            wgpuComputePassEncoderEnd(pass);
+            wgpuCommandEncoderCopyBufferToBuffer(encoder, scores, 0, readback, 0, bytes.next_multiple_of(4) as u64);
+            let commands = wgpuCommandEncoderFinish(encoder, ptr::null());
+            wgpuQueueSubmit(self.queue, 1, &commands);
+
+            let mut status = u32::MAX;
+            wgpuBufferMapAsync(readback, MAP_MODE_READ, 0, bytes, on_map, &mut status as *mut u32 as *mut c_void);
+            wgpuDevicePoll(self.device, 1, ptr::null());
+            let out = if status == STATUS_SUCCESS {
+                let mapped = wgpuBufferGetConstMappedRange(readback, 0, bytes) as *const f32;
+                let out = std::slice::from_raw_parts(mapped, count * chunk.rows).to_vec();
+                wgpuBufferUnmap(readback);
+                out
+            } else {
+                vec![f32::NAN; count * chunk.rows]
+            };
+
+            wgpuCommandBufferRelease(commands);
+            wgpuComputePassEncoderRelease(pass);
+            wgpuCommandEncoderRelease(encoder);
+            wgpuBindGroupRelease(group);
+            for buffer in [query_buffer, params_buffer, scores, readback] {
+                wgpuBufferRelease(buffer);
+            }
+            out
+        }
+    }
+}
+
+impl EmbeddingStore for GpuStore {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.inner.id(word)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        self.inner.iter()
+    }
+
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        self.cosine_scores_batch(&[query.to_vec()]).pop().unwrap_or_default()
+    }
+
+    fn cosine_scores_batch(&self, queries: &[WordVec]) -> Vec<Vec<f32>> {
+        let mut results = vec![vec![0.0f32; self.len()]; queries.len()];
+        let normalized: Vec<f32> = queries
+            .iter()
+            .flat_map(|q| {
+                let n = norm(q);
+                q.iter().map(move |x| if n > 0.0 { x / n } else { 0.0 }).collect::<Vec<_>>()
+            })
+            .collect();
+        let dim = self.dim().max(1);
+        for chunk in &self.chunks {
+            // As many queries per dispatch as keep the scores within one binding
+            let per_dispatch = (MAX_BINDING_BYTES / (4 * chunk.rows)).clamp(1, MAX_WORKGROUPS);
+            for (batch, block) in normalized.chunks(per_dispatch * dim).enumerate() {
+                let count = block.len() / dim;
+                let scores = self.score_chunk(chunk, block, count);
+                for (i, row) in scores.chunks(chunk.rows).enumerate() {
+                    let out = &mut results[batch * per_dispatch + i];
+                    out[chunk.first_row..chunk.first_row + chunk.rows].copy_from_slice(row);
+                }
+            }
+        }
+        results
+    }
+}
+
+impl Drop for GpuStore {
+    fn drop(&mut self) {
+        unsafe {
+            for chunk in &self.chunks {
+                wgpuBufferRelease(chunk.buffer);
+            }
+            wgpuBindGroupLayoutRelease(self.layout);
+            wgpuComputePipelineRelease(self.pipeline);
+            wgpuShaderModuleRelease(self.module);
+            wgpuQueueRelease(self.queue);
+            wgpuDeviceRelease(self.device);
+            wgpuAdapterRelease(self.adapter);
+            wgpuInstanceRelease(self.instance);
+        }
+    }
+}
+
+/// Moves `store`'s cosine scans to the GPU when asked to and one is
+/// available, reporting (unless `quiet`) why it stays on the CPU otherwise
+pub fn with_gpu(store: Box<dyn EmbeddingStore>, quiet: bool) -> Box<dyn EmbeddingStore> {
+    match GpuStore::new(store) {
+        Ok(gpu) => Box::new(gpu),
+        Err((store, reason)) => {
+            if !quiet {
+                eprintln!("{}; searching on the CPU", reason);
+            }
+            store
+        }
+    }
+}
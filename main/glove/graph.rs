@@ -0,0 +1,110 @@
+//! k-nearest-neighbor graphs, written as GraphViz DOT or GEXF for Gephi.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::metric::BuiltinMetric;
+use crate::search::find_nearest_neighbors;
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+
+/// A directed graph from each word to its nearest neighbors, weighted by cosine similarity
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Graph {
+    pub nodes: Vec<String>,
+    /// `(from, to, similarity)`, indexing into `nodes`
+    pub edges: Vec<(usize, usize, f32)>,
+}
+
+impl Graph {
+    fn node(&mut self, word: &str) -> usize {
+        match self.nodes.iter().position(|n| n == word) {
+            Some(i) => i,
+            None => {
+                self.nodes.push(word.to_string());
+                self.nodes.len() - 1
+            }
+        }
+    }
+}
+
+/// Links every seed word to its `k` nearest neighbors in the whole vocabulary;
+/// unknown seeds are left out
+pub fn seed_graph<S: AsRef<str>>(store: &dyn EmbeddingStore, seeds: &[S], k: usize) -> Graph {
+    let mut graph = Graph::default();
+    for seed in seeds {
+        let seed = seed.as_ref();
+        let Some(vec) = store.get(seed) else { continue };
+        let from = graph.node(seed);
+        let found = find_nearest_neighbors(&vec, store, &[seed.to_string()], k, &BuiltinMetric::Cosine);
+        for (neighbor, similarity) in found {
+            let to = graph.node(&neighbor);
+            graph.edges.push((from, to, similarity));
+        }
+    }
+    graph
+}
+
+/// Links each of the first `n` words of the store (the most frequent, in
+/// GloVe's ordering) to its `k` nearest neighbors among those same words
+pub fn vocabulary_graph(store: &dyn EmbeddingStore, n: usize, k: usize) -> Graph {
+    let rows: Vec<(String, Vec<f32>)> = store.iter().take(n).map(|(w, v)| (w.to_string(), v.to_vec())).collect();
+    let mut graph = Graph {
+        nodes: rows.iter().map(|(w, _)| w.clone()).collect(),
+        edges: Vec::new(),
+    };
+    for (from, (_, vec)) in rows.iter().enumerate() {
+        let mut scores: Vec<(usize, f32)> = rows
+            .iter()
+            .enumerate()
+            .filter(|&(to, _)| to != from)
+            .map(|(to, (_, other))| (to, cosine_similarity(vec, other)))
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        graph.edges.extend(scores.into_iter().take(k).map(|(to, similarity)| (from, to, similarity)));
+    }
+    graph
+}
+
+/// Writes the graph in GraphViz DOT, with the similarity as each edge's `weight`
+pub fn write_dot(graph: &Graph, path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "digraph neighbors {{")?;
+    for (i, word) in graph.nodes.iter().enumerate() {
+        writeln!(out, "  n{} [label=\"{}\"];", i, word.replace('\\', "\\\\").replace('"', "\\\""))?;
+    }
+    for (from, to, similarity) in &graph.edges {
+        writeln!(out, "  n{} -> n{} [weight={:.4}];", from, to, similarity)?;
+    }
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+/// Writes the graph in GEXF 1.2, which Gephi opens directly
+pub fn write_gexf(graph: &Graph, path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<gexf xmlns=\"http://gexf.net/1.2\" version=\"1.2\">")?;
+    writeln!(out, "  <graph mode=\"static\" defaultedgetype=\"directed\">")?;
+    writeln!(out, "    <nodes>")?;
+    for (i, word) in graph.nodes.iter().enumerate() {
+        writeln!(out, "      <node id=\"{}\" label=\"{}\"/>", i, xml_escape(word))?;
+    }
+    writeln!(out, "    </nodes>")?;
+    writeln!(out, "    <edges>")?;
+    for (i, (from, to, similarity)) in graph.edges.iter().enumerate() {
+        writeln!(out, "      <edge id=\"{}\" source=\"{}\" target=\"{}\" weight=\"{:.4}\"/>", i, from, to, similarity)?;
+    }
+    writeln!(out, "    </edges>")?;
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</gexf>")?;
+    out.flush()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,155 @@
+//! Half-precision (IEEE 754 binary16) storage: vectors are kept as `u16`
+//! bit patterns and widened to `f32` on the fly while scoring.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::vector::{cosine_from_parts, norm};
+use crate::WordVec;
+
+/// Converts to the nearest half-precision value (ties to even)
+pub fn f32_to_f16(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+
+    // Infinity and NaN (keeping NaN quiet)
+    if exp == 0xff {
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if e <= 0 {
+        // Subnormal half, or too small and flushed to zero
+        if e < -10 {
+            return sign;
+        }
+        let m = mant | 0x80_0000;
+        let shift = (14 - e) as u32;
+        let half = m >> shift;
+        let rem = m & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let rounded = if rem > halfway || (rem == halfway && half & 1 == 1) { half + 1 } else { half };
+        return sign | rounded as u16;
+    }
+
+    let half = ((e as u32) << 10) | (mant >> 13);
+    let rem = mant & 0x1fff;
+    // A carry out of the mantissa correctly bumps the exponent (up to infinity)
+    let rounded = if rem > 0x1000 || (rem == 0x1000 && half & 1 == 1) { half + 1 } else { half };
+    sign | rounded as u16
+}
+
+/// Widens a half-precision value exactly
+pub fn f16_to_f32(h: u16) -> f32 {
+    let sign = ((h & 0x8000) as u32) << 16;
+    let exp = ((h >> 10) & 0x1f) as u32;
+    let mant = (h & 0x3ff) as u32;
+
+    let bits = match exp {
+        0 if mant == 0 => sign,
+        0 => {
+            // Subnormal: shift the mantissa up until it has an implicit bit
+            let mut e = 113;
+            let mut m = mant;
+            while m & 0x400 == 0 {
+                m <<= 1;
+                e -= 1;
+            }
+            sign | (e << 23) | ((m & 0x3ff) << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mant << 13),
+        _ => sign | ((exp + 112) << 23) | (mant << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// All vectors held in memory as half floats, in file order
+#[derive(Default)]
+pub struct HalfStore {
+    words: Vec<String>,
+    index: HashMap<String, usize>,
+    dim: usize,
+    /// Row-major `len * dim` half-precision bit patterns
+    data: Vec<u16>,
+    /// Length of each widened row, so a cosine scan needs no second pass
+    norms: Vec<f32>,
+}
+
+impl HalfStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a word, replacing the vector of an existing entry. Vectors whose
+    /// length differs from the first row's are skipped.
+    pub fn insert(&mut self, word: String, vec: &[f32]) {
+        if self.words.is_empty() {
+            self.dim = vec.len();
+        }
+        if vec.len() != self.dim {
+            return;
+        }
+
+        let halves: Vec<u16> = vec.iter().map(|&x| f32_to_f16(x)).collect();
+        let widened: WordVec = halves.iter().map(|&h| f16_to_f32(h)).collect();
+        if let Some(&id) = self.index.get(&word) {
+            self.data[id * self.dim..(id + 1) * self.dim].copy_from_slice(&halves);
+            self.norms[id] = norm(&widened);
+            return;
+        }
+        self.index.insert(word.clone(), self.words.len());
+        self.words.push(word);
+        self.data.extend(halves);
+        self.norms.push(norm(&widened));
+    }
+
+    /// Converts every entry of another store to half precision
+    pub fn from_store(store: &dyn EmbeddingStore) -> Self {
+        let mut half = Self::new();
+        for (word, vec) in store.iter() {
+            half.insert(word.into_owned(), &vec);
+        }
+        half
+    }
+
+    fn row(&self, id: usize) -> &[u16] {
+        &self.data[id * self.dim..(id + 1) * self.dim]
+    }
+}
+
+impl EmbeddingStore for HalfStore {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.index.get(word).copied()
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let word = self.words.get(id)?;
+        let vec: WordVec = self.row(id).iter().map(|&h| f16_to_f32(h)).collect();
+        Some((Cow::Borrowed(word.as_str()), Cow::Owned(vec)))
+    }
+
+    /// Widens each component as it is used instead of materializing rows
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        let q_norm = norm(query);
+        (0..self.words.len())
+            .map(|id| {
+                let dot: f32 = self.row(id).iter().zip(query).map(|(&h, q)| f16_to_f32(h) * q).sum();
+                cosine_from_parts(dot, q_norm, self.norms[id])
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,465 @@
+//! HDF5 files, as scientific pipelines (h5py, MATLAB, Julia) write them,
+//! through the system `libhdf5`.
+//!
+//! Written files hold two datasets at the root: `vectors`, a float32
+//! `[words, dim]` matrix, and `words`, the variable-length UTF-8 strings
+//! naming its rows. Reading takes the largest 2-d float dataset at the root,
+//! converted to f32 whatever its precision, and the words from a 1-d string
+//! dataset of as many rows (fixed or variable length), else from a vocabulary
+//! text file as for `.npy`. Groups below the root are not searched.
+//!
+//! This needs a build with the `hdf5` feature, and the file must be a plain,
+//! uncompressed path.
+
+use std::io;
+
+use crate::input::Source;
+use crate::load::RowSink;
+use crate::store::EmbeddingStore;
+
+/// First bytes of an HDF5 file with no user block before its superblock
+pub(crate) const HDF5_MAGIC: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+#[cfg(feature = "hdf5")]
+pub(crate) fn read_hdf5(source: &Source, sink: &mut RowSink) -> io::Result<()> {
+    use crate::formats::npy_vocabulary;
+    use h5::*;
+
+    let Source::Path(path) = source else {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "HDF5 files cannot be read from archives"));
+    };
+    let file = File::open(path)?;
+    let mut matrix: Option<(Dataset, usize, usize)> = None;
+    let mut vocab = Vec::new();
+    for name in file.dataset_names()? {
+        // Groups and other objects fail to open as datasets and are passed over
+        let Ok(dataset) = file.dataset(&name) else { continue };
+        match (dataset.class()?, dataset.dims()?.as_slice()) {
+            (H5T_FLOAT, &[rows, dim]) if matrix.as_ref().is_none_or(|&(_, r, d)| rows * dim > r * d) => {
+                matrix = Some((dataset, rows, dim));
+            }
+            (H5T_STRING, &[len]) => vocab.push((dataset, len)),
+            _ => {}
+        }
+    }
+    let Some((matrix, rows, dim)) = matrix else {
+        return Err(invalid("no 2-d float dataset at the root of the HDF5 file"));
+    };
+    let words = match vocab.into_iter().find(|&(_, len)| len == rows) {
+        Some((dataset, len)) => dataset.read_strings(len)?,
+        None => npy_vocabulary(source)?,
+    };
+    if words.len() < rows {
+        return Err(invalid(format!("the matrix has {} rows but only {} vocabulary words", rows, words.len())));
+    }
+
+    let mut batch = vec![0f32; BATCH_ROWS * dim];
+    for start in (0..rows).step_by(BATCH_ROWS) {
+        if sink.full() {
+            break;
+        }
+        let count = (rows - start).min(BATCH_ROWS);
+        matrix.read_rows(start, count, dim, &mut batch[..count * dim])?;
+        for (word, vec) in words[start..start + count].iter().zip(batch.chunks_exact(dim)) {
+            if sink.full() {
+                break;
+            }
+            sink.read(dim * 4);
+            if sink.wants(word) {
+                sink.add(word, vec.to_vec());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "hdf5"))]
+pub(crate) fn read_hdf5(_source: &Source, _sink: &mut RowSink) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reading HDF5 files needs a build with the hdf5 feature"))
+}
+
+/// Writes the vectors as a float32 `vectors` dataset and their words as a
+/// `words` dataset of UTF-8 strings
+#[cfg(feature = "hdf5")]
+pub fn write_hdf5(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    use std::ffi::CString;
+
+    use h5::*;
+
+    let (len, dim) = (store.len(), store.dim());
+    let file = File::create(path)?;
+    let vectors = file.create_matrix(c"vectors", len, dim)?;
+    let mut words = Vec::with_capacity(len);
+    let mut batch = Vec::with_capacity(BATCH_ROWS * dim);
+    let mut start = 0;
+    for (word, vec) in store.iter() {
+        let word = CString::new(word.as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} contains a NUL byte", word)))?;
+        words.push(word);
+        batch.extend_from_slice(&vec);
+        if batch.len() == BATCH_ROWS * dim {
+            vectors.write_rows(start, BATCH_ROWS, dim, &batch)?;
+            start += BATCH_ROWS;
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        vectors.write_rows(start, batch.len() / dim, dim, &batch)?;
+    }
+    file.create_strings(c"words", &words)
+}
+
+#[cfg(not(feature = "hdf5"))]
+pub fn write_hdf5(_store: &dyn EmbeddingStore, _path: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "writing HDF5 files needs a build with the hdf5 feature"))
+}
+
+#[cfg(feature = "hdf5")]
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Owned HDF5 objects over the C API
+#[cfg(feature = "hdf5")]
+mod h5 {
+    use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString};
+    use std::io;
+    use std::ptr;
+    use std::sync::Once;
+
+    #[allow(non_camel_case_types)]
+    type hid_t = i64;
+    #[allow(non_camel_case_types)]
+    type herr_t = c_int;
+    #[allow(non_camel_case_types)]
+    type hsize_t = u64;
+
+    /// Rows read or written per call
+    pub const BATCH_ROWS: usize = 4096;
+
+    pub const H5T_FLOAT: c_int = 1;
+    pub const H5T_STRING: c_int = 3;
+    const H5P_DEFAULT: hid_t = 0;
+    const H5S_ALL: hid_t = 0;
+    const H5E_DEFAULT: hid_t = 0;
+    const H5F_ACC_RDONLY: c_uint = 0;
+    const H5F_ACC_TRUNC: c_uint = 2;
+    const H5S_SELECT_SET: c_int = 0;
+    const H5T_CSET_UTF8: c_int = 1;
+    const H5T_STR_NULLPAD: c_int = 1;
+    const H5T_VARIABLE: usize = usize::MAX;
+    const H5_INDEX_NAME: c_int = 0;
+    const H5_ITER_INC: c_int = 0;
+
+    #[repr(C)]
+    struct GroupInfo {
+        storage_type: c_int,
+        nlinks: hsize_t,
+        max_corder: i64,
+        mounted: bool,
+    }
+
+    #[link(name = "hdf5")]
+    extern "C" {
+        static H5T_NATIVE_FLOAT_g: hid_t;
+        static H5T_IEEE_F32LE_g: hid_t;
+        static H5T_C_S1_g: hid_t;
+
+        fn H5open() -> herr_t;
+        fn H5Eset_auto2(stack: hid_t, func: *const c_void, data: *mut c_void) -> herr_t;
+        fn H5free_memory(mem: *mut c_void) -> herr_t;
+
+        fn H5Fopen(name: *const c_char, flags: c_uint, fapl: hid_t) -> hid_t;
+        fn H5Fcreate(name: *const c_char, flags: c_uint, fcpl: hid_t, fapl: hid_t) -> hid_t;
+        fn H5Fclose(id: hid_t) -> herr_t;
+        fn H5Gget_info(loc: hid_t, info: *mut GroupInfo) -> herr_t;
+        fn H5Lget_name_by_idx(
+            loc: hid_t,
+            group: *const c_char,
+            index: c_int,
+            order: c_int,
+            n: hsize_t,
+            name: *mut c_char,
+            size: usize,
+            lapl: hid_t,
+        ) -> isize;
+
+        fn H5Dopen2(loc: hid_t, name: *const c_char, dapl: hid_t) -> hid_t;
+        fn H5Dcreate2(
+            loc: hid_t,
+            name: *const c_char,
+            type_id: hid_t,
+            space: hid_t,
+            lcpl: hid_t,
+            dcpl: hid_t,
+            dapl: hid_t,
+        ) -> hid_t;
+        fn H5Dget_space(id: hid_t) -> hid_t;
+        fn H5Dget_type(id: hid_t) -> hid_t;
+        fn H5Dread(
+            id: hid_t,
+            mem_type: hid_t,
+            mem_space: hid_t,
+            file_space: hid_t,
+            xfer: hid_t,
+            buf: *mut c_void,
+        ) -> herr_t;
+        fn H5Dwrite(
+            id: hid_t,
+            mem_type: hid_t,
+            mem_space: hid_t,
+            file_space: hid_t,
+            xfer: hid_t,
+            buf: *const c_void,
+        ) -> herr_t;
+        fn H5Dclose(id: hid_t) -> herr_t;
+
+        fn H5Screate_simple(rank: c_int, dims: *const hsize_t, max_dims: *const hsize_t) -> hid_t;
+        fn H5Sget_simple_extent_ndims(id: hid_t) -> c_int;
+        fn H5Sget_simple_extent_dims(id: hid_t, dims: *mut hsize_t, max_dims: *mut hsize_t) -> c_int;
+        fn H5Sselect_hyperslab(
+            id: hid_t,
+            op: c_int,
+            start: *const hsize_t,
+            stride: *const hsize_t,
+            count: *const hsize_t,
+            block: *const hsize_t,
+        ) -> herr_t;
+        fn H5Sclose(id: hid_t) -> herr_t;
+
+        fn H5Tcopy(id: hid_t) -> hid_t;
+        fn H5Tget_class(id: hid_t) -> c_int;
+        fn H5Tget_size(id: hid_t) -> usize;
+        fn H5Tset_size(id: hid_t, size: usize) -> herr_t;
+        fn H5Tget_cset(id: hid_t) -> c_int;
+        fn H5Tset_cset(id: hid_t, cset: c_int) -> herr_t;
+        fn H5Tset_strpad(id: hid_t, pad: c_int) -> herr_t;
+        fn H5Tis_variable_str(id: hid_t) -> c_int;
+        fn H5Tclose(id: hid_t) -> herr_t;
+    }
+
+    /// Initializes the library once, turning off its printing of error
+    /// stacks to stderr; failures are reported through `io::Error`s instead
+    fn init() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| unsafe {
+            H5open();
+            H5Eset_auto2(H5E_DEFAULT, ptr::null(), ptr::null_mut());
+        });
+    }
+
+    fn check(status: herr_t, what: impl FnOnce() -> String) -> io::Result<()> {
+        if status < 0 {
+            return Err(io::Error::other(format!("hdf5: {}", what())));
+        }
+        Ok(())
+    }
+
+    /// An open identifier, closed on drop by the function for its kind
+    struct Handle {
+        id: hid_t,
+        close: unsafe extern "C" fn(hid_t) -> herr_t,
+    }
+
+    impl Handle {
+        fn new(
+            id: hid_t,
+            close: unsafe extern "C" fn(hid_t) -> herr_t,
+            what: impl FnOnce() -> String,
+        ) -> io::Result<Handle> {
+            check(if id < 0 { -1 } else { 0 }, what)?;
+            Ok(Handle { id, close })
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe {
+                (self.close)(self.id);
+            }
+        }
+    }
+
+    fn c_string(s: &str) -> io::Result<CString> {
+        CString::new(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// A simple dataspace of the given dimensions
+    fn space(dims: &[usize]) -> io::Result<Handle> {
+        let dims: Vec<hsize_t> = dims.iter().map(|&d| d as hsize_t).collect();
+        let id = unsafe { H5Screate_simple(dims.len() as c_int, dims.as_ptr(), ptr::null()) };
+        Handle::new(id, H5Sclose, || "cannot create a dataspace".to_string())
+    }
+
+    /// A memory string type: variable length, or `size` bytes padded with NULs
+    fn string_type(size: usize, cset: c_int) -> io::Result<Handle> {
+        let t = Handle::new(unsafe { H5Tcopy(H5T_C_S1_g) }, H5Tclose, || "cannot create a string type".to_string())?;
+        check(unsafe { H5Tset_size(t.id, size) }, || "cannot size a string type".to_string())?;
+        check(unsafe { H5Tset_cset(t.id, cset) }, || "cannot set a string encoding".to_string())?;
+        check(unsafe { H5Tset_strpad(t.id, H5T_STR_NULLPAD) }, || "cannot set string padding".to_string())?;
+        Ok(t)
+    }
+
+    pub struct File(Handle);
+
+    impl File {
+        pub fn open(path: &str) -> io::Result<File> {
+            init();
+            let c_path = c_string(path)?;
+            let id = unsafe { H5Fopen(c_path.as_ptr(), H5F_ACC_RDONLY, H5P_DEFAULT) };
+            Ok(File(Handle::new(id, H5Fclose, || format!("cannot open {}", path))?))
+        }
+
+        pub fn create(path: &str) -> io::Result<File> {
+            init();
+            let c_path = c_string(path)?;
+            let id = unsafe { H5Fcreate(c_path.as_ptr(), H5F_ACC_TRUNC, H5P_DEFAULT, H5P_DEFAULT) };
+            Ok(File(Handle::new(id, H5Fclose, || format!("cannot create {}", path))?))
+        }
+
+        /// Names of the links at the root, in alphabetical order
+        pub fn dataset_names(&self) -> io::Result<Vec<String>> {
+            let mut info = GroupInfo { storage_type: 0, nlinks: 0, max_corder: 0, mounted: false };
+            check(unsafe { H5Gget_info(self.0.id, &mut info) }, || "cannot list the root group".to_string())?;
+            let mut names = Vec::new();
+            for n in 0..info.nlinks {
+                let name_at = |buf: *mut c_char, size: usize| unsafe {
+                    H5Lget_name_by_idx(self.0.id, c".".as_ptr(), H5_INDEX_NAME, H5_ITER_INC, n, buf, size, H5P_DEFAULT)
+                };
+                let len = name_at(ptr::null_mut(), 0);
+                check(if len < 0 { -1 } else { 0 }, || "cannot read a link name".to_string())?;
+                let mut buf = vec![0u8; len as usize + 1];
+                name_at(buf.as_mut_ptr().cast(), buf.len());
+                buf.pop();
+                names.push(String::from_utf8_lossy(&buf).into_owned());
+            }
+            Ok(names)
+        }
+
+        pub fn dataset(&self, name: &str) -> io::Result<Dataset> {
+            let c_name = c_string(name)?;
+            let id = unsafe { H5Dopen2(self.0.id, c_name.as_ptr(), H5P_DEFAULT) };
+            Ok(Dataset(Handle::new(id, H5Dclose, || format!("cannot open dataset {}", name))?))
+        }
+
+        /// Creates a float32 `[rows, dim]` dataset
+        pub fn create_matrix(&self, name: &CStr, rows: usize, dim: usize) -> io::Result<Dataset> {
+            let space = space(&[rows, dim])?;
+            let id = unsafe {
+                H5Dcreate2(self.0.id, name.as_ptr(), H5T_IEEE_F32LE_g, space.id, H5P_DEFAULT, H5P_DEFAULT, H5P_DEFAULT)
+            };
+            Ok(Dataset(Handle::new(id, H5Dclose, || format!("cannot create dataset {:?}", name))?))
+        }
+
+        /// Creates and fills a dataset of variable-length UTF-8 strings
+        pub fn create_strings(&self, name: &CStr, strings: &[CString]) -> io::Result<()> {
+            let (string, space) = (string_type(H5T_VARIABLE, H5T_CSET_UTF8)?, space(&[strings.len()])?);
+            let id = unsafe {
+                H5Dcreate2(self.0.id, name.as_ptr(), string.id, space.id, H5P_DEFAULT, H5P_DEFAULT, H5P_DEFAULT)
+            };
+            let dataset = Handle::new(id, H5Dclose, || format!("cannot create dataset {:?}", name))?;
+            let pointers: Vec<*const c_char> = strings.iter().map(|s| s.as_ptr()).collect();
+            let status =
+                unsafe { H5Dwrite(dataset.id, string.id, H5S_ALL, H5S_ALL, H5P_DEFAULT, pointers.as_ptr().cast()) };
+            check(status, || format!("cannot write dataset {:?}", name))
+        }
+    }
+
+    pub struct Dataset(Handle);
+
+    impl Dataset {
+        fn file_type(&self) -> io::Result<Handle> {
+            Handle::new(unsafe { H5Dget_type(self.0.id) }, H5Tclose, || "cannot read a dataset type".to_string())
+        }
+
+        fn space(&self) -> io::Result<Handle> {
+            Handle::new(unsafe { H5Dget_space(self.0.id) }, H5Sclose, || "cannot read a dataspace".to_string())
+        }
+
+        /// The type class, such as `H5T_FLOAT` or `H5T_STRING`
+        pub fn class(&self) -> io::Result<c_int> {
+            Ok(unsafe { H5Tget_class(self.file_type()?.id) })
+        }
+
+        pub fn dims(&self) -> io::Result<Vec<usize>> {
+            let space = self.space()?;
+            let rank = unsafe { H5Sget_simple_extent_ndims(space.id) };
+            check(rank, || "cannot read a dataset's rank".to_string())?;
+            let mut dims = vec![0 as hsize_t; rank as usize];
+            let status = unsafe { H5Sget_simple_extent_dims(space.id, dims.as_mut_ptr(), ptr::null_mut()) };
+            check(status, || "cannot read a dataset's dimensions".to_string())?;
+            Ok(dims.into_iter().map(|d| d as usize).collect())
+        }
+
+        /// The dataspace with rows `start..start + count` of a `[rows, dim]`
+        /// dataset selected, and a memory dataspace of their size
+        fn select_rows(&self, start: usize, count: usize, dim: usize) -> io::Result<(Handle, Handle)> {
+            let file_space = self.space()?;
+            let (offset, size) = ([start as hsize_t, 0], [count as hsize_t, dim as hsize_t]);
+            let (offset_ptr, size_ptr) = (offset.as_ptr(), size.as_ptr());
+            let status = unsafe {
+                H5Sselect_hyperslab(file_space.id, H5S_SELECT_SET, offset_ptr, ptr::null(), size_ptr, ptr::null())
+            };
+            check(status, || format!("cannot select rows {}..{}", start, start + count))?;
+            Ok((file_space, space(&[count, dim])?))
+        }
+
+        /// Reads `count` rows of `dim` values from `start` into `out`, as f32
+        pub fn read_rows(&self, start: usize, count: usize, dim: usize, out: &mut [f32]) -> io::Result<()> {
+            let (file_space, memory) = self.select_rows(start, count, dim)?;
+            let status = unsafe {
+                H5Dread(self.0.id, H5T_NATIVE_FLOAT_g, memory.id, file_space.id, H5P_DEFAULT, out.as_mut_ptr().cast())
+            };
+            check(status, || format!("cannot read rows {}..{}", start, start + count))
+        }
+
+        pub fn write_rows(&self, start: usize, count: usize, dim: usize, rows: &[f32]) -> io::Result<()> {
+            let (file_space, memory) = self.select_rows(start, count, dim)?;
+            let status = unsafe {
+                H5Dwrite(self.0.id, H5T_NATIVE_FLOAT_g, memory.id, file_space.id, H5P_DEFAULT, rows.as_ptr().cast())
+            };
+            check(status, || format!("cannot write rows {}..{}", start, start + count))
+        }
+
+        /// Reads a 1-d dataset of `len` fixed- or variable-length strings
+        pub fn read_strings(&self, len: usize) -> io::Result<Vec<String>> {
+            let file_type = self.file_type()?;
+            let cset = unsafe { H5Tget_cset(file_type.id) };
+            let failed = || "cannot read the words".to_string();
+            if unsafe { H5Tis_variable_str(file_type.id) } > 0 {
+                let memory = string_type(H5T_VARIABLE, cset)?;
+                let mut pointers = vec![ptr::null_mut::<c_char>(); len];
+                let status = unsafe {
+                    H5Dread(self.0.id, memory.id, H5S_ALL, H5S_ALL, H5P_DEFAULT, pointers.as_mut_ptr().cast())
+                };
+                check(status, failed)?;
+                Ok(pointers
+                    .into_iter()
+                    .map(|p| {
+                        if p.is_null() {
+                            return String::new();
+                        }
+                        let word = unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned();
+                        unsafe { H5free_memory(p.cast()) };
+                        word
+                    })
+                    .collect())
+            } else {
+                let size = unsafe { H5Tget_size(file_type.id) };
+                let memory = string_type(size, cset)?;
+                let mut bytes = vec![0u8; len * size];
+                let status = unsafe {
+                    H5Dread(self.0.id, memory.id, H5S_ALL, H5S_ALL, H5P_DEFAULT, bytes.as_mut_ptr().cast())
+                };
+                check(status, failed)?;
+                Ok(bytes
+                    .chunks_exact(size.max(1))
+                    .map(|b| {
+                        let end = b.iter().position(|&c| c == 0).unwrap_or(b.len());
+                        String::from_utf8_lossy(&b[..end]).trim_end().to_string()
+                    })
+                    .collect())
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Approximate nearest-neighbor indexes: instead of scoring every row, a
+//! query is scored exactly against a shortlist of candidate rows only.
+
+use std::io;
+
+use crate::annoy::AnnoyIndex;
+use crate::ivf::IvfIndex;
+use crate::lsh::LshIndex;
+use crate::store::{EmbeddingStore, Entry};
+use crate::vector::{cosine_from_parts, dot_product, norm};
+
+/// Names accepted by `--index`
+pub const INDEXES: &[&str] = &["lsh", "ivf", "annoy"];
+
+/// Which index to build, with its parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndexSpec {
+    /// Random-hyperplane LSH: `tables` independent `bits`-bit signatures,
+    /// each contributing its `candidates` closest rows by Hamming distance
+    Lsh { bits: usize, tables: usize, candidates: usize },
+    /// Inverted file: `nlist` k-means cells (default: about the square root
+    /// of the vocabulary size), of which the `nprobe` closest are searched
+    Ivf { nlist: Option<usize>, nprobe: usize },
+    /// Annoy forest of `trees` random-projection trees, or the one saved in
+    /// `file`; a query visits `search_k` nodes (default 10 per tree)
+    Annoy { trees: usize, search_k: Option<usize>, file: Option<String> },
+}
+
+impl IndexSpec {
+    pub const LSH_BITS: usize = 128;
+    pub const LSH_TABLES: usize = 4;
+    pub const LSH_CANDIDATES: usize = 200;
+    pub const IVF_NPROBE: usize = 8;
+    pub const ANNOY_TREES: usize = 10;
+}
+
+/// A structure that shortlists rows likely to be near a query
+pub trait CandidateIndex {
+    /// Row ids to score exactly, in any order and possibly with repeats
+    fn candidates(&self, query: &[f32]) -> Vec<usize>;
+}
+
+/// View of a store whose cosine scans only score the index's candidates;
+/// every other row scores NaN, which `search::top_k` skips. Other metrics
+/// still scan every row.
+pub struct IndexedStore {
+    inner: Box<dyn EmbeddingStore>,
+    index: Box<dyn CandidateIndex>,
+}
+
+/// Builds (or, for a saved Annoy file, loads) the index `spec` describes over `store`
+pub fn build_index(store: &dyn EmbeddingStore, spec: &IndexSpec) -> io::Result<Box<dyn CandidateIndex>> {
+    Ok(match spec {
+        &IndexSpec::Lsh { bits, tables, candidates } => Box::new(LshIndex::build(store, bits, tables, candidates)),
+        &IndexSpec::Ivf { nlist, nprobe } => Box::new(IvfIndex::build(store, nlist, nprobe)),
+        IndexSpec::Annoy { search_k, file: Some(path), .. } => {
+            Box::new(AnnoyIndex::load(path, store.dim(), *search_k)?)
+        }
+        &IndexSpec::Annoy { trees, search_k, file: None } => Box::new(AnnoyIndex::build(store, trees, search_k)),
+    })
+}
+
+/// Cosine scores of `index`'s candidates for `query`, NaN for every other row
+pub fn candidate_scores(store: &dyn EmbeddingStore, index: &dyn CandidateIndex, query: &[f32]) -> Vec<f32> {
+    let q_norm = norm(query);
+    let mut scores = vec![f32::NAN; store.len()];
+    for id in index.candidates(query) {
+        // A saved index may cover more rows than were loaded
+        if !scores.get(id).is_some_and(|s| s.is_nan()) {
+            continue;
+        }
+        if let Some((_, vec)) = store.get_by_id(id) {
+            scores[id] = cosine_from_parts(dot_product(query, &vec), q_norm, norm(&vec));
+        }
+    }
+    scores
+}
+
+impl IndexedStore {
+    pub fn new(inner: Box<dyn EmbeddingStore>, spec: &IndexSpec) -> io::Result<Self> {
+        let index = build_index(inner.as_ref(), spec)?;
+        Ok(IndexedStore { inner, index })
+    }
+}
+
+impl EmbeddingStore for IndexedStore {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.inner.id(word)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        self.inner.iter()
+    }
+
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        candidate_scores(self.inner.as_ref(), self.index.as_ref(), query)
+    }
+}
+
+/// Wraps `store` in an `IndexedStore` when an index is requested
+pub fn with_index(store: Box<dyn EmbeddingStore>, spec: Option<&IndexSpec>) -> io::Result<Box<dyn EmbeddingStore>> {
+    match spec {
+        Some(spec) => Ok(Box::new(IndexedStore::new(store, spec)?)),
+        None => Ok(store),
+    }
+}
@@ -0,0 +1,146 @@
+//! Opening embedding files, transparently decompressing `.gz` and `.zst`
+//! and extracting members of `.zip` archives.
+//!
+//! Decompression streams through the system `gzip` / `zstd` / `unzip` tools,
+//! so a compressed download never has to be expanded on disk first.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression applied to an embedding file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects compression from the file extension, falling back to magic bytes
+    pub fn detect(path: &str) -> io::Result<Compression> {
+        if path.ends_with(".gz") {
+            return Ok(Compression::Gzip);
+        }
+        if path.ends_with(".zst") {
+            return Ok(Compression::Zstd);
+        }
+
+        let mut magic = [0u8; 4];
+        let mut file = File::open(path)?;
+        let n = file.read(&mut magic)?;
+        Ok(if magic[..n].starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if magic[..n].starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        })
+    }
+
+    fn tool(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+}
+
+/// Output of an external tool; reports its exit status at end of stream
+struct ToolOutput {
+    tool: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for ToolOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("{} exited with {}", self.tool, status)));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Runs a tool and streams its standard output
+fn spawn_tool(tool: &'static str, args: &[&str]) -> io::Result<ToolOutput> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("unable to run {}: {}", tool, e)))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    Ok(ToolOutput { tool, child, stdout })
+}
+
+/// Opens a possibly compressed file for line-by-line reading
+pub fn open_input(path: &str) -> io::Result<Box<dyn BufRead>> {
+    let compression = Compression::detect(path)?;
+    let Some(tool) = compression.tool() else {
+        return Ok(Box::new(BufReader::new(File::open(path)?)));
+    };
+
+    Ok(Box::new(BufReader::new(spawn_tool(tool, &["-dc", path])?)))
+}
+
+/// Lists the files inside a zip archive
+pub fn archive_members(archive: &str) -> io::Result<Vec<String>> {
+    let mut listing = String::new();
+    spawn_tool("unzip", &["-Z1", archive])?.read_to_string(&mut listing)?;
+    Ok(listing.lines().map(|l| l.to_string()).collect())
+}
+
+/// Where embedding text is read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A plain or compressed file, or a backend-specific location such as `host:port`
+    Path(String),
+    /// A file inside a zip archive, e.g. `glove.6B.100d.txt` in `glove.6B.zip`
+    ArchiveMember { archive: String, member: String },
+}
+
+impl Source {
+    /// Size in bytes of the text the source yields, when known without reading it
+    pub fn text_len(&self) -> Option<u64> {
+        match self {
+            Source::Path(path) if Compression::detect(path).ok()? == Compression::None => {
+                Some(std::fs::metadata(path).ok()?.len())
+            }
+            _ => None,
+        }
+    }
+
+    /// Opens the source for line-by-line reading
+    pub fn open(&self) -> io::Result<Box<dyn BufRead>> {
+        match self {
+            Source::Path(path) => open_input(path),
+            Source::ArchiveMember { archive, member } => Ok(Box::new(BufReader::new(
+                spawn_tool("unzip", &["-p", archive, member])?,
+            ))),
+        }
+    }
+}
+
+impl From<&str> for Source {
+    fn from(path: &str) -> Self {
+        Source::Path(path.to_string())
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Path(path) => write!(f, "{}", path),
+            Source::ArchiveMember { archive, member } => write!(f, "{}/{}", archive, member),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Inverted-file (IVF) index: the vocabulary is clustered around `nlist`
+//! centroids, and a query only looks at the words of its `nprobe` closest
+//! clusters. Raising `nprobe` trades speed for recall.
+//!
+//! Clustering is spherical k-means (on unit vectors, by cosine), trained on
+//! a sample of the rows and then used to assign every row.
+
+use crate::index::CandidateIndex;
+use crate::rng::Rng;
+use crate::store::EmbeddingStore;
+use crate::vector::dot_product;
+use crate::WordVec;
+
+/// Fixed so the same file always gets the same clusters
+const SEED: u64 = 0x1f5e_ed0c_e47e_0d5b;
+
+/// Rows sampled per centroid for training
+const SAMPLE_PER_CENTROID: usize = 32;
+
+const ITERATIONS: usize = 10;
+
+pub struct IvfIndex {
+    centroids: Vec<WordVec>,
+    /// Row ids of each centroid's cluster
+    lists: Vec<Vec<usize>>,
+    nprobe: usize,
+}
+
+impl IvfIndex {
+    /// Clusters `store` into `nlist` cells (by default about the square root
+    /// of the vocabulary size); queries look into `nprobe` of them
+    pub fn build(store: &dyn EmbeddingStore, nlist: Option<usize>, nprobe: usize) -> Self {
+        let nlist = nlist.unwrap_or_else(|| (store.len() as f64).sqrt().round() as usize).clamp(1, store.len().max(1));
+        let mut rng = Rng::new(SEED);
+
+        let mut sample: Vec<WordVec> = if store.len() <= nlist * SAMPLE_PER_CENTROID {
+            store.iter().map(|(_, vec)| unit(&vec)).collect()
+        } else {
+            (0..nlist * SAMPLE_PER_CENTROID)
+                .filter_map(|_| store.get_by_id(rng.below(store.len())))
+                .map(|(_, vec)| unit(&vec))
+                .collect()
+        };
+        sample.retain(|vec| vec.iter().any(|&x| x != 0.0));
+        let centroids = train(&sample, nlist, &mut rng);
+
+        let mut lists = vec![Vec::new(); centroids.len()];
+        for (id, (_, vec)) in store.iter().enumerate() {
+            lists[nearest(&centroids, &vec)].push(id);
+        }
+        IvfIndex { centroids, lists, nprobe }
+    }
+}
+
+impl CandidateIndex for IvfIndex {
+    fn candidates(&self, query: &[f32]) -> Vec<usize> {
+        let mut cells: Vec<(usize, f32)> =
+            self.centroids.iter().enumerate().map(|(i, c)| (i, dot_product(query, c))).collect();
+        cells.sort_by(|a, b| b.1.total_cmp(&a.1));
+        cells.iter().take(self.nprobe).flat_map(|&(i, _)| self.lists[i].iter().copied()).collect()
+    }
+}
+
+/// Spherical k-means over unit vectors, starting from distinct random samples
+fn train(sample: &[WordVec], k: usize, rng: &mut Rng) -> Vec<WordVec> {
+    let Some(dim) = sample.first().map(|v| v.len()) else {
+        return vec![Vec::new(); 1];
+    };
+    let k = k.min(sample.len());
+    let mut order: Vec<usize> = (0..sample.len()).collect();
+    for i in 0..k {
+        let j = i + rng.below(sample.len() - i);
+        order.swap(i, j);
+    }
+    let mut centroids: Vec<WordVec> = order[..k].iter().map(|&i| sample[i].clone()).collect();
+
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![vec![0.0f32; dim]; k];
+        let mut counts = vec![0usize; k];
+        for vec in sample {
+            let cell = nearest(&centroids, vec);
+            counts[cell] += 1;
+            sums[cell].iter_mut().zip(vec).for_each(|(s, x)| *s += x);
+        }
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            // An empty cell is restarted from a random sample
+            *centroid = if count == 0 { sample[rng.below(sample.len())].clone() } else { unit(&sum) };
+        }
+    }
+    centroids
+}
+
+/// Index of the centroid most similar to `vec`
+fn nearest(centroids: &[WordVec], vec: &[f32]) -> usize {
+    let mut best = (0, f32::NEG_INFINITY);
+    for (i, centroid) in centroids.iter().enumerate() {
+        let score = dot_product(vec, centroid);
+        if score > best.1 {
+            best = (i, score);
+        }
+    }
+    best.0
+}
+
+fn unit(vec: &[f32]) -> WordVec {
+    let norm = dot_product(vec, vec).sqrt();
+    if norm == 0.0 {
+        return vec.to_vec();
+    }
+    vec.iter().map(|x| x / norm).collect()
+}
@@ -0,0 +1,214 @@
+//! Lazy lookups in an uncompressed text file: only a word-to-byte-offset
+//! index is held in memory, and a word's vector is read from its line when
+//! asked for. Scans (nearest-neighbor search) stream the file from disk.
+//!
+//! The index is built on first use and cached under
+//! `<cache>/offsets/<hash>.idx`, so later runs start without parsing the file;
+//! it is rebuilt whenever the file's size or modification time changes.
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::formats::is_count_dim_header;
+use crate::models::cache_dir;
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+const INDEX_MAGIC: &str = "glove-offsets";
+const INDEX_VERSION: u32 = 1;
+
+/// A text vector file read on demand through a byte-offset index
+pub struct LazyStore {
+    path: String,
+    dim: usize,
+    words: Vec<String>,
+    /// Byte offset of each word's line
+    offsets: Vec<u64>,
+    index: HashMap<String, usize>,
+    reader: Mutex<BufReader<File>>,
+}
+
+impl LazyStore {
+    /// Opens `path`, reading its cached index or building (and caching) one
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let stamp = stamp(&file)?;
+        let cached = index_path(path);
+        let (dim, entries) = match read_index(&cached, stamp) {
+            Some(index) => index,
+            None => {
+                let index = build_index(path)?;
+                // A cache that cannot be written only costs the next run a rebuild
+                let _ = write_index(&cached, stamp, index.0, &index.1);
+                index
+            }
+        };
+
+        let mut store = LazyStore {
+            path: path.to_string(),
+            dim,
+            words: Vec::with_capacity(entries.len()),
+            offsets: Vec::with_capacity(entries.len()),
+            index: HashMap::with_capacity(entries.len()),
+            reader: Mutex::new(BufReader::new(file)),
+        };
+        for (word, offset) in entries {
+            store.index.insert(word.clone(), store.words.len());
+            store.words.push(word);
+            store.offsets.push(offset);
+        }
+        Ok(store)
+    }
+
+    /// Reads the vector on the line at `offset`
+    fn read_row(&self, offset: u64) -> Option<WordVec> {
+        let mut reader = self.reader.lock().unwrap_or_else(|e| e.into_inner());
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        parse_vector(&line, self.dim)
+    }
+}
+
+impl EmbeddingStore for LazyStore {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.index.get(word).copied()
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let vec = self.read_row(*self.offsets.get(id)?)?;
+        Some((Cow::Borrowed(self.words[id].as_str()), Cow::Owned(vec)))
+    }
+
+    /// Streams the file once instead of seeking to every row
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        let Ok(file) = File::open(&self.path) else {
+            return Box::new(std::iter::empty());
+        };
+        let mut reader = BufReader::new(file);
+        let mut position = 0u64;
+        let mut line = String::new();
+        Box::new((0..self.words.len()).filter_map(move |id| {
+            // Rows are in file order, so this is almost always a short skip forward
+            let offset = self.offsets[id];
+            if offset != position {
+                reader.seek_relative(offset as i64 - position as i64).ok()?;
+                position = offset;
+            }
+            line.clear();
+            position += reader.read_line(&mut line).ok()? as u64;
+            let vec = parse_vector(&line, self.dim)?;
+            Some((Cow::Borrowed(self.words[id].as_str()), Cow::Owned(vec)))
+        }))
+    }
+}
+
+/// Where the index of a file is cached: `<cache>/offsets/<hash>.idx`
+pub fn index_path(path: &str) -> PathBuf {
+    // Canonicalize so `glove.txt` and `./glove.txt` share an index
+    let key = fs::canonicalize(path).map_or(path.to_string(), |p| p.display().to_string());
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    cache_dir().join("offsets").join(format!("{:016x}.idx", hasher.finish()))
+}
+
+/// The file's size and modification time, which a cached index must match
+fn stamp(file: &File) -> io::Result<(u64, u128)> {
+    let metadata = file.metadata()?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    Ok((metadata.len(), modified))
+}
+
+/// Finds every well-formed row's word and line offset, the way the text
+/// loader reads them: a word2vec header is skipped, rows whose length
+/// differs from the first row's or that hold a non-number are left out, and a
+/// repeated word keeps its first row id but reads the later row
+fn build_index(path: &str) -> io::Result<(usize, Vec<(String, u64)>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut entries: Vec<(String, u64)> = Vec::new();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut dim = None;
+    let mut offset = 0u64;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let bytes = reader.read_line(&mut line)?;
+        if bytes == 0 {
+            break;
+        }
+        let start = offset;
+        offset += bytes as u64;
+        if start == 0 && is_count_dim_header(&line) {
+            continue;
+        }
+        let Some(word) = line.split_whitespace().next() else { continue };
+        let expected = *dim.get_or_insert_with(|| line.split_whitespace().count() - 1);
+        if parse_vector(&line, expected).is_none() {
+            continue;
+        }
+        match ids.get(word) {
+            Some(&id) => entries[id].1 = start,
+            None => {
+                ids.insert(word.to_string(), entries.len());
+                entries.push((word.to_string(), start));
+            }
+        }
+    }
+    Ok((dim.unwrap_or(0), entries))
+}
+
+/// Parses `word f1 f2 ...`, requiring exactly `dim` numbers
+fn parse_vector(line: &str, dim: usize) -> Option<WordVec> {
+    let vec = line.split_whitespace().skip(1).map(|x| x.parse::<f32>().ok()).collect::<Option<WordVec>>()?;
+    (vec.len() == dim).then_some(vec)
+}
+
+/// Reads a cached index when its header matches the file's `stamp`
+fn read_index(path: &PathBuf, stamp: (u64, u128)) -> Option<(usize, Vec<(String, u64)>)> {
+    let mut lines = BufReader::new(File::open(path).ok()?).lines();
+    let header = lines.next()?.ok()?;
+    let fields: Vec<&str> = header.split(' ').collect();
+    let [magic, version, len, modified, dim, count] = fields[..] else { return None };
+    if magic != INDEX_MAGIC
+        || version.parse::<u32>().ok()? != INDEX_VERSION
+        || (len.parse().ok()?, modified.parse().ok()?) != stamp
+    {
+        return None;
+    }
+    let count: usize = count.parse().ok()?;
+    let mut entries = Vec::with_capacity(count);
+    for line in lines {
+        let line = line.ok()?;
+        let (offset, word) = line.split_once(' ')?;
+        entries.push((word.to_string(), offset.parse().ok()?));
+    }
+    (entries.len() == count).then_some((dim.parse().ok()?, entries))
+}
+
+/// Caches an index as a header line followed by `offset word` lines
+fn write_index(path: &PathBuf, stamp: (u64, u128), dim: usize, entries: &[(String, u64)]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "{} {} {} {} {} {}", INDEX_MAGIC, INDEX_VERSION, stamp.0, stamp.1, dim, entries.len())?;
+    for (word, offset) in entries {
+        writeln!(out, "{} {}", offset, word)?;
+    }
+    out.flush()
+}
@@ -0,0 +1,156 @@
+//! Fallback from inflected forms to their lemma for words missing from a
+//! vocabulary, e.g. `running` → `run` or `wolves` → `wolf`, by stripping
+//! English suffixes (plus a few common irregular forms) rather than with a
+//! full morphological analyzer.
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+/// Irregular forms suffix stripping cannot undo
+const IRREGULAR: &[(&str, &str)] = &[
+    ("children", "child"),
+    ("men", "man"),
+    ("women", "woman"),
+    ("people", "person"),
+    ("feet", "foot"),
+    ("teeth", "tooth"),
+    ("mice", "mouse"),
+    ("geese", "goose"),
+    ("went", "go"),
+    ("gone", "go"),
+    ("was", "be"),
+    ("were", "be"),
+    ("been", "be"),
+    ("had", "have"),
+    ("did", "do"),
+    ("done", "do"),
+    ("said", "say"),
+    ("made", "make"),
+    ("took", "take"),
+    ("taken", "take"),
+    ("came", "come"),
+    ("saw", "see"),
+    ("seen", "see"),
+    ("knew", "know"),
+    ("known", "know"),
+    ("got", "get"),
+    ("gave", "give"),
+    ("given", "give"),
+    ("found", "find"),
+    ("thought", "think"),
+    ("told", "tell"),
+    ("became", "become"),
+    ("left", "leave"),
+    ("felt", "feel"),
+    ("brought", "bring"),
+    ("began", "begin"),
+    ("kept", "keep"),
+    ("held", "hold"),
+    ("wrote", "write"),
+    ("written", "write"),
+    ("stood", "stand"),
+    ("heard", "hear"),
+    ("ran", "run"),
+    ("better", "good"),
+    ("best", "good"),
+    ("worse", "bad"),
+    ("worst", "bad"),
+];
+
+/// Suffixes and what replaces them, tried in order; the stem left must keep
+/// at least `MIN_STEM` characters
+const SUFFIXES: &[(&str, &str)] = &[
+    ("ies", "y"),
+    ("ied", "y"),
+    ("ves", "f"),
+    ("ves", "fe"),
+    ("es", ""),
+    ("s", ""),
+    ("ing", ""),
+    ("ing", "e"),
+    ("ed", ""),
+    ("ed", "e"),
+    ("iest", "y"),
+    ("ier", "y"),
+    ("est", ""),
+    ("est", "e"),
+    ("er", ""),
+    ("er", "e"),
+    ("ly", ""),
+];
+
+const MIN_STEM: usize = 2;
+
+/// Candidate lemmas of `word`, most likely first
+pub fn lemmas(word: &str) -> Vec<String> {
+    let mut found: Vec<String> = IRREGULAR.iter().filter(|(form, _)| *form == word).map(|(_, l)| l.to_string()).collect();
+    for (suffix, replacement) in SUFFIXES {
+        let Some(stem) = word.strip_suffix(suffix) else { continue };
+        if stem.chars().count() < MIN_STEM || (*suffix == "s" && stem.ends_with('s')) {
+            continue;
+        }
+        found.push(format!("{}{}", stem, replacement));
+        // running → run, stopped → stop, bigger → big
+        let mut chars = stem.chars().rev();
+        if replacement.is_empty() && ["ing", "ed", "er", "est"].contains(suffix) {
+            if let (Some(last), Some(before)) = (chars.next(), chars.next()) {
+                if last == before && !"aeiouls".contains(last) {
+                    found.push(stem[..stem.len() - last.len_utf8()].to_string());
+                }
+            }
+        }
+    }
+    found.dedup();
+    found
+}
+
+/// View of a store that looks a missing word up by its lemma; the row found
+/// keeps its own word, so callers can tell a fallback happened
+pub struct LemmaStore {
+    inner: Box<dyn EmbeddingStore>,
+}
+
+impl LemmaStore {
+    pub fn new(inner: Box<dyn EmbeddingStore>) -> Self {
+        LemmaStore { inner }
+    }
+}
+
+impl EmbeddingStore for LemmaStore {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.inner.id(word).or_else(|| lemmas(word).iter().find_map(|lemma| self.inner.id(lemma)))
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        self.inner.iter()
+    }
+
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        self.inner.cosine_scores(query)
+    }
+
+    fn cosine_scores_batch(&self, queries: &[WordVec]) -> Vec<Vec<f32>> {
+        self.inner.cosine_scores_batch(queries)
+    }
+}
+
+/// Wraps `store` in a `LemmaStore` when the fallback is enabled
+pub fn with_lemmas(store: Box<dyn EmbeddingStore>, enabled: bool) -> Box<dyn EmbeddingStore> {
+    if enabled {
+        Box::new(LemmaStore::new(store))
+    } else {
+        store
+    }
+}
@@ -0,0 +1,101 @@
+//! Shared loading, storage and search code for the GloVe word-sum demos.
+//!
+//! Build with `rustc --edition 2021 --crate-type=lib --crate-name glove glove/lib.rs`
+//! and link the binaries with `--extern glove=libglove.rlib`.
+
+pub mod align;
+pub mod all_pairs;
+pub mod analogy;
+pub mod annoy;
+pub mod args;
+#[cfg(feature = "ndarray")]
+pub mod arrays;
+pub mod axis;
+pub mod background;
+pub mod bench;
+pub mod binary;
+#[cfg(feature = "blas")]
+pub mod blas;
+pub mod cache;
+pub mod calibrate;
+pub mod config;
+pub mod cooccur;
+pub mod correct;
+pub mod counterfit;
+pub mod coverage;
+pub mod debias;
+pub mod dedup;
+pub mod dense;
+pub mod drift;
+pub mod error;
+pub mod explain;
+pub mod export;
+pub mod expression;
+pub mod formats;
+pub mod fuse;
+pub mod gguf;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod graph;
+pub mod half;
+pub mod hdf5;
+pub mod index;
+pub mod input;
+pub mod ivf;
+pub mod json;
+pub mod lazy;
+pub mod lemma;
+pub mod load;
+pub mod lsh;
+pub mod magnitude;
+pub mod mask;
+pub mod merge;
+pub mod metric;
+#[cfg(all(unix, target_pointer_width = "64", target_endian = "little"))]
+pub mod mmap;
+pub mod model_stats;
+pub mod models;
+pub mod numberbatch;
+pub mod odd_one_out;
+pub mod parquet;
+pub mod path;
+pub mod pattern;
+pub mod pca;
+pub mod postprocess;
+pub mod pq;
+pub mod progress;
+pub mod quantize;
+pub mod remote;
+pub mod retrofit;
+pub mod rng;
+pub mod safetensors;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb;
+pub mod search;
+pub mod sentence;
+pub mod server;
+pub mod session;
+pub mod shards;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod stopwords;
+pub mod store;
+pub mod subword;
+pub mod suggest;
+#[cfg(test)]
+mod testing;
+pub mod train;
+pub mod vector;
+pub mod vector_file;
+pub mod weat;
+
+pub use dense::DenseStore;
+pub use error::{Error, Result};
+pub use formats::{Embeddings, Format};
+pub use input::Source;
+pub use load::{load_glove_source, load_glove_vectors, LoadOptions, Precision};
+pub use store::{open_store, EmbeddingStore};
+
+/// Define a type alias for a word vector for clarity
+pub type WordVec = Vec<f32>;
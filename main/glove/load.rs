@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, Read};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::thread;
+
+use crate::dense::DenseStore;
+use crate::error::{Error, Result};
+use crate::formats::{is_count_dim_header, read_binary_rows, Format};
+use crate::half::HalfStore;
+use crate::input::Source;
+use crate::merge::{Conflict, Merger};
+use crate::numberbatch::{row_word, DEFAULT_LANGUAGE};
+use crate::pq::PqStore;
+use crate::progress::Progress;
+use crate::quantize::QuantizedStore;
+use crate::shards::{is_sharded, shard_paths};
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// How vectors are held in memory once loaded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    F32,
+    /// Half floats widened while scoring; see `HalfStore`
+    F16,
+    /// Int8 codes with a per-vector scale; see `QuantizedStore`
+    Int8,
+    /// Product-quantization codes: `subquantizers` one-byte codes per
+    /// vector (default: a quarter of the dimensions), each choosing among
+    /// `2^bits` centroids; see `PqStore`
+    Pq { subquantizers: Option<usize>, bits: u8 },
+}
+
+impl Precision {
+    pub fn parse(name: &str) -> Option<Precision> {
+        match name {
+            "f32" => Some(Precision::F32),
+            "f16" => Some(Precision::F16),
+            "int8" => Some(Precision::Int8),
+            "pq" => Some(Precision::Pq { subquantizers: None, bits: 8 }),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for parsing a GloVe text file
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// Suppress the progress line
+    pub quiet: bool,
+    pub precision: Precision,
+    /// Only keep these words, if set
+    pub words: Option<HashSet<String>>,
+    /// Stop after this many rows; GloVe files are sorted by frequency, so
+    /// this keeps the most common words
+    pub max_vocab: Option<usize>,
+    /// Fail on the first malformed text line instead of skipping it; skipped
+    /// lines are counted and summarized on stderr
+    pub strict: bool,
+    /// Keep only the ConceptNet URI rows of this language, under their plain
+    /// terms; English if unset. See `numberbatch`.
+    pub language: Option<String>,
+    /// The tensor of a GGUF file to read; `token_embd.weight` if unset. See `gguf`.
+    pub tensor: Option<String>,
+    /// What a word found in several shards keeps. See `shards`.
+    pub conflict: Conflict,
+    /// Threads parsing text files; one per core if unset
+    pub threads: Option<usize>,
+    /// Counts the bytes read, for watching the load from another thread. See `background`.
+    pub progress: Option<Arc<AtomicU64>>,
+}
+
+/// Loads a GloVe-style vector file (optionally `.gz`/`.zst` compressed) into memory;
+/// any other format `Format::sniff` recognizes is read as well
+pub fn load_glove_vectors(path: &str) -> Result<DenseStore> {
+    load_glove_source(&Source::from(path), &LoadOptions::default())
+}
+
+/// Loads GloVe-style vectors from a file, archive member or set of shards into memory
+pub fn load_glove_source(source: &Source, options: &LoadOptions) -> Result<DenseStore> {
+    if let Source::Path(path) = source {
+        if is_sharded(path) {
+            return load_shards(&shard_paths(path)?, options);
+        }
+    }
+    let mut vectors = DenseStore::new();
+    for_each_row(source, options, |word, vec| vectors.insert(word.to_string(), vec))?;
+    Ok(vectors)
+}
+
+/// Loads GloVe-style vectors, converting each row to half precision as it is parsed
+pub fn load_glove_half(source: &Source, options: &LoadOptions) -> Result<HalfStore> {
+    let mut vectors = HalfStore::new();
+    for_each_row(source, options, |word, vec| vectors.insert(word.to_string(), &vec))?;
+    Ok(vectors)
+}
+
+/// Loads GloVe-style vectors, quantizing each row to int8 as it is parsed
+/// so the full-precision matrix is never held in memory
+pub fn load_glove_quantized(source: &Source, options: &LoadOptions) -> Result<QuantizedStore> {
+    let mut vectors = QuantizedStore::new();
+    for_each_row(source, options, |word, vec| vectors.insert(word.to_string(), &vec))?;
+    Ok(vectors)
+}
+
+/// Loads GloVe-style vectors into product-quantization codes; only the
+/// codebook training rows are ever held at full precision
+pub fn load_glove_pq(
+    source: &Source,
+    options: &LoadOptions,
+    subquantizers: Option<usize>,
+    bits: u8,
+) -> Result<PqStore> {
+    let mut vectors = PqStore::new(subquantizers, bits);
+    for_each_row(source, options, |word, vec| vectors.insert(word.to_string(), &vec))?;
+    Ok(vectors.finish())
+}
+
+/// Loads every shard and merges them into one vocabulary by the `conflict`
+/// policy; `max_vocab` is shared by the shards, in order
+fn load_shards(paths: &[String], options: &LoadOptions) -> Result<DenseStore> {
+    let mut merger = Merger::new(options.conflict);
+    let mut loaded = 0;
+    for path in paths {
+        let max_vocab = options.max_vocab.map(|max| max.saturating_sub(loaded));
+        if max_vocab == Some(0) {
+            break;
+        }
+        let shard = load_glove_source(&Source::from(path.as_str()), &LoadOptions { max_vocab, ..options.clone() })?;
+        loaded += shard.len();
+        merger.add(&shard).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e)))?;
+    }
+    Ok(merger.finish())
+}
+
+/// Applies the `LoadOptions` filters to parsed rows and reports progress,
+/// shared by the text parser and the binary format readers
+pub(crate) struct RowSink<'a> {
+    options: &'a LoadOptions,
+    progress: Progress,
+    rows: usize,
+    /// Malformed lines skipped so far, and the first few of their errors
+    malformed: usize,
+    examples: Vec<Error>,
+    add: &'a mut dyn FnMut(&str, WordVec),
+}
+
+/// Malformed lines quoted in the summary
+const MALFORMED_EXAMPLES: usize = 3;
+
+impl RowSink<'_> {
+    /// Whether `max_vocab` rows have been seen
+    pub fn full(&self) -> bool {
+        self.options.max_vocab.is_some_and(|max| self.rows >= max)
+    }
+
+    /// Records `bytes` of input consumed
+    pub fn read(&mut self, bytes: usize) {
+        self.progress.line(bytes);
+    }
+
+    /// Counts a row, returning whether its word passes the `words` filter
+    pub fn wants(&mut self, word: &str) -> bool {
+        self.rows += 1;
+        self.options.words.as_ref().is_none_or(|keep| keep.contains(word))
+    }
+
+    pub fn add(&mut self, word: &str, vec: WordVec) {
+        (self.add)(word, vec)
+    }
+
+    /// The GGUF tensor asked for, if any
+    pub fn tensor(&self) -> Option<&str> {
+        self.options.tensor.as_deref()
+    }
+
+    /// Fails with a malformed line's error in strict mode, else counts it
+    fn malformed(&mut self, error: Error) -> Result<()> {
+        if self.options.strict {
+            return Err(error);
+        }
+        self.malformed += 1;
+        if self.examples.len() < MALFORMED_EXAMPLES {
+            self.examples.push(error);
+        }
+        Ok(())
+    }
+}
+
+/// Parses every row of the source, in whichever format it is, and hands it to `add`
+fn for_each_row(
+    source: &Source,
+    options: &LoadOptions,
+    mut add: impl FnMut(&str, WordVec),
+) -> Result<()> {
+    let mut reader = source.open()?;
+    let format = Format::sniff(reader.fill_buf()?);
+    let mut sink = RowSink {
+        options,
+        progress: Progress::new(source.text_len(), !options.quiet).counting(options.progress.clone()),
+        rows: 0,
+        malformed: 0,
+        examples: Vec::new(),
+        add: &mut add,
+    };
+
+    let result = match format {
+        Format::GloveText | Format::Word2VecText => read_text_rows(reader.as_mut(), &mut sink),
+        _ => read_binary_rows(format, source, reader.as_mut(), &mut sink).map_err(Error::from),
+    };
+    sink.progress.finish();
+    result?;
+
+    if sink.malformed > 0 {
+        let examples: Vec<String> = sink.examples.iter().map(Error::to_string).collect();
+        let more = sink.malformed - examples.len();
+        eprintln!(
+            "Skipped {} malformed line{} in {}: {}{}",
+            sink.malformed,
+            if sink.malformed == 1 { "" } else { "s" },
+            source,
+            examples.join("; "),
+            if more > 0 { format!("; and {} more", more) } else { String::new() }
+        );
+    }
+    Ok(())
+}
+
+/// Parses `word f1 f2 ...` lines, skipping a word2vec `count dim` header.
+/// A line with a component that is not a number, or whose length differs
+/// from the first row's, is malformed: an error naming the line in strict
+/// mode, else skipped and counted.
+///
+/// Parsing the numbers is what loading spends its time on, so the text is
+/// read in line-aligned chunks that are parsed on `threads` threads at once;
+/// the rows are then handed on in file order, so the result does not depend
+/// on the number of threads. Chunks start small and grow, so a small
+/// `max_vocab` does not parse far past the rows it keeps.
+fn read_text_rows(reader: &mut dyn BufRead, sink: &mut RowSink) -> Result<()> {
+    let threads = sink.options.threads.or_else(|| thread::available_parallelism().ok().map(|n| n.get())).unwrap_or(1);
+    let language = sink.options.language.clone().unwrap_or_else(|| DEFAULT_LANGUAGE.to_string());
+    let (words, language) = (sink.options.words.as_ref(), language.as_str());
+    let mut chunk_len = FIRST_CHUNK_BYTES;
+    let mut line = 0;
+    let mut dim = None;
+    while !sink.full() {
+        // Read the next chunk for each thread; a failing decompressor surfaces here as an error
+        let mut chunks = Vec::with_capacity(threads);
+        for _ in 0..threads.max(1) {
+            let chunk = read_chunk(reader, chunk_len)?;
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+            chunk_len = (chunk_len * 2).min(CHUNK_BYTES);
+        }
+        if chunks.is_empty() {
+            break;
+        }
+
+        let parsed: Vec<Vec<(usize, Line)>> = if chunks.len() == 1 {
+            vec![parse_chunk(&chunks[0], line == 0, words, language)]
+        } else {
+            thread::scope(|scope| {
+                let workers: Vec<_> = chunks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, chunk)| scope.spawn(move || parse_chunk(chunk, line == 0 && i == 0, words, language)))
+                    .collect();
+                workers.into_iter().map(|w| w.join().expect("parsing thread panicked")).collect()
+            })
+        };
+
+        for (bytes, parsed) in parsed.into_iter().flatten() {
+            if sink.full() {
+                break;
+            }
+            sink.read(bytes);
+            line += 1;
+            let Line::Row { word, vec } = parsed else { continue };
+            if !sink.wants(&word) {
+                continue;
+            }
+            let vec = match vec {
+                // Caught before `dim`, which a stray word-only line would otherwise fix at 0
+                Some(Ok(vec)) if vec.is_empty() => {
+                    sink.malformed(Error::EmptyRow { line })?;
+                    continue;
+                }
+                Some(Ok(vec)) => vec,
+                Some(Err(token)) => {
+                    sink.malformed(Error::Parse { line, token })?;
+                    continue;
+                }
+                None => unreachable!("rows outside the words filter are not wanted"),
+            };
+            match *dim.get_or_insert(vec.len()) {
+                expected if expected != vec.len() => {
+                    sink.malformed(Error::DimensionMismatch { line: Some(line), expected, found: vec.len() })?
+                }
+                _ => sink.add(&word, vec),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bytes of text in the first chunk, and at most in any later one
+const FIRST_CHUNK_BYTES: usize = 64 << 10;
+const CHUNK_BYTES: usize = 4 << 20;
+
+/// One line of a text file as a parsing thread leaves it
+enum Line {
+    /// The `count dim` header, a blank line, or a ConceptNet row of another language
+    Skipped,
+    /// A row's word and its components, or the first one that is not a
+    /// number; rows the `words` filter drops are not parsed
+    Row { word: String, vec: Option<std::result::Result<WordVec, String>> },
+}
+
+/// Reads about `len` bytes of whole lines (less at the end of the input)
+fn read_chunk(reader: &mut dyn BufRead, len: usize) -> io::Result<String> {
+    let mut bytes = Vec::with_capacity(len + 4096);
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.last().is_some_and(|&b| b != b'\n') {
+        reader.read_until(b'\n', &mut bytes)?;
+    }
+    String::from_utf8(bytes)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))
+}
+
+/// Splits a chunk into lines, each with its length in bytes; `first` is set
+/// for the chunk the file starts with, whose first line may be a header
+fn parse_chunk(chunk: &str, first: bool, words: Option<&HashSet<String>>, language: &str) -> Vec<(usize, Line)> {
+    chunk
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, l)| {
+            if first && i == 0 && is_count_dim_header(l) {
+                return (l.len(), Line::Skipped);
+            }
+            let mut parts = l.split_whitespace();
+            let Some(word) = parts.next().and_then(|word| row_word(word, language)) else {
+                return (l.len(), Line::Skipped);
+            };
+            let vec = words.is_none_or(|keep| keep.contains(word)).then(|| {
+                parts.map(|x| x.parse::<f32>().map_err(|_| x.to_string())).collect()
+            });
+            (l.len(), Line::Row { word: word.to_string(), vec })
+        })
+        .collect()
+}
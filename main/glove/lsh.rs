@@ -0,0 +1,113 @@
+//! Random-hyperplane locality-sensitive hashing (Charikar, 2002).
+//!
+//! Each signature bit records which side of a random hyperplane a vector
+//! falls on; two vectors at angle `θ` disagree on a bit with probability
+//! `θ / π`, so the Hamming distance between signatures estimates the angle.
+//! A query's candidates are the rows whose signatures are closest to its
+//! own in any of several independent tables.
+
+use crate::index::CandidateIndex;
+use crate::rng::Rng;
+use crate::store::EmbeddingStore;
+
+/// Fixed so the same file always gets the same index
+const SEED: u64 = 0x5eed_1a5b_0c7e_2f19;
+
+/// One set of hyperplanes and the signature of every row under it
+struct Table {
+    /// `bits` hyperplane normals of `dim` components each, row-major
+    planes: Vec<f32>,
+    /// `bits / 64` (rounded up) words per row
+    signatures: Vec<u64>,
+}
+
+pub struct LshIndex {
+    bits: usize,
+    dim: usize,
+    candidates: usize,
+    tables: Vec<Table>,
+}
+
+impl LshIndex {
+    /// Hashes every row of `store` into `tables` tables of `bits`-bit
+    /// signatures; each table shortlists its `candidates` closest rows
+    pub fn build(store: &dyn EmbeddingStore, bits: usize, tables: usize, candidates: usize) -> Self {
+        let dim = store.dim();
+        let mut rng = Rng::new(SEED);
+        let mut index = LshIndex { bits, dim, candidates, tables: Vec::with_capacity(tables) };
+        // Nothing to hash; with no tables every query simply has no candidates
+        if dim == 0 || store.is_empty() {
+            return index;
+        }
+        for _ in 0..tables {
+            let planes = (0..bits * dim).map(|_| gaussian(&mut rng)).collect();
+            let mut table = Table { planes, signatures: Vec::with_capacity(store.len() * index.words()) };
+            for (_, vec) in store.iter() {
+                let signature = index.signature(&table, &vec);
+                table.signatures.extend(signature);
+            }
+            index.tables.push(table);
+        }
+        index
+    }
+
+    fn words(&self) -> usize {
+        self.bits.div_ceil(64)
+    }
+
+    fn signature(&self, table: &Table, vec: &[f32]) -> Vec<u64> {
+        let mut signature = vec![0u64; self.words()];
+        for (bit, plane) in table.planes.chunks(self.dim).enumerate() {
+            let side: f32 = plane.iter().zip(vec).map(|(p, x)| p * x).sum();
+            if side >= 0.0 {
+                signature[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        signature
+    }
+}
+
+impl CandidateIndex for LshIndex {
+    fn candidates(&self, query: &[f32]) -> Vec<usize> {
+        let words = self.words();
+        let mut found = Vec::new();
+        for table in &self.tables {
+            let query_signature = self.signature(table, query);
+            let distances: Vec<u32> = table
+                .signatures
+                .chunks(words)
+                .map(|row| row.iter().zip(&query_signature).map(|(a, b)| (a ^ b).count_ones()).sum())
+                .collect();
+
+            // Counting sort on distance: take whole distance levels until the shortlist is full
+            let mut counts = vec![0usize; self.bits + 1];
+            for &d in &distances {
+                counts[d as usize] += 1;
+            }
+            let mut cutoff = 0;
+            let mut taken = 0;
+            while cutoff < self.bits && taken + counts[cutoff] < self.candidates {
+                taken += counts[cutoff];
+                cutoff += 1;
+            }
+            let mut room_at_cutoff = self.candidates.saturating_sub(taken);
+            for (id, &d) in distances.iter().enumerate() {
+                let d = d as usize;
+                if d < cutoff {
+                    found.push(id);
+                } else if d == cutoff && room_at_cutoff > 0 {
+                    found.push(id);
+                    room_at_cutoff -= 1;
+                }
+            }
+        }
+        found
+    }
+}
+
+/// A standard normal sample (Box-Muller)
+pub fn gaussian(rng: &mut Rng) -> f32 {
+    let u = rng.next_f32().max(f32::MIN_POSITIVE);
+    let v = rng.next_f32();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos()
+}
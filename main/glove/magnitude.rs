@@ -0,0 +1,73 @@
+//! Magnitude files (`.magnitude`), the SQLite container of the pymagnitude
+//! library.
+//!
+//! The `magnitude` table holds one row per word in the original file's order:
+//! its `key`, the vector's original length in `magnitude`, and the unit
+//! vector's components as integers in `dim_0`, `dim_1`, ..., scaled by
+//! `10^precision`. The `magnitude_format` table of `key, value` pairs gives
+//! `dim` and `precision`. Vectors are read back at their original length, so
+//! sums of words match the text file the database was converted from; the
+//! subword and approximate-search tables are not used.
+//!
+//! Reading goes through the system `libsqlite3`, so it needs a build with the
+//! `sqlite` feature, and the file must be a plain, uncompressed path.
+
+use std::io;
+
+use crate::input::Source;
+use crate::load::RowSink;
+
+/// First bytes of every SQLite database
+pub(crate) const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+#[cfg(feature = "sqlite")]
+pub(crate) fn read_magnitude(source: &Source, sink: &mut RowSink) -> io::Result<()> {
+    use crate::sqlite::Db;
+
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let Source::Path(path) = source else {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, ".magnitude files cannot be read from archives"));
+    };
+    let db = Db::open_readonly(path)?;
+
+    let mut format = db.prepare("SELECT key, value FROM magnitude_format").map_err(|_| {
+        invalid(format!("{} is a SQLite database but not a Magnitude file; try --backend sqlite", path))
+    })?;
+    let (mut dim, mut precision) = (None, None);
+    while format.step()? {
+        match format.column_text(0).as_str() {
+            "dim" => dim = Some(format.column_int(1) as usize),
+            "precision" => precision = Some(format.column_int(1) as i32),
+            _ => {}
+        }
+    }
+    drop(format);
+    let (Some(dim), Some(precision)) = (dim, precision) else {
+        return Err(invalid("the Magnitude file gives no dim or precision".to_string()));
+    };
+
+    let columns: Vec<String> = (0..dim).map(|i| format!("dim_{}", i)).collect();
+    let sql = format!("SELECT key, magnitude, {} FROM magnitude ORDER BY rowid", columns.join(", "));
+    let mut rows = db.prepare(&sql)?;
+    let scale = 10f64.powi(precision);
+    while rows.step()? {
+        if sink.full() {
+            break;
+        }
+        let word = rows.column_text(0);
+        // Roughly what the row takes on disk, for the progress line
+        sink.read(word.len() + 8 + dim * 4);
+        if !sink.wants(&word) {
+            continue;
+        }
+        let length = rows.column_double(1);
+        let vec = (0..dim).map(|i| (rows.column_int(i as i32 + 2) as f64 / scale * length) as f32).collect();
+        sink.add(&word, vec);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub(crate) fn read_magnitude(_source: &Source, _sink: &mut RowSink) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reading .magnitude files needs a build with the sqlite feature"))
+}
@@ -0,0 +1,116 @@
+//! Query-time dimension masking for interpretability experiments.
+
+use std::borrow::Cow;
+use std::fs;
+
+use crate::store::{EmbeddingStore, Entry};
+
+/// Set of (0-based) dimensions to zero out during scoring
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DimMask {
+    dims: Vec<usize>,
+}
+
+impl DimMask {
+    /// Parses a list such as `5,17,30-40`; ranges are inclusive
+    pub fn parse(spec: &str) -> Result<DimMask, String> {
+        let mut dims = Vec::new();
+        for part in spec.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+            let bad = || format!("invalid dimension '{}' in mask", part);
+            match part.split_once('-') {
+                Some((start, end)) => {
+                    let start: usize = start.parse().map_err(|_| bad())?;
+                    let end: usize = end.parse().map_err(|_| bad())?;
+                    if start > end {
+                        return Err(bad());
+                    }
+                    dims.extend(start..=end);
+                }
+                None => dims.push(part.parse().map_err(|_| bad())?),
+            }
+        }
+        dims.sort_unstable();
+        dims.dedup();
+        Ok(DimMask { dims })
+    }
+
+    /// Reads a mask file: the same syntax as `parse`, across any number of
+    /// lines, with `#` starting a comment
+    pub fn from_file(path: &str) -> Result<DimMask, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let spec: Vec<&str> = text.lines().map(|l| l.split('#').next().unwrap_or("")).collect();
+        DimMask::parse(&spec.join(","))
+    }
+
+    pub fn dims(&self) -> &[usize] {
+        &self.dims
+    }
+
+    /// Zeroes the masked dimensions of `vec` in place
+    pub fn apply(&self, vec: &mut [f32]) {
+        for &d in &self.dims {
+            if let Some(x) = vec.get_mut(d) {
+                *x = 0.0;
+            }
+        }
+    }
+}
+
+/// View of a store with some dimensions zeroed in every vector it returns;
+/// the wrapped store itself is left untouched
+pub struct MaskedStore {
+    inner: Box<dyn EmbeddingStore>,
+    mask: DimMask,
+}
+
+impl MaskedStore {
+    /// Fails if the mask names a dimension the store does not have
+    pub fn new(inner: Box<dyn EmbeddingStore>, mask: DimMask) -> Result<Self, String> {
+        if let Some(&max) = mask.dims.last() {
+            if max >= inner.dim() {
+                return Err(format!("mask dimension {} is out of range for {}-d vectors", max, inner.dim()));
+            }
+        }
+        Ok(MaskedStore { inner, mask })
+    }
+
+    fn masked<'a>(&self, vec: Cow<'a, [f32]>) -> Cow<'a, [f32]> {
+        let mut vec = vec.into_owned();
+        self.mask.apply(&mut vec);
+        Cow::Owned(vec)
+    }
+}
+
+impl EmbeddingStore for MaskedStore {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.inner.id(word)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let (word, vec) = self.inner.get_by_id(id)?;
+        Some((word, self.masked(vec)))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        Box::new(self.inner.iter().map(|(word, vec)| (word, self.masked(vec))))
+    }
+}
+
+/// Wraps `store` in a `MaskedStore` when a mask is given
+pub fn with_mask(
+    store: Box<dyn EmbeddingStore>,
+    mask: Option<&DimMask>,
+) -> Result<Box<dyn EmbeddingStore>, String> {
+    match mask {
+        Some(mask) => Ok(Box::new(MaskedStore::new(store, mask.clone())?)),
+        None => Ok(store),
+    }
+}
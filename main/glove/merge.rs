@@ -0,0 +1,107 @@
+//! Combining several embedding files into one vocabulary.
+
+use std::collections::HashMap;
+
+use crate::dense::DenseStore;
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// What to do with a word found in more than one file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Conflict {
+    /// The first file's vector wins
+    #[default]
+    KeepFirst,
+    /// The vector from the file added last wins
+    KeepSecond,
+    /// The mean of every file's vector
+    Average,
+}
+
+impl Conflict {
+    pub const NAMES: &'static [&'static str] = &["keep-first", "keep-second", "average"];
+
+    pub fn parse(name: &str) -> Option<Conflict> {
+        match name {
+            "keep-first" => Some(Conflict::KeepFirst),
+            "keep-second" | "keep-last" => Some(Conflict::KeepSecond),
+            "average" => Some(Conflict::Average),
+            _ => None,
+        }
+    }
+}
+
+/// Accumulates stores one at a time, so only one input needs to be open at once
+#[derive(Default)]
+pub struct Merger {
+    conflict: Conflict,
+    dim: Option<usize>,
+    words: Vec<String>,
+    vectors: Vec<WordVec>,
+    /// How many files each word was found in
+    counts: Vec<usize>,
+    index: HashMap<String, usize>,
+}
+
+/// What adding one store changed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Added {
+    /// Words not seen in earlier stores
+    pub new: usize,
+    /// Words that were, resolved by the conflict policy
+    pub conflicts: usize,
+}
+
+impl Merger {
+    pub fn new(conflict: Conflict) -> Merger {
+        Merger { conflict, ..Merger::default() }
+    }
+
+    /// Adds every word of `store`; all stores must have the same dimensionality
+    pub fn add(&mut self, store: &dyn EmbeddingStore) -> Result<Added, String> {
+        match self.dim {
+            Some(dim) if dim != store.dim() => {
+                return Err(format!("{} dimensions, expected {} like the earlier files", store.dim(), dim));
+            }
+            _ => self.dim = Some(store.dim()),
+        }
+
+        let mut added = Added::default();
+        for (word, vec) in store.iter() {
+            let Some(&id) = self.index.get(word.as_ref()) else {
+                self.index.insert(word.to_string(), self.words.len());
+                self.words.push(word.into_owned());
+                self.vectors.push(vec.into_owned());
+                self.counts.push(1);
+                added.new += 1;
+                continue;
+            };
+            added.conflicts += 1;
+            self.counts[id] += 1;
+            match self.conflict {
+                Conflict::KeepFirst => {}
+                Conflict::KeepSecond => self.vectors[id] = vec.into_owned(),
+                Conflict::Average => {
+                    for (sum, x) in self.vectors[id].iter_mut().zip(vec.iter()) {
+                        *sum += x;
+                    }
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// The merged vocabulary, in order of first appearance
+    pub fn finish(self) -> DenseStore {
+        let mut merged = DenseStore::new();
+        for ((word, mut vec), count) in self.words.into_iter().zip(self.vectors).zip(self.counts) {
+            if self.conflict == Conflict::Average && count > 1 {
+                for x in &mut vec {
+                    *x /= count as f32;
+                }
+            }
+            merged.insert(word, vec);
+        }
+        merged
+    }
+}
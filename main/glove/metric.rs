@@ -0,0 +1,146 @@
+//! How candidates are compared with a query vector. Every search takes a
+//! `&dyn Metric`: one of the `BuiltinMetric`s that `--metric` names, a type
+//! implementing the trait, or a closure wrapped in `Similarity` or `Distance`,
+//! e.g. `Distance(|a: &[f32], b: &[f32]| chebyshev(a, b))`.
+
+use crate::store::EmbeddingStore;
+use crate::vector::{angular_from_cosine, cosine_similarity, dot_product, euclidean_distance, manhattan_distance};
+use crate::WordVec;
+
+/// A way of scoring a candidate vector against the query
+pub trait Metric {
+    /// Score of candidate `b` for query `a`
+    fn score(&self, a: &[f32], b: &[f32]) -> f32;
+
+    /// Similarities rank high-to-low, distances low-to-high
+    fn higher_is_better(&self) -> bool;
+
+    /// Scores every row of `store` against `query`, in row order. Metrics
+    /// built on the cosine override this to use the store's own (possibly
+    /// faster or indexed) cosine scan.
+    fn score_rows(&self, query: &[f32], store: &dyn EmbeddingStore) -> Vec<f32> {
+        store.iter().map(|(_, vec)| self.score(query, &vec)).collect()
+    }
+
+    /// `score_rows` for several queries
+    fn score_rows_batch(&self, queries: &[WordVec], store: &dyn EmbeddingStore) -> Vec<Vec<f32>> {
+        queries.iter().map(|query| self.score_rows(query, store)).collect()
+    }
+}
+
+/// A closure scoring a candidate against the query, higher meaning closer
+#[derive(Clone, Copy, Debug)]
+pub struct Similarity<F>(pub F);
+
+impl<F: Fn(&[f32], &[f32]) -> f32> Metric for Similarity<F> {
+    fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        (self.0)(a, b)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// A closure measuring how far a candidate is from the query, lower meaning closer
+#[derive(Clone, Copy, Debug)]
+pub struct Distance<F>(pub F);
+
+impl<F: Fn(&[f32], &[f32]) -> f32> Metric for Distance<F> {
+    fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        (self.0)(a, b)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        false
+    }
+}
+
+/// The metrics `--metric` names
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BuiltinMetric {
+    #[default]
+    Cosine,
+    Euclidean,
+    Manhattan,
+    Dot,
+    Angular,
+}
+
+impl BuiltinMetric {
+    pub const NAMES: &'static [&'static str] = &["cosine", "euclidean", "manhattan", "dot", "angular"];
+    pub const ALL: &'static [BuiltinMetric] = &[
+        BuiltinMetric::Cosine,
+        BuiltinMetric::Euclidean,
+        BuiltinMetric::Manhattan,
+        BuiltinMetric::Dot,
+        BuiltinMetric::Angular,
+    ];
+
+    pub fn parse(name: &str) -> Option<BuiltinMetric> {
+        match name {
+            "cosine" => Some(BuiltinMetric::Cosine),
+            "euclidean" => Some(BuiltinMetric::Euclidean),
+            "manhattan" => Some(BuiltinMetric::Manhattan),
+            "dot" => Some(BuiltinMetric::Dot),
+            "angular" => Some(BuiltinMetric::Angular),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinMetric::Cosine => "cosine",
+            BuiltinMetric::Euclidean => "euclidean",
+            BuiltinMetric::Manhattan => "manhattan",
+            BuiltinMetric::Dot => "dot",
+            BuiltinMetric::Angular => "angular",
+        }
+    }
+
+    /// What a score is called when printed
+    pub fn score_label(self) -> &'static str {
+        match self {
+            BuiltinMetric::Cosine => "similarity",
+            BuiltinMetric::Dot => "dot product",
+            _ => "distance",
+        }
+    }
+}
+
+impl Metric for BuiltinMetric {
+    fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            BuiltinMetric::Cosine => cosine_similarity(a, b),
+            BuiltinMetric::Euclidean => euclidean_distance(a, b),
+            BuiltinMetric::Manhattan => manhattan_distance(a, b),
+            BuiltinMetric::Dot => dot_product(a, b),
+            BuiltinMetric::Angular => angular_from_cosine(cosine_similarity(a, b)),
+        }
+    }
+
+    fn higher_is_better(&self) -> bool {
+        matches!(self, BuiltinMetric::Cosine | BuiltinMetric::Dot)
+    }
+
+    fn score_rows(&self, query: &[f32], store: &dyn EmbeddingStore) -> Vec<f32> {
+        match self {
+            BuiltinMetric::Cosine => store.cosine_scores(query),
+            BuiltinMetric::Angular => store.cosine_scores(query).into_iter().map(angular_from_cosine).collect(),
+            _ => store.iter().map(|(_, vec)| self.score(query, &vec)).collect(),
+        }
+    }
+
+    fn score_rows_batch(&self, queries: &[WordVec], store: &dyn EmbeddingStore) -> Vec<Vec<f32>> {
+        match self {
+            BuiltinMetric::Cosine => store.cosine_scores_batch(queries),
+            BuiltinMetric::Angular => {
+                let mut rows = store.cosine_scores_batch(queries);
+                rows.iter_mut().flatten().for_each(|s| *s = angular_from_cosine(*s));
+                rows
+            }
+            _ => queries.iter().map(|query| self.score_rows(query, store)).collect(),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::{c_int, c_void};
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+use crate::binary::{parse_header, HEADER_LEN};
+use crate::store::{EmbeddingStore, Entry};
+
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: i64)
+        -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+/// A binary vector file mapped into memory; only the word index is
+/// built up front, vectors are paged in by the OS on first access
+pub struct MmapStore {
+    ptr: *const u8,
+    len: usize,
+    dim: usize,
+    /// (start, end) byte range of each word in the mapping
+    words: Vec<(usize, usize)>,
+    index: HashMap<String, usize>,
+}
+
+impl MmapStore {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len < HEADER_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "file too small"));
+        }
+
+        let ptr = unsafe { mmap(ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut store = MmapStore {
+            ptr: ptr as *const u8,
+            len,
+            dim: 0,
+            words: Vec::new(),
+            index: HashMap::new(),
+        };
+        store.read_word_table()?;
+        Ok(store)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn read_word_table(&mut self) -> io::Result<()> {
+        let truncated = || io::Error::new(io::ErrorKind::UnexpectedEof, "truncated word table");
+        let header = parse_header(self.bytes())?;
+        // Each word takes at least its 4-byte length, which bounds how many the file can hold
+        let capacity = header.count.min((self.len - header.words_offset()) / 4);
+        let mut words = Vec::with_capacity(capacity);
+        let mut index = HashMap::with_capacity(capacity);
+
+        let bytes = self.bytes();
+        let mut pos = header.words_offset();
+        for id in 0..header.count {
+            let len_bytes = bytes.get(pos..pos + 4).ok_or_else(truncated)?;
+            let word_len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+            let start = pos + 4;
+            let end = start.checked_add(word_len).ok_or_else(truncated)?;
+            let word = bytes.get(start..end).ok_or_else(truncated)?;
+            let word = std::str::from_utf8(word)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            index.insert(word.to_string(), id);
+            words.push((start, end));
+            pos = end;
+        }
+
+        self.dim = header.dim;
+        self.words = words;
+        self.index = index;
+        Ok(())
+    }
+
+    fn word(&self, id: usize) -> &str {
+        let (start, end) = self.words[id];
+        // Validated as UTF-8 when the word table was read
+        unsafe { std::str::from_utf8_unchecked(&self.bytes()[start..end]) }
+    }
+
+    fn vector(&self, id: usize) -> &[f32] {
+        // The header is 4-byte aligned and the mapping is page aligned,
+        // so every row starts on an f32 boundary; `parse_header` checked that
+        // the file holds all `count` rows, and `id` is one of them
+        unsafe {
+            let row = self.ptr.add(HEADER_LEN + id * self.dim * 4) as *const f32;
+            std::slice::from_raw_parts(row, self.dim)
+        }
+    }
+}
+
+impl Drop for MmapStore {
+    fn drop(&mut self) {
+        unsafe {
+            munmap(self.ptr as *mut c_void, self.len);
+        }
+    }
+}
+
+impl EmbeddingStore for MmapStore {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.index.get(word).copied()
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        if id >= self.words.len() {
+            return None;
+        }
+        Some((Cow::Borrowed(self.word(id)), Cow::Borrowed(self.vector(id))))
+    }
+}
@@ -0,0 +1,167 @@
+//! Summary statistics of a vector file, for a quick check that a model
+//! loaded as expected and for comparing the geometry of two models.
+//!
+//! - Norms: their spread, and how many vectors are all zeros.
+//! - Mean vector: its length relative to the average norm; large values mean
+//!   every vector shares a common direction (as in raw GloVe).
+//! - Isotropy: `min_c Z(c) / max_c Z(c)` with `Z(c) = Σ_w exp(c · w)` over
+//!   the principal directions `c` (Mu and Viswanath, 2018); 1 means the
+//!   vectors spread evenly in every direction.
+//! - Hubness: how often each word is among the 10 nearest neighbors of the
+//!   others (its k-occurrence); a strongly right-skewed distribution means a
+//!   few "hub" words crowd into many neighbor lists.
+//!
+//! Isotropy and hubness are estimated on evenly spaced samples of rows, so
+//! they take seconds even for millions of words.
+
+use std::fmt;
+
+use crate::pca::{sample_rows, Pca};
+use crate::store::EmbeddingStore;
+use crate::vector::norm;
+
+/// Rows the principal directions and the isotropy sums are computed on
+const ISOTROPY_SAMPLE: usize = 10_000;
+
+/// Rows whose neighbor lists (among each other) measure hubness
+const HUBNESS_SAMPLE: usize = 2_000;
+
+/// Neighbors per word when counting k-occurrences
+const HUBNESS_K: usize = 10;
+
+/// Hubs listed in the report
+const TOP_HUBS: usize = 10;
+
+/// What `stats` reports about a store
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelStats {
+    pub words: usize,
+    pub dim: usize,
+    /// Vectors with every component zero
+    pub zero_vectors: usize,
+    /// Minimum, 25th percentile, median, 75th percentile and maximum norm
+    pub norm_quartiles: [f32; 5],
+    pub mean_norm: f32,
+    /// Length of the mean vector
+    pub mean_vector_norm: f32,
+    pub isotropy: f32,
+    /// Skewness of the k-occurrence distribution in the hubness sample
+    pub hubness_skew: f32,
+    /// Sampled words most often among the others' nearest neighbors, with their counts
+    pub hubs: Vec<(String, usize)>,
+}
+
+/// Computes the statistics of `store`; norms and the mean cover every row
+pub fn model_stats(store: &dyn EmbeddingStore) -> ModelStats {
+    let dim = store.dim();
+    let mut norms = Vec::with_capacity(store.len());
+    let mut sum = vec![0.0f64; dim];
+    for (_, vec) in store.iter() {
+        norms.push(norm(&vec));
+        for (s, x) in sum.iter_mut().zip(vec.iter()) {
+            *s += *x as f64;
+        }
+    }
+    let count = norms.len().max(1);
+    let mean: Vec<f32> = sum.iter().map(|s| (s / count as f64) as f32).collect();
+    let zero_vectors = norms.iter().filter(|&&n| n == 0.0).count();
+    let mean_norm = (norms.iter().map(|&n| n as f64).sum::<f64>() / count as f64) as f32;
+    norms.sort_by(|a, b| a.total_cmp(b));
+    let quartile = |q: f32| norms.get(((norms.len().saturating_sub(1)) as f32 * q).round() as usize).copied();
+    let norm_quartiles = [0.0, 0.25, 0.5, 0.75, 1.0].map(|q| quartile(q).unwrap_or(0.0));
+
+    let (hubness_skew, hubs) = hubness(store);
+    ModelStats {
+        words: store.len(),
+        dim,
+        zero_vectors,
+        norm_quartiles,
+        mean_norm,
+        mean_vector_norm: norm(&mean),
+        isotropy: isotropy(&sample_rows(store, ISOTROPY_SAMPLE), dim),
+        hubness_skew,
+        hubs,
+    }
+}
+
+/// Mu and Viswanath's isotropy measure over `rows`
+pub fn isotropy(rows: &[Vec<f32>], dim: usize) -> f32 {
+    if rows.is_empty() {
+        return 1.0;
+    }
+    let pca = Pca::fit(rows, dim);
+    let partitions: Vec<f64> = pca
+        .components
+        .iter()
+        .map(|c| rows.iter().map(|row| (c.iter().zip(row).map(|(a, b)| a * b).sum::<f32>() as f64).exp()).sum())
+        .collect();
+    let min = partitions.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = partitions.iter().copied().fold(0.0, f64::max);
+    if max > 0.0 && max.is_finite() {
+        (min / max) as f32
+    } else {
+        0.0
+    }
+}
+
+/// The skewness of the k-occurrence counts among a sample of rows, and the
+/// sampled words with the highest counts
+fn hubness(store: &dyn EmbeddingStore) -> (f32, Vec<(String, usize)>) {
+    let len = store.len();
+    let sample = HUBNESS_SAMPLE.min(len);
+    let rows: Vec<(String, Vec<f32>)> = (0..sample)
+        .filter_map(|i| store.get_by_id(i * len / sample))
+        .map(|(word, vec)| {
+            let n = norm(&vec).max(f32::MIN_POSITIVE);
+            (word.into_owned(), vec.iter().map(|x| x / n).collect())
+        })
+        .collect();
+    let k = HUBNESS_K.min(rows.len().saturating_sub(1));
+    if k == 0 {
+        return (0.0, Vec::new());
+    }
+
+    let mut occurrences = vec![0usize; rows.len()];
+    let mut scores = Vec::with_capacity(rows.len());
+    for (i, (_, a)) in rows.iter().enumerate() {
+        scores.clear();
+        let others = rows.iter().enumerate().filter(|&(j, _)| j != i);
+        scores.extend(others.map(|(j, (_, b))| (j, a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>())));
+        scores.select_nth_unstable_by(k - 1, |x, y| y.1.total_cmp(&x.1));
+        for &(j, _) in &scores[..k] {
+            occurrences[j] += 1;
+        }
+    }
+
+    let n = occurrences.len() as f64;
+    let mean = occurrences.iter().sum::<usize>() as f64 / n;
+    let moment = |p: i32| occurrences.iter().map(|&o| (o as f64 - mean).powi(p)).sum::<f64>() / n;
+    let variance = moment(2);
+    let skew = if variance > 0.0 { moment(3) / variance.powf(1.5) } else { 0.0 };
+
+    let mut ranked: Vec<usize> = (0..rows.len()).collect();
+    ranked.sort_by(|&a, &b| occurrences[b].cmp(&occurrences[a]).then(a.cmp(&b)));
+    let hubs = ranked.into_iter().take(TOP_HUBS).map(|i| (rows[i].0.clone(), occurrences[i])).collect();
+    (skew as f32, hubs)
+}
+
+impl fmt::Display for ModelStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Vocabulary: {} words, {} dimensions", self.words, self.dim)?;
+        let [min, q1, median, q3, max] = self.norm_quartiles;
+        writeln!(
+            f,
+            "Norms: min {:.4}, 25% {:.4}, median {:.4}, 75% {:.4}, max {:.4}, mean {:.4}",
+            min, q1, median, q3, max, self.mean_norm
+        )?;
+        if self.zero_vectors > 0 {
+            writeln!(f, "Zero vectors: {}", self.zero_vectors)?;
+        }
+        let ratio = self.mean_vector_norm / self.mean_norm.max(f32::MIN_POSITIVE);
+        writeln!(f, "Mean vector norm: {:.4} ({:.1}% of the mean norm)", self.mean_vector_norm, 100.0 * ratio)?;
+        writeln!(f, "Isotropy: {:.4} (1 is perfectly isotropic)", self.isotropy)?;
+        writeln!(f, "Hubness: k-occurrence skewness {:.2} (k = {})", self.hubness_skew, HUBNESS_K)?;
+        let hubs: Vec<String> = self.hubs.iter().map(|(word, count)| format!("{} ({})", word, count)).collect();
+        write!(f, "Top hubs: {}", hubs.join(", "))
+    }
+}
@@ -0,0 +1,115 @@
+//! Well-known pretrained embeddings that `download` / `--model` can fetch.
+//!
+//! Archives are downloaded with the system `curl` into the cache directory
+//! and read in place through `Source::ArchiveMember`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::input::Source;
+
+/// A named embedding file inside a downloadable archive
+pub struct Model {
+    pub name: &'static str,
+    pub url: &'static str,
+    pub member: &'static str,
+}
+
+const GLOVE_6B: &str = "https://nlp.stanford.edu/data/glove.6B.zip";
+const GLOVE_TWITTER: &str = "https://nlp.stanford.edu/data/glove.twitter.27B.zip";
+
+pub const MODELS: &[Model] = &[
+    Model { name: "glove-6B-50d", url: GLOVE_6B, member: "glove.6B.50d.txt" },
+    Model { name: "glove-6B-100d", url: GLOVE_6B, member: "glove.6B.100d.txt" },
+    Model { name: "glove-6B-200d", url: GLOVE_6B, member: "glove.6B.200d.txt" },
+    Model { name: "glove-6B-300d", url: GLOVE_6B, member: "glove.6B.300d.txt" },
+    Model {
+        name: "glove-42B-300d",
+        url: "https://nlp.stanford.edu/data/glove.42B.300d.zip",
+        member: "glove.42B.300d.txt",
+    },
+    Model {
+        name: "glove-840B-300d",
+        url: "https://nlp.stanford.edu/data/glove.840B.300d.zip",
+        member: "glove.840B.300d.txt",
+    },
+    Model { name: "glove-twitter-27B-25d", url: GLOVE_TWITTER, member: "glove.twitter.27B.25d.txt" },
+    Model { name: "glove-twitter-27B-50d", url: GLOVE_TWITTER, member: "glove.twitter.27B.50d.txt" },
+    Model { name: "glove-twitter-27B-100d", url: GLOVE_TWITTER, member: "glove.twitter.27B.100d.txt" },
+    Model { name: "glove-twitter-27B-200d", url: GLOVE_TWITTER, member: "glove.twitter.27B.200d.txt" },
+];
+
+/// Looks up a model by name, e.g. `glove-6B-50d`
+pub fn find_model(name: &str) -> Option<&'static Model> {
+    MODELS.iter().find(|m| m.name == name)
+}
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Replaces the default cache directory, e.g. with `cache_dir` from the
+/// configuration file; only the first call has an effect
+pub fn set_cache_dir(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+}
+
+/// The directory given to `set_cache_dir`, else `$XDG_CACHE_HOME/glove-demo`,
+/// or `~/.cache/glove-demo`
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = CACHE_DIR.get() {
+        return dir.clone();
+    }
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".cache"),
+    };
+    base.join("glove-demo")
+}
+
+impl Model {
+    /// Where the archive is kept in the cache
+    pub fn archive_path(&self) -> PathBuf {
+        let file_name = self.url.rsplit('/').next().unwrap_or(self.name);
+        cache_dir().join(file_name)
+    }
+
+    pub fn is_downloaded(&self) -> bool {
+        self.archive_path().is_file()
+    }
+
+    /// Downloads the archive unless it is already cached, returning its path
+    pub fn download(&self) -> io::Result<PathBuf> {
+        let path = self.archive_path();
+        if path.is_file() {
+            return Ok(path);
+        }
+        fs::create_dir_all(cache_dir())?;
+
+        // Download next to the final name so an interrupted transfer is never mistaken for a cached one
+        let partial = path.with_extension("zip.part");
+        let status = Command::new("curl")
+            .args(["--location", "--fail", "--output"])
+            .arg(&partial)
+            .arg(self.url)
+            .status()
+            .map_err(|e| io::Error::new(e.kind(), format!("unable to run curl: {}", e)))?;
+        if !status.success() {
+            let _ = fs::remove_file(&partial);
+            return Err(io::Error::other(format!("downloading {} failed: curl exited with {}", self.url, status)));
+        }
+
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    /// The embedding file inside the cached archive
+    pub fn source(&self) -> Source {
+        Source::ArchiveMember {
+            archive: self.archive_path().to_string_lossy().into_owned(),
+            member: self.member.to_string(),
+        }
+    }
+}
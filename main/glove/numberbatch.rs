@@ -0,0 +1,31 @@
+//! ConceptNet Numberbatch files: word2vec text whose rows are named by
+//! ConceptNet URIs such as `/c/en/ice_cream` or `/c/fr/chat`, mixing every
+//! language in one file.
+//!
+//! While loading, a row named by a URI is kept only if its language is the
+//! one asked for (English by default), under the plain term, so
+//! `/c/en/ice_cream` is looked up as `ice_cream`. Rows with plain names, as
+//! in the English-only release, are kept as they are.
+
+/// Language kept when none is given
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// The language and term of a concept URI, `/c/<language>/<term>`; a
+/// trailing part of speech or sense (`/c/en/run/v`) is dropped
+pub fn parse_uri(uri: &str) -> Option<(&str, &str)> {
+    let mut parts = uri.strip_prefix("/c/")?.split('/');
+    match (parts.next(), parts.next()) {
+        (Some(language), Some(term)) if !language.is_empty() && !term.is_empty() => Some((language, term)),
+        _ => None,
+    }
+}
+
+/// The name a row is kept under: its term if it is a URI in `language`,
+/// nothing if it is a URI in another one, and the name itself otherwise
+pub fn row_word<'a>(name: &'a str, language: &str) -> Option<&'a str> {
+    match parse_uri(name) {
+        Some((lang, term)) if lang == language => Some(term),
+        Some(_) => None,
+        None => Some(name),
+    }
+}
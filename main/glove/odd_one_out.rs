@@ -0,0 +1,40 @@
+//! The "doesn't match" puzzle: which word of a set belongs least with the rest.
+
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+
+/// Scores each word by its cosine similarity to the mean of the other words'
+/// unit vectors, least similar (the odd one out) first. Unknown words are
+/// skipped; at least three known words are needed.
+pub fn doesnt_match<S: AsRef<str>>(store: &dyn EmbeddingStore, words: &[S]) -> Result<Vec<(String, f32)>, String> {
+    let known: Vec<(String, Vec<f32>)> = words
+        .iter()
+        .filter_map(|w| {
+            let vec = store.get(w.as_ref())?;
+            let norm = vec.iter().map(|x| x * x).sum::<f32>().sqrt().max(f32::MIN_POSITIVE);
+            Some((w.as_ref().to_string(), vec.iter().map(|x| x / norm).collect()))
+        })
+        .collect();
+    if known.len() < 3 {
+        return Err(format!("need at least three known words, found {}", known.len()));
+    }
+
+    let dim = store.dim();
+    let mut total = vec![0.0f32; dim];
+    for (_, vec) in &known {
+        for (t, x) in total.iter_mut().zip(vec) {
+            *t += x;
+        }
+    }
+
+    let others = (known.len() - 1) as f32;
+    let mut scored: Vec<(String, f32)> = known
+        .iter()
+        .map(|(word, vec)| {
+            let mean: Vec<f32> = total.iter().zip(vec).map(|(t, x)| (t - x) / others).collect();
+            (word.clone(), cosine_similarity(vec, &mean))
+        })
+        .collect();
+    scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(scored)
+}
@@ -0,0 +1,966 @@
+//! Apache Parquet files, so vectors can move to and from dataframe tools
+//! (pandas, Polars, Spark) without text parsing.
+//!
+//! Written files hold a `word` string column and one `float` column per
+//! dimension (`v0`, `v1`, ...), uncompressed and plainly encoded, which
+//! `pandas.read_parquet` turns into one row per word.
+//!
+//! Reading accepts what those tools write by default: plain or dictionary
+//! encoding, uncompressed or Snappy pages (v1 or v2), and required or nullable
+//! columns. The words come from the string column named `word` (else the
+//! first top-level string column, such as a saved pandas index), and the
+//! vectors from either one column of float lists or every top-level
+//! float/double column in schema order.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+use crate::load::RowSink;
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+const MAGIC: &[u8] = b"PAR1";
+
+/// Rows per row group written; a group's columns are buffered in memory
+const ROW_GROUP_ROWS: usize = 16_384;
+
+// Physical types
+const BYTE_ARRAY: i64 = 6;
+const FLOAT: i64 = 4;
+const DOUBLE: i64 = 5;
+
+// Repetition types
+const REQUIRED: i64 = 0;
+const REPEATED: i64 = 2;
+
+// Encodings
+const PLAIN: i64 = 0;
+const PLAIN_DICTIONARY: i64 = 2;
+const RLE: i64 = 3;
+const RLE_DICTIONARY: i64 = 8;
+
+// Page types
+const DATA_PAGE: i64 = 0;
+const DICTIONARY_PAGE: i64 = 2;
+const DATA_PAGE_V2: i64 = 3;
+
+// Codecs
+const UNCOMPRESSED: i64 = 0;
+const SNAPPY: i64 = 1;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// ---- Thrift compact protocol, which Parquet uses for its metadata ----
+
+const T_BOOL_TRUE: u8 = 1;
+const T_BOOL_FALSE: u8 = 2;
+const T_BYTE: u8 = 3;
+const T_I16: u8 = 4;
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_DOUBLE: u8 = 7;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_SET: u8 = 10;
+const T_MAP: u8 = 11;
+const T_STRUCT: u8 = 12;
+
+/// Nesting of Thrift structs and containers allowed in metadata; real
+/// Parquet footers go a handful of levels deep
+const MAX_DEPTH: usize = 64;
+
+/// A decoded Thrift value; structs keep their fields by id
+#[derive(Debug)]
+enum Value {
+    Bool(bool),
+    Int(i64),
+    Double,
+    Binary(Vec<u8>),
+    List(Vec<Value>),
+    Struct(Vec<(i16, Value)>),
+}
+
+impl Value {
+    fn field(&self, id: i16) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(i, _)| *i == id).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn int(&self, id: i16) -> Option<i64> {
+        match self.field(id)? {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// A size or count field, 0 if unset; negative ones are refused
+    fn size(&self, id: i16) -> io::Result<usize> {
+        usize::try_from(self.int(id).unwrap_or(0)).map_err(|_| invalid("negative size in Parquet metadata"))
+    }
+
+    fn bool(&self, id: i16) -> Option<bool> {
+        match self.field(id)? {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn string(&self, id: i16) -> Option<String> {
+        match self.field(id)? {
+            Value::Binary(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            _ => None,
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Value] {
+        match self.field(id) {
+            Some(Value::List(items)) => items,
+            _ => &[],
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Structs and containers being decoded, bounded so corrupt metadata
+    /// cannot recurse until the stack overflows
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let b = *self.bytes.get(self.pos).ok_or_else(|| invalid("truncated Parquet metadata"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        let end = self.pos.checked_add(n).ok_or_else(|| invalid("truncated Parquet metadata"))?;
+        let bytes = self.bytes.get(self.pos..end).ok_or_else(|| invalid("truncated Parquet metadata"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("overlong varint in Parquet metadata"))
+    }
+
+    fn zigzag(&mut self) -> io::Result<i64> {
+        let v = self.varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn value(&mut self, ty: u8) -> io::Result<Value> {
+        let nested = matches!(ty, T_LIST | T_SET | T_MAP | T_STRUCT);
+        if nested {
+            if self.depth >= MAX_DEPTH {
+                return Err(invalid("Parquet metadata is nested too deeply"));
+            }
+            self.depth += 1;
+        }
+        let value = self.decode(ty);
+        if nested {
+            self.depth -= 1;
+        }
+        value
+    }
+
+    fn decode(&mut self, ty: u8) -> io::Result<Value> {
+        Ok(match ty {
+            T_BOOL_TRUE => Value::Bool(true),
+            T_BOOL_FALSE => Value::Bool(false),
+            T_BYTE => Value::Int(self.byte()? as i8 as i64),
+            T_I16 | T_I32 | T_I64 => Value::Int(self.zigzag()?),
+            T_DOUBLE => {
+                self.take(8)?;
+                Value::Double
+            }
+            T_BINARY => {
+                let len = self.varint()? as usize;
+                Value::Binary(self.take(len)?.to_vec())
+            }
+            T_LIST | T_SET => {
+                let header = self.byte()?;
+                let (size, elem) = (header >> 4, header & 0x0f);
+                let size = if size == 15 { self.varint()? as usize } else { size as usize };
+                let mut items = Vec::with_capacity(size.min(1 << 16));
+                for _ in 0..size {
+                    items.push(self.element(elem)?);
+                }
+                Value::List(items)
+            }
+            T_MAP => {
+                let size = self.varint()? as usize;
+                if size > 0 {
+                    let types = self.byte()?;
+                    for _ in 0..size {
+                        self.element(types >> 4)?;
+                        self.element(types & 0x0f)?;
+                    }
+                }
+                Value::List(Vec::new())
+            }
+            T_STRUCT => self.structure()?,
+            other => return Err(invalid(format!("unknown Thrift type {} in Parquet metadata", other))),
+        })
+    }
+
+    /// A list, set or map element; booleans in containers take a byte each,
+    /// so every element consumes input
+    fn element(&mut self, ty: u8) -> io::Result<Value> {
+        if ty == T_BOOL_TRUE || ty == T_BOOL_FALSE {
+            return Ok(Value::Bool(self.byte()? == 1));
+        }
+        self.value(ty)
+    }
+
+    fn structure(&mut self) -> io::Result<Value> {
+        let mut fields = Vec::new();
+        let mut last = 0i16;
+        loop {
+            let header = self.byte()?;
+            if header == 0 {
+                return Ok(Value::Struct(fields));
+            }
+            let (delta, ty) = (header >> 4, header & 0x0f);
+            let id = if delta == 0 { self.zigzag()? as i16 } else { last.wrapping_add(delta as i16) };
+            last = id;
+            fields.push((id, self.value(ty)?));
+        }
+    }
+}
+
+/// Writes Thrift compact structs; field ids are delta-encoded per struct
+#[derive(Default)]
+struct Encoder {
+    out: Vec<u8>,
+    last: Vec<i16>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.out.push(v as u8);
+    }
+
+    fn zigzag(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, ty: u8) {
+        let last = self.last.last_mut().expect("field outside a struct");
+        let delta = id - *last;
+        *last = id;
+        if (1..=15).contains(&delta) {
+            self.out.push((delta as u8) << 4 | ty);
+        } else {
+            self.out.push(ty);
+            self.zigzag(id as i64);
+        }
+    }
+
+    fn i32(&mut self, id: i16, v: i32) {
+        self.field(id, T_I32);
+        self.zigzag(v as i64);
+    }
+
+    fn i64(&mut self, id: i16, v: i64) {
+        self.field(id, T_I64);
+        self.zigzag(v);
+    }
+
+    fn binary(&mut self, id: i16, bytes: &[u8]) {
+        self.field(id, T_BINARY);
+        self.raw_binary(bytes);
+    }
+
+    fn raw_binary(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn list(&mut self, id: i16, elem: u8, size: usize) {
+        self.field(id, T_LIST);
+        if size < 15 {
+            self.out.push((size as u8) << 4 | elem);
+        } else {
+            self.out.push(0xf0 | elem);
+            self.varint(size as u64);
+        }
+    }
+
+    /// Starts a struct that is a field of the current one
+    fn begin(&mut self, id: i16) {
+        self.field(id, T_STRUCT);
+        self.begin_element();
+    }
+
+    /// Starts a struct that is a list element (or the top-level struct)
+    fn begin_element(&mut self) {
+        self.last.push(0);
+    }
+
+    fn end(&mut self) {
+        self.out.push(0);
+        self.last.pop();
+    }
+}
+
+// ---- Writing ----
+
+/// Byte offsets and sizes of one written column chunk
+struct ChunkInfo {
+    name: String,
+    physical: i64,
+    rows: usize,
+    offset: u64,
+    size: usize,
+}
+
+/// Writes every entry of a store as a Parquet file: a `word` column and one
+/// `float` column per dimension
+pub fn write_parquet(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    let dim = store.dim();
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as u64;
+    let mut groups: Vec<Vec<ChunkInfo>> = Vec::new();
+
+    let mut rows = store.iter().filter(|(_, vec)| vec.len() == dim).peekable();
+    let mut total_rows = 0;
+    while rows.peek().is_some() {
+        let batch: Vec<_> = rows.by_ref().take(ROW_GROUP_ROWS).collect();
+        total_rows += batch.len();
+
+        let mut columns: Vec<(String, i64, Vec<u8>)> = Vec::with_capacity(dim + 1);
+        let mut words = Vec::new();
+        for (word, _) in &batch {
+            words.extend_from_slice(&(word.len() as u32).to_le_bytes());
+            words.extend_from_slice(word.as_bytes());
+        }
+        columns.push(("word".to_string(), BYTE_ARRAY, words));
+        for d in 0..dim {
+            let values = batch.iter().flat_map(|(_, vec)| vec[d].to_le_bytes()).collect();
+            columns.push((format!("v{}", d), FLOAT, values));
+        }
+
+        let mut chunks = Vec::with_capacity(columns.len());
+        for (name, physical, values) in columns {
+            let mut header = Encoder::default();
+            header.begin_element();
+            header.i32(1, DATA_PAGE as i32);
+            header.i32(2, values.len() as i32);
+            header.i32(3, values.len() as i32);
+            header.begin(5);
+            header.i32(1, batch.len() as i32);
+            header.i32(2, PLAIN as i32);
+            header.i32(3, RLE as i32);
+            header.i32(4, RLE as i32);
+            header.end();
+            header.end();
+
+            out.write_all(&header.out)?;
+            out.write_all(&values)?;
+            let size = header.out.len() + values.len();
+            chunks.push(ChunkInfo { name, physical, rows: batch.len(), offset, size });
+            offset += size as u64;
+        }
+        groups.push(chunks);
+    }
+
+    let mut meta = Encoder::default();
+    meta.begin_element();
+    meta.i32(1, 1);
+    meta.list(2, T_STRUCT, dim + 2);
+    meta.begin_element();
+    meta.binary(4, b"schema");
+    meta.i32(5, (dim + 1) as i32);
+    meta.end();
+    meta.begin_element();
+    meta.i32(1, BYTE_ARRAY as i32);
+    meta.i32(3, REQUIRED as i32);
+    meta.binary(4, b"word");
+    // UTF8 converted type, and the STRING logical type
+    meta.i32(6, 0);
+    meta.begin(10);
+    meta.begin(1);
+    meta.end();
+    meta.end();
+    meta.end();
+    for d in 0..dim {
+        meta.begin_element();
+        meta.i32(1, FLOAT as i32);
+        meta.i32(3, REQUIRED as i32);
+        meta.binary(4, format!("v{}", d).as_bytes());
+        meta.end();
+    }
+    meta.i64(3, total_rows as i64);
+    meta.list(4, T_STRUCT, groups.len());
+    for chunks in &groups {
+        meta.begin_element();
+        meta.list(1, T_STRUCT, chunks.len());
+        for chunk in chunks {
+            meta.begin_element();
+            meta.i64(2, chunk.offset as i64);
+            meta.begin(3);
+            meta.i32(1, chunk.physical as i32);
+            meta.list(2, T_I32, 2);
+            meta.zigzag(PLAIN);
+            meta.zigzag(RLE);
+            meta.list(3, T_BINARY, 1);
+            meta.raw_binary(chunk.name.as_bytes());
+            meta.i32(4, UNCOMPRESSED as i32);
+            meta.i64(5, chunk.rows as i64);
+            meta.i64(6, chunk.size as i64);
+            meta.i64(7, chunk.size as i64);
+            meta.i64(9, chunk.offset as i64);
+            meta.end();
+            meta.end();
+        }
+        meta.i64(2, chunks.iter().map(|c| c.size as i64).sum());
+        meta.i64(3, chunks.first().map_or(0, |c| c.rows as i64));
+        meta.end();
+    }
+    meta.binary(6, b"GloVe-word-sum-demo");
+    meta.end();
+
+    out.write_all(&meta.out)?;
+    out.write_all(&(meta.out.len() as u32).to_le_bytes())?;
+    out.write_all(MAGIC)?;
+    out.flush()
+}
+
+// ---- Reading ----
+
+/// A leaf column of the schema
+struct Leaf {
+    name: String,
+    physical: i64,
+    /// Directly under the root, i.e. not inside a group
+    top_level: bool,
+    max_def: u32,
+    max_rep: u32,
+}
+
+/// Flattens the depth-first schema element list into its leaf columns
+fn leaves(schema: &[Value]) -> io::Result<Vec<Leaf>> {
+    fn walk(
+        schema: &[Value],
+        next: &mut usize,
+        depth: usize,
+        def: u32,
+        rep: u32,
+        out: &mut Vec<Leaf>,
+    ) -> io::Result<()> {
+        let element = schema.get(*next).ok_or_else(|| invalid("truncated Parquet schema"))?;
+        *next += 1;
+        let (mut def, mut rep) = (def, rep);
+        if depth > 0 {
+            match element.int(3).unwrap_or(REQUIRED) {
+                REQUIRED => {}
+                REPEATED => {
+                    def += 1;
+                    rep += 1;
+                }
+                _ => def += 1,
+            }
+        }
+        match element.int(5) {
+            Some(children) if children > 0 => {
+                for _ in 0..children {
+                    walk(schema, next, depth + 1, def, rep, out)?;
+                }
+            }
+            _ if depth > 0 => out.push(Leaf {
+                name: element.string(4).unwrap_or_default(),
+                physical: element.int(1).unwrap_or(-1),
+                top_level: depth == 1,
+                max_def: def,
+                max_rep: rep,
+            }),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    let mut out = Vec::new();
+    walk(schema, &mut 0, 0, 0, 0, &mut out)?;
+    Ok(out)
+}
+
+/// Values of one column chunk, nulls left out
+enum Values {
+    Floats(Vec<f32>),
+    Strings(Vec<String>),
+}
+
+/// A decoded column chunk: its values plus the levels that place them in rows
+struct Column {
+    values: Values,
+    def: Vec<u32>,
+    rep: Vec<u32>,
+}
+
+/// Reads a Parquet file (held in memory whole, since its metadata is at the end)
+pub(crate) fn read_parquet(reader: &mut dyn BufRead, sink: &mut RowSink) -> io::Result<()> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    sink.read(bytes.len());
+    if bytes.len() < 12 || !bytes.ends_with(MAGIC) {
+        return Err(invalid("not a Parquet file"));
+    }
+    let meta_len = u32::from_le_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap()) as usize;
+    let meta_start = (bytes.len() - 8).checked_sub(meta_len).ok_or_else(|| invalid("bad Parquet footer"))?;
+    let meta = Decoder { bytes: &bytes[meta_start..bytes.len() - 8], pos: 0, depth: 0 }.structure()?;
+
+    let leaves = leaves(meta.list(2))?;
+    let is_string = |leaf: &Leaf| leaf.top_level && leaf.physical == BYTE_ARRAY && leaf.max_rep == 0;
+    let word_column = leaves
+        .iter()
+        .position(|leaf| is_string(leaf) && leaf.name == "word")
+        .or_else(|| leaves.iter().position(is_string))
+        .ok_or_else(|| invalid("no string column to take the words from"))?;
+    let is_float = |leaf: &Leaf| leaf.physical == FLOAT || leaf.physical == DOUBLE;
+    let lists: Vec<usize> = (0..leaves.len()).filter(|&i| is_float(&leaves[i]) && leaves[i].max_rep == 1).collect();
+    let vector_columns: Vec<usize> = match lists[..] {
+        [list] => vec![list],
+        [] => (0..leaves.len()).filter(|&i| is_float(&leaves[i]) && leaves[i].top_level).collect(),
+        _ => return Err(invalid("more than one list column; expected one list of floats per word")),
+    };
+    if vector_columns.is_empty() {
+        return Err(invalid("no float or double columns to take the vectors from"));
+    }
+
+    for group in meta.list(4) {
+        if sink.full() {
+            break;
+        }
+        let chunks = group.list(1);
+        let column = |i: usize| -> io::Result<Column> {
+            let chunk = chunks.get(i).and_then(|c| c.field(3)).ok_or_else(|| invalid("missing column chunk"))?;
+            read_column(&bytes, chunk, &leaves[i])
+        };
+        let words = match column(word_column)? {
+            Column { values: Values::Strings(words), def, .. } => {
+                if def.iter().any(|&d| d < leaves[word_column].max_def) {
+                    return Err(invalid("the word column has null values"));
+                }
+                words
+            }
+            _ => return Err(invalid("the word column is not a string column")),
+        };
+
+        let vectors: Vec<WordVec> = if let [list] = vector_columns[..] {
+            split_lists(column(list)?, leaves[list].max_def)
+        } else {
+            let mut vectors = vec![Vec::with_capacity(vector_columns.len()); words.len()];
+            for &i in &vector_columns {
+                let Column { values: Values::Floats(values), .. } = column(i)? else { continue };
+                if values.len() != words.len() {
+                    return Err(invalid(format!("column {} has null values", leaves[i].name)));
+                }
+                vectors.iter_mut().zip(values).for_each(|(vec, x)| vec.push(x));
+            }
+            vectors
+        };
+        if vectors.len() != words.len() {
+            return Err(invalid("the word and vector columns have different lengths"));
+        }
+
+        for (word, vec) in words.iter().zip(vectors) {
+            if sink.full() {
+                break;
+            }
+            if sink.wants(word) {
+                sink.add(word, vec);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Groups a list column's values into one vector per row; a null or empty
+/// list gives an empty vector
+fn split_lists(column: Column, max_def: u32) -> Vec<WordVec> {
+    let Values::Floats(values) = column.values else { return Vec::new() };
+    let mut values = values.into_iter();
+    let mut rows: Vec<WordVec> = Vec::new();
+    for (i, &def) in column.def.iter().enumerate() {
+        if column.rep.get(i).copied().unwrap_or(0) == 0 {
+            rows.push(Vec::new());
+        }
+        if def == max_def {
+            if let (Some(row), Some(x)) = (rows.last_mut(), values.next()) {
+                row.push(x);
+            }
+        }
+    }
+    rows
+}
+
+/// Decodes every page of a column chunk
+fn read_column(bytes: &[u8], meta: &Value, leaf: &Leaf) -> io::Result<Column> {
+    let codec = meta.int(4).unwrap_or(UNCOMPRESSED);
+    if codec != UNCOMPRESSED && codec != SNAPPY {
+        return Err(invalid("only uncompressed or Snappy-compressed Parquet files are supported"));
+    }
+    let total = meta.size(5)?;
+    let data_offset = meta.int(9).unwrap_or(0);
+    let start = match meta.int(11) {
+        Some(dict) if dict > 0 && dict < data_offset => dict,
+        _ => data_offset,
+    };
+    let start = usize::try_from(start).map_err(|_| invalid("bad page offset"))?;
+
+    let mut column = Column {
+        values: if leaf.physical == BYTE_ARRAY { Values::Strings(Vec::new()) } else { Values::Floats(Vec::new()) },
+        def: Vec::new(),
+        rep: Vec::new(),
+    };
+    let mut dictionary: Option<Values> = None;
+    let mut seen = 0;
+    let mut pos = start;
+    while seen < total {
+        let rest = bytes.get(pos..).ok_or_else(|| invalid("bad page offset"))?;
+        let mut decoder = Decoder { bytes: rest, pos: 0, depth: 0 };
+        let header = decoder.structure()?;
+        let body_start = pos + decoder.pos;
+        let body_end = body_start.checked_add(header.size(3)?).ok_or_else(|| invalid("truncated Parquet page"))?;
+        let body = bytes.get(body_start..body_end).ok_or_else(|| invalid("truncated Parquet page"))?;
+        pos = body_end;
+
+        match header.int(1) {
+            Some(DICTIONARY_PAGE) => {
+                let page = header.field(7).ok_or_else(|| invalid("dictionary page without a header"))?;
+                let body = decompress(body, codec)?;
+                dictionary = Some(plain(&body, leaf.physical, page.size(1)?)?);
+            }
+            Some(DATA_PAGE) => {
+                let page = header.field(5).ok_or_else(|| invalid("data page without a header"))?;
+                let count = page_count(page, total - seen)?;
+                let body = decompress(body, codec)?;
+                let mut at = 0;
+                let mut levels = |max: u32| -> io::Result<Vec<u32>> {
+                    if max == 0 {
+                        return Ok(Vec::new());
+                    }
+                    let truncated = || invalid("truncated levels");
+                    let len = u32::from_le_bytes(body.get(at..at + 4).ok_or_else(truncated)?.try_into().unwrap());
+                    let end = (at + 4).checked_add(len as usize).ok_or_else(truncated)?;
+                    let levels = rle(body.get(at + 4..end).ok_or_else(truncated)?, bit_width(max), count)?;
+                    at = end;
+                    Ok(levels)
+                };
+                let rep = levels(leaf.max_rep)?;
+                let def = levels(leaf.max_def)?;
+                let present =
+                    if leaf.max_def == 0 { count } else { def.iter().filter(|&&d| d == leaf.max_def).count() };
+                let values =
+                    decode_values(&body[at..], page.int(2).unwrap_or(PLAIN), leaf.physical, present, &dictionary)?;
+                append(&mut column, values, def, rep);
+                seen += count;
+            }
+            Some(DATA_PAGE_V2) => {
+                let page = header.field(8).ok_or_else(|| invalid("data page without a header"))?;
+                let count = page_count(page, total - seen)?;
+                let present = count.checked_sub(page.size(2)?).ok_or_else(|| invalid("more nulls than values"))?;
+                let (def_len, rep_len) = (page.size(5)?, page.size(6)?);
+                let levels_len = rep_len.checked_add(def_len).ok_or_else(|| invalid("truncated levels"))?;
+                let levels = body.get(..levels_len).ok_or_else(|| invalid("truncated levels"))?;
+                let rep = if leaf.max_rep > 0 {
+                    rle(&levels[..rep_len], bit_width(leaf.max_rep), count)?
+                } else {
+                    Vec::new()
+                };
+                let def = if leaf.max_def > 0 {
+                    rle(&levels[rep_len..], bit_width(leaf.max_def), count)?
+                } else {
+                    Vec::new()
+                };
+                let values = &body[levels_len..];
+                let values = if page.bool(7).unwrap_or(true) { decompress(values, codec)? } else { values.to_vec() };
+                let values = decode_values(&values, page.int(4).unwrap_or(PLAIN), leaf.physical, present, &dictionary)?;
+                append(&mut column, values, def, rep);
+                seen += count;
+            }
+            // Index pages and anything newer carry no values
+            _ => {}
+        }
+    }
+    Ok(column)
+}
+
+/// A data page's value count, which cannot exceed what is left of its column chunk
+fn page_count(page: &Value, left: usize) -> io::Result<usize> {
+    let count = page.size(1)?;
+    if count > left {
+        return Err(invalid("Parquet page has more values than its column chunk"));
+    }
+    Ok(count)
+}
+
+fn append(column: &mut Column, values: Values, def: Vec<u32>, rep: Vec<u32>) {
+    match (&mut column.values, values) {
+        (Values::Floats(all), Values::Floats(new)) => all.extend(new),
+        (Values::Strings(all), Values::Strings(new)) => all.extend(new),
+        _ => {}
+    }
+    column.def.extend(def);
+    column.rep.extend(rep);
+}
+
+/// Bits needed for levels up to `max`
+fn bit_width(max: u32) -> u8 {
+    (32 - max.leading_zeros()) as u8
+}
+
+/// Decodes a page's values, plain or through the dictionary
+fn decode_values(
+    body: &[u8],
+    encoding: i64,
+    physical: i64,
+    count: usize,
+    dictionary: &Option<Values>,
+) -> io::Result<Values> {
+    match encoding {
+        PLAIN => plain(body, physical, count),
+        PLAIN_DICTIONARY | RLE_DICTIONARY => {
+            let dictionary =
+                dictionary.as_ref().ok_or_else(|| invalid("dictionary-encoded page without a dictionary"))?;
+            let width = *body.first().ok_or_else(|| invalid("truncated dictionary indices"))?;
+            let indices = rle(&body[1..], width, count)?;
+            let missing = || invalid("dictionary index out of range");
+            Ok(match dictionary {
+                Values::Floats(d) => Values::Floats(
+                    indices
+                        .iter()
+                        .map(|&i| d.get(i as usize).copied().ok_or_else(missing))
+                        .collect::<io::Result<_>>()?,
+                ),
+                Values::Strings(d) => Values::Strings(
+                    indices
+                        .iter()
+                        .map(|&i| d.get(i as usize).cloned().ok_or_else(missing))
+                        .collect::<io::Result<_>>()?,
+                ),
+            })
+        }
+        other => Err(invalid(format!("unsupported Parquet encoding {}", other))),
+    }
+}
+
+/// Decodes `count` plainly encoded values
+fn plain(body: &[u8], physical: i64, count: usize) -> io::Result<Values> {
+    let short = || invalid("truncated Parquet values");
+    match physical {
+        FLOAT => {
+            let bytes = body.get(..count.checked_mul(4).ok_or_else(short)?).ok_or_else(short)?;
+            Ok(Values::Floats(bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect()))
+        }
+        DOUBLE => {
+            let bytes = body.get(..count.checked_mul(8).ok_or_else(short)?).ok_or_else(short)?;
+            Ok(Values::Floats(
+                bytes.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap()) as f32).collect(),
+            ))
+        }
+        BYTE_ARRAY => {
+            // Each string takes at least its 4-byte length
+            let mut strings = Vec::with_capacity(count.min(body.len() / 4));
+            let mut at = 0;
+            for _ in 0..count {
+                let len = u32::from_le_bytes(body.get(at..at + 4).ok_or_else(short)?.try_into().unwrap()) as usize;
+                let end = (at + 4).checked_add(len).ok_or_else(short)?;
+                strings.push(String::from_utf8_lossy(body.get(at + 4..end).ok_or_else(short)?).into_owned());
+                at = end;
+            }
+            Ok(Values::Strings(strings))
+        }
+        _ => Err(invalid("unsupported Parquet column type")),
+    }
+}
+
+/// Decodes `count` values of the RLE / bit-packed hybrid encoding
+fn rle(bytes: &[u8], width: u8, count: usize) -> io::Result<Vec<u32>> {
+    // Runs can stand for many values each, so the capacity is only a guess
+    let mut out = Vec::with_capacity(count.min(bytes.len() * 8));
+    let mut decoder = Decoder { bytes, pos: 0, depth: 0 };
+    let value_bytes = (width as usize).div_ceil(8);
+    while out.len() < count {
+        let header = decoder.varint()?;
+        if header & 1 == 0 {
+            // A run of one repeated value
+            let run = (header >> 1) as usize;
+            let mut value = 0u32;
+            for (i, &b) in decoder.take(value_bytes)?.iter().enumerate() {
+                value |= u32::from(b) << (8 * i);
+            }
+            out.extend(std::iter::repeat_n(value, run.min(count - out.len())));
+        } else {
+            // Groups of eight values packed `width` bits each, least significant bit first
+            let groups = usize::try_from(header >> 1).map_err(|_| invalid("corrupt RLE run"))?;
+            let values = groups.checked_mul(8).ok_or_else(|| invalid("corrupt RLE run"))?;
+            let packed = decoder.take(groups.checked_mul(width as usize).ok_or_else(|| invalid("corrupt RLE run"))?)?;
+            for i in 0..values.min(count - out.len()) {
+                let mut value = 0u32;
+                for bit in 0..width as usize {
+                    let at = i * width as usize + bit;
+                    value |= u32::from(packed[at / 8] >> (at % 8) & 1) << bit;
+                }
+                out.push(value);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn decompress(body: &[u8], codec: i64) -> io::Result<Vec<u8>> {
+    match codec {
+        SNAPPY => snappy(body),
+        _ => Ok(body.to_vec()),
+    }
+}
+
+/// Decompresses a raw Snappy block
+fn snappy(input: &[u8]) -> io::Result<Vec<u8>> {
+    let corrupt = || invalid("corrupt Snappy data");
+    let mut decoder = Decoder { bytes: input, pos: 0, depth: 0 };
+    let len = usize::try_from(decoder.varint()?).map_err(|_| corrupt())?;
+    // A copy of up to 64 bytes takes at least 2 bytes of input, which bounds the output
+    let mut out: Vec<u8> = Vec::with_capacity(len.min(input.len().saturating_mul(32)));
+    let mut pos = decoder.pos;
+    while pos < input.len() {
+        let tag = input[pos];
+        pos += 1;
+        let (length, offset) = match tag & 3 {
+            0 => {
+                let mut length = (tag >> 2) as usize;
+                if length >= 60 {
+                    let n = length - 59;
+                    let bytes = input.get(pos..pos + n).ok_or_else(corrupt)?;
+                    length = bytes.iter().rev().fold(0, |acc, &b| acc << 8 | b as usize);
+                    pos += n;
+                }
+                if out.len() + length >= len {
+                    return Err(corrupt());
+                }
+                let literal = input.get(pos..pos + length + 1).ok_or_else(corrupt)?;
+                out.extend_from_slice(literal);
+                pos += length + 1;
+                continue;
+            }
+            1 => {
+                let low = *input.get(pos).ok_or_else(corrupt)? as usize;
+                pos += 1;
+                (((tag >> 2) & 7) as usize + 4, ((tag as usize) >> 5) << 8 | low)
+            }
+            2 => {
+                let bytes = input.get(pos..pos + 2).ok_or_else(corrupt)?;
+                pos += 2;
+                ((tag >> 2) as usize + 1, u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            }
+            _ => {
+                let bytes = input.get(pos..pos + 4).ok_or_else(corrupt)?;
+                pos += 4;
+                ((tag >> 2) as usize + 1, u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+            }
+        };
+        if offset == 0 || offset > out.len() || out.len() + length > len {
+            return Err(corrupt());
+        }
+        // Copies may overlap what they produce, so go byte by byte
+        let from = out.len() - offset;
+        for i in 0..length {
+            out.push(out[from + i]);
+        }
+    }
+    if out.len() != len {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_truncations_fail, load_rows, rows, sample_store, TempDir};
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.parquet");
+        let store = sample_store();
+        write_parquet(&store, &path).unwrap();
+        assert_eq!(load_rows(&path).unwrap(), rows(&store));
+    }
+
+    #[test]
+    fn truncated_files_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.parquet");
+        write_parquet(&sample_store(), &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_truncations_fail(&path, &bytes, MAGIC.len());
+    }
+
+    #[test]
+    fn footer_longer_than_the_file_fails() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.parquet");
+        write_parquet(&sample_store(), &path).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        let at = bytes.len() - 8;
+        bytes[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(load_rows(&path).is_err());
+    }
+
+    #[test]
+    fn corrupt_bytes_never_panic() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.parquet");
+        write_parquet(&sample_store(), &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        for at in 0..bytes.len() {
+            for value in [0x00, 0x7f, 0xff] {
+                let mut corrupt = bytes.clone();
+                corrupt[at] = value;
+                std::fs::write(&path, &corrupt).unwrap();
+                // Either outcome is fine; reaching the next byte is the test
+                let _ = load_rows(&path);
+            }
+        }
+    }
+
+    #[test]
+    fn snappy_expands_literals_and_overlapping_copies() {
+        // "abc", then a copy of 6 bytes from 3 back
+        assert_eq!(snappy(&[9, 0x08, b'a', b'b', b'c', 0x09, 3]).unwrap(), b"abcabcabc");
+        // A copy reaching before the start of the output
+        assert!(snappy(&[9, 0x08, b'a', b'b', b'c', 0x09, 4]).is_err());
+        // More output than the declared length
+        assert!(snappy(&[4, 0x08, b'a', b'b', b'c', 0x09, 3]).is_err());
+    }
+
+    #[test]
+    fn rle_decodes_runs_and_bit_packed_groups() {
+        assert_eq!(rle(&[10, 1], 1, 5).unwrap(), [1; 5]);
+        assert_eq!(rle(&[3, 0b1011_0010], 1, 8).unwrap(), [0, 1, 0, 0, 1, 1, 0, 1]);
+        assert!(rle(&[3], 1, 8).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+//! The "semantic corridor" between two words: points evenly spaced on the
+//! straight line from one vector to the other, each labelled with the
+//! vocabulary word nearest to it.
+
+use crate::search::top_k;
+use crate::store::{unknown_words, EmbeddingStore};
+use crate::{Error, WordVec};
+
+/// One point on the line between two words
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathStep {
+    /// How far along the line the point is, from 0 (the first word) to 1 (the second)
+    pub t: f32,
+    /// The nearest word to the point by cosine similarity, and that similarity
+    pub word: String,
+    pub similarity: f32,
+}
+
+/// The vectors `(1 - t) * from + t * to` for `t = 0, 1/steps, ..., 1`
+pub fn interpolate(from: &[f32], to: &[f32], steps: usize) -> Vec<(f32, WordVec)> {
+    let steps = steps.max(1);
+    (0..=steps)
+        .map(|i| {
+            let t = i as f32 / steps as f32;
+            (t, from.iter().zip(to).map(|(a, b)| (1.0 - t) * a + t * b).collect())
+        })
+        .collect()
+}
+
+/// Labels `steps + 1` points on the line from `from` to `to` with their
+/// nearest words. With `exclude_endpoints`, the two words themselves are
+/// never the label, so the words between them show even near the ends.
+pub fn interpolation_path(
+    store: &dyn EmbeddingStore,
+    from: &str,
+    to: &str,
+    steps: usize,
+    exclude_endpoints: bool,
+) -> Result<Vec<PathStep>, Error> {
+    let (Some(a), Some(b)) = (store.get(from), store.get(to)) else {
+        return Err(unknown_words(store, &[from, to]));
+    };
+    let (ts, points): (Vec<f32>, Vec<WordVec>) = interpolate(&a, &b, steps).into_iter().unzip();
+    let exclude = if exclude_endpoints { vec![from.to_string(), to.to_string()] } else { Vec::new() };
+    let steps = ts
+        .into_iter()
+        .zip(store.cosine_scores_batch(&points))
+        .filter_map(|(t, scores)| {
+            let (word, similarity) = top_k(scores, store, &exclude, 1, true).into_iter().next()?;
+            Some(PathStep { t, word, similarity })
+        })
+        .collect();
+    Ok(steps)
+}
@@ -0,0 +1,218 @@
+//! A small regular-expression matcher for selecting vocabulary words, so
+//! `export-subset --regex` needs no external crate.
+//!
+//! Supported: literals, `.`, classes (`[a-z]`, `[^0-9]`), the escapes `\d`,
+//! `\w`, `\s` and `\` before any punctuation, groups `( )` with alternation
+//! `|`, the quantifiers `*`, `+`, `?` and `{m}`, `{m,}`, `{m,n}`, and the
+//! anchors `^` and `$`. Like `grep`, a pattern matches anywhere in the word
+//! unless anchored.
+
+/// One element of a parsed pattern
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Char(char),
+    Any,
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Start,
+    End,
+    /// Alternatives, each a sequence
+    Group(Vec<Vec<Node>>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize> },
+}
+
+/// A compiled pattern
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    alternatives: Vec<Vec<Node>>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Pattern, String> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+        let alternatives = parse_alternatives(&chars, &mut pos)?;
+        if pos < chars.len() {
+            return Err(format!("unmatched ')' in pattern '{}'", pattern));
+        }
+        Ok(Pattern { alternatives })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let group = Node::Group(self.alternatives.clone());
+        (0..=chars.len()).any(|start| match_node(&group, &chars, start, &mut |_| true))
+    }
+}
+
+fn parse_alternatives(chars: &[char], pos: &mut usize) -> Result<Vec<Vec<Node>>, String> {
+    let mut alternatives = vec![Vec::new()];
+    while *pos < chars.len() {
+        let c = chars[*pos];
+        *pos += 1;
+        let node = match c {
+            ')' => {
+                *pos -= 1;
+                break;
+            }
+            '|' => {
+                alternatives.push(Vec::new());
+                continue;
+            }
+            '(' => {
+                let group = parse_alternatives(chars, pos)?;
+                if chars.get(*pos) != Some(&')') {
+                    return Err("unclosed '(' in pattern".to_string());
+                }
+                *pos += 1;
+                Node::Group(group)
+            }
+            '[' => parse_class(chars, pos)?,
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => {
+                let escaped = *chars.get(*pos).ok_or("pattern ends with '\\'")?;
+                *pos += 1;
+                escape(escaped)
+            }
+            '*' | '+' | '?' | '{' => return Err(format!("'{}' has nothing to repeat", c)),
+            c => Node::Char(c),
+        };
+        let node = parse_quantifier(chars, pos, node)?;
+        alternatives.last_mut().unwrap().push(node);
+    }
+    Ok(alternatives)
+}
+
+fn escape(c: char) -> Node {
+    let class = |ranges: &[(char, char)]| Node::Class { ranges: ranges.to_vec(), negated: false };
+    match c {
+        'd' => class(&[('0', '9')]),
+        'w' => class(&[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]),
+        's' => class(&[(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')]),
+        c => Node::Char(c),
+    }
+}
+
+/// Parses a class after its `[`
+fn parse_class(chars: &[char], pos: &mut usize) -> Result<Node, String> {
+    let negated = chars.get(*pos) == Some(&'^');
+    if negated {
+        *pos += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let c = *chars.get(*pos).ok_or("unclosed '[' in pattern")?;
+        *pos += 1;
+        // A ']' right after the opening bracket is a literal
+        if c == ']' && !first {
+            break;
+        }
+        first = false;
+        let low = if c == '\\' {
+            let escaped = *chars.get(*pos).ok_or("unclosed '[' in pattern")?;
+            *pos += 1;
+            match escape(escaped) {
+                Node::Class { ranges: class, .. } => {
+                    ranges.extend(class);
+                    continue;
+                }
+                _ => escaped,
+            }
+        } else {
+            c
+        };
+        if chars.get(*pos) == Some(&'-') && chars.get(*pos + 1).is_some_and(|&c| c != ']') {
+            let high = chars[*pos + 1];
+            *pos += 2;
+            if high < low {
+                return Err(format!("invalid range '{}-{}' in pattern", low, high));
+            }
+            ranges.push((low, high));
+        } else {
+            ranges.push((low, low));
+        }
+    }
+    Ok(Node::Class { ranges, negated })
+}
+
+fn parse_quantifier(chars: &[char], pos: &mut usize, node: Node) -> Result<Node, String> {
+    let (min, max) = match chars.get(*pos) {
+        Some('*') => (0, None),
+        Some('+') => (1, None),
+        Some('?') => (0, Some(1)),
+        Some('{') => {
+            let close = chars[*pos..].iter().position(|&c| c == '}').ok_or("unclosed '{' in pattern")?;
+            let spec: String = chars[*pos + 1..*pos + close].iter().collect();
+            let number = |s: &str| s.trim().parse::<usize>().map_err(|_| format!("invalid repeat '{{{}}}'", spec));
+            let bounds = match spec.split_once(',') {
+                None => (number(&spec)?, Some(number(&spec)?)),
+                Some((min, max)) if max.trim().is_empty() => (number(min)?, None),
+                Some((min, max)) => (number(min)?, Some(number(max)?)),
+            };
+            if bounds.1.is_some_and(|max| max < bounds.0) {
+                return Err(format!("invalid repeat '{{{}}}'", spec));
+            }
+            *pos += close;
+            bounds
+        }
+        _ => return Ok(node),
+    };
+    *pos += 1;
+    if matches!(node, Node::Start | Node::End) {
+        return Err("anchors cannot be repeated".to_string());
+    }
+    Ok(Node::Repeat { node: Box::new(node), min, max })
+}
+
+/// Matches `nodes` from `pos`, calling `rest` with each possible end until it
+/// accepts one (backtracking, greedy quantifiers)
+fn match_seq(nodes: &[Node], text: &[char], pos: usize, rest: &mut dyn FnMut(usize) -> bool) -> bool {
+    match nodes.split_first() {
+        None => rest(pos),
+        Some((node, tail)) => match_node(node, text, pos, &mut |next| match_seq(tail, text, next, rest)),
+    }
+}
+
+fn match_node(node: &Node, text: &[char], pos: usize, rest: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        Node::Start => pos == 0 && rest(pos),
+        Node::End => pos == text.len() && rest(pos),
+        Node::Group(alternatives) => alternatives.iter().any(|seq| match_seq(seq, text, pos, rest)),
+        Node::Repeat { node, min, max } => match_repeat(node, *min, *max, text, pos, rest),
+        single => match text.get(pos) {
+            Some(&c) if matches_char(single, c) => rest(pos + 1),
+            _ => false,
+        },
+    }
+}
+
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    text: &[char],
+    pos: usize,
+    rest: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    if max == Some(0) {
+        return rest(pos);
+    }
+    let next_max = max.map(|m| m - 1);
+    // Try one more repetition first; past `min`, one that consumes nothing would loop forever
+    let more = match_node(node, text, pos, &mut |next| {
+        (next > pos || min > 0) && match_repeat(node, min.saturating_sub(1), next_max, text, next, rest)
+    });
+    more || (min == 0 && rest(pos))
+}
+
+fn matches_char(node: &Node, c: char) -> bool {
+    match node {
+        Node::Char(expected) => c == *expected,
+        Node::Any => true,
+        Node::Class { ranges, negated } => ranges.iter().any(|&(low, high)| low <= c && c <= high) != *negated,
+        _ => false,
+    }
+}
@@ -0,0 +1,159 @@
+//! Principal component analysis of a store's vectors, fitted on a sample of
+//! rows: the mean, and the eigenvectors of the covariance matrix from the
+//! direction of most variance down.
+//!
+//! The covariance is a `dim x dim` matrix, so it is decomposed exactly with
+//! cyclic Jacobi rotations, which for a few hundred dimensions takes well
+//! under a second.
+
+use crate::dense::DenseStore;
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// Rows a fit reads by default; more barely move the leading components
+pub const SAMPLE_ROWS: usize = 50_000;
+
+/// Jacobi sweeps before giving up on convergence; a handful usually suffice
+const MAX_SWEEPS: usize = 50;
+
+/// Up to `count` rows spread evenly over the whole vocabulary, so rare words
+/// at the end of a frequency-sorted file are represented too
+pub fn sample_rows(store: &dyn EmbeddingStore, count: usize) -> Vec<WordVec> {
+    let len = store.len();
+    let count = count.min(len);
+    (0..count).filter_map(|i| store.get_by_id(i * len / count)).map(|(_, vec)| vec.into_owned()).collect()
+}
+
+/// The eigenvalues and unit eigenvectors of the symmetric `n x n` row-major
+/// `matrix`, largest eigenvalue first
+pub fn symmetric_eigen(matrix: &[f64], n: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let mut a = matrix.to_vec();
+    // Columns of `v` accumulate the rotations, ending as the eigenvectors
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    let scale: f64 = a.iter().map(|x| x * x).sum::<f64>().max(f64::MIN_POSITIVE);
+    for _ in 0..MAX_SWEEPS {
+        // The squared off-diagonal entries, which the rotations drive to zero
+        let off: f64 = (0..n).map(|i| (0..n).filter(|&j| j != i).map(|j| a[i * n + j].powi(2)).sum::<f64>()).sum();
+        if off <= scale * 1e-22 {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                // The rotation that zeroes a[p][q]
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| a[j * n + j].total_cmp(&a[i * n + i]));
+    let values = order.iter().map(|&i| a[i * n + i]).collect();
+    let vectors = order.iter().map(|&i| (0..n).map(|k| v[k * n + i]).collect()).collect();
+    (values, vectors)
+}
+
+/// The mean and principal directions of a set of vectors
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pca {
+    pub mean: WordVec,
+    /// Unit principal directions, most variance first
+    pub components: Vec<WordVec>,
+    /// The variance along each component
+    pub variances: Vec<f32>,
+}
+
+impl Pca {
+    /// Fits every component on `rows`, which must all have `dim` values
+    pub fn fit(rows: &[WordVec], dim: usize) -> Pca {
+        let count = rows.len().max(1) as f64;
+        let mut mean = vec![0.0f64; dim];
+        for row in rows {
+            for (m, x) in mean.iter_mut().zip(row) {
+                *m += *x as f64;
+            }
+        }
+        mean.iter_mut().for_each(|m| *m /= count);
+
+        // Only the upper triangle is accumulated, then mirrored
+        let mut covariance = vec![0.0f64; dim * dim];
+        let mut centered = vec![0.0f64; dim];
+        for row in rows {
+            for ((c, x), m) in centered.iter_mut().zip(row).zip(&mean) {
+                *c = *x as f64 - m;
+            }
+            for i in 0..dim {
+                let ci = centered[i];
+                for (cov, cj) in covariance[i * dim + i..(i + 1) * dim].iter_mut().zip(&centered[i..]) {
+                    *cov += ci * cj;
+                }
+            }
+        }
+        for i in 0..dim {
+            for j in i..dim {
+                let value = covariance[i * dim + j] / count;
+                covariance[i * dim + j] = value;
+                covariance[j * dim + i] = value;
+            }
+        }
+
+        let (values, vectors) = symmetric_eigen(&covariance, dim);
+        Pca {
+            mean: mean.iter().map(|&m| m as f32).collect(),
+            components: vectors.iter().map(|v| v.iter().map(|&x| x as f32).collect()).collect(),
+            variances: values.iter().map(|&x| x.max(0.0) as f32).collect(),
+        }
+    }
+
+    /// Fits every component on `sample` rows of `store`, spread over the vocabulary
+    pub fn fit_store(store: &dyn EmbeddingStore, sample: usize) -> Pca {
+        Pca::fit(&sample_rows(store, sample), store.dim())
+    }
+
+    /// The coordinates of `vec`, less the mean, along the first `dims` components
+    pub fn project(&self, vec: &[f32], dims: usize) -> WordVec {
+        let centered: WordVec = vec.iter().zip(&self.mean).map(|(x, m)| x - m).collect();
+        self.components.iter().take(dims).map(|c| c.iter().zip(&centered).map(|(a, b)| a * b).sum()).collect()
+    }
+
+    /// The fraction of the total variance along the first `dims` components
+    pub fn explained(&self, dims: usize) -> f32 {
+        let total: f32 = self.variances.iter().sum();
+        self.variances.iter().take(dims).sum::<f32>() / total.max(f32::MIN_POSITIVE)
+    }
+}
+
+/// Every vector of `store` projected on the first `dims` principal
+/// components of `sample` rows, in row order, with the fitted PCA
+pub fn reduce(store: &dyn EmbeddingStore, dims: usize, sample: usize) -> (DenseStore, Pca) {
+    let pca = Pca::fit_store(store, sample);
+    let mut out = DenseStore::new();
+    for (word, vec) in store.iter() {
+        out.insert(word.into_owned(), pca.project(&vec, dims));
+    }
+    (out, pca)
+}
@@ -0,0 +1,98 @@
+//! Post-processing applied to every vector when a source is opened, before
+//! it is searched, indexed or exported.
+//!
+//! - `abtt`, "all-but-the-top" (Mu and Viswanath, 2018): subtracts the mean
+//!   vector and then each vector's projection on the `D` dominant principal
+//!   directions. Those directions mostly encode word frequency, and removing
+//!   them improves word similarity and analogy scores.
+//! - `whiten`, ZCA whitening: subtracts the mean and rescales every principal
+//!   direction to unit variance, `W = E diag(1 / sqrt(λ + ε)) Eᵀ`, leaving an
+//!   isotropic space that stays as close as possible to the original axes.
+//!
+//! The transform is fitted on rows spread over the vocabulary, and the
+//! transformed vectors are held in memory whatever the backend.
+
+use crate::dense::DenseStore;
+use crate::pca::{Pca, SAMPLE_ROWS};
+use crate::store::EmbeddingStore;
+use crate::vector::matrix_vector;
+use crate::WordVec;
+
+/// Names accepted by `--postprocess`
+pub const POSTPROCESSES: &[&str] = &["abtt", "whiten"];
+
+/// Added to every variance before whitening, relative to the largest, so
+/// directions with almost no variance are not blown up
+const WHITEN_EPSILON: f32 = 1e-5;
+
+/// Which post-processing to apply, with its parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Postprocess {
+    /// Remove the mean and this many dominant components; `None` picks one
+    /// per 100 dimensions, the paper's rule of thumb
+    Abtt { components: Option<usize> },
+    /// ZCA whitening
+    Whiten,
+}
+
+/// A transform fitted to a store
+pub struct Transform {
+    mean: WordVec,
+    /// Unit directions whose components are removed
+    remove: Vec<WordVec>,
+    /// Row-major `dim x dim` matrix applied after centering, for whitening
+    matrix: Option<Vec<f32>>,
+}
+
+impl Transform {
+    /// Fits `postprocess` on a sample of `store`'s rows
+    pub fn fit(store: &dyn EmbeddingStore, postprocess: &Postprocess) -> Transform {
+        let pca = Pca::fit_store(store, SAMPLE_ROWS);
+        match postprocess {
+            Postprocess::Abtt { components } => {
+                let count = components.unwrap_or((store.dim() / 100).max(1));
+                let remove = pca.components.into_iter().take(count).collect();
+                Transform { mean: pca.mean, remove, matrix: None }
+            }
+            Postprocess::Whiten => {
+                let dim = store.dim();
+                let epsilon = WHITEN_EPSILON * pca.variances.first().copied().unwrap_or(1.0);
+                let mut matrix = vec![0.0f32; dim * dim];
+                for (component, variance) in pca.components.iter().zip(&pca.variances) {
+                    let scale = 1.0 / (variance + epsilon).sqrt();
+                    for (i, a) in component.iter().enumerate() {
+                        for (m, b) in matrix[i * dim..(i + 1) * dim].iter_mut().zip(component) {
+                            *m += scale * a * b;
+                        }
+                    }
+                }
+                Transform { mean: pca.mean, remove: Vec::new(), matrix: Some(matrix) }
+            }
+        }
+    }
+
+    pub fn apply(&self, vec: &[f32]) -> WordVec {
+        let mut out: WordVec = vec.iter().zip(&self.mean).map(|(x, m)| x - m).collect();
+        for direction in &self.remove {
+            let along: f32 = out.iter().zip(direction).map(|(x, d)| x * d).sum();
+            for (x, d) in out.iter_mut().zip(direction) {
+                *x -= along * d;
+            }
+        }
+        match &self.matrix {
+            Some(matrix) => matrix_vector(matrix, out.len(), &out),
+            None => out,
+        }
+    }
+}
+
+/// Replaces `store` with its post-processed vectors when asked to
+pub fn with_postprocess(store: Box<dyn EmbeddingStore>, postprocess: Option<&Postprocess>) -> Box<dyn EmbeddingStore> {
+    let Some(postprocess) = postprocess else { return store };
+    let transform = Transform::fit(store.as_ref(), postprocess);
+    let mut dense = DenseStore::new();
+    for (word, vec) in store.iter() {
+        dense.insert(word.into_owned(), transform.apply(&vec));
+    }
+    Box::new(dense)
+}
@@ -0,0 +1,244 @@
+//! Product quantization (Jégou et al., 2011): each vector is split into `m`
+//! sub-vectors and each sub-vector is replaced by the index of its nearest
+//! centroid in a per-subspace codebook of `2^bits` entries, so a row takes
+//! `m` bytes instead of `4 * dim`.
+//!
+//! Queries are scored asymmetrically: the query stays in full precision and
+//! its dot product with every centroid is tabulated once, after which each
+//! row costs `m` table lookups.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::rng::Rng;
+use crate::store::{EmbeddingStore, Entry};
+use crate::vector::{cosine_from_parts, dot_product, norm};
+use crate::WordVec;
+
+/// Rows buffered at full precision to train the codebooks on; GloVe files
+/// are sorted by frequency, so these are the most common words
+pub const TRAIN_ROWS: usize = 32_768;
+
+/// Candidates re-scored with exact vectors when `--rescore` is not given
+pub const PQ_RESCORE: usize = 100;
+
+const ITERATIONS: usize = 10;
+
+/// Fixed so the same file always gets the same codebooks
+const SEED: u64 = 0x9e0d_c0de_b00c_5eed;
+
+/// All vectors held in memory as product-quantization codes, in file order
+pub struct PqStore {
+    words: Vec<String>,
+    index: HashMap<String, usize>,
+    dim: usize,
+    subquantizers: Option<usize>,
+    bits: u8,
+    /// `(start, end)` of each subspace within a vector
+    ranges: Vec<(usize, usize)>,
+    /// Per subspace, `2^bits` centroids of the subspace's width, row-major
+    codebooks: Vec<Vec<f32>>,
+    /// Row-major `len * m` centroid indices
+    codes: Vec<u8>,
+    /// Norms of the reconstructed vectors, for cosine scoring
+    norms: Vec<f32>,
+    /// Rows waiting for the codebooks to be trained
+    pending: Vec<(String, WordVec)>,
+}
+
+impl PqStore {
+    /// An empty store splitting vectors into `subquantizers` subspaces (by
+    /// default a quarter of the dimensions, i.e. four components each) with
+    /// `2^bits` centroids per subspace; `bits` is at most 8
+    pub fn new(subquantizers: Option<usize>, bits: u8) -> Self {
+        PqStore {
+            words: Vec::new(),
+            index: HashMap::new(),
+            dim: 0,
+            subquantizers,
+            bits: bits.clamp(1, 8),
+            ranges: Vec::new(),
+            codebooks: Vec::new(),
+            codes: Vec::new(),
+            norms: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds a word, replacing the vector of an existing entry. Vectors whose
+    /// length differs from the first row's are skipped. The first
+    /// `TRAIN_ROWS` rows are held until the codebooks are trained on them.
+    pub fn insert(&mut self, word: String, vec: &[f32]) {
+        if self.words.is_empty() && self.pending.is_empty() {
+            self.dim = vec.len();
+        }
+        if vec.len() != self.dim {
+            return;
+        }
+        if self.codebooks.is_empty() {
+            self.pending.push((word, vec.to_vec()));
+            if self.pending.len() >= TRAIN_ROWS {
+                self.train();
+            }
+            return;
+        }
+
+        let codes = self.encode(vec);
+        let norm = norm(&self.decode(&codes));
+        let m = self.ranges.len();
+        if let Some(&id) = self.index.get(&word) {
+            self.codes[id * m..(id + 1) * m].copy_from_slice(&codes);
+            self.norms[id] = norm;
+            return;
+        }
+        self.index.insert(word.clone(), self.words.len());
+        self.words.push(word);
+        self.codes.extend_from_slice(&codes);
+        self.norms.push(norm);
+    }
+
+    /// Trains on and encodes any rows still held back; call once every row is in
+    pub fn finish(mut self) -> Self {
+        if self.codebooks.is_empty() && !self.pending.is_empty() {
+            self.train();
+        }
+        self
+    }
+
+    /// Quantizes every entry of another store
+    pub fn from_store(store: &dyn EmbeddingStore, subquantizers: Option<usize>, bits: u8) -> Self {
+        let mut pq = Self::new(subquantizers, bits);
+        for (word, vec) in store.iter() {
+            pq.insert(word.into_owned(), &vec);
+        }
+        pq.finish()
+    }
+
+    /// Bytes used by the codes
+    pub fn code_bytes(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Runs k-means in every subspace over the pending rows, then encodes them
+    fn train(&mut self) {
+        let m = self.subquantizers.unwrap_or(self.dim / 4).clamp(1, self.dim.max(1));
+        // Near-equal widths; the first `dim % m` subspaces take one extra component
+        let (width, extra) = (self.dim / m, self.dim % m);
+        let mut start = 0;
+        for i in 0..m {
+            let end = start + width + usize::from(i < extra);
+            self.ranges.push((start, end));
+            start = end;
+        }
+
+        let mut rng = Rng::new(SEED);
+        let centroids = 1usize << self.bits;
+        for &(start, end) in &self.ranges {
+            let sample: Vec<&[f32]> = self.pending.iter().map(|(_, v)| &v[start..end]).collect();
+            self.codebooks.push(kmeans(&sample, centroids, end - start, &mut rng));
+        }
+        for (word, vec) in std::mem::take(&mut self.pending) {
+            self.insert(word, &vec);
+        }
+    }
+
+    fn encode(&self, vec: &[f32]) -> Vec<u8> {
+        self.ranges
+            .iter()
+            .zip(&self.codebooks)
+            .map(|(&(start, end), codebook)| nearest(codebook, &vec[start..end]) as u8)
+            .collect()
+    }
+
+    fn decode(&self, codes: &[u8]) -> WordVec {
+        let mut vec = Vec::with_capacity(self.dim);
+        for ((&(start, end), codebook), &code) in self.ranges.iter().zip(&self.codebooks).zip(codes) {
+            let width = end - start;
+            vec.extend_from_slice(&codebook[code as usize * width..(code as usize + 1) * width]);
+        }
+        vec
+    }
+
+    fn row(&self, id: usize) -> &[u8] {
+        let m = self.ranges.len();
+        &self.codes[id * m..(id + 1) * m]
+    }
+}
+
+impl EmbeddingStore for PqStore {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.index.get(word).copied()
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let word = self.words.get(id)?;
+        Some((Cow::Borrowed(word.as_str()), Cow::Owned(self.decode(self.row(id)))))
+    }
+
+    /// Asymmetric distance computation: one table of query-centroid dot
+    /// products per subspace, then a lookup per code
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        let q_norm = norm(query);
+        let tables: Vec<Vec<f32>> = self
+            .ranges
+            .iter()
+            .zip(&self.codebooks)
+            .map(|(&(start, end), codebook)| {
+                codebook.chunks(end - start).map(|c| dot_product(&query[start..end], c)).collect()
+            })
+            .collect();
+
+        (0..self.words.len())
+            .map(|id| {
+                let dot: f32 = self.row(id).iter().zip(&tables).map(|(&code, table)| table[code as usize]).sum();
+                cosine_from_parts(dot, q_norm, self.norms[id])
+            })
+            .collect()
+    }
+}
+
+/// Euclidean k-means over `sample` sub-vectors, returning `k` centroids
+/// row-major; with fewer distinct samples than `k`, some centroids repeat
+fn kmeans(sample: &[&[f32]], k: usize, width: usize, rng: &mut Rng) -> Vec<f32> {
+    let mut centroids: Vec<f32> = (0..k).flat_map(|_| sample[rng.below(sample.len())].iter().copied()).collect();
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![0.0f32; k * width];
+        let mut counts = vec![0usize; k];
+        for vec in sample {
+            let cell = nearest(&centroids, vec);
+            counts[cell] += 1;
+            sums[cell * width..(cell + 1) * width].iter_mut().zip(vec.iter()).for_each(|(s, x)| *s += x);
+        }
+        for (cell, count) in counts.into_iter().enumerate() {
+            let centroid = &mut centroids[cell * width..(cell + 1) * width];
+            if count == 0 {
+                // An empty cell is restarted from a random sample
+                centroid.copy_from_slice(sample[rng.below(sample.len())]);
+            } else {
+                let sum = &sums[cell * width..(cell + 1) * width];
+                centroid.iter_mut().zip(sum).for_each(|(c, s)| *c = s / count as f32);
+            }
+        }
+    }
+    centroids
+}
+
+/// Index of the centroid (of `vec`'s width, row-major) closest to `vec`
+fn nearest(centroids: &[f32], vec: &[f32]) -> usize {
+    let mut best = (0, f32::INFINITY);
+    for (i, centroid) in centroids.chunks(vec.len().max(1)).enumerate() {
+        let distance: f32 = centroid.iter().zip(vec).map(|(c, x)| (c - x) * (c - x)).sum();
+        if distance < best.1 {
+            best = (i, distance);
+        }
+    }
+    best.0
+}
@@ -0,0 +1,89 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often the progress line is redrawn
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+const BAR_WIDTH: usize = 30;
+
+/// Progress line for long loads: bytes read, lines parsed and, when the
+/// total size is known, a bar and ETA. Drawn on stderr only when it is a terminal.
+pub struct Progress {
+    total_bytes: Option<u64>,
+    bytes: u64,
+    lines: u64,
+    start: Instant,
+    last_draw: Instant,
+    enabled: bool,
+    /// Also counts the bytes here, for a thread watching the load
+    counter: Option<Arc<AtomicU64>>,
+}
+
+impl Progress {
+    pub fn new(total_bytes: Option<u64>, enabled: bool) -> Self {
+        let now = Instant::now();
+        Progress {
+            total_bytes,
+            bytes: 0,
+            lines: 0,
+            start: now,
+            last_draw: now,
+            enabled: enabled && io::stderr().is_terminal(),
+            counter: None,
+        }
+    }
+
+    /// Adds the bytes read to `counter` as well, if given
+    pub fn counting(self, counter: Option<Arc<AtomicU64>>) -> Self {
+        Progress { counter, ..self }
+    }
+
+    /// Records one parsed line of `bytes` bytes
+    pub fn line(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+        self.lines += 1;
+        if let Some(counter) = &self.counter {
+            counter.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+        // Checking the clock on every line would dominate parsing of short lines
+        if self.enabled && self.lines.is_multiple_of(4096) && self.last_draw.elapsed() >= REDRAW_INTERVAL {
+            self.draw();
+            self.last_draw = Instant::now();
+        }
+    }
+
+    fn draw(&self) {
+        let mb = self.bytes as f64 / 1e6;
+        let mut line = match self.total_bytes {
+            Some(total) if total > 0 => {
+                let fraction = (self.bytes as f64 / total as f64).min(1.0);
+                let filled = (fraction * BAR_WIDTH as f64) as usize;
+                let elapsed = self.start.elapsed().as_secs_f64();
+                let eta = if fraction > 0.0 { elapsed / fraction - elapsed } else { 0.0 };
+                format!(
+                    "[{}{}] {:.1}/{:.1} MB, ETA {:.0}s",
+                    "#".repeat(filled),
+                    ".".repeat(BAR_WIDTH - filled),
+                    mb,
+                    total as f64 / 1e6,
+                    eta
+                )
+            }
+            _ => format!("{:.1} MB", mb),
+        };
+        line.push_str(&format!(", {} lines", self.lines));
+
+        let mut err = io::stderr();
+        let _ = write!(err, "\r{}\x1b[K", line);
+        let _ = err.flush();
+    }
+
+    /// Clears the progress line
+    pub fn finish(&self) {
+        if self.enabled {
+            let _ = write!(io::stderr(), "\r\x1b[K");
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Int8 scalar quantization: each vector is stored as `i8` codes plus one
+//! `f32` scale (`x ≈ code * scale`), a quarter of the memory of `f32` rows.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+/// Quantizes a vector symmetrically so its largest component maps to ±127
+pub fn quantize(vec: &[f32]) -> (Vec<i8>, f32) {
+    let max = vec.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+    let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+    let codes = vec.iter().map(|x| (x / scale).round().clamp(-127.0, 127.0) as i8).collect();
+    (codes, scale)
+}
+
+/// All vectors held in memory as int8 codes, in file order
+#[derive(Default)]
+pub struct QuantizedStore {
+    words: Vec<String>,
+    index: HashMap<String, usize>,
+    dim: usize,
+    /// Row-major `len * dim` codes
+    codes: Vec<i8>,
+    scales: Vec<f32>,
+    /// Norms of the dequantized vectors, for cosine scoring
+    norms: Vec<f32>,
+}
+
+impl QuantizedStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a word, replacing the vector of an existing entry. Vectors whose
+    /// length differs from the first row's are skipped.
+    pub fn insert(&mut self, word: String, vec: &[f32]) {
+        if self.words.is_empty() {
+            self.dim = vec.len();
+        }
+        if vec.len() != self.dim {
+            return;
+        }
+
+        let (codes, scale) = quantize(vec);
+        let norm = codes.iter().map(|&c| (c as f32 * scale).powi(2)).sum::<f32>().sqrt();
+        if let Some(&id) = self.index.get(&word) {
+            self.codes[id * self.dim..(id + 1) * self.dim].copy_from_slice(&codes);
+            self.scales[id] = scale;
+            self.norms[id] = norm;
+            return;
+        }
+        self.index.insert(word.clone(), self.words.len());
+        self.words.push(word);
+        self.codes.extend_from_slice(&codes);
+        self.scales.push(scale);
+        self.norms.push(norm);
+    }
+
+    /// Quantizes every entry of another store
+    pub fn from_store(store: &dyn EmbeddingStore) -> Self {
+        let mut quantized = Self::new();
+        for (word, vec) in store.iter() {
+            quantized.insert(word.into_owned(), &vec);
+        }
+        quantized
+    }
+
+    fn row(&self, id: usize) -> &[i8] {
+        &self.codes[id * self.dim..(id + 1) * self.dim]
+    }
+
+    fn dequantize(&self, id: usize) -> WordVec {
+        let scale = self.scales[id];
+        self.row(id).iter().map(|&c| c as f32 * scale).collect()
+    }
+}
+
+impl EmbeddingStore for QuantizedStore {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.index.get(word).copied()
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let word = self.words.get(id)?;
+        Some((Cow::Borrowed(word.as_str()), Cow::Owned(self.dequantize(id))))
+    }
+
+    /// Quantizes the query too and scores with integer dot products
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        let (q_codes, q_scale) = quantize(query);
+        let q_norm = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+        (0..self.words.len())
+            .map(|id| {
+                let dot: i32 = self
+                    .row(id)
+                    .iter()
+                    .zip(&q_codes)
+                    .map(|(&a, &b)| a as i32 * b as i32)
+                    .sum();
+                dot as f32 * q_scale * self.scales[id] / (q_norm * self.norms[id] + 1e-10)
+            })
+            .collect()
+    }
+}
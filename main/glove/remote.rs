@@ -0,0 +1,150 @@
+use std::borrow::Cow;
+use std::cell::{RefCell, RefMut};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Sends a request line in one write, so it goes out as one segment
+    fn send(&mut self, request: &str) -> io::Result<()> {
+        self.writer.write_all(format!("{}\n", request).as_bytes())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection"));
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    fn request(&mut self, request: &str) -> io::Result<String> {
+        self.send(request)?;
+        self.read_line()
+    }
+}
+
+fn parse_row(line: &str) -> Option<(String, WordVec)> {
+    let mut parts = line.split(' ');
+    let word = parts.next()?.to_string();
+    let vec = parts.map(|x| x.parse().ok()).collect::<Option<WordVec>>()?;
+    Some((word, vec))
+}
+
+/// Client for a store exposed by `serve` on another machine or process.
+///
+/// Requests share one connection, and `iter` has it to itself until the
+/// iterator is dropped: lookups made meanwhile find nothing, and an `iter`
+/// started meanwhile is empty. Callers that need both collect the rows first.
+pub struct RemoteStore {
+    conn: RefCell<Connection>,
+    len: usize,
+    dim: usize,
+}
+
+impl RemoteStore {
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        let writer = TcpStream::connect(addr)?;
+        // Each lookup waits for its reply, so Nagle's algorithm would only
+        // hold requests back until the server's delayed ACK
+        writer.set_nodelay(true)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        let mut conn = Connection { reader, writer };
+
+        let info = conn.request("INFO")?;
+        let mut fields = info.split(' ').map(|x| x.parse::<usize>());
+        let (len, dim) = match (fields.next(), fields.next()) {
+            (Some(Ok(len)), Some(Ok(dim))) => (len, dim),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "bad INFO response")),
+        };
+
+        Ok(RemoteStore { conn: RefCell::new(conn), len, dim })
+    }
+
+    /// Fetches the server's per-word access report
+    pub fn working_set(&self) -> io::Result<String> {
+        let mut conn = self.conn.try_borrow_mut().map_err(|_| {
+            io::Error::new(io::ErrorKind::WouldBlock, "the connection is busy streaming rows")
+        })?;
+        conn.send("WORKING-SET")?;
+        let mut report = String::new();
+        loop {
+            let line = conn.read_line()?;
+            if line.is_empty() {
+                return Ok(report);
+            }
+            report.push_str(&line);
+            report.push('\n');
+        }
+    }
+
+    fn request(&self, request: &str) -> Option<String> {
+        self.conn.try_borrow_mut().ok()?.request(request).ok()
+    }
+}
+
+impl EmbeddingStore for RemoteStore {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.request(&format!("ID {}", word))?.parse().ok()
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let (word, vec) = parse_row(&self.request(&format!("GET {}", id))?)?;
+        Some((Cow::Owned(word), Cow::Owned(vec)))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        // Stream the whole vocabulary over one request instead of a round trip per row
+        let Ok(mut conn) = self.conn.try_borrow_mut() else { return Box::new(std::iter::empty()) };
+        if conn.send("ITER").is_err() {
+            return Box::new(std::iter::empty());
+        }
+        Box::new(Rows { conn, done: false })
+    }
+}
+
+struct Rows<'a> {
+    conn: RefMut<'a, Connection>,
+    done: bool,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.conn.read_line() {
+                Ok(line) if !line.is_empty() => {
+                    // Skip rows that did not survive the trip intact
+                    if let Some((word, vec)) = parse_row(&line) {
+                        return Some((Cow::Owned(word), Cow::Owned(vec)));
+                    }
+                }
+                _ => self.done = true,
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Rows<'_> {
+    fn drop(&mut self) {
+        // Drain the rest of the listing so the next request reads its own reply
+        while self.next().is_some() {}
+    }
+}
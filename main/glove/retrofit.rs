@@ -0,0 +1,104 @@
+//! Retrofitting (Faruqui et al., 2015): pulls the vectors of words that a
+//! semantic lexicon relates (synonyms from WordNet, paraphrases from PPDB)
+//! towards each other, while keeping each close to its original vector.
+//!
+//! Each pass replaces a lexicon word's vector with the weighted mean of its
+//! original vector and its neighbors' current vectors, the original weighted
+//! by the number of neighbors and each neighbor by one, which is the paper's
+//! `α = 1, β = 1 / degree` setting. Words are updated in row order and later
+//! words see the earlier words' new vectors, as in the reference code. Words
+//! outside the lexicon keep their vectors.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::dense::DenseStore;
+use crate::store::EmbeddingStore;
+
+/// Passes made by default; the paper found ten enough to converge
+pub const DEFAULT_ITERATIONS: usize = 10;
+
+/// A semantic lexicon: each word's related words
+pub type Lexicon = HashMap<String, Vec<String>>;
+
+/// What a retrofitting run changed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Words moved towards their neighbors
+    pub retrofitted: usize,
+    /// Lexicon relations between two vocabulary words
+    pub edges: usize,
+    /// Lexicon words that are not in the vocabulary
+    pub unknown: usize,
+}
+
+/// Reads a lexicon in the format of the paper's WordNet and PPDB files: one
+/// `word related1 related2 ...` line per word. A word listed on several
+/// lines gets all of their related words; `#` starts a comment.
+pub fn read_lexicon(path: &str) -> io::Result<Lexicon> {
+    let mut lexicon = Lexicon::new();
+    for line in fs::read_to_string(path)?.lines() {
+        let mut tokens = line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(word) = tokens.next() else { continue };
+        lexicon.entry(word.to_string()).or_default().extend(tokens.map(str::to_string));
+    }
+    Ok(lexicon)
+}
+
+/// Retrofits every vector of `store` to `lexicon` over `iterations` passes,
+/// returning the vectors in the store's row order
+pub fn retrofit(store: &dyn EmbeddingStore, lexicon: &Lexicon, iterations: usize) -> (DenseStore, Report) {
+    let dim = store.dim();
+    let mut words = Vec::with_capacity(store.len());
+    let mut original = Vec::with_capacity(store.len() * dim);
+    for (word, vec) in store.iter() {
+        words.push(word.into_owned());
+        original.extend_from_slice(&vec);
+    }
+
+    let mut report = Report::default();
+    // (row, neighbor rows) for every lexicon word with a neighbor in the vocabulary
+    let mut graph: Vec<(usize, Vec<usize>)> = Vec::new();
+    for (word, related) in lexicon {
+        let Some(id) = store.id(word) else {
+            report.unknown += 1;
+            continue;
+        };
+        let mut neighbors: Vec<usize> =
+            related.iter().filter_map(|w| store.id(w)).filter(|&n| n != id && n < words.len()).collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        if id < words.len() && !neighbors.is_empty() {
+            report.edges += neighbors.len();
+            graph.push((id, neighbors));
+        }
+    }
+    graph.sort_unstable();
+    report.retrofitted = graph.len();
+
+    let mut current = original.clone();
+    let mut updated = vec![0.0f32; dim];
+    for _ in 0..iterations {
+        for (id, neighbors) in &graph {
+            let degree = neighbors.len() as f32;
+            for (u, x) in updated.iter_mut().zip(&original[id * dim..(id + 1) * dim]) {
+                *u = degree * x;
+            }
+            for n in neighbors {
+                for (u, x) in updated.iter_mut().zip(&current[n * dim..(n + 1) * dim]) {
+                    *u += x;
+                }
+            }
+            for (c, u) in current[id * dim..(id + 1) * dim].iter_mut().zip(&updated) {
+                *c = u / (2.0 * degree);
+            }
+        }
+    }
+
+    let mut out = DenseStore::new();
+    for (id, word) in words.into_iter().enumerate() {
+        out.insert(word, current[id * dim..(id + 1) * dim].to_vec());
+    }
+    (out, report)
+}
@@ -0,0 +1,310 @@
+//! RocksDB-backed store, linked against the system `librocksdb`.
+//!
+//! Opening a database reads two keys, so start-up takes no time and no memory
+//! whatever the vocabulary size; every lookup is a key read. Keys:
+//!
+//! - `meta:dim` and `meta:len`: decimal numbers
+//! - `w` + word: the word's id, 8 bytes big-endian
+//! - `i` + id (8 bytes big-endian, so ids iterate in order): the word's
+//!   length as 4 little-endian bytes, the word, then its vector as
+//!   little-endian f32 bytes
+
+use std::borrow::Cow;
+use std::ffi::{c_char, c_uchar, CStr, CString};
+use std::io;
+use std::ptr;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+#[allow(non_camel_case_types)]
+enum rocksdb_t {}
+#[allow(non_camel_case_types)]
+enum rocksdb_options_t {}
+#[allow(non_camel_case_types)]
+enum rocksdb_readoptions_t {}
+#[allow(non_camel_case_types)]
+enum rocksdb_writeoptions_t {}
+#[allow(non_camel_case_types)]
+enum rocksdb_writebatch_t {}
+#[allow(non_camel_case_types)]
+enum rocksdb_iterator_t {}
+
+/// Rows written per batch when creating a database
+const BATCH_ROWS: usize = 10_000;
+
+#[link(name = "rocksdb")]
+extern "C" {
+    fn rocksdb_options_create() -> *mut rocksdb_options_t;
+    fn rocksdb_options_destroy(options: *mut rocksdb_options_t);
+    fn rocksdb_options_set_create_if_missing(options: *mut rocksdb_options_t, value: c_uchar);
+    fn rocksdb_open(options: *const rocksdb_options_t, name: *const c_char, errptr: *mut *mut c_char)
+        -> *mut rocksdb_t;
+    fn rocksdb_open_for_read_only(
+        options: *const rocksdb_options_t,
+        name: *const c_char,
+        error_if_wal_file_exists: c_uchar,
+        errptr: *mut *mut c_char,
+    ) -> *mut rocksdb_t;
+    fn rocksdb_destroy_db(options: *const rocksdb_options_t, name: *const c_char, errptr: *mut *mut c_char);
+    fn rocksdb_close(db: *mut rocksdb_t);
+    fn rocksdb_free(ptr: *mut std::ffi::c_void);
+
+    fn rocksdb_readoptions_create() -> *mut rocksdb_readoptions_t;
+    fn rocksdb_readoptions_destroy(options: *mut rocksdb_readoptions_t);
+    fn rocksdb_writeoptions_create() -> *mut rocksdb_writeoptions_t;
+    fn rocksdb_writeoptions_destroy(options: *mut rocksdb_writeoptions_t);
+
+    fn rocksdb_get(
+        db: *mut rocksdb_t,
+        options: *const rocksdb_readoptions_t,
+        key: *const c_char,
+        keylen: usize,
+        vallen: *mut usize,
+        errptr: *mut *mut c_char,
+    ) -> *mut c_char;
+
+    fn rocksdb_writebatch_create() -> *mut rocksdb_writebatch_t;
+    fn rocksdb_writebatch_destroy(batch: *mut rocksdb_writebatch_t);
+    fn rocksdb_writebatch_clear(batch: *mut rocksdb_writebatch_t);
+    fn rocksdb_writebatch_put(
+        batch: *mut rocksdb_writebatch_t,
+        key: *const c_char,
+        keylen: usize,
+        val: *const c_char,
+        vallen: usize,
+    );
+    fn rocksdb_write(
+        db: *mut rocksdb_t,
+        options: *const rocksdb_writeoptions_t,
+        batch: *mut rocksdb_writebatch_t,
+        errptr: *mut *mut c_char,
+    );
+
+    fn rocksdb_create_iterator(db: *mut rocksdb_t, options: *const rocksdb_readoptions_t) -> *mut rocksdb_iterator_t;
+    fn rocksdb_iter_destroy(iter: *mut rocksdb_iterator_t);
+    fn rocksdb_iter_seek(iter: *mut rocksdb_iterator_t, key: *const c_char, keylen: usize);
+    fn rocksdb_iter_valid(iter: *const rocksdb_iterator_t) -> c_uchar;
+    fn rocksdb_iter_next(iter: *mut rocksdb_iterator_t);
+    fn rocksdb_iter_key(iter: *const rocksdb_iterator_t, keylen: *mut usize) -> *const c_char;
+    fn rocksdb_iter_value(iter: *const rocksdb_iterator_t, vallen: *mut usize) -> *const c_char;
+}
+
+/// Turns a RocksDB error string (if set) into an `io::Error`, freeing it
+fn check(err: *mut c_char) -> io::Result<()> {
+    if err.is_null() {
+        return Ok(());
+    }
+    let msg = unsafe { CStr::from_ptr(err) }.to_string_lossy().into_owned();
+    unsafe { rocksdb_free(err as *mut std::ffi::c_void) };
+    Err(io::Error::other(format!("rocksdb: {}", msg)))
+}
+
+/// Owned database handle with the options it was opened with
+struct Db {
+    raw: *mut rocksdb_t,
+    options: *mut rocksdb_options_t,
+    read: *mut rocksdb_readoptions_t,
+}
+
+impl Db {
+    fn open(path: &str, writable: bool) -> io::Result<Db> {
+        let c_path = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut err = ptr::null_mut();
+        unsafe {
+            let options = rocksdb_options_create();
+            let raw = if writable {
+                // Replace any previous database, as `--to sqlite` replaces its tables
+                rocksdb_destroy_db(options, c_path.as_ptr(), &mut err);
+                if let Err(e) = check(err) {
+                    rocksdb_options_destroy(options);
+                    return Err(e);
+                }
+                rocksdb_options_set_create_if_missing(options, 1);
+                rocksdb_open(options, c_path.as_ptr(), &mut err)
+            } else {
+                rocksdb_open_for_read_only(options, c_path.as_ptr(), 0, &mut err)
+            };
+            if let Err(e) = check(err) {
+                rocksdb_options_destroy(options);
+                return Err(e);
+            }
+            Ok(Db { raw, options, read: rocksdb_readoptions_create() })
+        }
+    }
+
+    /// Reads the value of `key`, if present
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let mut len = 0;
+        let mut err = ptr::null_mut();
+        let value =
+            unsafe { rocksdb_get(self.raw, self.read, key.as_ptr() as *const c_char, key.len(), &mut len, &mut err) };
+        check(err)?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(value as *const u8, len) }.to_vec();
+        unsafe { rocksdb_free(value as *mut std::ffi::c_void) };
+        Ok(Some(bytes))
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        unsafe {
+            rocksdb_readoptions_destroy(self.read);
+            rocksdb_close(self.raw);
+            rocksdb_options_destroy(self.options);
+        }
+    }
+}
+
+fn word_key(word: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(word.len() + 1);
+    key.push(b'w');
+    key.extend_from_slice(word.as_bytes());
+    key
+}
+
+fn id_key(id: usize) -> [u8; 9] {
+    let mut key = [b'i'; 9];
+    key[1..].copy_from_slice(&(id as u64).to_be_bytes());
+    key
+}
+
+/// Splits an `i` row into its word and vector
+fn decode_row(value: &[u8]) -> Option<(String, WordVec)> {
+    let len = u32::from_le_bytes(value.get(..4)?.try_into().ok()?) as usize;
+    let word = String::from_utf8_lossy(value.get(4..4 + len)?).into_owned();
+    let vec = value[4 + len..].chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    Some((word, vec))
+}
+
+/// Vectors kept in a RocksDB database and read one key at a time
+pub struct RocksStore {
+    db: Db,
+    len: usize,
+    dim: usize,
+}
+
+impl RocksStore {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let db = Db::open(path, false)?;
+        let number = |key: &[u8]| -> io::Result<usize> {
+            let value = db.get(key)?.unwrap_or_default();
+            String::from_utf8_lossy(&value)
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a vector database written by convert"))
+        };
+        let (dim, len) = (number(b"meta:dim")?, number(b"meta:len")?);
+        Ok(RocksStore { db, len, dim })
+    }
+
+    /// Writes every entry of a store into a new RocksDB database
+    pub fn create(path: &str, store: &dyn EmbeddingStore) -> io::Result<()> {
+        let db = Db::open(path, true)?;
+        let mut err = ptr::null_mut();
+        unsafe {
+            let write = rocksdb_writeoptions_create();
+            let batch = rocksdb_writebatch_create();
+            let put = |key: &[u8], value: &[u8]| {
+                rocksdb_writebatch_put(
+                    batch,
+                    key.as_ptr() as *const c_char,
+                    key.len(),
+                    value.as_ptr() as *const c_char,
+                    value.len(),
+                )
+            };
+
+            let mut len = 0;
+            let mut result = Ok(());
+            for (id, (word, vec)) in store.iter().enumerate() {
+                let mut row = Vec::with_capacity(4 + word.len() + 4 * vec.len());
+                row.extend_from_slice(&(word.len() as u32).to_le_bytes());
+                row.extend_from_slice(word.as_bytes());
+                row.extend(vec.iter().flat_map(|x| x.to_le_bytes()));
+                put(&word_key(&word), &(id as u64).to_be_bytes());
+                put(&id_key(id), &row);
+                len = id + 1;
+                if len % BATCH_ROWS == 0 {
+                    rocksdb_write(db.raw, write, batch, &mut err);
+                    rocksdb_writebatch_clear(batch);
+                    result = check(err);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }
+            if result.is_ok() {
+                put(b"meta:dim", store.dim().to_string().as_bytes());
+                put(b"meta:len", len.to_string().as_bytes());
+                rocksdb_write(db.raw, write, batch, &mut err);
+                result = check(err);
+            }
+
+            rocksdb_writebatch_destroy(batch);
+            rocksdb_writeoptions_destroy(write);
+            result
+        }
+    }
+}
+
+impl EmbeddingStore for RocksStore {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        let value = self.db.get(&word_key(word)).ok()??;
+        Some(u64::from_be_bytes(value.try_into().ok()?) as usize)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let (word, vec) = decode_row(&self.db.get(&id_key(id)).ok()??)?;
+        Some((Cow::Owned(word), Cow::Owned(vec)))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        // One iterator over the `i` keys, in id order, instead of a read per row
+        let raw = unsafe { rocksdb_create_iterator(self.db.raw, self.db.read) };
+        unsafe { rocksdb_iter_seek(raw, b"i".as_ptr() as *const c_char, 1) };
+        Box::new(Rows { raw, _db: &self.db })
+    }
+}
+
+struct Rows<'a> {
+    raw: *mut rocksdb_iterator_t,
+    _db: &'a Db,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if rocksdb_iter_valid(self.raw) == 0 {
+                return None;
+            }
+            let mut len = 0;
+            let key = rocksdb_iter_key(self.raw, &mut len);
+            if len != 9 || *key as u8 != b'i' {
+                return None;
+            }
+            let value = rocksdb_iter_value(self.raw, &mut len);
+            let row = decode_row(std::slice::from_raw_parts(value as *const u8, len));
+            rocksdb_iter_next(self.raw);
+            row.map(|(word, vec)| (Cow::Owned(word), Cow::Owned(vec)))
+        }
+    }
+}
+
+impl Drop for Rows<'_> {
+    fn drop(&mut self) {
+        unsafe { rocksdb_iter_destroy(self.raw) };
+    }
+}
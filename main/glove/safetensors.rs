@@ -0,0 +1,231 @@
+//! safetensors files: an 8-byte header length, a JSON header describing each
+//! tensor, then the raw tensor data.
+//!
+//! Written files hold one `embeddings` tensor (float32, one row per word) and
+//! have their vocabulary beside them in `<name>.vocab.json`, mapping each word
+//! to its row. Reading takes the largest 2-d float tensor (F32, F16, BF16 or
+//! F64) and the words from `<name>.vocab.json` or `vocab.json` (a word-to-row
+//! object or an array of words), else from a vocabulary text file as for `.npy`.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::formats::{decode_floats, npy_vocabulary, read_bytes};
+use crate::half::f16_to_f32;
+use crate::input::Source;
+use crate::json::{json_string, Json};
+use crate::load::RowSink;
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// Headers above this size are treated as not being safetensors at all
+const MAX_HEADER_LEN: u64 = 100 << 20;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Whether the first bytes look like a safetensors header
+pub(crate) fn is_safetensors(head: &[u8]) -> bool {
+    head.len() > 8 && u64::from_le_bytes(head[..8].try_into().unwrap()) <= MAX_HEADER_LEN && head[8] == b'{'
+}
+
+/// Where the vocabulary of a safetensors file is written: `<name>.vocab.json`
+pub fn vocab_path(path: &str) -> String {
+    Path::new(path).with_extension("vocab.json").display().to_string()
+}
+
+/// Writes the vectors as an `embeddings` float32 tensor, and the word-to-row
+/// mapping in `<name>.vocab.json` beside it
+pub fn write_safetensors(store: &dyn EmbeddingStore, path: &str) -> io::Result<()> {
+    let (len, dim) = (store.len(), store.dim());
+    let mut header = format!(
+        "{{\"embeddings\":{{\"dtype\":\"F32\",\"shape\":[{},{}],\"data_offsets\":[0,{}]}}}}",
+        len,
+        dim,
+        len * dim * 4
+    );
+    // The data is kept 8-byte aligned by padding the header with spaces
+    header.extend(std::iter::repeat_n(' ', header.len().next_multiple_of(8) - header.len()));
+
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut vocab = BufWriter::new(File::create(vocab_path(path))?);
+    out.write_all(&(header.len() as u64).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    write!(vocab, "{{")?;
+    for (id, (word, vec)) in store.iter().enumerate() {
+        for val in vec.iter() {
+            out.write_all(&val.to_le_bytes())?;
+        }
+        let separator = if id == 0 { "" } else { ",\n" };
+        write!(vocab, "{}{}: {}", separator, json_string(&word), id)?;
+    }
+    writeln!(vocab, "}}")?;
+    out.flush()?;
+    vocab.flush()
+}
+
+/// The tensor the vectors are read from
+struct Tensor {
+    name: String,
+    dtype: String,
+    rows: usize,
+    dim: usize,
+    /// Bytes per component
+    width: usize,
+    begin: usize,
+}
+
+/// Reads the largest 2-d float tensor of a safetensors file as the vectors
+pub(crate) fn read_safetensors(source: &Source, reader: &mut dyn BufRead, sink: &mut RowSink) -> io::Result<()> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_HEADER_LEN {
+        return Err(invalid(format!("safetensors header of {} bytes is too long", len)));
+    }
+    let header = read_bytes(reader, len as usize)?;
+    sink.read(8 + header.len());
+    let data_len = source.text_len().map(|total| total.saturating_sub(8 + len));
+    let header = Json::parse(&String::from_utf8_lossy(&header)).map_err(invalid)?;
+    let Json::Object(entries) = &header else { return Err(invalid("the safetensors header is not an object")) };
+
+    let mut tensor: Option<Tensor> = None;
+    for (name, info) in entries {
+        let dtype = info.get("dtype").and_then(Json::as_str).unwrap_or_default();
+        let shape = info.get("shape").and_then(Json::as_array).unwrap_or_default();
+        let shape: Vec<usize> = shape.iter().filter_map(Json::as_usize).collect();
+        let offsets = info.get("data_offsets").and_then(Json::as_array).unwrap_or_default();
+        let offsets: Vec<usize> = offsets.iter().filter_map(Json::as_usize).collect();
+        let ("F32" | "F16" | "BF16" | "F64", &[rows, dim], &[begin, end]) = (dtype, &shape[..], &offsets[..]) else {
+            continue;
+        };
+        let width = match dtype {
+            "F64" => 8,
+            "F32" => 4,
+            _ => 2,
+        };
+        // The shape has to fit between the offsets, and the offsets inside the file
+        let size = rows.checked_mul(dim).and_then(|n| n.checked_mul(width));
+        if size.is_none_or(|size| begin.checked_add(size).is_none_or(|needed| needed > end))
+            || data_len.is_some_and(|data_len| end as u64 > data_len)
+        {
+            return Err(invalid(format!("tensor {} does not fit its data offsets", name)));
+        }
+        if tensor.as_ref().is_none_or(|t| rows * dim > t.rows * t.dim) {
+            tensor = Some(Tensor { name: name.clone(), dtype: dtype.to_string(), rows, dim, width, begin });
+        }
+    }
+    let Some(tensor) = tensor else { return Err(invalid("no 2-d float tensor in the safetensors file")) };
+
+    let vocab = safetensors_vocabulary(source)?;
+    if vocab.len() < tensor.rows {
+        return Err(invalid(format!(
+            "tensor {} has {} rows but only {} vocabulary words",
+            tensor.name,
+            tensor.rows,
+            vocab.len()
+        )));
+    }
+
+    // Tensors are stored one after another, so skip those before this one
+    io::copy(&mut (&mut *reader).take(tensor.begin as u64), &mut io::sink())?;
+    for word in &vocab[..tensor.rows] {
+        if sink.full() {
+            break;
+        }
+        let bytes = read_bytes(reader, tensor.dim * tensor.width)?;
+        sink.read(bytes.len());
+        if sink.wants(word) {
+            sink.add(word, decode(&bytes, &tensor.dtype));
+        }
+    }
+    Ok(())
+}
+
+fn decode(bytes: &[u8], dtype: &str) -> WordVec {
+    let halves = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    match dtype {
+        "F16" => halves.map(f16_to_f32).collect(),
+        "BF16" => halves.map(|h| f32::from_bits((h as u32) << 16)).collect(),
+        "F64" => decode_floats(bytes, 8),
+        _ => decode_floats(bytes, 4),
+    }
+}
+
+/// Words for each row, from `<name>.vocab.json` or `vocab.json` next to the file,
+/// else from a vocabulary text file as for `.npy`
+fn safetensors_vocabulary(source: &Source) -> io::Result<Vec<String>> {
+    let Source::Path(path) = source else {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "safetensors files cannot be read from archives"));
+    };
+    let candidates = [vocab_path(path), Path::new(path).with_file_name("vocab.json").display().to_string()];
+    let Some(vocab) = candidates.iter().find(|p| Path::new(p).is_file()) else {
+        return npy_vocabulary(source);
+    };
+
+    let json = Json::parse(&fs::read_to_string(vocab)?).map_err(|e| invalid(format!("{}: {}", vocab, e)))?;
+    match json {
+        Json::Array(words) => Ok(words.iter().map(|w| w.as_str().unwrap_or_default().to_string()).collect()),
+        Json::Object(mapping) => {
+            let mut words = vec![String::new(); mapping.len()];
+            for (word, row) in mapping {
+                match row.as_usize() {
+                    Some(row) if row < words.len() => words[row] = word,
+                    _ => return Err(invalid(format!("{}: bad row for {}", vocab, word))),
+                }
+            }
+            Ok(words)
+        }
+        _ => Err(invalid(format!("{}: expected a word-to-row object or an array of words", vocab))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_truncations_fail, load_rows, rows, sample_store, TempDir};
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.safetensors");
+        let store = sample_store();
+        write_safetensors(&store, &path).unwrap();
+        assert_eq!(load_rows(&path).unwrap(), rows(&store));
+    }
+
+    #[test]
+    fn truncated_files_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.safetensors");
+        write_safetensors(&sample_store(), &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_truncations_fail(&path, &bytes, 9);
+    }
+
+    #[test]
+    fn shapes_outside_the_data_fail() {
+        let dir = TempDir::new();
+        let path = dir.file("vectors.safetensors");
+        write_safetensors(&sample_store(), &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap()) as usize;
+        let header = String::from_utf8(bytes[8..8 + header_len].to_vec()).unwrap();
+        for (from, to) in [
+            ("[5,4]", "[4294967296,4294967296]"),
+            ("[5,4]", "[10,4]"),
+            ("[0,80]", "[0,8000]"),
+            ("[0,80]", "[40,80]"),
+        ] {
+            let changed = header.replace(from, to);
+            assert_ne!(changed, header);
+            let mut corrupt = (changed.len() as u64).to_le_bytes().to_vec();
+            corrupt.extend(changed.as_bytes());
+            corrupt.extend(&bytes[8 + header_len..]);
+            fs::write(&path, &corrupt).unwrap();
+            assert!(load_rows(&path).is_err(), "{} went unnoticed", changed);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::pattern::Pattern;
+use crate::store::EmbeddingStore;
+use crate::metric::Metric;
+use crate::WordVec;
+
+/// Queries scored together by `find_nearest_neighbors_batch`, bounding the
+/// memory held by their score rows
+const BATCH: usize = 64;
+
+/// Which rows may be returned as neighbors, chosen by patterns on their words
+pub struct CandidateFilter {
+    allowed: Vec<bool>,
+}
+
+impl CandidateFilter {
+    /// Allows the words of `store` that match `include` (if given) and do not
+    /// match `exclude` (if given)
+    pub fn new(store: &dyn EmbeddingStore, include: Option<&Pattern>, exclude: Option<&Pattern>) -> Self {
+        let allowed = store
+            .iter()
+            .map(|(word, _)| include.is_none_or(|p| p.is_match(&word)) && !exclude.is_some_and(|p| p.is_match(&word)))
+            .collect();
+        CandidateFilter { allowed }
+    }
+
+    /// Marks the scores of disallowed rows NaN, which `top_k` skips
+    pub fn apply(&self, scores: &mut [f32]) {
+        for (score, &allowed) in scores.iter_mut().zip(&self.allowed) {
+            if !allowed {
+                *score = f32::NAN;
+            }
+        }
+    }
+}
+
+/// `find_nearest_neighbors` for several queries, each with its own excluded
+/// words, scored in batches by `Metric::score_rows_batch` (for the built-in
+/// cosine-based metrics, the store's `cosine_scores_batch`)
+pub fn find_nearest_neighbors_batch(
+    queries: &[(WordVec, Vec<String>)],
+    store: &dyn EmbeddingStore,
+    k: usize,
+    metric: &dyn Metric,
+) -> Vec<Vec<(String, f32)>> {
+    find_nearest_neighbors_filtered(queries, store, k, metric, None)
+}
+
+/// `find_nearest_neighbors_batch` considering only the rows `filter` allows
+pub fn find_nearest_neighbors_filtered(
+    queries: &[(WordVec, Vec<String>)],
+    store: &dyn EmbeddingStore,
+    k: usize,
+    metric: &dyn Metric,
+    filter: Option<&CandidateFilter>,
+) -> Vec<Vec<(String, f32)>> {
+    let mut found = Vec::with_capacity(queries.len());
+    for batch in queries.chunks(BATCH) {
+        let vectors: Vec<WordVec> = batch.iter().map(|(query, _)| query.clone()).collect();
+        for (mut scores, (_, exclude)) in metric.score_rows_batch(&vectors, store).into_iter().zip(batch) {
+            if let Some(filter) = filter {
+                filter.apply(&mut scores);
+            }
+            found.push(top_k(scores, store, exclude, k, metric.higher_is_better()));
+        }
+    }
+    found
+}
+
+/// Finds the most similar word under `metric`, excluding the input words themselves
+pub fn find_nearest_neighbor(
+    target_vec: &[f32],
+    store: &dyn EmbeddingStore,
+    exclude_words: &[String],
+    metric: &dyn Metric,
+) -> Option<(String, f32)> {
+    find_nearest_neighbors(target_vec, store, exclude_words, 1, metric).pop()
+}
+
+/// Finds the `k` most similar words, best first, excluding the input words
+pub fn find_nearest_neighbors(
+    target_vec: &[f32],
+    store: &dyn EmbeddingStore,
+    exclude_words: &[String],
+    k: usize,
+    metric: &dyn Metric,
+) -> Vec<(String, f32)> {
+    let scores = metric.score_rows(target_vec, store);
+    top_k(scores, store, exclude_words, k, metric.higher_is_better())
+}
+
+/// Every row id with its score under `metric`, best first
+pub fn rank_all(target_vec: &[f32], store: &dyn EmbeddingStore, metric: &dyn Metric) -> Vec<(usize, f32)> {
+    let mut ranked: Vec<(usize, f32)> = metric.score_rows(target_vec, store).into_iter().enumerate().collect();
+    if metric.higher_is_better() {
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    } else {
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+    }
+    ranked
+}
+
+/// Picks the `k` best of per-row `scores`, best first, skipping excluded words
+/// and NaN scores (rows an approximate index did not consider)
+pub fn top_k(
+    scores: Vec<f32>,
+    store: &dyn EmbeddingStore,
+    exclude_words: &[String],
+    k: usize,
+    higher_is_better: bool,
+) -> Vec<(String, f32)> {
+    let exclude_ids: Vec<usize> = exclude_words.iter().filter_map(|w| store.id(w)).collect();
+    let better = |a: f32, b: f32| if higher_is_better { a > b } else { a < b };
+
+    // Kept sorted best-first and capped at k entries
+    let mut best: Vec<(usize, f32)> = Vec::with_capacity(k.min(scores.len()) + 1);
+    for (id, score) in scores.into_iter().enumerate() {
+        // Skip input words
+        if exclude_ids.contains(&id) || score.is_nan() {
+            continue;
+        }
+        if best.len() == k && !best.last().is_some_and(|&(_, worst)| better(score, worst)) {
+            continue;
+        }
+        let pos = best.partition_point(|&(_, s)| !better(score, s));
+        best.insert(pos, (id, score));
+        best.truncate(k);
+    }
+
+    best.into_iter()
+        .filter_map(|(id, score)| Some((store.get_by_id(id)?.0.into_owned(), score)))
+        .collect()
+}
+
+/// Re-scores candidates against exact vectors (e.g. after an approximate
+/// scan over quantized vectors) and re-sorts them best first
+pub fn rescore(
+    target_vec: &[f32],
+    candidates: Vec<(String, f32)>,
+    exact: &dyn EmbeddingStore,
+    metric: &dyn Metric,
+) -> Vec<(String, f32)> {
+    let mut rescored: Vec<(String, f32)> = candidates
+        .into_iter()
+        .map(|(word, approx)| {
+            let score = exact.get(&word).map_or(approx, |vec| metric.score(target_vec, &vec));
+            (word, score)
+        })
+        .collect();
+
+    if metric.higher_is_better() {
+        rescored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    } else {
+        rescored.sort_by(|a, b| a.1.total_cmp(&b.1));
+    }
+    rescored
+}
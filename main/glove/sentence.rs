@@ -0,0 +1,197 @@
+//! Sentence embeddings built from the vectors of a sentence's words.
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::expression::{Combine, Expression};
+use crate::stopwords::Stopwords;
+use crate::store::EmbeddingStore;
+use crate::WordVec;
+
+/// Lowercases `text` and splits it on whitespace, trimming punctuation from
+/// both ends of each token (`"Hello, world!"` gives `hello`, `world`) but
+/// keeping it inside words such as `don't` or `u.s`
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|token| token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+/// The tokens of `text` that are not stopwords, and the stopwords left out.
+/// A sentence whose only known words are stopwords keeps them all, so it
+/// still has a vector.
+fn content_tokens(
+    store: &dyn EmbeddingStore,
+    text: &str,
+    stopwords: Option<&Stopwords>,
+) -> (Vec<String>, Vec<String>) {
+    let tokens = tokenize(text);
+    let Some(stopwords) = stopwords else { return (tokens, Vec::new()) };
+    let (stopped, content): (Vec<String>, Vec<String>) = tokens.iter().cloned().partition(|t| stopwords.contains(t));
+    if !content.iter().any(|t| store.contains(t)) {
+        (tokens, Vec::new())
+    } else {
+        (content, stopped)
+    }
+}
+
+/// A sentence's vector and which of its tokens contributed to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SentenceVector {
+    pub vector: WordVec,
+    /// Tokens found in the vocabulary, in sentence order
+    pub known: Vec<String>,
+    /// Tokens that were skipped
+    pub unknown: Vec<String>,
+    /// Stopwords that were left out
+    pub stopped: Vec<String>,
+}
+
+/// Sums or averages the vectors of the in-vocabulary tokens of `text`, less
+/// any `stopwords`; `None` if the store knows none of them
+pub fn embed(
+    store: &dyn EmbeddingStore,
+    text: &str,
+    combine: Combine,
+    stopwords: Option<&Stopwords>,
+) -> Option<SentenceVector> {
+    let (tokens, stopped) = content_tokens(store, text, stopwords);
+    let vector = Expression::sum(&tokens).combine(store, combine)?;
+    let (known, unknown) = tokens.into_iter().partition(|t| store.contains(t));
+    Some(SentenceVector { vector, known, unknown, stopped })
+}
+
+/// The SIF smoothing term `a` recommended by Arora et al. (2017)
+pub const SIF_A: f32 = 1e-3;
+
+/// Smooth Inverse Frequency weighting (Arora, Liang & Ma, 2017): each word is
+/// weighted by `a / (a + p(word))` so frequent words count for less, and the
+/// component that sentences share (largely syntax and stop words) can be
+/// projected out of every sentence vector.
+#[derive(Clone, Debug, Default)]
+pub struct Sif {
+    a: f32,
+    probabilities: HashMap<String, f32>,
+    /// First principal component of a set of reference sentences
+    component: Option<WordVec>,
+}
+
+impl Sif {
+    /// Reads word frequencies from `word count` lines, e.g. the
+    /// `enwiki_vocab_min200.txt` file distributed with SIF
+    pub fn from_file(path: &str, a: f32) -> Result<Sif, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut counts = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let (Some(word), Some(count), None) = (fields.next(), fields.next(), fields.next()) else {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Err(format!("{}: line {}: expected 'word count'", path, n + 1));
+            };
+            let count: f64 = count.parse().map_err(|_| format!("{}: line {}: invalid count", path, n + 1))?;
+            counts.push((word.to_lowercase(), count));
+        }
+        let total: f64 = counts.iter().map(|(_, c)| c).sum();
+        if total <= 0.0 {
+            return Err(format!("{}: no word counts", path));
+        }
+        let probabilities = counts.into_iter().map(|(w, c)| (w, (c / total) as f32)).collect();
+        Ok(Sif { a, probabilities, component: None })
+    }
+
+    /// `a / (a + p(word))`; words missing from the frequency file get weight 1
+    pub fn weight(&self, word: &str) -> f32 {
+        match self.probabilities.get(word) {
+            Some(p) => self.a / (self.a + p),
+            None => 1.0,
+        }
+    }
+
+    /// The weighted average of the sentence's word vectors, with the common
+    /// component removed if one has been fitted; `None` if no word is known
+    pub fn embed(
+        &self,
+        store: &dyn EmbeddingStore,
+        text: &str,
+        stopwords: Option<&Stopwords>,
+    ) -> Option<SentenceVector> {
+        let mut embedded = self.weighted_average(store, text, stopwords)?;
+        if let Some(component) = &self.component {
+            remove_component(&mut embedded.vector, component);
+        }
+        Some(embedded)
+    }
+
+    /// Estimates the common component from reference sentences (at least
+    /// two must contain known words); returns whether one was found
+    pub fn fit_component<S: AsRef<str>>(
+        &mut self,
+        store: &dyn EmbeddingStore,
+        sentences: &[S],
+        stopwords: Option<&Stopwords>,
+    ) -> bool {
+        let vectors: Vec<WordVec> = sentences
+            .iter()
+            .filter_map(|s| self.weighted_average(store, s.as_ref(), stopwords))
+            .map(|embedded| embedded.vector)
+            .collect();
+        self.component = if vectors.len() >= 2 { first_principal_component(&vectors) } else { None };
+        self.component.is_some()
+    }
+
+    fn weighted_average(
+        &self,
+        store: &dyn EmbeddingStore,
+        text: &str,
+        stopwords: Option<&Stopwords>,
+    ) -> Option<SentenceVector> {
+        let (tokens, stopped) = content_tokens(store, text, stopwords);
+        let weighted = Expression {
+            terms: tokens.iter().map(|t| (self.weight(t), t.clone())).collect(),
+        };
+        let vector = weighted.combine(store, Combine::Average)?;
+        let (known, unknown) = tokens.into_iter().partition(|t| store.contains(t));
+        Some(SentenceVector { vector, known, unknown, stopped })
+    }
+}
+
+/// The top right singular vector of the matrix whose rows are `vectors`,
+/// found by power iteration on its `d x d` Gram matrix; `None` if all are zero
+pub fn first_principal_component(vectors: &[WordVec]) -> Option<WordVec> {
+    let dim = vectors.first()?.len();
+    let mut gram = vec![0.0f64; dim * dim];
+    for vec in vectors {
+        for (row, &vi) in gram.chunks_mut(dim).zip(vec) {
+            for (g, &vj) in row.iter_mut().zip(vec) {
+                *g += vi as f64 * vj as f64;
+            }
+        }
+    }
+
+    let mut u = vec![1.0 / (dim as f64).sqrt(); dim];
+    for _ in 0..100 {
+        let mut next: Vec<f64> = gram.chunks(dim).map(|row| row.iter().zip(&u).map(|(g, x)| g * x).sum()).collect();
+        let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm == 0.0 {
+            return None;
+        }
+        next.iter_mut().for_each(|x| *x /= norm);
+        let moved: f64 = next.iter().zip(&u).map(|(a, b)| (a - b).abs()).sum();
+        u = next;
+        if moved < 1e-9 {
+            break;
+        }
+    }
+    Some(u.into_iter().map(|x| x as f32).collect())
+}
+
+/// Subtracts the projection of `vec` onto the unit vector `component`
+pub fn remove_component(vec: &mut [f32], component: &[f32]) {
+    let projection: f32 = vec.iter().zip(component).map(|(x, u)| x * u).sum();
+    for (x, u) in vec.iter_mut().zip(component) {
+        *x -= projection * u;
+    }
+}
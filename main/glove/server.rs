@@ -0,0 +1,169 @@
+//! Line-based TCP protocol that exposes any store to `RemoteStore` clients.
+//!
+//! ```text
+//! INFO        -> "<len> <dim>"
+//! ID <word>   -> "<id>" or "-"
+//! GET <id>    -> "<word> <floats...>" or "-"
+//! ITER        -> one "<word> <floats...>" line per row, then an empty line
+//! WORKING-SET -> the per-word access report, then an empty line
+//! NEAREST <k> <expression>
+//!             -> one "<word> <similarity>" line per cosine neighbor, then an empty line
+//! CACHE-STATS -> "<entries> <capacity> <hits> <misses>"
+//! ```
+//!
+//! Connections are served one at a time, in the order they arrive: a client
+//! holds the server until it disconnects, and others wait in the listener's
+//! backlog meanwhile, so clients should not keep idle connections open.
+//! `NEAREST` answers are kept in an LRU cache shared by every connection.
+//! With `normalize`, `GET` and `ITER` send every row scaled to unit length,
+//! so clients that score by dot product get cosine similarities.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::cache::LruCache;
+use crate::expression::Expression;
+use crate::metric::BuiltinMetric;
+use crate::search::find_nearest_neighbors;
+use crate::stats::AccessStats;
+use crate::store::EmbeddingStore;
+use crate::vector::normalize as to_unit;
+
+/// Cached `NEAREST` answers, keyed by the normalized expression and `k`
+type NearestCache = LruCache<(String, usize), Vec<(String, f32)>>;
+
+/// Formats a row the way `RemoteStore` parses it back
+pub fn format_row(word: &str, vec: &[f32]) -> String {
+    let mut line = word.to_string();
+    for val in vec {
+        line.push(' ');
+        line.push_str(&val.to_string());
+    }
+    line
+}
+
+/// Serves the store on `addr`, one connection at a time, caching up to
+/// `cache_capacity` query answers and, with `normalize`, sending unit vectors
+pub fn serve(store: &dyn EmbeddingStore, addr: &str, cache_capacity: usize, normalize: bool) -> io::Result<()> {
+    serve_on(TcpListener::bind(addr)?, store, cache_capacity, normalize)
+}
+
+/// Serves the store on a listener already bound, as `serve` does; connections
+/// made before this is called wait in the listener's backlog
+pub fn serve_on(
+    listener: TcpListener,
+    store: &dyn EmbeddingStore,
+    cache_capacity: usize,
+    normalize: bool,
+) -> io::Result<()> {
+    eprintln!("Serving {} words on {}", store.len(), listener.local_addr()?);
+
+    // Kept across connections so the report reflects the whole server lifetime
+    let mut stats = AccessStats::new();
+    let mut cache = LruCache::new(cache_capacity);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Connection failed: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = handle_client(store, &mut stats, &mut cache, normalize, stream) {
+            eprintln!("Client error: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_client(
+    store: &dyn EmbeddingStore,
+    stats: &mut AccessStats,
+    cache: &mut NearestCache,
+    normalize: bool,
+    stream: TcpStream,
+) -> io::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut out = BufWriter::new(stream);
+    let row = |word: &str, vec: &[f32]| {
+        if normalize {
+            let mut vec = vec.to_vec();
+            to_unit(&mut vec);
+            format_row(word, &vec)
+        } else {
+            format_row(word, vec)
+        }
+    };
+
+    for line in reader.lines() {
+        let line = line?;
+        let (command, arg) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+
+        match command {
+            "INFO" => writeln!(out, "{} {}", store.len(), store.dim())?,
+            "ID" => match store.id(arg) {
+                Some(id) => {
+                    stats.record(arg);
+                    writeln!(out, "{}", id)?
+                }
+                None => writeln!(out, "-")?,
+            },
+            "GET" => match arg.parse().ok().and_then(|id| store.get_by_id(id)) {
+                Some((word, vec)) => writeln!(out, "{}", row(&word, &vec))?,
+                None => writeln!(out, "-")?,
+            },
+            "ITER" => {
+                for (word, vec) in store.iter() {
+                    writeln!(out, "{}", row(&word, &vec))?;
+                }
+                writeln!(out)?;
+            }
+            "WORKING-SET" => {
+                write!(out, "{}", stats.working_set(store.len()))?;
+                writeln!(out)?;
+            }
+            "NEAREST" => match nearest(store, cache, arg) {
+                Ok(found) => {
+                    for (word, similarity) in found {
+                        writeln!(out, "{} {}", word, similarity)?;
+                    }
+                    writeln!(out)?;
+                }
+                Err(e) => writeln!(out, "ERR {}", e)?,
+            },
+            "CACHE-STATS" => {
+                let s = cache.stats();
+                writeln!(out, "{} {} {} {}", s.entries, s.capacity, s.hits, s.misses)?
+            }
+            _ => writeln!(out, "ERR unknown command")?,
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Answers `NEAREST <k> <expression>` from the cache or by a cosine scan;
+/// unknown words are left out of the query, and the query's words out of the answer
+fn nearest(store: &dyn EmbeddingStore, cache: &mut NearestCache, arg: &str) -> Result<Vec<(String, f32)>, String> {
+    let (k, expression) = arg.split_once(' ').ok_or("usage: NEAREST <k> <expression>")?;
+    let k: usize = k.parse().map_err(|_| format!("'{}' is not a number of neighbors", k))?;
+    // No answer can hold more than every row, and the cache key should not tell them apart
+    let k = k.min(store.len());
+    let expression = Expression::parse(expression)?;
+    let key = (expression.to_string(), k);
+    if let Some(found) = cache.get(&key) {
+        return Ok(found);
+    }
+    let found = match expression.known_vector(store) {
+        Some(query) => {
+            let exclude: Vec<String> = expression.words().map(str::to_string).collect();
+            find_nearest_neighbors(&query, store, &exclude, k, &BuiltinMetric::Cosine)
+        }
+        None => Vec::new(),
+    };
+    cache.put(key, found.clone());
+    Ok(found)
+}
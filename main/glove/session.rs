@@ -0,0 +1,131 @@
+//! Named vectors for an interactive session: `let royal = king - man` keeps
+//! the expression's vector under `royal`, and later expressions such as
+//! `royal + woman` can use the name like any word.
+//!
+//! A binding also remembers the words its vector was built from, so a query
+//! over it can leave those words out of its results as it would if they had
+//! been typed out.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::expression::Expression;
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+/// A named vector and the vocabulary words behind it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub vector: WordVec,
+    pub words: Vec<String>,
+}
+
+/// The variables defined so far in a session
+#[derive(Clone, Debug, Default)]
+pub struct Bindings {
+    bindings: HashMap<String, Binding>,
+}
+
+impl Bindings {
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        self.bindings.get(name)
+    }
+
+    /// Binds `name`, replacing any earlier binding of it
+    pub fn insert(&mut self, name: String, binding: Binding) {
+        self.bindings.insert(name, binding);
+    }
+
+    /// The names bound so far, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Whether `expression` uses any variable
+    pub fn used_by(&self, expression: &Expression) -> bool {
+        expression.words().any(|w| self.bindings.contains_key(w))
+    }
+
+    /// The vocabulary words behind `expression`: its own words, with each
+    /// variable replaced by the words it was built from
+    pub fn expand(&self, expression: &Expression) -> Vec<String> {
+        let mut words = Vec::new();
+        for word in expression.words() {
+            match self.bindings.get(word) {
+                Some(binding) => words.extend(binding.words.iter().cloned()),
+                None => words.push(word.to_string()),
+            }
+        }
+        words
+    }
+}
+
+/// Parses `let <name> = <expression>`; `None` if the line is not a `let`.
+/// A name is one token not starting with an operator, and may hide a
+/// vocabulary word of the same spelling for the rest of the session.
+pub fn parse_let(line: &str) -> Option<Result<(String, Expression), String>> {
+    let rest = line.strip_prefix("let ")?;
+    let Some((name, expression)) = rest.split_once('=') else {
+        return Some(Err(format!("expected 'let <name> = <expression>' in '{}'", line)));
+    };
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) || name.starts_with(['+', '-']) {
+        return Some(Err(format!("invalid variable name '{}'", name)));
+    }
+    Some(Expression::parse(expression).map(|expression| (name.to_string(), expression)))
+}
+
+/// View of a store that answers lookups of bound names with their vectors.
+/// Like subword vectors, bound names have no row id and never appear in
+/// results, so only the query vector is built through this view.
+pub struct BoundStore<'a> {
+    inner: &'a dyn EmbeddingStore,
+    bindings: &'a Bindings,
+}
+
+impl<'a> BoundStore<'a> {
+    pub fn new(inner: &'a dyn EmbeddingStore, bindings: &'a Bindings) -> Self {
+        BoundStore { inner, bindings }
+    }
+}
+
+impl EmbeddingStore for BoundStore<'_> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.inner.id(word)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get(&self, word: &str) -> Option<Cow<'_, [f32]>> {
+        match self.bindings.get(word) {
+            Some(binding) => Some(Cow::Borrowed(&binding.vector)),
+            None => self.inner.get(word),
+        }
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.bindings.get(word).is_some() || self.inner.contains(word)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        self.inner.iter()
+    }
+
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        self.inner.cosine_scores(query)
+    }
+
+    fn cosine_scores_batch(&self, queries: &[WordVec]) -> Vec<Vec<f32>> {
+        self.inner.cosine_scores_batch(queries)
+    }
+}
@@ -0,0 +1,63 @@
+//! Embedding dumps split over several files, such as the `part-00000.txt`,
+//! `part-00001.txt`, ... a distributed job writes.
+//!
+//! A source names shards when it is a directory, meaning every file in it, or
+//! a path whose file name has `*` or `?` wildcards, such as
+//! `dump/part-*.txt`. Shards are read in file name order, each in whichever
+//! format it is in, and merged into one vocabulary; a word found in several
+//! shards is resolved by the `--on-conflict` policy of `merge`. Names starting
+//! with `.` or `_` (such as the `_SUCCESS` marker Spark and Hadoop leave) are
+//! skipped, as those tools skip them.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Whether `path` names shards rather than one file
+pub fn is_sharded(path: &str) -> bool {
+    let name = Path::new(path).file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    name.contains(['*', '?']) || Path::new(path).is_dir()
+}
+
+/// The shard files `path` names, in file name order
+pub fn shard_paths(path: &str) -> io::Result<Vec<String>> {
+    let (dir, pattern) = if Path::new(path).is_dir() {
+        (Path::new(path), "*".to_string())
+    } else {
+        let dir = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let pattern = Path::new(path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        (dir, pattern)
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "only the file name may have wildcards"));
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if name.first().is_some_and(|&c| c == '.' || c == '_') || !entry.file_type()?.is_file() {
+            continue;
+        }
+        if matches(&pattern, &name) {
+            paths.push(entry.path().display().to_string());
+        }
+    }
+    if paths.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no shards match {}", path)));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether `name` matches a pattern where `*` stands for any run of
+/// characters and `?` for any one
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+    }
+}
@@ -0,0 +1,313 @@
+//! SQLite-backed store, linked against the system `libsqlite3`.
+//!
+//! Schema: `meta(key, value)` holding `dim`, and
+//! `vectors(id INTEGER PRIMARY KEY, word TEXT UNIQUE, vector BLOB)` with
+//! ids `0..len` and vectors stored as little-endian f32 bytes.
+
+use std::borrow::Cow;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::io;
+use std::ptr;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+#[allow(non_camel_case_types)]
+enum sqlite3 {}
+#[allow(non_camel_case_types)]
+enum sqlite3_stmt {}
+
+const SQLITE_OK: c_int = 0;
+const SQLITE_ROW: c_int = 100;
+const SQLITE_DONE: c_int = 101;
+const SQLITE_OPEN_READONLY: c_int = 0x1;
+const SQLITE_OPEN_READWRITE: c_int = 0x2;
+const SQLITE_OPEN_CREATE: c_int = 0x4;
+/// Tells SQLite to copy bound text/blobs before the call returns
+const SQLITE_TRANSIENT: isize = -1;
+
+#[link(name = "sqlite3")]
+extern "C" {
+    fn sqlite3_open_v2(
+        filename: *const c_char,
+        db: *mut *mut sqlite3,
+        flags: c_int,
+        vfs: *const c_char,
+    ) -> c_int;
+    fn sqlite3_close(db: *mut sqlite3) -> c_int;
+    fn sqlite3_errmsg(db: *mut sqlite3) -> *const c_char;
+    fn sqlite3_prepare_v2(
+        db: *mut sqlite3,
+        sql: *const c_char,
+        len: c_int,
+        stmt: *mut *mut sqlite3_stmt,
+        tail: *mut *const c_char,
+    ) -> c_int;
+    fn sqlite3_step(stmt: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_reset(stmt: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_finalize(stmt: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_bind_int64(stmt: *mut sqlite3_stmt, idx: c_int, val: i64) -> c_int;
+    fn sqlite3_bind_text(
+        stmt: *mut sqlite3_stmt,
+        idx: c_int,
+        text: *const c_char,
+        len: c_int,
+        destructor: isize,
+    ) -> c_int;
+    fn sqlite3_bind_blob(
+        stmt: *mut sqlite3_stmt,
+        idx: c_int,
+        data: *const c_void,
+        len: c_int,
+        destructor: isize,
+    ) -> c_int;
+    fn sqlite3_column_int64(stmt: *mut sqlite3_stmt, col: c_int) -> i64;
+    fn sqlite3_column_double(stmt: *mut sqlite3_stmt, col: c_int) -> f64;
+    fn sqlite3_column_text(stmt: *mut sqlite3_stmt, col: c_int) -> *const u8;
+    fn sqlite3_column_blob(stmt: *mut sqlite3_stmt, col: c_int) -> *const c_void;
+    fn sqlite3_column_bytes(stmt: *mut sqlite3_stmt, col: c_int) -> c_int;
+}
+
+/// Owned database connection
+pub(crate) struct Db(*mut sqlite3);
+
+impl Db {
+    /// Opens an existing database for reading
+    pub(crate) fn open_readonly(path: &str) -> io::Result<Db> {
+        Db::open(path, SQLITE_OPEN_READONLY)
+    }
+
+    fn open(path: &str, flags: c_int) -> io::Result<Db> {
+        let c_path = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut raw = ptr::null_mut();
+        let rc = unsafe { sqlite3_open_v2(c_path.as_ptr(), &mut raw, flags, ptr::null()) };
+        let db = Db(raw);
+        if rc != SQLITE_OK {
+            return Err(db.error());
+        }
+        Ok(db)
+    }
+
+    fn error(&self) -> io::Error {
+        let msg = unsafe { CStr::from_ptr(sqlite3_errmsg(self.0)) };
+        io::Error::other(format!("sqlite: {}", msg.to_string_lossy()))
+    }
+
+    pub(crate) fn prepare(&self, sql: &str) -> io::Result<Statement<'_>> {
+        let c_sql = CString::new(sql).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut raw = ptr::null_mut();
+        let rc = unsafe { sqlite3_prepare_v2(self.0, c_sql.as_ptr(), -1, &mut raw, ptr::null_mut()) };
+        if rc != SQLITE_OK {
+            return Err(self.error());
+        }
+        Ok(Statement { db: self, raw })
+    }
+
+    fn execute(&self, sql: &str) -> io::Result<()> {
+        self.prepare(sql)?.step().map(|_| ())
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        unsafe {
+            sqlite3_close(self.0);
+        }
+    }
+}
+
+/// Prepared statement, finalized on drop
+pub(crate) struct Statement<'a> {
+    db: &'a Db,
+    raw: *mut sqlite3_stmt,
+}
+
+impl Statement<'_> {
+    fn check(&self, rc: c_int) -> io::Result<()> {
+        if rc == SQLITE_OK {
+            Ok(())
+        } else {
+            Err(self.db.error())
+        }
+    }
+
+    fn bind_int(&mut self, idx: c_int, val: i64) -> io::Result<()> {
+        let rc = unsafe { sqlite3_bind_int64(self.raw, idx, val) };
+        self.check(rc)
+    }
+
+    fn bind_text(&mut self, idx: c_int, text: &str) -> io::Result<()> {
+        let rc = unsafe {
+            sqlite3_bind_text(self.raw, idx, text.as_ptr() as *const c_char, text.len() as c_int, SQLITE_TRANSIENT)
+        };
+        self.check(rc)
+    }
+
+    fn bind_blob(&mut self, idx: c_int, data: &[u8]) -> io::Result<()> {
+        let rc = unsafe {
+            sqlite3_bind_blob(self.raw, idx, data.as_ptr() as *const c_void, data.len() as c_int, SQLITE_TRANSIENT)
+        };
+        self.check(rc)
+    }
+
+    /// Advances the statement, returning whether a row is available
+    pub(crate) fn step(&mut self) -> io::Result<bool> {
+        match unsafe { sqlite3_step(self.raw) } {
+            SQLITE_ROW => Ok(true),
+            SQLITE_DONE => Ok(false),
+            _ => Err(self.db.error()),
+        }
+    }
+
+    fn reset(&mut self) {
+        unsafe {
+            sqlite3_reset(self.raw);
+        }
+    }
+
+    pub(crate) fn column_int(&self, col: c_int) -> i64 {
+        unsafe { sqlite3_column_int64(self.raw, col) }
+    }
+
+    pub(crate) fn column_double(&self, col: c_int) -> f64 {
+        unsafe { sqlite3_column_double(self.raw, col) }
+    }
+
+    fn column_bytes(&self, col: c_int, text: bool) -> &[u8] {
+        unsafe {
+            let data = if text {
+                sqlite3_column_text(self.raw, col)
+            } else {
+                sqlite3_column_blob(self.raw, col) as *const u8
+            };
+            let len = sqlite3_column_bytes(self.raw, col) as usize;
+            if data.is_null() {
+                &[]
+            } else {
+                std::slice::from_raw_parts(data, len)
+            }
+        }
+    }
+
+    pub(crate) fn column_text(&self, col: c_int) -> String {
+        String::from_utf8_lossy(self.column_bytes(col, true)).into_owned()
+    }
+
+    fn column_vector(&self, col: c_int) -> WordVec {
+        self.column_bytes(col, false)
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect()
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            sqlite3_finalize(self.raw);
+        }
+    }
+}
+
+/// Vectors kept in a SQLite database and read one row at a time
+pub struct SqliteStore {
+    db: Db,
+    len: usize,
+    dim: usize,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let db = Db::open_readonly(path)?;
+
+        let mut stmt = db.prepare("SELECT value FROM meta WHERE key = 'dim'")?;
+        let dim = if stmt.step()? { stmt.column_text(0).parse().unwrap_or(0) } else { 0 };
+        drop(stmt);
+
+        let mut stmt = db.prepare("SELECT COUNT(*) FROM vectors")?;
+        stmt.step()?;
+        let len = stmt.column_int(0) as usize;
+        drop(stmt);
+
+        Ok(SqliteStore { db, len, dim })
+    }
+
+    /// Writes every entry of a store into a new SQLite database
+    pub fn create(path: &str, store: &dyn EmbeddingStore) -> io::Result<()> {
+        let db = Db::open(path, SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE)?;
+        db.execute("DROP TABLE IF EXISTS meta")?;
+        db.execute("DROP TABLE IF EXISTS vectors")?;
+        db.execute("CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)")?;
+        db.execute(
+            "CREATE TABLE vectors (id INTEGER PRIMARY KEY, word TEXT NOT NULL UNIQUE, vector BLOB NOT NULL)",
+        )?;
+        db.execute(&format!("INSERT INTO meta VALUES ('dim', '{}')", store.dim()))?;
+
+        db.execute("BEGIN")?;
+        let mut insert = db.prepare("INSERT INTO vectors (id, word, vector) VALUES (?1, ?2, ?3)")?;
+        for (id, (word, vec)) in store.iter().enumerate() {
+            let blob: Vec<u8> = vec.iter().flat_map(|x| x.to_le_bytes()).collect();
+            insert.bind_int(1, id as i64)?;
+            insert.bind_text(2, &word)?;
+            insert.bind_blob(3, &blob)?;
+            insert.step()?;
+            insert.reset();
+        }
+        drop(insert);
+        db.execute("COMMIT")
+    }
+}
+
+impl EmbeddingStore for SqliteStore {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        let mut stmt = self.db.prepare("SELECT id FROM vectors WHERE word = ?1").ok()?;
+        stmt.bind_text(1, word).ok()?;
+        if stmt.step().ok()? {
+            Some(stmt.column_int(0) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        let mut stmt = self.db.prepare("SELECT word, vector FROM vectors WHERE id = ?1").ok()?;
+        stmt.bind_int(1, id as i64).ok()?;
+        if stmt.step().ok()? {
+            Some((Cow::Owned(stmt.column_text(0)), Cow::Owned(stmt.column_vector(1))))
+        } else {
+            None
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        // One statement for the whole scan instead of a query per row
+        match self.db.prepare("SELECT word, vector FROM vectors ORDER BY id") {
+            Ok(stmt) => Box::new(Rows { stmt }),
+            Err(_) => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+struct Rows<'a> {
+    stmt: Statement<'a>,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stmt.step().ok()? {
+            Some((Cow::Owned(self.stmt.column_text(0)), Cow::Owned(self.stmt.column_vector(1))))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Shares of all accesses the working-set report breaks down
+const COVERAGE_LEVELS: &[f64] = &[0.5, 0.9, 0.95, 0.99];
+
+/// How many of the hottest words are listed in the report
+const TOP_WORDS: usize = 10;
+
+/// Per-word access counts collected while serving queries
+#[derive(Default)]
+pub struct AccessStats {
+    counts: HashMap<String, u64>,
+    total: u64,
+}
+
+impl AccessStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts one lookup of a word
+    pub fn record(&mut self, word: &str) {
+        *self.counts.entry(word.to_string()).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    /// Summarizes how concentrated the accesses are on a few words
+    pub fn working_set(&self, vocab_size: usize) -> WorkingSetReport {
+        let mut ranked: Vec<(&String, &u64)> = self.counts.iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        // Walk the hottest words first until each coverage level is reached
+        let mut coverage = Vec::new();
+        let mut served = 0;
+        let mut levels = COVERAGE_LEVELS.iter().peekable();
+        for (i, (_, &count)) in ranked.iter().enumerate() {
+            served += count;
+            while let Some(&&level) = levels.peek() {
+                if served as f64 >= level * self.total as f64 {
+                    coverage.push((level, i + 1));
+                    levels.next();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        WorkingSetReport {
+            total_accesses: self.total,
+            distinct_words: ranked.len(),
+            vocab_size,
+            coverage,
+            top: ranked
+                .iter()
+                .take(TOP_WORDS)
+                .map(|(w, &c)| (w.to_string(), c))
+                .collect(),
+        }
+    }
+}
+
+/// Output of the `working-set` report
+pub struct WorkingSetReport {
+    pub total_accesses: u64,
+    pub distinct_words: usize,
+    pub vocab_size: usize,
+    /// (share of accesses, number of hottest words needed to serve it)
+    pub coverage: Vec<(f64, usize)>,
+    /// Hottest words and their access counts
+    pub top: Vec<(String, u64)>,
+}
+
+impl fmt::Display for WorkingSetReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Accesses: {} over {} distinct words (vocabulary: {})",
+            self.total_accesses, self.distinct_words, self.vocab_size
+        )?;
+        for &(level, words) in &self.coverage {
+            writeln!(
+                f,
+                "{:.0}% of accesses served by the top {} words ({:.2}% of vocabulary)",
+                level * 100.0,
+                words,
+                100.0 * words as f64 / self.vocab_size.max(1) as f64
+            )?;
+        }
+        if !self.top.is_empty() {
+            writeln!(f, "Top words:")?;
+        }
+        for (word, count) in &self.top {
+            writeln!(f, "  {} {}", word, count)?;
+        }
+        Ok(())
+    }
+}
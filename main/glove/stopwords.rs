@@ -0,0 +1,53 @@
+//! Stopword lists for sentence and document embeddings, whose averages are
+//! otherwise dominated by function words such as `the`, `of` and `on`.
+
+use std::collections::HashSet;
+use std::fs;
+
+/// Built-in English list: articles, pronouns, prepositions, conjunctions and
+/// auxiliary verbs, after NLTK's list without its contraction fragments
+const ENGLISH: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "before", "being", "below", "between", "both", "but", "by", "can", "could", "did", "do",
+    "does", "doing", "down", "during", "each", "few", "for", "from", "further", "had", "has", "have", "having", "he",
+    "her", "here", "hers", "herself", "him", "himself", "his", "how", "i", "if", "in", "into", "is", "it", "its",
+    "itself", "just", "me", "more", "most", "my", "myself", "no", "nor", "not", "now", "of", "off", "on", "once",
+    "only", "or", "other", "our", "ours", "ourselves", "out", "over", "own", "same", "she", "should", "so", "some",
+    "such", "than", "that", "the", "their", "theirs", "them", "themselves", "then", "there", "these", "they", "this",
+    "those", "through", "to", "too", "under", "until", "up", "very", "was", "we", "were", "what", "when", "where",
+    "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your", "yours", "yourself",
+    "yourselves",
+];
+
+/// A set of words left out of sentence vectors
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stopwords {
+    words: HashSet<String>,
+}
+
+impl Stopwords {
+    /// The built-in English list
+    pub fn english() -> Stopwords {
+        Stopwords { words: ENGLISH.iter().map(|w| w.to_string()).collect() }
+    }
+
+    /// Reads a list of words separated by whitespace, with `#` starting a
+    /// comment; words are lowercased like sentence tokens
+    pub fn from_file(path: &str) -> Result<Stopwords, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let words = text.lines().flat_map(|l| l.split('#').next().unwrap_or("").split_whitespace());
+        Ok(Stopwords { words: words.map(str::to_lowercase).collect() })
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
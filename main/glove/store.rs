@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+use std::io;
+
+use crate::error::{Error, Result};
+use crate::input::{Compression, Source};
+use crate::lazy::LazyStore;
+use crate::half::HalfStore;
+use crate::load::{load_glove_half, load_glove_pq, load_glove_quantized, load_glove_source, LoadOptions, Precision};
+use crate::metric::Metric;
+use crate::pq::PqStore;
+use crate::quantize::QuantizedStore;
+use crate::remote::RemoteStore;
+use crate::shards::is_sharded;
+use crate::suggest::did_you_mean;
+use crate::vector::cosine_similarity;
+use crate::WordVec;
+
+/// A word and its vector, borrowed from the store when it can be
+pub type Entry<'a> = (Cow<'a, str>, Cow<'a, [f32]>);
+
+/// Read access to a vocabulary of word vectors.
+///
+/// Every word has a row id in `0..len()`; search and CLI code only talk
+/// to this trait so new backends can be added without touching them.
+pub trait EmbeddingStore {
+    /// Number of words in the vocabulary
+    fn len(&self) -> usize;
+
+    /// Number of components in every vector
+    fn dim(&self) -> usize;
+
+    /// Row id of a word, if it is in the vocabulary
+    fn id(&self, word: &str) -> Option<usize>;
+
+    /// Word and vector stored at a row id
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Vector for a word, if it is in the vocabulary
+    fn get(&self, word: &str) -> Option<Cow<'_, [f32]>> {
+        let id = self.id(word)?;
+        self.get_by_id(id).map(|(_, vec)| vec)
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.id(word).is_some()
+    }
+
+    /// Score of two words under `metric`, or an error naming whichever of
+    /// them is not in the vocabulary
+    fn similarity(&self, a: &str, b: &str, metric: &dyn Metric) -> Result<f32> {
+        match (self.get(a), self.get(b)) {
+            (Some(a_vec), Some(b_vec)) => Ok(metric.score(&a_vec, &b_vec)),
+            _ => Err(unknown_words(self, &[a, b])),
+        }
+    }
+
+    /// Iterates over every word and vector in row order
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        Box::new((0..self.len()).filter_map(move |id| self.get_by_id(id)))
+    }
+
+    /// Cosine similarity of `query` against every row, in row order.
+    /// Backends with a faster representation for scanning override this.
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        self.iter().map(|(_, vec)| cosine_similarity(query, &vec)).collect()
+    }
+
+    /// `cosine_scores` for several queries at once; backends that pay a
+    /// fixed cost per scan (such as a GPU dispatch) override this
+    fn cosine_scores_batch(&self, queries: &[WordVec]) -> Vec<Vec<f32>> {
+        queries.iter().map(|query| self.cosine_scores(query)).collect()
+    }
+}
+
+/// An `OutOfVocabulary` error for the words of `words` missing from `store`,
+/// each with a "did you mean" suggestion when one is close enough
+pub fn unknown_words<S: EmbeddingStore + ?Sized>(store: &S, words: &[&str]) -> Error {
+    let mut unknown = Vec::new();
+    for (i, &word) in words.iter().enumerate() {
+        if store.contains(word) || words[..i].contains(&word) {
+            continue;
+        }
+        unknown.push((word.to_string(), did_you_mean(store, word)));
+    }
+    Error::OutOfVocabulary(unknown)
+}
+
+/// Names accepted by `--backend`
+pub const BACKENDS: &[&str] = &["dense", "lazy", "mmap", "sqlite", "rocksdb", "remote"];
+
+/// Opens `source` with the named backend:
+///
+/// - `dense`: any file `Format::sniff` recognizes (or zip archive member), loaded into memory
+/// - `lazy`: uncompressed text file, read a word at a time through a cached byte-offset index
+/// - `mmap`: binary file written by `convert --to binary`, memory-mapped
+/// - `sqlite`: database written by `convert --to sqlite` (needs the `sqlite` feature)
+/// - `rocksdb`: database written by `convert --to rocksdb` (needs the `rocksdb` feature)
+/// - `remote`: `host:port` of a running `serve` instance
+///
+/// Most `options` only affect backends that parse text (`dense`); a reduced
+/// `precision` is applied to every backend, converting a copy of its vectors.
+pub fn open_store(
+    backend: &str,
+    source: &Source,
+    options: &LoadOptions,
+) -> Result<Box<dyn EmbeddingStore>> {
+    if backend == "dense" {
+        return load_dense(source, options).map(|store| store as Box<dyn EmbeddingStore>);
+    }
+    if matches!(source, Source::Path(path) if is_sharded(path)) {
+        let msg = format!("the {} backend cannot read shards; load them with the dense backend", backend);
+        return Err(io::Error::new(io::ErrorKind::Unsupported, msg).into());
+    }
+    let store = open_exact_store(backend, source)?;
+    Ok(match options.precision {
+        Precision::F32 => store,
+        precision => with_precision(store.as_ref(), precision),
+    })
+}
+
+/// Reads `source` into memory at `options.precision`, as the `dense` backend
+/// does. The store can be moved to another thread, so it can be loaded on one.
+pub fn load_dense(source: &Source, options: &LoadOptions) -> Result<Box<dyn EmbeddingStore + Send>> {
+    let sharded = matches!(source, Source::Path(path) if is_sharded(path));
+    Ok(match options.precision {
+        Precision::F32 => Box::new(load_glove_source(source, options)?),
+        // Shards are merged at full precision, then converted
+        precision if sharded => with_precision(&load_glove_source(source, options)?, precision),
+        Precision::F16 => Box::new(load_glove_half(source, options)?),
+        Precision::Int8 => Box::new(load_glove_quantized(source, options)?),
+        Precision::Pq { subquantizers, bits } => Box::new(load_glove_pq(source, options, subquantizers, bits)?),
+    })
+}
+
+/// Converts full-precision vectors to a lower `precision`
+fn with_precision(store: &dyn EmbeddingStore, precision: Precision) -> Box<dyn EmbeddingStore + Send> {
+    match precision {
+        Precision::F32 => unreachable!("f32 vectors are kept as they are"),
+        Precision::F16 => Box::new(HalfStore::from_store(store)),
+        Precision::Int8 => Box::new(QuantizedStore::from_store(store)),
+        Precision::Pq { subquantizers, bits } => Box::new(PqStore::from_store(store, subquantizers, bits)),
+    }
+}
+
+/// Opens full-precision vectors for just `words` (every word `options` loads
+/// if `None`), e.g. to re-score candidates found with a quantized store. Text
+/// sources are re-read keeping only those rows, with the same `max_vocab`
+/// limit, language, tensor and shards as `options`.
+pub fn open_exact_subset(
+    backend: &str,
+    source: &Source,
+    words: Option<&[String]>,
+    options: &LoadOptions,
+) -> Result<Box<dyn EmbeddingStore>> {
+    if backend == "dense" {
+        let options = LoadOptions {
+            quiet: true,
+            precision: Precision::F32,
+            words: words.map(|words| words.iter().cloned().collect()).or_else(|| options.words.clone()),
+            strict: false,
+            ..options.clone()
+        };
+        return Ok(Box::new(load_glove_source(source, &options)?));
+    }
+    Ok(open_exact_store(backend, source)?)
+}
+
+/// Opens one of the non-text backends at full precision
+fn open_exact_store(backend: &str, source: &Source) -> io::Result<Box<dyn EmbeddingStore>> {
+    let Source::Path(location) = source else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("the {} backend cannot read from archives", backend),
+        ));
+    };
+
+    match backend {
+        "lazy" if Compression::detect(location)? != Compression::None => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the lazy backend needs an uncompressed text file",
+        )),
+        "lazy" => Ok(Box::new(LazyStore::open(location)?)),
+        #[cfg(all(unix, target_pointer_width = "64", target_endian = "little"))]
+        "mmap" => Ok(Box::new(crate::mmap::MmapStore::open(location)?)),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Ok(Box::new(crate::sqlite::SqliteStore::open(location)?)),
+        #[cfg(feature = "rocksdb")]
+        "rocksdb" => Ok(Box::new(crate::rocksdb::RocksStore::open(location)?)),
+        "remote" => Ok(Box::new(RemoteStore::connect(location)?)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("backend '{}' is not available in this build", backend),
+        )),
+    }
+}
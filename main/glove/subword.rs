@@ -0,0 +1,230 @@
+//! fastText-style vectors for words missing from a vocabulary, built from
+//! their character n-grams: every n-gram (3 to 6 characters of `<word>`,
+//! the brackets marking its ends) is hashed into one of a fixed number of
+//! buckets, each bucket holds the mean unit vector of the known words with
+//! an n-gram in it, and an unknown word gets the mean of its buckets. Typos
+//! (`freind`) and rare inflections (`unfriendliness`) land near the words
+//! they share most n-grams with.
+//!
+//! The buckets are trained from the most frequent rows when the vectors are
+//! opened, or read from a file saved by an earlier run. The file holds
+//! `GLOVENGR`, then `min_n`, `max_n`, the bucket count and the
+//! dimensionality as little-endian u32s, each bucket's word count (u32),
+//! and the bucket vectors (f32).
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::store::{EmbeddingStore, Entry};
+use crate::WordVec;
+
+const MAGIC: &[u8; 8] = b"GLOVENGR";
+
+/// Hash buckets; collisions blur rare n-grams together, as in fastText
+const BUCKETS: usize = 1 << 16;
+
+/// Shortest and longest n-grams, fastText's defaults
+const MIN_N: usize = 3;
+const MAX_N: usize = 6;
+
+/// Rows the buckets are trained from; GloVe files are sorted by frequency,
+/// and rarer rows add little but time
+const TRAIN_ROWS: usize = 200_000;
+
+/// Bucket vectors for character n-grams
+pub struct SubwordModel {
+    min_n: usize,
+    max_n: usize,
+    dim: usize,
+    /// Known words that contributed to each bucket
+    counts: Vec<u32>,
+    /// `counts.len() * dim` values, each bucket the mean of its words' unit vectors
+    vectors: Vec<f32>,
+}
+
+/// FNV-1a, the hash fastText uses for n-grams
+fn hash(ngram: &[u8]) -> u32 {
+    ngram.iter().fold(2_166_136_261u32, |h, &b| (h ^ b as u32).wrapping_mul(16_777_619))
+}
+
+impl SubwordModel {
+    /// Trains the buckets from the first `TRAIN_ROWS` rows of `store`
+    pub fn train(store: &dyn EmbeddingStore) -> SubwordModel {
+        let dim = store.dim();
+        let mut model = SubwordModel {
+            min_n: MIN_N,
+            max_n: MAX_N,
+            dim,
+            counts: vec![0; BUCKETS],
+            vectors: vec![0.0; BUCKETS * dim],
+        };
+        for (word, vec) in store.iter().take(TRAIN_ROWS) {
+            let norm = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm == 0.0 {
+                continue;
+            }
+            for bucket in model.buckets(&word) {
+                model.counts[bucket] += 1;
+                let row = &mut model.vectors[bucket * dim..(bucket + 1) * dim];
+                for (r, x) in row.iter_mut().zip(vec.iter()) {
+                    *r += x / norm;
+                }
+            }
+        }
+        for (row, &count) in model.vectors.chunks_mut(dim).zip(&model.counts) {
+            if count > 0 {
+                row.iter_mut().for_each(|r| *r /= count as f32);
+            }
+        }
+        model
+    }
+
+    /// Reads buckets saved by `save`
+    pub fn load(path: &str) -> io::Result<SubwordModel> {
+        let bytes = fs::read(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a subword file", path));
+        if bytes.len() < 24 || &bytes[..8] != MAGIC {
+            return Err(invalid());
+        }
+        let words: Vec<u32> = bytes[8..].chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect();
+        let [min_n, max_n, buckets, dim] = [words[0], words[1], words[2], words[3]].map(|w| w as usize);
+        if words.len() != 4 + buckets * (dim + 1) || min_n == 0 || min_n > max_n {
+            return Err(invalid());
+        }
+        let counts = words[4..4 + buckets].to_vec();
+        let vectors = words[4 + buckets..].iter().map(|&w| f32::from_bits(w)).collect();
+        Ok(SubwordModel { min_n, max_n, dim, counts, vectors })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        for n in [self.min_n, self.max_n, self.counts.len(), self.dim] {
+            out.write_all(&(n as u32).to_le_bytes())?;
+        }
+        for count in &self.counts {
+            out.write_all(&count.to_le_bytes())?;
+        }
+        for x in &self.vectors {
+            out.write_all(&x.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Buckets of the n-grams of `<word>`
+    fn buckets(&self, word: &str) -> Vec<usize> {
+        let bracketed: Vec<char> = format!("<{}>", word).chars().collect();
+        let mut buckets = Vec::new();
+        let mut utf8 = String::new();
+        for start in 0..bracketed.len() {
+            for n in self.min_n..=self.max_n.min(bracketed.len() - start) {
+                utf8.clear();
+                utf8.extend(&bracketed[start..start + n]);
+                buckets.push(hash(utf8.as_bytes()) as usize % self.counts.len());
+            }
+        }
+        buckets
+    }
+
+    /// The mean of the trained buckets of `word`'s n-grams; `None` if none were trained
+    pub fn vector(&self, word: &str) -> Option<WordVec> {
+        let mut sum = vec![0.0; self.dim];
+        let mut used = 0;
+        for bucket in self.buckets(word).into_iter().filter(|&b| self.counts[b] > 0) {
+            for (s, x) in sum.iter_mut().zip(&self.vectors[bucket * self.dim..(bucket + 1) * self.dim]) {
+                *s += x;
+            }
+            used += 1;
+        }
+        if used == 0 {
+            return None;
+        }
+        sum.iter_mut().for_each(|s| *s /= used as f32);
+        Some(sum)
+    }
+}
+
+/// View of a store that answers lookups of missing words with their n-gram
+/// vectors. Such words still have no row id, and never appear in results.
+pub struct SubwordStore {
+    inner: Box<dyn EmbeddingStore>,
+    model: SubwordModel,
+}
+
+impl SubwordStore {
+    pub fn new(inner: Box<dyn EmbeddingStore>, model: SubwordModel) -> Self {
+        SubwordStore { inner, model }
+    }
+}
+
+impl EmbeddingStore for SubwordStore {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn id(&self, word: &str) -> Option<usize> {
+        self.inner.id(word)
+    }
+
+    fn get_by_id(&self, id: usize) -> Option<Entry<'_>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get(&self, word: &str) -> Option<Cow<'_, [f32]>> {
+        self.inner.get(word).or_else(|| self.model.vector(word).map(Cow::Owned))
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.inner.contains(word) || self.model.vector(word).is_some()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry<'_>> + '_> {
+        self.inner.iter()
+    }
+
+    fn cosine_scores(&self, query: &[f32]) -> Vec<f32> {
+        self.inner.cosine_scores(query)
+    }
+
+    fn cosine_scores_batch(&self, queries: &[WordVec]) -> Vec<Vec<f32>> {
+        self.inner.cosine_scores_batch(queries)
+    }
+}
+
+/// Wraps `store` in a `SubwordStore` when enabled. With `file`, the buckets
+/// are read from it if it exists, and otherwise trained and saved there.
+pub fn with_subwords(
+    store: Box<dyn EmbeddingStore>,
+    enabled: bool,
+    file: Option<&str>,
+) -> io::Result<Box<dyn EmbeddingStore>> {
+    if !enabled {
+        return Ok(store);
+    }
+    let model = match file {
+        Some(path) if Path::new(path).exists() => SubwordModel::load(path)?,
+        Some(path) => {
+            let model = SubwordModel::train(store.as_ref());
+            model.save(path)?;
+            model
+        }
+        None => SubwordModel::train(store.as_ref()),
+    };
+    if model.dim() != store.dim() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the subword file has {} dimensions, the vectors {}", model.dim(), store.dim()),
+        ));
+    }
+    Ok(Box::new(SubwordStore::new(store, model)))
+}
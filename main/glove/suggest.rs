@@ -0,0 +1,76 @@
+//! "Did you mean" suggestions for words missing from a vocabulary, e.g.
+//! `freind` → `friend`: the entries closest by edit distance, ties going to
+//! the longer common prefix and then to the earlier (more frequent) row.
+
+use crate::store::EmbeddingStore;
+
+/// Edits allowed between a word of `len` characters and a suggestion: one
+/// for short words, two from five characters on
+fn max_distance(len: usize) -> usize {
+    if len < 5 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Up to `count` vocabulary words within a few edits of `word`, closest first
+pub fn suggestions<S: EmbeddingStore + ?Sized>(store: &S, word: &str, count: usize) -> Vec<String> {
+    candidates(store, word).into_iter().take(count).map(|(word, _)| word).collect()
+}
+
+/// Every vocabulary word other than `word` within a few edits of it, with its
+/// edit distance, closest first
+pub fn candidates<S: EmbeddingStore + ?Sized>(store: &S, word: &str) -> Vec<(String, usize)> {
+    let target: Vec<char> = word.chars().collect();
+    let limit = max_distance(target.len());
+    let mut found = Vec::new();
+    let mut candidate = Vec::new();
+    for (entry, _) in store.iter() {
+        candidate.clear();
+        candidate.extend(entry.chars());
+        if candidate.len().abs_diff(target.len()) > limit || candidate == target {
+            continue;
+        }
+        if let Some(distance) = edit_distance(&target, &candidate, limit) {
+            let prefix = target.iter().zip(&candidate).take_while(|(a, b)| a == b).count();
+            found.push((distance, usize::MAX - prefix, entry.into_owned()));
+        }
+    }
+    // Row order breaks the remaining ties, as the sort is stable
+    found.sort_by_key(|&(distance, prefix, _)| (distance, prefix));
+    found.into_iter().map(|(distance, _, word)| (word, distance)).collect()
+}
+
+/// The single best suggestion for `word`: its lowercase form when only that is
+/// known (GloVe vocabularies are lowercased), else the closest entry
+pub fn did_you_mean<S: EmbeddingStore + ?Sized>(store: &S, word: &str) -> Option<String> {
+    let lower = word.to_lowercase();
+    if lower != word && store.contains(&lower) {
+        return Some(lower);
+    }
+    suggestions(store, &lower, 1).pop()
+}
+
+/// Edit distance counting insertions, deletions, substitutions and swaps of
+/// adjacent characters, or `None` once it must exceed `limit`
+fn edit_distance(a: &[char], b: &[char], limit: usize) -> Option<usize> {
+    // Rows i - 2, i - 1 and i of the distance table
+    let mut rows = [vec![0; b.len() + 1], (0..=b.len()).collect(), vec![0; b.len() + 1]];
+    for i in 1..=a.len() {
+        let [before, previous, current] = &mut rows;
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        if current.iter().all(|&d| d > limit) {
+            return None;
+        }
+        rows.rotate_left(1);
+    }
+    Some(rows[1][b.len()]).filter(|&d| d <= limit)
+}
@@ -0,0 +1,63 @@
+//! Fixtures shared by the unit tests: a small store, scratch directories,
+//! and loading a file the way the command line does.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::store::EmbeddingStore;
+use crate::{load_glove_source, DenseStore, LoadOptions, Source, WordVec};
+
+/// A few words with distinct vectors, all exactly representable in f16 too
+pub fn sample_store() -> DenseStore {
+    let mut store = DenseStore::new();
+    for (i, word) in ["the", "king", "queen", "man", "woman"].iter().enumerate() {
+        let vec = (0..4).map(|j| (i * 4 + j) as f32 * 0.25 - 2.0).collect();
+        store.insert(word.to_string(), vec);
+    }
+    store
+}
+
+/// Every row of `store`, in row order
+pub fn rows(store: &dyn EmbeddingStore) -> Vec<(String, WordVec)> {
+    store.iter().map(|(word, vec)| (word.into_owned(), vec.into_owned())).collect()
+}
+
+/// Loads `path` quietly, detecting its format, and returns its rows
+pub fn load_rows(path: &str) -> crate::Result<Vec<(String, WordVec)>> {
+    let options = LoadOptions { quiet: true, ..LoadOptions::default() };
+    load_glove_source(&Source::from(path), &options).map(|store| rows(&store))
+}
+
+/// A scratch directory removed when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("glove-test-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// Path of `name` inside the directory
+    pub fn file(&self, name: &str) -> String {
+        self.0.join(name).display().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Checks that every proper prefix of `bytes` at least `magic` bytes long
+/// (so the format is still recognized), written to `path`, fails to load
+pub fn assert_truncations_fail(path: &str, bytes: &[u8], magic: usize) {
+    for len in magic..bytes.len() {
+        fs::write(path, &bytes[..len]).unwrap();
+        assert!(load_rows(path).is_err(), "a file cut to {} of {} bytes loaded", len, bytes.len());
+    }
+}
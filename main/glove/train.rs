@@ -0,0 +1,109 @@
+//! Trains GloVe vectors (Pennington et al., 2014) from co-occurrence counts
+//! (see `cooccur`), as the reference implementation does: word and context
+//! vectors and biases are fitted so that `wᵢ·w̃ⱼ + bᵢ + b̃ⱼ` approaches
+//! `ln Xᵢⱼ`, weighting each pair by `min(1, (Xᵢⱼ / x_max)^α)`, with AdaGrad
+//! over the pairs in a fresh random order every iteration. The vectors
+//! written for each word are `w + w̃`.
+
+use crate::cooccur::{Cooccurrence, Vocabulary};
+use crate::dense::DenseStore;
+use crate::rng::Rng;
+
+/// Training settings; the defaults are those of the reference `demo.sh`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainOptions {
+    pub dim: usize,
+    pub iterations: usize,
+    /// Co-occurrence count at which a pair gets the full weight
+    pub x_max: f32,
+    /// Exponent of the weighting function below `x_max`
+    pub alpha: f32,
+    /// Initial AdaGrad learning rate
+    pub learning_rate: f32,
+    pub seed: u64,
+}
+
+impl Default for TrainOptions {
+    fn default() -> Self {
+        TrainOptions {
+            dim: 50,
+            iterations: 15,
+            x_max: 10.0,
+            alpha: 0.75,
+            learning_rate: 0.05,
+            seed: 1,
+        }
+    }
+}
+
+/// Fits GloVe vectors for `vocab_size` words to the co-occurrence `entries`,
+/// calling `progress` with each finished iteration (from 1) and its mean
+/// cost. Returns `w + w̃` for every word id as one row-major matrix.
+pub fn train(
+    entries: &[Cooccurrence],
+    vocab_size: usize,
+    options: &TrainOptions,
+    mut progress: impl FnMut(usize, f64),
+) -> Vec<f32> {
+    let dim = options.dim;
+    let mut rng = Rng::new(options.seed);
+    let mut init = |n: usize| -> Vec<f32> { (0..n).map(|_| (rng.next_f32() - 0.5) / dim as f32).collect() };
+    let mut words = init(vocab_size * dim);
+    let mut contexts = init(vocab_size * dim);
+    let mut word_bias = init(vocab_size);
+    let mut context_bias = init(vocab_size);
+    // AdaGrad's sums of squared gradients, started at one as in the reference code
+    let mut words_sq = vec![1.0f32; vocab_size * dim];
+    let mut contexts_sq = vec![1.0f32; vocab_size * dim];
+    let mut word_bias_sq = vec![1.0f32; vocab_size];
+    let mut context_bias_sq = vec![1.0f32; vocab_size];
+
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    for iteration in 1..=options.iterations {
+        // Fisher-Yates shuffle
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+        let mut cost = 0.0f64;
+        for &e in &order {
+            let (w, c, x) = entries[e];
+            let (w, c, x) = (w as usize, c as usize, x as f32);
+            let (wr, cr) = (w * dim..(w + 1) * dim, c * dim..(c + 1) * dim);
+            let dot: f32 = words[wr.clone()].iter().zip(&contexts[cr.clone()]).map(|(a, b)| a * b).sum();
+            let diff = dot + word_bias[w] + context_bias[c] - x.ln();
+            let weight = if x < options.x_max { (x / options.x_max).powf(options.alpha) } else { 1.0 };
+            let weighted = weight * diff;
+            if !weighted.is_finite() {
+                continue;
+            }
+            cost += 0.5 * (weighted * diff) as f64;
+            let step = options.learning_rate * weighted;
+            for d in 0..dim {
+                let (wi, ci) = (wr.start + d, cr.start + d);
+                let word_grad = step * contexts[ci];
+                let context_grad = step * words[wi];
+                words[wi] -= word_grad / words_sq[wi].sqrt();
+                contexts[ci] -= context_grad / contexts_sq[ci].sqrt();
+                words_sq[wi] += word_grad * word_grad;
+                contexts_sq[ci] += context_grad * context_grad;
+            }
+            word_bias[w] -= step / word_bias_sq[w].sqrt();
+            context_bias[c] -= step / context_bias_sq[c].sqrt();
+            word_bias_sq[w] += step * step;
+            context_bias_sq[c] += step * step;
+        }
+        progress(iteration, cost / entries.len().max(1) as f64);
+    }
+
+    words.iter_mut().zip(&contexts).for_each(|(w, c)| *w += c);
+    words
+}
+
+/// The trained `matrix` as a store of the vocabulary's words, in frequency order
+pub fn into_store(vocab: &Vocabulary, matrix: &[f32], dim: usize) -> DenseStore {
+    let mut store = DenseStore::new();
+    for ((word, _), vec) in vocab.words.iter().zip(matrix.chunks_exact(dim)) {
+        store.insert(word.clone(), vec.to_vec());
+    }
+    store
+}
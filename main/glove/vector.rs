@@ -0,0 +1,90 @@
+use crate::WordVec;
+
+/// Computes cosine similarity between two vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f32>();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    cosine_from_parts(dot, norm_a, norm_b)
+}
+
+/// Computes Euclidean distance between two vectors
+pub fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Computes Manhattan (L1) distance between two vectors
+pub fn manhattan_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum::<f32>()
+}
+
+/// Computes the raw dot product of two vectors
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f32>()
+}
+
+/// Dot product of `query` with each of the `rows` rows of a row-major
+/// `matrix`: a single GEMV through the system BLAS in builds with the `blas`
+/// feature, else one `dot_product` per row
+pub fn matrix_vector(matrix: &[f32], rows: usize, query: &[f32]) -> Vec<f32> {
+    let dim = matrix.len().checked_div(rows).unwrap_or(0);
+    if dim == 0 {
+        return vec![0.0; rows];
+    }
+    #[cfg(feature = "blas")]
+    if query.len() == dim {
+        return crate::blas::sgemv(matrix, rows, query);
+    }
+    matrix.chunks_exact(dim).map(|row| dot_product(row, query)).collect()
+}
+
+/// Computes the Euclidean length of a vector
+pub fn norm(a: &[f32]) -> f32 {
+    a.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Scales `vec` to unit length in place; a zero vector stays zero
+pub fn normalize(vec: &mut [f32]) {
+    let n = norm(vec);
+    if n > 0.0 {
+        vec.iter_mut().for_each(|x| *x /= n);
+    }
+}
+
+/// Cosine similarity from a precomputed dot product and norms, matching
+/// `cosine_similarity` exactly
+pub fn cosine_from_parts(dot: f32, norm_a: f32, norm_b: f32) -> f32 {
+    dot / (norm_a * norm_b + 1e-10) // add epsilon to avoid division by zero
+}
+
+/// Converts a cosine similarity to angular distance: the angle between the
+/// vectors as a fraction of pi, so 0 is identical and 1 is opposite
+pub fn angular_from_cosine(cosine: f32) -> f32 {
+    cosine.clamp(-1.0, 1.0).acos() / std::f32::consts::PI
+}
+
+/// Adds multiple vectors together element-wise, accumulating in f64
+fn sum_vectors_f64(vectors: &[&[f32]]) -> Vec<f64> {
+    let mut sum = vec![0.0; vectors[0].len()];
+    for vec in vectors {
+        for (i, val) in vec.iter().enumerate() {
+            sum[i] += *val as f64;
+        }
+    }
+    sum
+}
+
+/// Adds multiple vectors together element-wise
+pub fn sum_vectors(vectors: &[&[f32]]) -> WordVec {
+    sum_vectors_f64(vectors).iter().map(|&s| s as f32).collect()
+}
+
+/// Averages a list of word vectors element-wise
+pub fn average_vectors(vectors: &[&[f32]]) -> WordVec {
+    let count = vectors.len() as f64;
+    sum_vectors_f64(vectors).iter().map(|s| (s / count) as f32).collect()
+}
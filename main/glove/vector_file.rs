@@ -0,0 +1,79 @@
+//! Single vectors saved to disk, e.g. the result of `king - man + woman`,
+//! for reuse in later queries or other tools.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+use crate::formats::{decode_floats, is_count_dim_header, read_bytes, read_npy_header, NPY_MAGIC};
+use crate::WordVec;
+
+/// Writes `vec` to `path`: a 1-d float32 NumPy array if the name ends in
+/// `.npy`, otherwise a one-row word2vec text file (`1 <dim>` header, then
+/// `name` and the values) that any of the text loaders can read back
+pub fn save_vector(path: &str, name: &str, vec: &[f32]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    if path.ends_with(".npy") {
+        write_npy(&mut out, vec)?;
+    } else {
+        // Tokens are whitespace-delimited, so the name must not contain any
+        let name: String = name.split_whitespace().collect::<Vec<_>>().join("_");
+        writeln!(out, "1 {}", vec.len())?;
+        let values: Vec<String> = vec.iter().map(|x| x.to_string()).collect();
+        writeln!(out, "{} {}", name, values.join(" "))?;
+    }
+    out.flush()
+}
+
+/// Writes a version 1.0 `.npy` file holding `vec` as a little-endian float32 array
+fn write_npy(out: &mut impl Write, vec: &[f32]) -> io::Result<()> {
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({},), }}", vec.len());
+    // Magic, version and length take 10 bytes; the header is padded so the data starts 64-byte aligned
+    let padded = (10 + header.len() + 1).div_ceil(64) * 64;
+    header.push_str(&" ".repeat(padded - 10 - header.len() - 1));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for x in vec {
+        out.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads a vector written by `save_vector` or another tool: a 1-d (or
+/// one-row 2-d) float32/float64 `.npy` array, or text holding either one
+/// word2vec/GloVe row (`name v1 v2 ...`, header optional) or bare numbers
+pub fn load_vector(path: &str) -> io::Result<WordVec> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(NPY_MAGIC) {
+        let (width, shape) = read_npy_header(&mut reader)?;
+        let len = match shape[..] {
+            [len] | [1, len] => len,
+            _ => return Err(invalid(format!("expected a 1-d .npy array, found shape {:?}", shape))),
+        };
+        let bytes = read_bytes(&mut reader, len.checked_mul(width).ok_or_else(|| invalid("vector length overflows"))?)?;
+        return Ok(decode_floats(&bytes, width));
+    }
+
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty()).skip_while(|l| is_count_dim_header(l));
+    let first = lines.next().ok_or_else(|| invalid("no vector found"))?;
+    let mut tokens = first.split_whitespace().peekable();
+    let vec: Result<WordVec, _> = if tokens.peek().is_some_and(|t| t.parse::<f32>().is_err()) {
+        // A named row: the values follow the name on the same line
+        tokens.skip(1).map(str::parse).collect()
+    } else {
+        // Bare numbers, on one line or one per line as `numpy.savetxt` writes them
+        first.split_whitespace().chain(lines.flat_map(str::split_whitespace)).map(str::parse).collect()
+    };
+    match vec {
+        Ok(vec) if !vec.is_empty() => Ok(vec),
+        _ => Err(invalid("expected a row of numbers")),
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
@@ -0,0 +1,139 @@
+//! The Word Embedding Association Test (Caliskan et al., 2017), which
+//! measures whether two sets of target words (e.g. career and family terms)
+//! are differently associated with two sets of attribute words (e.g. male and
+//! female names).
+
+use crate::rng::Rng;
+use crate::store::EmbeddingStore;
+use crate::vector::cosine_similarity;
+
+/// The four word sets of a test
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WordSets {
+    pub targets_x: Vec<String>,
+    pub targets_y: Vec<String>,
+    pub attributes_a: Vec<String>,
+    pub attributes_b: Vec<String>,
+}
+
+/// The outcome of a test
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weat {
+    /// Cohen's d of the association difference: around ±0.2 is small, ±0.8 large
+    pub effect_size: f32,
+    /// One-sided p-value: the share of equal-size repartitions of `X ∪ Y`
+    /// whose test statistic exceeds the observed one
+    pub p_value: f64,
+    /// `sum_x s(x, A, B) - sum_y s(y, A, B)`
+    pub statistic: f32,
+    /// Repartitions examined
+    pub partitions: usize,
+    /// Whether every repartition was examined rather than a random sample
+    pub exact: bool,
+    /// Words left out because the store does not have them
+    pub unknown: Vec<String>,
+}
+
+/// Runs the test, examining every repartition if there are at most
+/// `permutations` of them and that many random ones otherwise. Unknown words
+/// are skipped; each set needs at least one known word.
+pub fn weat(store: &dyn EmbeddingStore, sets: &WordSets, permutations: usize, rng: &mut Rng) -> Result<Weat, String> {
+    let mut unknown = Vec::new();
+    let mut lookup = |words: &[String], name: &str| -> Result<Vec<Vec<f32>>, String> {
+        let mut found = Vec::new();
+        for word in words {
+            match store.get(word) {
+                Some(vec) => found.push(vec.into_owned()),
+                None => unknown.push(word.clone()),
+            }
+        }
+        if found.is_empty() {
+            return Err(format!("none of the {} words are in the vocabulary", name));
+        }
+        Ok(found)
+    };
+    let x = lookup(&sets.targets_x, "X target")?;
+    let y = lookup(&sets.targets_y, "Y target")?;
+    let a = lookup(&sets.attributes_a, "A attribute")?;
+    let b = lookup(&sets.attributes_b, "B attribute")?;
+
+    // s(w, A, B): how much closer w is to A than to B on average
+    let association = |w: &[f32]| -> f32 {
+        let mean = |set: &[Vec<f32>]| set.iter().map(|v| cosine_similarity(w, v)).sum::<f32>() / set.len() as f32;
+        mean(&a) - mean(&b)
+    };
+    let scores: Vec<f32> = x.iter().chain(&y).map(|w| association(w)).collect();
+    let (x_scores, y_scores) = scores.split_at(x.len());
+
+    let mean = |s: &[f32]| s.iter().sum::<f32>() / s.len() as f32;
+    let all_mean = mean(&scores);
+    let variance = scores.iter().map(|s| (s - all_mean).powi(2)).sum::<f32>() / (scores.len().max(2) - 1) as f32;
+    let effect_size = (mean(x_scores) - mean(y_scores)) / variance.sqrt().max(f32::MIN_POSITIVE);
+    let statistic = x_scores.iter().sum::<f32>() - y_scores.iter().sum::<f32>();
+
+    // A repartition's statistic is 2 * sum(Xi) - total, so comparing sums suffices
+    let observed: f64 = x_scores.iter().map(|&s| s as f64).sum();
+    let exceeds = |chosen: &[usize]| chosen.iter().map(|&i| scores[i] as f64).sum::<f64>() > observed + 1e-9;
+    let n = scores.len();
+    let k = x.len();
+    let (partitions, above, exact) = if binomial(n, k) <= permutations as u128 {
+        let mut chosen: Vec<usize> = (0..k).collect();
+        let (mut partitions, mut above) = (0, 0);
+        loop {
+            partitions += 1;
+            above += exceeds(&chosen) as usize;
+            if !next_combination(&mut chosen, n) {
+                break;
+            }
+        }
+        (partitions, above, true)
+    } else {
+        let mut indices: Vec<usize> = (0..n).collect();
+        let mut above = 0;
+        for _ in 0..permutations {
+            // Partial Fisher-Yates: the first k entries are a uniform random subset
+            for i in 0..k {
+                let j = i + rng.below(n - i);
+                indices.swap(i, j);
+            }
+            above += exceeds(&indices[..k]) as usize;
+        }
+        (permutations, above, false)
+    };
+
+    Ok(Weat {
+        effect_size,
+        p_value: above as f64 / partitions.max(1) as f64,
+        statistic,
+        partitions,
+        exact,
+        unknown,
+    })
+}
+
+/// `n` choose `k`, saturating
+fn binomial(n: usize, k: usize) -> u128 {
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        match result.checked_mul((n - i) as u128) {
+            Some(product) => result = product / (i + 1) as u128,
+            None => return u128::MAX,
+        }
+    }
+    result
+}
+
+/// Advances `chosen` (ascending indices below `n`) to the next combination
+/// in lexicographic order, returning false after the last one
+fn next_combination(chosen: &mut [usize], n: usize) -> bool {
+    let k = chosen.len();
+    let Some(i) = (0..k).rev().find(|&i| chosen[i] < n - k + i) else {
+        return false;
+    };
+    chosen[i] += 1;
+    for j in i + 1..k {
+        chosen[j] = chosen[j - 1] + 1;
+    }
+    true
+}
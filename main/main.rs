@@ -1,116 +1,143 @@
-use std::collections::HashMap;
-use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::f32;
-
-// Define a type alias for better readability
-type WordVec = Vec<f32>;
-
-/// Loads a GloVe-style vector file into a HashMap
-fn load_glove_vectors(path: &str) -> HashMap<String, WordVec> {
-    let file = File::open(path).expect("Unable to open file");
-    let reader = BufReader::new(file);
-    let mut vectors = HashMap::new();
-
-    // Read each line in the file
-    for line in reader.lines() {
-        if let Ok(l) = line {
-            // Split the line into word and its 50 floats
-            let mut parts = l.split_whitespace();
-            if let Some(word) = parts.next() {
-                // Parse all the floats
-                let vec: WordVec = parts.map(|x| x.parse::<f32>().unwrap()).collect();
-                vectors.insert(word.to_string(), vec);
-            }
-        }
-    }
-
-    vectors
-}
-
-/// Computes cosine similarity between two vectors
-fn cosine_similarity(a: &WordVec, b: &WordVec) -> f32 {
-    let dot = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f32>();
-    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-    dot / (norm_a * norm_b + 1e-10)  // add epsilon to avoid division by zero
-}
-
-/// Adds multiple vectors together element-wise
-fn sum_vectors(vectors: Vec<&WordVec>) -> WordVec {
-    let mut sum = vec![0.0; vectors[0].len()];
-    for vec in vectors {
-        for (i, val) in vec.iter().enumerate() {
-            sum[i] += val;
-        }
-    }
-    sum
-}
+//! Command-line front end. Every command takes the same flags for the vector
+//! source (`--backend`, `--model`, `--archive`, loading and masking options);
+//! a bare `main <source> word1 word2 ...` is the original word sum.
 
-/// Finds the nearest neighbor word to a given vector,
-/// excluding the input words themselves
-fn find_nearest_neighbor<'a>(
-    sum_vec: &WordVec,
-    vectors: &'a HashMap<String, WordVec>,
-    exclude_words: &[String],
-) -> Option<(&'a String, f32)> {
-    let mut best_word = None;
-    let mut best_score = -f32::INFINITY;
+mod cli;
 
-    for (word, vec) in vectors.iter() {
-        // Skip input words
-        if exclude_words.contains(word) {
-            continue;
-        }
+use std::env;
 
-        let similarity = cosine_similarity(sum_vec, vec);
-        if similarity > best_score {
-            best_score = similarity;
-            best_word = Some(word);
-        }
+use cli::{explore, query, storage, text};
+use glove::index::INDEXES;
+use glove::metric::BuiltinMetric;
+use glove::postprocess::POSTPROCESSES;
+use glove::store::BACKENDS;
+
+/// Prints every command form
+fn print_usage(program: &str) {
+    let forms = [
+        "sum <glove.txt> word1 word2 ... [--top N] [--metric <name>] [--output text|tsv|json]".to_string(),
+        "average <glove.txt> word1 word2 ... [--top N] [--metric <name>] [--output text|tsv|json]".to_string(),
+        "nearest <glove.txt> <word> [--top N] [--metric <name>] [--output text|tsv|json]".to_string(),
+        "similarity <glove.txt> <word1> <word2> [--metric <name> | --all-metrics] [--output text|tsv|json]".to_string(),
+        "matrix <glove.txt> word1 word2 ... [--metric <name>] [--output text|csv|tsv|json]".to_string(),
+        "sum|average|nearest <glove.txt> --queries <queries.txt> | --stdin".to_string(),
+        "embed-sentence <glove.txt> <sentence ...> [--sum | --sif <freq.txt>] [--vector] [--top N]".to_string(),
+        "doc-sim <glove.txt> <a.txt> (<b.txt> | --dir <docs>) [--sif <freq.txt>] [--top N]".to_string(),
+        "coverage <glove.txt> <corpus.txt> [--top N]".to_string(),
+        "export projector <glove.txt> <tensor.tsv> <metadata.tsv>".to_string(),
+        "export parquet|npy|npz|safetensors|hdf5 <glove.txt> <out>".to_string(),
+        "export annoy <glove.txt> <out.ann> [--trees N]".to_string(),
+        "convert <glove.txt> <out> --to text|binary|parquet|npy|npz|safetensors|hdf5|sqlite|rocksdb".to_string(),
+        "export-subset <glove.txt> <out> [--words <list.txt>] [--regex <pattern>] [--top N] [--to <format>]".to_string(),
+        "graph <glove.txt> <out.dot|out.gexf> (--seeds <word> ... | --vocab-top N) [--k K]".to_string(),
+        "all-pairs <glove.txt> <out.tsv> [--k K] [--words <list.txt> | --vocab-top N]".to_string(),
+        "merge <out> <a.txt> <b.txt> ... [--on-conflict keep-first|keep-second|average]".to_string(),
+        "align <a.txt> <b.txt> <out> [--dictionary <pairs.txt>] [--save-mapping <mapping.txt>]".to_string(),
+        "debias <glove.txt> <out> --definitional <pairs.txt> [--equalize <pairs.txt>] [--neutralize|--keep <words.txt>]"
+            .to_string(),
+        "retrofit <glove.txt> <out> --lexicon <lexicon.txt> [--iterations N]".to_string(),
+        "counterfit <glove.txt> <out> [--synonyms <pairs.txt>] [--antonyms <pairs.txt>] [--epochs N]".to_string(),
+        "reduce <glove.txt> <out> --dims N [--sample N]".to_string(),
+        "cooccur <corpus.txt> <vocab.txt> <cooccurrence.bin> [--window N] [--weighting <name>]".to_string(),
+        "train <corpus.txt> <out> [--dim N] [--window N] [--min-count N] [--iterations N] [--x-max X]".to_string(),
+        "train --cooccurrences <cooccurrence.bin> --vocab <vocab.txt> <out> [--dim N] [--iterations N]".to_string(),
+        "serve <glove.txt> [host:port] [--cache N] [--normalize]".to_string(),
+        "working-set [host:port]".to_string(),
+        "download [model]".to_string(),
+        "calibrate <glove.txt> [--samples N] [--seed S]".to_string(),
+        "opposite <glove.txt> --word <word> [--axis <from> <to>] [--top N]".to_string(),
+        "axis <glove.txt> --pair <from> <to> [--pair <from> <to> ...] [word ...] [--top N]".to_string(),
+        "fuse <glove.txt> <weight>:<expression> ... [--top N]".to_string(),
+        "analogy <glove.txt> <a> <b> <c> [--method 3cosadd|3cosmul] [--top N]".to_string(),
+        "doesnt-match <glove.txt> <word1> <word2> <word3> ...".to_string(),
+        "correct <glove.txt> <word> [<context word> ...] [--top N]".to_string(),
+        "path <glove.txt> <word1> <word2> [--steps N] [--exclude-endpoints]".to_string(),
+        "translate <source.txt> <target.txt> <word> ... --mapping <mapping.txt> [--top N]".to_string(),
+        "eval weat <glove.txt> --targets-x ... --targets-y ... --attributes-a ... --attributes-b ...".to_string(),
+        "vocab <glove.txt> [word ...] [--regex <pattern>] [--prefix <prefix>] [--limit N]".to_string(),
+        "vocab-diff <a.txt> <b.txt> [--show N] [--only-a <out.txt>] [--only-b <out.txt>] [--both <out.txt>]"
+            .to_string(),
+        "tui <glove.txt> [--top N]".to_string(),
+        "stats <glove.txt>".to_string(),
+        "dedup-report <glove.txt> [--threshold T]".to_string(),
+        "bench <glove.txt> [--configs exact,scan,batch,lsh,ivf,annoy] [--samples N] [--top K]".to_string(),
+        "compare <a.txt> <b.txt> [--words <list.txt> | --samples N [--pool N] [--seed S]] [--k K]".to_string(),
+        "drift --word <word> --models <a.txt> <b.txt> ... [--top N]".to_string(),
+        format!("--metric {}", BuiltinMetric::NAMES.join("|")),
+    ];
+    eprintln!("Usage: {} <command> ...", program);
+    for form in forms {
+        eprintln!("       {} {}", program, form);
     }
-
-    best_word.map(|w| (w, best_score))
+    eprintln!();
+    eprintln!("Source flags accepted by every command that loads vectors:");
+    eprintln!("  --backend {}   --model <name>   --archive <glove.zip> --member <glove.txt>", BACKENDS.join("|"));
+    eprintln!("  --quiet   --strict   --max-vocab N   --threads N   --language <code>   --tensor <name>");
+    eprintln!("  --on-conflict keep-first|keep-second|average (for a directory or 'part-*.txt' of shards)");
+    eprintln!("  --precision f32|f16|int8|pq [--rescore N]   --pq-subquantizers M   --pq-bits B");
+    eprintln!("  --mask-dims 5,17,30-40 | --mask-file <path>   --lemmatize   --subwords [--subword-file <path>]");
+    eprintln!("  --postprocess {} [--abtt-components D]", POSTPROCESSES.join("|"));
+    eprintln!("  --index {} [--bits N] [--tables N] [--candidates N] [--nlist N] [--nprobe N]", INDEXES.join("|"));
+    eprintln!("          [--trees N] [--search-k N] [--index-file <index.ann>]");
+    eprintln!("  --gpu (builds with the gpu feature; falls back to the CPU)");
 }
 
 fn main() {
-    // Usage: cargo run glove.txt word1 word2 word3 ...
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() < 3 {
-        eprintln!("Usage: {} <glove.txt> word1 word2 ...", args[0]);
+    let mut args: Vec<String> = env::args().collect();
+    if !cli::load_config() {
         return;
     }
 
-    let glove_path = &args[1];
-    let input_words: Vec<String> = args[2..].to_vec();
-
-    println!("Loading GloVe vectors...");
-    let glove = load_glove_vectors(glove_path);
-
-    // Collect vectors for all valid input words
-    let mut found_vectors = Vec::new();
-    for word in &input_words {
-        if let Some(vec) = glove.get(word) {
-            found_vectors.push(vec);
-        } else {
-            println!("Skipping unknown word: {}", word);
+    match args.get(1).map(|a| a.as_str()) {
+        Some("sum") => query::run_sum(args),
+        Some("average") => query::run_average(args),
+        Some("nearest") => query::run_nearest(args),
+        Some("similarity") => query::run_similarity(args),
+        Some("matrix") => query::run_matrix(args),
+        Some("embed-sentence") => text::run_embed_sentence(args),
+        Some("doc-sim") => text::run_doc_sim(args),
+        Some("coverage") => text::run_coverage(args),
+        Some("export") => storage::run_export(args),
+        Some("convert") => storage::run_convert(args),
+        Some("export-subset") => storage::run_export_subset(args),
+        Some("graph") => storage::run_graph(args),
+        Some("all-pairs") => storage::run_all_pairs(args),
+        Some("merge") => storage::run_merge(args),
+        Some("align") => storage::run_align(args),
+        Some("debias") => storage::run_debias(args),
+        Some("retrofit") => storage::run_retrofit(args),
+        Some("counterfit") => storage::run_counterfit(args),
+        Some("reduce") => storage::run_reduce(args),
+        Some("cooccur") => storage::run_cooccur(args),
+        Some("train") => storage::run_train(args),
+        Some("serve") => storage::run_serve(args),
+        Some("working-set") => storage::run_working_set(args),
+        Some("download") => storage::run_download(args),
+        Some("calibrate") => explore::run_calibrate(args),
+        Some("opposite") => explore::run_opposite(args),
+        Some("axis") => explore::run_axis(args),
+        Some("fuse") => explore::run_fuse(args),
+        Some("analogy") => explore::run_analogy(args),
+        Some("doesnt-match") => explore::run_doesnt_match(args),
+        Some("correct") => explore::run_correct(args),
+        Some("path") => explore::run_path(args),
+        Some("translate") => explore::run_translate(args),
+        Some("eval") => explore::run_eval(args),
+        Some("drift") => explore::run_drift(args),
+        Some("compare") => explore::run_compare(args),
+        Some("bench") => query::run_bench(args),
+        Some("vocab") => explore::run_vocab(args),
+        Some("vocab-diff") => explore::run_vocab_diff(args),
+        Some("stats") => explore::run_stats(args),
+        Some("dedup-report") => explore::run_dedup_report(args),
+        #[cfg(unix)]
+        Some("tui") => cli::tui::run_tui(args),
+        None | Some("help" | "--help") => print_usage(&args[0]),
+        // Bare `main [flags] <source> word1 word2 ...` is the original word sum
+        Some(_) if args.iter().any(|a| a == "--help") => print_usage(&args[0]),
+        Some(_) => {
+            args.insert(1, "sum".to_string());
+            query::run_sum(args);
         }
     }
-
-    if found_vectors.is_empty() {
-        println!("No valid input words found in the database.");
-        return;
-    }
-
-    // Sum the vectors of the valid words
-    let sum_vec = sum_vectors(found_vectors);
-
-    // Find the nearest neighbor that isn't one of the input words
-    if let Some((nearest_word, similarity)) = find_nearest_neighbor(&sum_vec, &glove, &input_words) {
-        println!("Nearest neighbor: {} (similarity: {:.4})", nearest_word, similarity);
-    } else {
-        println!("No nearest neighbor found.");
-    }
 }